@@ -0,0 +1,63 @@
+#once
+
+#include "ext.asm"
+
+; Debug port at 0xFA00, see `docs/memory.md`.
+; The macros keep every register except the flags.
+#ruledef mb8_debug
+{
+    ; Write register `src` to debug port register `reg`
+    DBG_OUT { reg: u8 } { src: register } => asm {
+        PUSH IH
+        PUSH IL
+        LDI IH 0xFA
+        LDI IL {reg}
+        ST [IH:IL] {src}
+        POP IL
+        POP IH
+    }
+
+    ; Write the immediate `value` to debug port register `reg`
+    DBG_OUTI { reg: u8 } { value: u8 } => asm {
+        PUSH R7
+        LDI R7 {value}
+        DBG_OUT {reg} R7
+        POP R7
+    }
+
+    ; Append the byte in `src` to the host log
+    DBG_LOG { src: register } => asm {
+        DBG_OUT 0x00 {src}
+    }
+
+    ; Append the value of `src` to the host log as two hex digits
+    DBG_HEX { src: register } => asm {
+        DBG_OUT 0x01 {src}
+    }
+
+    ; Report assertion `id` as passed
+    DBG_PASS { id: u8 } => asm {
+        DBG_OUTI 0x02 {id}
+    }
+
+    ; Report assertion `id` as failed
+    DBG_FAIL { id: u8 } => asm {
+        DBG_OUTI 0x03 {id}
+    }
+
+    ; Stop the machine with exit status `status`
+    DBG_EXIT { status: u8 } => asm {
+        DBG_OUTI 0x04 {status}
+    }
+
+    ; Report assertion `id` as passed if `reg` equals `value`, as failed otherwise
+    ASSERT_EQ { reg: register } { value: u8 } { id: u8 } => asm {
+        CMPI {reg} {value}
+        JNZR [fail]
+        DBG_PASS {id}
+        JR [end]
+        fail:
+        DBG_FAIL {id}
+        end:
+    }
+}
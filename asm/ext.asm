@@ -66,7 +66,15 @@
         0x33 @ offset`8
     }
 
-    ; Jump if zero flag is not set to an absolute label
+    ; Jump if carry flag is set to an absolute label
+    JCR [{ addr: u16 }] => {
+        offset = addr - $ - 2
+        assert(offset <= 127)
+        assert(offset >= -128)
+        0x34 @ offset`8
+    }
+
+    ; Jump if carry flag is not set to an absolute label
     JNCR [{ addr: u16 }] => {
         offset = addr - $ - 2
         assert(offset <= 127)
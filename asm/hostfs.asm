@@ -0,0 +1,20 @@
+#once
+
+; Host directory device at 0xFB00, see `docs/memory.md`.
+HOSTFS_CMD = 0xFB00
+HOSTFS_STATUS = 0xFB01
+HOSTFS_HANDLE = 0xFB02
+HOSTFS_LEN = 0xFB03
+HOSTFS_INDEX = 0xFB04
+HOSTFS_SIZE = 0xFB05
+HOSTFS_BUFFER = 0xFB80
+
+HOSTFS_CMD_OPEN_READ = 0x01
+HOSTFS_CMD_OPEN_WRITE = 0x02
+HOSTFS_CMD_READ = 0x03
+HOSTFS_CMD_WRITE = 0x04
+HOSTFS_CMD_CLOSE = 0x05
+HOSTFS_CMD_LIST = 0x06
+
+HOSTFS_OK = 0x00
+HOSTFS_NOT_FOUND = 0x01
@@ -1,5 +1,7 @@
 #once
 
+; Generated from crates/mb8-isa/src/table.rs by `make isa`. Do not edit.
+
 #subruledef register
 {
     R0  => 0x0
@@ -20,8 +22,8 @@
     R15 => 0xF
 
     A   => 0x0
-    IL  => 0x9
-    IH  => 0xA
+    IH  => 0x9
+    IL  => 0xA
     FPH => 0xB
     FPL => 0xC
     SPH => 0xD
@@ -31,9 +33,10 @@
 
 #ruledef mb8_isa
 {
-    NOP  => 0x0000
+    NOP => 0x0000
     HALT => 0x0100
     HALT { code: u8 } => 0x01 @ code
+    SYS => 0x0200
     MOV { dst: register } { src: register } => 0x10 @ dst @ src
     ADD { dst: register } { src: register } => 0x11 @ dst @ src
     SUB { dst: register } { src: register } => 0x12 @ dst @ src
@@ -55,5 +58,5 @@
     PUSH { src: register } => 0x42 @ src @ 0x0
     POP { dst: register } => 0x43 @ dst @ 0x0
     LD { dst: register } [{ hi: register }:{ lo: register }] => 0x5 @ dst @ hi @ lo
-    ST [{ hi: register }:{ lo: register }] { dst: register } => 0x6 @ dst @ hi @ lo
+    ST [{ hi: register }:{ lo: register }] { src: register } => 0x6 @ src @ hi @ lo
 }
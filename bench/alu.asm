@@ -0,0 +1,32 @@
+#include "../asm/isa.asm"
+#include "../asm/ext.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; Tight ALU loop: 16 * 256 * 256 rounds of register arithmetic and logic.
+start:
+    LDI R0 16
+    LDI R1 0x00
+    LDI R5 0x01
+.outer:
+    LDI R2 0x00
+.inner:
+    ADD R3 R2
+    XOR R4 R3
+    SHL R4 R5
+    SUB R3 R5
+    OR R4 R2
+    AND R3 R4
+    ADD R2 R5
+    JNZR [.inner]
+    ADD R1 R5
+    JNZR [.outer]
+    SUB R0 R5
+    JNZR [.outer]
+    HALT
@@ -0,0 +1,28 @@
+#include "../asm/isa.asm"
+#include "../asm/ext.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; Memory copy: 16 passes copying 4 KiB from 0x1000 to 0x4000 byte by byte.
+start:
+    LDI R0 16
+.pass:
+    LDI R1 R2 0x1000
+    LDI R3 R4 0x4000
+.copy:
+    LD R5 [R1:R2]
+    ST [R3:R4] R5
+    INC16 R1 R2
+    INC16 R3 R4
+    CMPI R1 0x20
+    JNZR [.copy]
+    DEC R0
+    CMPI R0 0
+    JNZR [.pass]
+    HALT
@@ -0,0 +1,27 @@
+#include "../asm/isa.asm"
+#include "../asm/ext.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; Call-heavy recursion: a binary call tree of depth 17, 2^18 - 1 calls.
+start:
+    LDI R1 17
+    CALL [tree]
+    HALT
+
+; Call itself twice with R1 - 1 until R1 is 0. Keeps R1.
+tree:
+    CMPI R1 0
+    JZR [.leaf]
+    DEC R1
+    CALL [tree]
+    CALL [tree]
+    INC R1
+.leaf:
+    RET
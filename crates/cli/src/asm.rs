@@ -0,0 +1,170 @@
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+use mb8_asm::{
+    assemble_file, assemble_object_file,
+    error::AsmError,
+    linker::{link, LinkScript},
+    Object,
+};
+use mb8_isa::exec::{Executable, USER_BASE};
+
+use crate::symbols::write_symbols;
+
+fn report(errors: &[AsmError], action: &str, path: &Path) {
+    for error in errors {
+        eprintln!("error: {error}");
+    }
+    eprintln!("Failed to {action} {}", path.display());
+}
+
+/// Wrap `image`, loaded at `load`, in an executable header. Execution starts at
+/// the `entry` label, or at the load address without one.
+fn executable(
+    image: &[u8],
+    load: u16,
+    entry: Option<&str>,
+    lookup: impl Fn(&str) -> Option<i64>,
+) -> Result<Vec<u8>, String> {
+    let entry = match entry {
+        None => load,
+        Some(name) => {
+            let value =
+                lookup(name).ok_or_else(|| format!("entry label '{name}' is not defined"))?;
+            u16::try_from(value).map_err(|_| format!("entry label '{name}' is not an address"))?
+        }
+    };
+    Executable::from_image(image, load, entry).map(|exec| exec.to_bytes())
+}
+
+/// Assemble `source` and write the image to `output` (or `source` with a `.bin` extension).
+/// With `object`, write a relocatable object instead (default extension `.o`).
+/// `symbols` receives the label and line table of the image.
+/// With `exec`, the image is written as an executable starting at the given entry label.
+/// Returns `false` if assembly failed.
+pub fn run_asm(
+    source: &Path,
+    output: Option<&Path>,
+    object: bool,
+    symbols: Option<&Path>,
+    exec: Option<Option<&str>>,
+) -> bool {
+    let (mut bytes, extension, map, labels) = if object {
+        match assemble_object_file(source) {
+            Ok(object) => (object.to_bytes(), "o", None, BTreeMap::new()),
+            Err(errors) => {
+                report(&errors, "assemble", source);
+                return false;
+            }
+        }
+    } else {
+        match assemble_file(source) {
+            Ok(assembly) => (assembly.binary, "bin", Some(assembly.map), assembly.symbols),
+            Err(errors) => {
+                report(&errors, "assemble", source);
+                return false;
+            }
+        }
+    };
+
+    if let Some(entry) = exec {
+        let lookup = |name: &str| {
+            labels
+                .get(name)
+                .and_then(|value| i64::try_from(*value).ok())
+        };
+        match executable(&bytes, USER_BASE, entry, lookup) {
+            Ok(exec) => bytes = exec,
+            Err(err) => {
+                eprintln!("Failed to build executable {}: {err}", source.display());
+                return false;
+            }
+        }
+    }
+
+    let output = output.map_or_else(|| source.with_extension(extension), Path::to_path_buf);
+    if let Err(err) = std::fs::write(&output, &bytes) {
+        eprintln!("Failed to write {}: {err}", output.display());
+        return false;
+    }
+    match (symbols, map) {
+        (Some(path), Some(map)) => write_symbols(path, &map),
+        _ => true,
+    }
+}
+
+/// Link `objects` into the image `output`, placing sections as described by `script`.
+/// `symbols` receives the final address of every label.
+/// With `exec`, the image is written as an executable starting at the given entry label.
+/// Returns `false` if linking failed.
+#[must_use]
+pub fn run_link(
+    objects: &[PathBuf],
+    output: &Path,
+    script: Option<&Path>,
+    symbols: Option<&Path>,
+    exec: Option<Option<&str>>,
+) -> bool {
+    let script = match script {
+        None => LinkScript::default(),
+        Some(path) => {
+            let parsed = std::fs::read_to_string(path)
+                .map_err(|err| err.to_string())
+                .and_then(|text| {
+                    LinkScript::parse(&text).map_err(|(line, message)| format!("{line}: {message}"))
+                });
+            match parsed {
+                Ok(script) => script,
+                Err(err) => {
+                    eprintln!("Failed to read link script {}: {err}", path.display());
+                    return false;
+                }
+            }
+        }
+    };
+
+    let mut loaded = Vec::new();
+    for path in objects {
+        let object = std::fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|data| Object::from_bytes(&data));
+        match object {
+            Ok(object) => loaded.push((path.display().to_string(), object)),
+            Err(err) => {
+                eprintln!("Failed to read object {}: {err}", path.display());
+                return false;
+            }
+        }
+    }
+
+    let linked = match link(&loaded, &script) {
+        Ok(linked) => linked,
+        Err(errors) => {
+            report(&errors, "link", output);
+            return false;
+        }
+    };
+    let mut binary = linked.binary;
+    if let Some(entry) = exec {
+        let built = match script.regions.as_slice() {
+            [region] => executable(&binary, region.origin, entry, |name| {
+                linked.symbols.get(name).copied()
+            }),
+            _ => Err("executables need a link script with a single region".to_string()),
+        };
+        match built {
+            Ok(exec) => binary = exec,
+            Err(err) => {
+                eprintln!("Failed to build executable {}: {err}", output.display());
+                return false;
+            }
+        }
+    }
+    if let Err(err) = std::fs::write(output, &binary) {
+        eprintln!("Failed to write {}: {err}", output.display());
+        return false;
+    }
+    symbols.is_none_or(|path| write_symbols(path, &linked.map))
+}
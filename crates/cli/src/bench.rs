@@ -0,0 +1,94 @@
+use std::{
+    fmt,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use mb8::vm::VirtualMachine;
+use mb8_asm::assemble_file;
+
+/// Instructions a benchmark runs unless it halts first.
+pub const DEFAULT_INSTRUCTIONS: u64 = 10_000_000;
+
+/// Timing of one benchmark run.
+#[derive(Debug, Clone, Copy)]
+pub struct BenchResult {
+    pub instructions: u64,
+    pub cycles: u64,
+    pub elapsed: Duration,
+    /// Whether the program halted before the instruction limit.
+    pub halted: bool,
+}
+
+impl BenchResult {
+    /// Emulated instructions per host second.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn instructions_per_second(&self) -> f64 {
+        self.instructions as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+
+    /// Emulated CPU cycles per host second.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss)]
+    pub fn cycles_per_second(&self) -> f64 {
+        self.cycles as f64 / self.elapsed.as_secs_f64().max(f64::EPSILON)
+    }
+}
+
+impl fmt::Display for BenchResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let end = if self.halted { "halted" } else { "limit" };
+        write!(
+            f,
+            "{} instructions, {} cycles ({end}) in {:.3} s: {:.2} MIPS, {:.2} M cycles/s",
+            self.instructions,
+            self.cycles,
+            self.elapsed.as_secs_f64(),
+            self.instructions_per_second() / 1e6,
+            self.cycles_per_second() / 1e6,
+        )
+    }
+}
+
+/// Read a ROM image, assembling it first if `path` is an `.asm` source.
+///
+/// # Errors
+///
+/// Returns a message if the file cannot be read or assembled.
+pub fn load_rom(path: &Path) -> Result<Vec<u8>, String> {
+    if path.extension().is_some_and(|ext| ext == "asm") {
+        assemble_file(path)
+            .map(|assembly| assembly.binary)
+            .map_err(|errors| {
+                let errors: Vec<String> = errors.iter().map(ToString::to_string).collect();
+                format!(
+                    "Failed to assemble {}:\n{}",
+                    path.display(),
+                    errors.join("\n")
+                )
+            })
+    } else {
+        std::fs::read(path).map_err(|err| format!("Failed to read {}: {err}", path.display()))
+    }
+}
+
+/// Run `rom` from 0xE000 for at most `max_instructions` instructions.
+#[must_use]
+pub fn bench(rom: &[u8], max_instructions: u64) -> BenchResult {
+    let mut vm = VirtualMachine::default();
+    vm.load_rom(rom);
+
+    let start = Instant::now();
+    let mut instructions = 0;
+    while !vm.halted && instructions < max_instructions {
+        vm.step();
+        instructions += 1;
+    }
+    BenchResult {
+        instructions,
+        cycles: vm.cycles,
+        elapsed: start.elapsed(),
+        halted: vm.halted,
+    }
+}
@@ -1,37 +1,275 @@
+use std::{io, path::PathBuf, time::Duration};
+
 use clap::Parser;
 use mb8::{
-    dev::gpu::registers::{BITMAP_HEIGHT, BITMAP_WIDTH, TTY_COLS, TTY_ROWS},
+    dev::{
+        disk::{Latency, DRIVES},
+        gpu::registers::{BITMAP_HEIGHT, BITMAP_WIDTH, TTY_COLS, TTY_ROWS},
+        rtc::TimeSource,
+    },
     vm,
 };
-use mb8_cli::{bitmap::Bitmap, config, debug::Debug};
+use mb8_cli::{
+    asm::{run_asm, run_link},
+    bench::{bench, load_rom},
+    bitmap::Bitmap,
+    clock::Clock,
+    config::{self, DiskCommand, RunArgs},
+    debug::Debug,
+    disasm::run_disasm,
+    diskimage::DiskImage,
+    disktool,
+    e2e::run_script,
+    filesystem::Version,
+    headless::{Headless, Stop},
+    serial::{Serial, SerialSpec},
+    symbols::load_symbols,
+    terminal::TerminalRun,
+};
 use mb8_cli::{tty::Tty, vmrun};
 use mb8c::compile;
 
-fn main() {
-    let cli = config::Cli::parse();
+/// A machine configured by the `run` options other than the kernel and user files.
+fn machine(args: &RunArgs) -> Result<vm::VirtualMachine, String> {
+    let mut vm = vm::VirtualMachine::default();
+    vm.protection.enabled = args.protect;
+    vm.trace = args.trace;
+    if let Some(seconds) = args.rtc_epoch {
+        vm.devices.rtc().source = TimeSource::Fixed(seconds);
+    }
+    vm.devices.sound().recording = args.wav.is_some();
+    let disk = vm.devices.disk();
+    disk.latency = Latency {
+        seek: args.disk_seek,
+        transfer: args.disk_transfer,
+    };
+    for bad in &args.bad_blocks {
+        disk.drive(bad.drive).bad_blocks.insert(bad.block);
+    }
+    if let Some(dir) = &args.hostfs {
+        vm.devices
+            .hostfs()
+            .set_root(dir)
+            .map_err(|err| format!("Failed to share {}: {err}", dir.display()))?;
+    }
+    if let Some(path) = &args.cartridge {
+        std::fs::read(path)
+            .map_err(|err| err.to_string())
+            .and_then(|image| vm.devices.banks().load_cartridge(&image))
+            .map_err(|err| format!("Failed to load cartridge {}: {err}", path.display()))?;
+    }
+    Ok(vm)
+}
 
-    match cli.command {
-        config::Commands::Run {
-            kernel,
-            user,
-            debug,
+/// The disk images of `args`: `--disk` in drive 0 and the `--drive` images
+/// after it. An existing drive 0 image replaces the user files.
+fn disk_images(args: &RunArgs) -> Result<Vec<DiskImage>, String> {
+    if args.drives.len() >= DRIVES {
+        return Err(format!("At most {} --drive images fit", DRIVES - 1));
+    }
+    let mut disks = Vec::new();
+    if let Some(path) = &args.disk {
+        if path.exists() && !args.user.is_empty() {
+            eprintln!(
+                "Warning: the disk is loaded from {}, ignoring the user files",
+                path.display()
+            );
+        }
+        disks.push(DiskImage::new(path.clone(), args.disk_mode));
+    }
+    for (index, path) in args.drives.iter().enumerate() {
+        let mut disk = DiskImage::new(path.clone(), args.disk_mode);
+        disk.drive = index + 1;
+        disks.push(disk);
+    }
+    Ok(disks)
+}
+
+/// Boot the machine of `args` and load its disk images.
+fn boot(
+    vm: &mut vm::VirtualMachine,
+    args: &RunArgs,
+    seed: Option<u16>,
+) -> Result<Vec<DiskImage>, String> {
+    vmrun::boot(vm, &args.kernel, args.user.clone(), seed)?;
+    let disks = disk_images(args)?;
+    for disk in &disks {
+        disk.load(vm)?;
+    }
+    Ok(disks)
+}
+
+/// The desktop runtime for `args`.
+fn desktop(args: &RunArgs) -> Result<vmrun::VmRun, String> {
+    let symbols = load_symbols(&args.symbols)?;
+    let vm = machine(args)?;
+    let tty = Tty::new(TTY_COLS as usize, TTY_ROWS as usize, 1024);
+    let bitmap = Bitmap::new(BITMAP_WIDTH, BITMAP_HEIGHT);
+    let debugcli = Debug::with_symbols(symbols);
+    let mut vm_desk = vmrun::VmRun::new(vm, tty, bitmap, debugcli)
+        .map_err(|err| format!("Failed to init VM: {err}"))?;
+    vm_desk.serial = args.serial.as_ref().map(Serial::open).transpose()?;
+    vm_desk.debug_enabled = args.debug;
+    vm_desk.wav.clone_from(&args.wav);
+    vm_desk.clock = Clock::new(args.hz);
+    vm_desk.disks = disk_images(args)?;
+    Ok(vm_desk)
+}
+
+/// Run `args` in the host terminal, returning the process exit code.
+fn terminal(args: RunArgs, seed: Option<u16>) -> Result<i32, String> {
+    if args.serial == Some(SerialSpec::Stdio) {
+        return Err("--serial stdio cannot be used with --terminal".to_string());
+    }
+    let mut vm = machine(&args)?;
+    let disks = boot(&mut vm, &args, seed)?;
+    let mut run = TerminalRun::new(vm);
+    run.disks = disks;
+    run.serial = args.serial.as_ref().map(Serial::open).transpose()?;
+    run.wav = args.wav;
+    run.clock = Clock::new(args.hz);
+    run.run().map_err(|err| format!("Terminal error: {err}"))?;
+    // Leaving with Ctrl+C is not a failure.
+    Ok(match (run.vm.halted, run.vm.exit_status) {
+        (false, _) => 0,
+        (true, status) => status.map_or(1, i32::from),
+    })
+}
+
+/// Run `args` without a window, returning the process exit code.
+fn headless(args: RunArgs, seed: Option<u16>) -> Result<i32, String> {
+    if args.serial == Some(SerialSpec::Stdio) {
+        return Err("--serial stdio cannot be used with --headless".to_string());
+    }
+    let mut vm = machine(&args)?;
+    let disks = boot(&mut vm, &args, seed)?;
+    let mut headless = Headless::new(vm);
+    headless.disks = disks;
+    headless.serial = args.serial.as_ref().map(Serial::open).transpose()?;
+    headless.wav = args.wav;
+    headless.max_cycles = args.max_cycles;
+    headless.timeout = args.timeout.map(Duration::from_secs);
+
+    let stop = headless
+        .run(io::stdin(), &mut io::stdout())
+        .map_err(|err| format!("Failed to write output: {err}"))?;
+    match stop {
+        Stop::CycleLimit => eprintln!("\nCycle limit reached after {} cycles", headless.vm.cycles),
+        Stop::TimeLimit => eprintln!("\nTime limit reached"),
+        Stop::Halted(_) => {}
+    }
+    if let Some(path) = args.dump_screen {
+        let screen = headless.screen();
+        if path.as_os_str() == "-" {
+            print!("{screen}");
+        } else {
+            std::fs::write(&path, screen)
+                .map_err(|err| format!("Failed to write {}: {err}", path.display()))?;
+        }
+    }
+    Ok(stop.exit_code())
+}
+
+/// Run the end-to-end scripts and report each one. Returns whether all passed.
+fn test(scripts: &[PathBuf]) -> bool {
+    let mut failed = 0;
+    for script in scripts {
+        match run_script(script) {
+            Ok(()) => println!("ok   {}", script.display()),
+            Err(err) => {
+                println!("FAIL {err}");
+                failed += 1;
+            }
+        }
+    }
+    println!("{} passed, {failed} failed", scripts.len() - failed);
+    failed == 0
+}
+
+fn benchmark(roms: &[PathBuf], instructions: u64) -> Result<(), String> {
+    for path in roms {
+        let rom = load_rom(path)?;
+        println!("{}: {}", path.display(), bench(&rom, instructions));
+    }
+    Ok(())
+}
+
+/// Run a `disk` subcommand.
+fn disk(command: DiskCommand) -> Result<(), String> {
+    match command {
+        DiskCommand::Mkfs {
+            image,
+            files,
+            version,
+            blocks,
+            force,
         } => {
-            let vm = vm::VirtualMachine::default();
-            let tty = Tty::new(TTY_COLS as usize, TTY_ROWS as usize, 1024);
-            let bitmap = Bitmap::new(BITMAP_WIDTH, BITMAP_HEIGHT);
-            let debugcli = Debug::new();
-            let mut vm_desk = match vmrun::VmRun::new(vm, tty, bitmap, debugcli) {
-                Ok(v) => v,
-                Err(e) => {
-                    eprintln!("Failed to init VM: {e}");
-                    return;
-                }
+            let version = if version == 2 {
+                Version::V2
+            } else {
+                Version::V1
             };
-            if debug {
-                vm_desk.debug_enabled = true;
+            disktool::mkfs(&image, &files, version, blocks, force)
+        }
+        DiskCommand::Ls { image } => disktool::ls(&image).map(|table| print!("{table}")),
+        DiskCommand::Put { image, files, name } => {
+            for entry in disktool::put(&image, &files, name.as_deref())? {
+                println!(
+                    "{}: {} blocks from block {}",
+                    entry.name, entry.blocks, entry.start
+                );
+            }
+            Ok(())
+        }
+        DiskCommand::Get {
+            image,
+            name,
+            output,
+        } => {
+            let output = output.unwrap_or_else(|| PathBuf::from(&name));
+            disktool::get(&image, &name, &output)
+        }
+        DiskCommand::Rm { image, names } => disktool::rm(&image, &names),
+        DiskCommand::Migrate { image, output } => {
+            disktool::migrate(&image, output.as_ref().unwrap_or(&image))
+        }
+        DiskCommand::Check { image } => {
+            let problems = disktool::check(&image)?;
+            for problem in &problems {
+                println!("{problem}");
+            }
+            if problems.is_empty() {
+                println!("{}: ok", image.display());
+                Ok(())
+            } else {
+                Err(format!("{}: {} problems", image.display(), problems.len()))
             }
-            vm_desk.run_desktop(kernel, user, cli.seed);
         }
+    }
+}
+
+fn main() {
+    let cli = config::Cli::parse();
+
+    match cli.command {
+        config::Commands::Run(args) if args.headless => match headless(*args, cli.seed) {
+            Ok(code) => std::process::exit(code),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        },
+        config::Commands::Run(args) if args.terminal => match terminal(*args, cli.seed) {
+            Ok(code) => std::process::exit(code),
+            Err(err) => {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        },
+        config::Commands::Run(args) => match desktop(&args) {
+            Ok(mut vm_desk) => vm_desk.run_desktop(&args.kernel, args.user, cli.seed),
+            Err(err) => eprintln!("{err}"),
+        },
         config::Commands::Compile { source } => {
             let code = match std::fs::read_to_string(source) {
                 Ok(code) => code,
@@ -47,5 +285,67 @@ fn main() {
                 }
             }
         }
+        config::Commands::Asm {
+            source,
+            output,
+            object,
+            symbols,
+            exec,
+            entry,
+        } => {
+            let exec = exec.then_some(entry.as_deref());
+            if !run_asm(&source, output.as_deref(), object, symbols.as_deref(), exec) {
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Link {
+            objects,
+            output,
+            script,
+            symbols,
+            exec,
+            entry,
+        } => {
+            let exec = exec.then_some(entry.as_deref());
+            if !run_link(
+                &objects,
+                &output,
+                script.as_deref(),
+                symbols.as_deref(),
+                exec,
+            ) {
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Disasm {
+            binary,
+            base,
+            start,
+            labels,
+            aliases,
+            symbols,
+        } => {
+            if !run_disasm(&binary, base, start, labels, aliases, &symbols) {
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Test { scripts } => {
+            if !test(&scripts) {
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Bench { roms, instructions } => {
+            if let Err(err) = benchmark(&roms, instructions) {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Disk { command } => {
+            if let Err(err) = disk(command) {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+        config::Commands::Isa => print!("{}", mb8_isa::table::ruledef()),
     }
 }
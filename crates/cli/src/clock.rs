@@ -0,0 +1,176 @@
+use std::time::{Duration, Instant};
+
+/// Longest the emulated CPU may fall behind host time. A slower host drops the
+/// rest instead of running in bursts to catch up.
+const MAX_LAG: Duration = Duration::from_millis(100);
+/// Cycles run per call in turbo mode, between checks of the window.
+const TURBO_CYCLES: u64 = 100_000;
+/// Host time the effective speed is averaged over.
+const MEASURE_INTERVAL: Duration = Duration::from_millis(500);
+/// Length of the frame `advance` runs, as the runners draw at about 60 Hz.
+const FRAME: Duration = Duration::from_millis(16);
+
+/// Paces the emulated CPU at a clock frequency against host time, and measures
+/// the speed it actually reaches.
+#[derive(Debug)]
+pub struct Clock {
+    /// Emulated CPU frequency in Hz.
+    hz: u64,
+    /// Run as fast as the host allows.
+    turbo: bool,
+    paused: bool,
+    /// Cycles `budget` grants once while paused.
+    advance: u64,
+    /// Host time and cycle count the pacing is measured from.
+    anchor: Option<(Instant, u64)>,
+    /// Start of the current measurement.
+    sample: Option<(Instant, u64)>,
+    /// Cycles per host second over the last measurement.
+    speed: Option<u64>,
+}
+
+impl Clock {
+    #[must_use]
+    pub fn new(hz: u64) -> Self {
+        Self {
+            hz: hz.max(1),
+            turbo: false,
+            paused: false,
+            advance: 0,
+            anchor: None,
+            sample: None,
+            speed: None,
+        }
+    }
+
+    #[must_use]
+    pub fn hz(&self) -> u64 {
+        self.hz
+    }
+
+    #[must_use]
+    pub fn turbo(&self) -> bool {
+        self.turbo
+    }
+
+    #[must_use]
+    pub fn paused(&self) -> bool {
+        self.paused
+    }
+
+    pub fn set_turbo(&mut self, turbo: bool) {
+        self.turbo = turbo;
+        self.resync();
+    }
+
+    pub fn set_paused(&mut self, paused: bool) {
+        self.paused = paused;
+        self.resync();
+    }
+
+    /// Run one more frame of cycles while paused.
+    pub fn advance(&mut self) {
+        if self.paused {
+            self.advance += self.frame_cycles();
+        }
+    }
+
+    /// Cycles the CPU should run now to keep up with host time, given that it has
+    /// run `cycles` so far.
+    pub fn budget(&mut self, cycles: u64, now: Instant) -> u64 {
+        if self.paused {
+            return std::mem::take(&mut self.advance);
+        }
+        if self.turbo {
+            return TURBO_CYCLES;
+        }
+        let (start, start_cycles) = *self.anchor.get_or_insert((now, cycles));
+        let due = start_cycles + cycles_in(now.duration_since(start), self.hz);
+        let lag = due.saturating_sub(cycles);
+        let max_lag = cycles_in(MAX_LAG, self.hz);
+        if lag > max_lag {
+            self.anchor = Some((now, cycles + max_lag));
+            return max_lag;
+        }
+        lag
+    }
+
+    /// Host time until the CPU is due to run again, for the runner to sleep.
+    #[must_use]
+    pub fn idle(&self) -> Duration {
+        if self.paused && self.advance == 0 {
+            FRAME
+        } else if self.turbo {
+            Duration::ZERO
+        } else {
+            Duration::from_millis(1)
+        }
+    }
+
+    /// Count the cycles run so far towards the effective speed.
+    pub fn measure(&mut self, cycles: u64, now: Instant) {
+        let (start, start_cycles) = *self.sample.get_or_insert((now, cycles));
+        let elapsed = now.duration_since(start);
+        if elapsed >= MEASURE_INTERVAL {
+            let run = u128::from(cycles.saturating_sub(start_cycles));
+            let speed = run * 1_000_000 / elapsed.as_micros().max(1);
+            self.speed = Some(u64::try_from(speed).unwrap_or(u64::MAX));
+            self.sample = Some((now, cycles));
+        }
+    }
+
+    /// Cycles per host second over the last measurement, once there is one.
+    #[must_use]
+    pub fn speed(&self) -> Option<u64> {
+        self.speed
+    }
+
+    /// Short description of the state for a status line, such as
+    /// `1.00 MHz (100%)`, `turbo 42.10 MHz` or `paused`.
+    #[must_use]
+    pub fn status(&self, halted: bool) -> String {
+        if halted {
+            return "halted".to_string();
+        }
+        if self.paused {
+            return "paused".to_string();
+        }
+        let speed = self.speed.map_or("-".to_string(), format_hz);
+        if self.turbo {
+            format!("turbo {speed}")
+        } else {
+            let percent = self
+                .speed
+                .map_or(0, |speed| u128::from(speed) * 100 / u128::from(self.hz));
+            format!("{speed} ({percent}%)")
+        }
+    }
+
+    fn frame_cycles(&self) -> u64 {
+        cycles_in(FRAME, self.hz).max(1)
+    }
+
+    /// Start pacing afresh, so time spent paused or in turbo is not made up.
+    fn resync(&mut self) {
+        self.anchor = None;
+        self.sample = None;
+        self.speed = None;
+    }
+}
+
+/// `hz` as `1.00 MHz`, `500.00 kHz` or `100 Hz`.
+#[must_use]
+pub fn format_hz(hz: u64) -> String {
+    if hz >= 1_000_000 {
+        format!("{}.{:02} MHz", hz / 1_000_000, hz % 1_000_000 / 10_000)
+    } else if hz >= 1_000 {
+        format!("{}.{:02} kHz", hz / 1_000, hz % 1_000 / 10)
+    } else {
+        format!("{hz} Hz")
+    }
+}
+
+fn cycles_in(duration: Duration, hz: u64) -> u64 {
+    let cycles = duration.as_nanos() * u128::from(hz) / 1_000_000_000;
+    u64::try_from(cycles).unwrap_or(u64::MAX)
+}
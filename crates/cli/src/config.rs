@@ -1,6 +1,13 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use clap::{Args, Parser, Subcommand};
+use mb8_isa::CPU_FREQUENCY;
+
+use crate::{
+    bench::DEFAULT_INSTRUCTIONS,
+    diskimage::{BadBlock, DiskMode},
+    serial::SerialSpec,
+};
 
 #[derive(Parser, Debug)]
 #[command(name = "mb8", version, about = "MB8 VM")]
@@ -13,20 +20,299 @@ pub struct Cli {
 #[derive(Subcommand, Debug)]
 pub enum Commands {
     /// Run an executable file for the VM
-    Run {
-        /// Path to the executable file
-        kernel: PathBuf,
-
-        /// Path to the user spaace
-        user: Vec<PathBuf>,
-
-        /// debug variable
-        #[arg(long)]
-        debug: bool,
-    },
+    Run(Box<RunArgs>),
     /// Compile a source file to an executable file
     Compile {
         /// Path to the source file
         source: PathBuf,
     },
+    /// Assemble a source file to a binary image
+    Asm {
+        /// Path to the assembly source file
+        source: PathBuf,
+
+        /// Path to the output binary (defaults to the source path with a `.bin` extension)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+
+        /// Write a relocatable object (`.o`) for the `link` command instead of a binary image
+        #[arg(long)]
+        object: bool,
+
+        /// Write the label and line table of the image to this file
+        #[arg(long, conflicts_with = "object")]
+        symbols: Option<PathBuf>,
+
+        /// Write an MB8 executable (header for `SYS_EXEC`, loaded at 0x1000)
+        #[arg(long, conflicts_with = "object")]
+        exec: bool,
+
+        /// Label the executable starts at (defaults to the load address)
+        #[arg(long, requires = "exec")]
+        entry: Option<String>,
+    },
+    /// Link relocatable objects into a binary image
+    Link {
+        /// Paths to the object files
+        #[arg(required = true)]
+        objects: Vec<PathBuf>,
+
+        /// Path to the output binary
+        #[arg(short, long)]
+        output: PathBuf,
+
+        /// Link script describing memory regions (defaults to user RAM at 0x1000)
+        #[arg(long)]
+        script: Option<PathBuf>,
+
+        /// Write the final address of every label to this file
+        #[arg(long)]
+        symbols: Option<PathBuf>,
+
+        /// Write an MB8 executable loaded at the origin of the script's only region
+        #[arg(long)]
+        exec: bool,
+
+        /// Label the executable starts at (defaults to the load address)
+        #[arg(long, requires = "exec")]
+        entry: Option<String>,
+    },
+    /// Disassemble a binary image
+    Disasm {
+        /// Path to the binary image
+        binary: PathBuf,
+
+        /// Address the image is loaded at
+        #[arg(long, default_value = "0x1000", value_parser = parse_u16)]
+        base: u16,
+
+        /// Address to start disassembling from (defaults to the base address)
+        #[arg(long, value_parser = parse_u16)]
+        start: Option<u16>,
+
+        /// Emit labels for jump and call targets
+        #[arg(long)]
+        labels: bool,
+
+        /// Render registers with their aliases (A, IH, IL, ...)
+        #[arg(long)]
+        aliases: bool,
+
+        /// Symbol files whose labels replace the generated `L_XXXX` names (repeatable)
+        #[arg(long)]
+        symbols: Vec<PathBuf>,
+    },
+    /// Run end-to-end test scripts against the kernel (see `tests/e2e`)
+    Test {
+        /// Paths to the script files
+        #[arg(required = true)]
+        scripts: Vec<PathBuf>,
+    },
+    /// Measure emulator speed on ROM images (`.bin`, or `.asm` sources assembled first)
+    Bench {
+        /// Paths to the ROM images, such as `bench/*.asm`
+        #[arg(required = true)]
+        roms: Vec<PathBuf>,
+
+        /// Stop each run after this many instructions if it has not halted
+        #[arg(long, default_value_t = DEFAULT_INSTRUCTIONS)]
+        instructions: u64,
+    },
+    /// Create, inspect and change disk images for `run --disk`
+    Disk {
+        #[command(subcommand)]
+        command: DiskCommand,
+    },
+    /// Print the customasm rules generated from the ISA table (`asm/isa.asm`)
+    Isa,
+}
+
+/// Subcommands of the `disk` command.
+#[derive(Subcommand, Debug)]
+pub enum DiskCommand {
+    /// Create an empty formatted image, optionally with files on it
+    Mkfs {
+        /// Path to the image
+        image: PathBuf,
+
+        /// Host files to add, named after their file stem (`.bin` images get an executable header)
+        files: Vec<PathBuf>,
+
+        /// File system layout: 1, which the ROM kernel reads, or 2 (see `docs/filesystem.md`)
+        #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u8).range(1..=2))]
+        version: u8,
+
+        /// Size of the image in 256-byte blocks, up to 65536 (version 1 only uses the first 256)
+        #[arg(long, default_value_t = 256)]
+        blocks: usize,
+
+        /// Overwrite an existing image
+        #[arg(long)]
+        force: bool,
+    },
+    /// List the directory: entry index, start block, block count, size and name
+    Ls {
+        /// Path to the image
+        image: PathBuf,
+    },
+    /// Add host files, named after their file stem (`.bin` images get an executable header)
+    Put {
+        /// Path to the image
+        image: PathBuf,
+
+        /// Host files to add
+        #[arg(required = true)]
+        files: Vec<PathBuf>,
+
+        /// Name to store a single file under
+        #[arg(long)]
+        name: Option<String>,
+    },
+    /// Extract a file (whole blocks on version 1 disks, which have no byte sizes)
+    Get {
+        /// Path to the image
+        image: PathBuf,
+
+        /// Name of the file on the disk
+        name: String,
+
+        /// Host file to write (defaults to the name in the working directory)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+    /// Delete files
+    Rm {
+        /// Path to the image
+        image: PathBuf,
+
+        /// Names of the files on the disk
+        #[arg(required = true)]
+        names: Vec<String>,
+    },
+    /// Verify the directory and block allocation
+    Check {
+        /// Path to the image
+        image: PathBuf,
+    },
+    /// Convert a version 1 image to version 2
+    Migrate {
+        /// Path to the image
+        image: PathBuf,
+
+        /// Path to write the converted image to (defaults to converting in place)
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+    },
+}
+
+/// Options of the `run` command.
+#[derive(Args, Debug)]
+#[allow(clippy::struct_excessive_bools)]
+pub struct RunArgs {
+    /// Path to the executable file
+    pub kernel: PathBuf,
+
+    /// Path to the user spaace
+    pub user: Vec<PathBuf>,
+
+    /// debug variable
+    #[arg(long)]
+    pub debug: bool,
+
+    /// Print every executed instruction, its address and the registers to stdout
+    #[arg(long)]
+    pub trace: bool,
+
+    /// Symbol files used to name addresses in the debugger and traces (repeatable)
+    #[arg(long)]
+    pub symbols: Vec<PathBuf>,
+
+    /// Cartridge image whose 8 KiB pages can be switched into 0xC000..=0xDFFF
+    #[arg(long)]
+    pub cartridge: Option<PathBuf>,
+
+    /// Fault when user programs write kernel memory or touch MMIO directly
+    #[arg(long)]
+    pub protect: bool,
+
+    /// Connect the UART to `stdio`, `tcp:HOST:PORT` or `pty:PATH`
+    #[arg(long)]
+    pub serial: Option<SerialSpec>,
+
+    /// Fix the real-time clock at this many seconds since the Unix epoch instead of host time
+    #[arg(long)]
+    pub rtc_epoch: Option<u64>,
+
+    /// Record the sound generator and write it to this WAV file when the VM stops
+    #[arg(long)]
+    pub wav: Option<PathBuf>,
+
+    /// Disk image file to run from (drive 0), created from the user files if missing
+    #[arg(long)]
+    pub disk: Option<PathBuf>,
+
+    /// Disk image files for drives 1 to 3, blank 64 KiB images if missing (repeatable)
+    #[arg(long = "drive", value_name = "PATH")]
+    pub drives: Vec<PathBuf>,
+
+    /// What happens to disk writes: `rw` saves them to the image on exit or flush,
+    /// `ro` ignores them, `overlay` drops them when the VM stops
+    #[arg(long, default_value_t = DiskMode::ReadWrite)]
+    pub disk_mode: DiskMode,
+
+    /// CPU cycles a disk read or write waits when it moves to another block
+    #[arg(long, default_value_t = 0, value_name = "CYCLES")]
+    pub disk_seek: u64,
+
+    /// CPU cycles every disk read or write takes
+    #[arg(long, default_value_t = 0, value_name = "CYCLES")]
+    pub disk_transfer: u64,
+
+    /// Make reads and writes of `[DRIVE:]BLOCK` fail, to test disk error handling (repeatable)
+    #[arg(long = "bad-block", value_name = "[DRIVE:]BLOCK")]
+    pub bad_blocks: Vec<BadBlock>,
+
+    /// Host directory whose files the guest can read and write through the hostfs device
+    #[arg(long, value_name = "DIR")]
+    pub hostfs: Option<PathBuf>,
+
+    /// Emulated CPU clock in Hz for the window and terminal (F7 in the window runs unthrottled)
+    #[arg(long, default_value_t = CPU_FREQUENCY, value_parser = clap::value_parser!(u64).range(1..), conflicts_with = "headless")]
+    pub hz: u64,
+
+    /// Run without a window: print the TTY to stdout and read keys from stdin
+    #[arg(long)]
+    pub headless: bool,
+
+    /// Run in the host terminal, drawing the screen with ANSI escape codes (Ctrl+C quits)
+    #[arg(long, conflicts_with = "headless")]
+    pub terminal: bool,
+
+    /// Stop a headless run after this many CPU cycles
+    #[arg(long, requires = "headless")]
+    pub max_cycles: Option<u64>,
+
+    /// Stop a headless run after this many seconds
+    #[arg(long, requires = "headless")]
+    pub timeout: Option<u64>,
+
+    /// Write the final TTY screen of a headless run to this file (`-` for stdout)
+    #[arg(long, requires = "headless")]
+    pub dump_screen: Option<PathBuf>,
+}
+
+/// Parse a 16-bit value written in hex (`0xE000`) or decimal.
+///
+/// # Errors
+///
+/// Returns an error message if the value is not a valid 16-bit number.
+pub fn parse_u16(value: &str) -> Result<u16, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u16::from_str_radix(hex, 16),
+        None => value.parse(),
+    };
+    parsed.map_err(|err| format!("invalid 16-bit value '{value}': {err}"))
 }
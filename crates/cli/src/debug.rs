@@ -1,22 +1,32 @@
 //write a debug helper that can be used by both architectures.
 use mb8::vm::VirtualMachine;
-use mb8_isa::registers::Register;
+use mb8_isa::{registers::Register, symbols::SymbolMap, STACK_TOP};
 use minifb::Key;
+use std::collections::BTreeSet;
 use std::io::{self, Write};
 
+/// High byte of the `CALL [hi:lo]` instruction word.
+const CALL_OPCODE: u8 = 0x40;
+
 #[derive(Debug)]
 pub enum DebugCmd {
     Step,
     Continue,
     Registers,
     Memory(Option<String>),
+    Break(Option<String>),
+    Backtrace,
     Help,
     Invalid,
     None,
 }
 
 #[derive(Debug)]
-pub struct Debug {}
+pub struct Debug {
+    /// Names printed next to raw addresses.
+    pub symbols: SymbolMap,
+    pub breakpoints: BTreeSet<u16>,
+}
 
 impl Default for Debug {
     fn default() -> Self {
@@ -27,7 +37,28 @@ impl Default for Debug {
 impl Debug {
     #[must_use]
     pub fn new() -> Debug {
-        Self {}
+        Self::with_symbols(SymbolMap::new())
+    }
+
+    #[must_use]
+    pub fn with_symbols(symbols: SymbolMap) -> Debug {
+        Self {
+            symbols,
+            breakpoints: BTreeSet::new(),
+        }
+    }
+
+    /// `addr` followed by its symbol and source line, e.g. `E6B6 sys_fs_find+0x12 (kernel/fs.asm:120)`.
+    #[must_use]
+    pub fn describe(&self, addr: u16) -> String {
+        let name = match self.symbols.lookup(addr) {
+            Some(_) => format!(" {}", self.symbols.describe(addr)),
+            None => String::new(),
+        };
+        match self.symbols.source(addr) {
+            Some(source) => format!("{addr:04X}{name} ({source})"),
+            None => format!("{addr:04X}{name}"),
+        }
     }
 
     pub fn poll_command(&mut self, window: &minifb::Window) -> Option<DebugCmd> {
@@ -49,6 +80,7 @@ impl Debug {
     pub fn print_registers(&mut self, vm: &mut VirtualMachine) {
         let r = &vm.registers;
         println!("=== CURRENT STEP REGISTERS ===");
+        println!("PC:  {}", self.describe(vm.program_counter));
         println!("R0:  {:02X}", r.read(Register::R0));
         println!("R1:  {:02X}", r.read(Register::R1));
         println!("R2:  {:02X}", r.read(Register::R2));
@@ -75,6 +107,8 @@ impl Debug {
         println!("  c  - Continue Execution");
         println!("  r  - Print Registers");
         println!("  m  - Print Memory");
+        println!("  b  - Toggle Breakpoint (b <addr|label>, list without argument)");
+        println!("  bt - Print Backtrace");
         println!("  h  - Help");
         println!(" ");
     }
@@ -116,6 +150,8 @@ impl Debug {
                 let arg = parts.next().map(ToString::to_string);
                 DebugCmd::Memory(arg)
             }
+            "b" => DebugCmd::Break(parts.next().map(ToString::to_string)),
+            "bt" => DebugCmd::Backtrace,
             "h" => DebugCmd::Help,
             _ => DebugCmd::Invalid,
         }
@@ -134,8 +170,22 @@ impl Debug {
             Key::M => b'm',
             Key::S => b's',
             Key::R => b'r',
+            Key::G => b'g',
             Key::H => b'h',
+            Key::I => b'i',
+            Key::J => b'j',
+            Key::K => b'k',
+            Key::L => b'l',
+            Key::O => b'o',
+            Key::P => b'p',
+            Key::Q => b'q',
+            Key::T => b't',
+            Key::U => b'u',
+            Key::V => b'v',
+            Key::W => b'w',
             Key::X => b'x',
+            Key::Y => b'y',
+            Key::Z => b'z',
 
             Key::Key0 => b'0',
             Key::Key1 => b'1',
@@ -150,6 +200,7 @@ impl Debug {
 
             Key::Space => b' ',
             Key::Minus => b'-',
+            Key::Period => b'.',
 
             Key::Enter => b'\n',
             Key::Backspace => 0x08,
@@ -274,4 +325,49 @@ impl Debug {
 
         let _ = io::stdout().flush();
     }
+
+    /// Toggle a breakpoint at a label or hex address, or list them without an argument.
+    pub fn toggle_breakpoint(&mut self, arg: Option<&str>) {
+        let Some(arg) = arg else {
+            for addr in &self.breakpoints {
+                println!("{}", self.describe(*addr));
+            }
+            return;
+        };
+        let Some(addr) = self.symbols.resolve(arg) else {
+            println!("Unknown address or symbol: {arg}");
+            return;
+        };
+        if self.breakpoints.remove(&addr) {
+            println!("Breakpoint removed at {}", self.describe(addr));
+        } else {
+            self.breakpoints.insert(addr);
+            println!("Breakpoint set at {}", self.describe(addr));
+        }
+    }
+
+    /// Print the current location followed by the callers found on the stack.
+    ///
+    /// The stack holds no frame pointers, so every stacked word that points just
+    /// past a `CALL` instruction is taken to be a return address.
+    pub fn print_backtrace(&mut self, vm: &mut VirtualMachine) {
+        println!("#0  {}", self.describe(vm.program_counter));
+        let sp = u16::from_be_bytes([
+            vm.registers.read(Register::SPH),
+            vm.registers.read(Register::SPL),
+        ]);
+        let mut frame = 1;
+        let mut addr = sp.saturating_add(1);
+        while usize::from(addr) < STACK_TOP {
+            let ret = u16::from_be_bytes([vm.devices.read(addr), vm.devices.read(addr + 1)]);
+            let call = ret.wrapping_sub(2);
+            if ret >= 2 && vm.devices.read(call) == CALL_OPCODE {
+                println!("#{frame:<2} {}", self.describe(call));
+                frame += 1;
+                addr += 2;
+            } else {
+                addr += 1;
+            }
+        }
+    }
 }
@@ -0,0 +1,40 @@
+use std::path::{Path, PathBuf};
+
+use mb8_isa::disasm::Disassembler;
+
+use crate::symbols::load_symbols;
+
+/// Print the disassembly of `binary`. Returns `false` if it or the symbol
+/// files could not be read.
+#[must_use]
+pub fn run_disasm(
+    binary: &Path,
+    base: u16,
+    start: Option<u16>,
+    labels: bool,
+    aliases: bool,
+    symbols: &[PathBuf],
+) -> bool {
+    let data = match std::fs::read(binary) {
+        Ok(data) => data,
+        Err(err) => {
+            eprintln!("Failed to read binary file: {err}");
+            return false;
+        }
+    };
+
+    let symbols = match load_symbols(symbols) {
+        Ok(symbols) => symbols,
+        Err(err) => {
+            eprintln!("{err}");
+            return false;
+        }
+    };
+
+    let disassembler = Disassembler::new(base)
+        .with_labels(labels)
+        .with_aliases(aliases)
+        .with_symbols(symbols);
+    print!("{}", disassembler.disassemble(&data, start.unwrap_or(base)));
+    true
+}
@@ -0,0 +1,196 @@
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+
+use mb8::{
+    dev::disk::{BLOCK_SIZE, DISK_SIZE, DRIVES, MAX_BLOCKS, MAX_DISK_SIZE},
+    vm::VirtualMachine,
+};
+
+/// Size of a new disk image file.
+pub const IMAGE_SIZE: usize = DISK_SIZE;
+
+/// What happens to blocks the guest writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DiskMode {
+    /// Writes are saved back to the image file.
+    #[default]
+    ReadWrite,
+    /// Writes are ignored by the disk controller.
+    ReadOnly,
+    /// Writes are kept in memory and dropped when the VM stops.
+    Overlay,
+}
+
+impl FromStr for DiskMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value {
+            "rw" => Ok(Self::ReadWrite),
+            "ro" => Ok(Self::ReadOnly),
+            "overlay" => Ok(Self::Overlay),
+            _ => Err(format!(
+                "invalid disk mode '{value}', expected rw, ro or overlay"
+            )),
+        }
+    }
+}
+
+impl fmt::Display for DiskMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::ReadWrite => "rw",
+            Self::ReadOnly => "ro",
+            Self::Overlay => "overlay",
+        })
+    }
+}
+
+/// A block that fails reads and writes: `BLOCK` on drive 0 or `DRIVE:BLOCK`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BadBlock {
+    pub drive: usize,
+    pub block: usize,
+}
+
+impl FromStr for BadBlock {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let (drive, block) = value.split_once(':').unwrap_or(("0", value));
+        let drive = drive
+            .parse()
+            .ok()
+            .filter(|&drive| drive < DRIVES)
+            .ok_or(format!(
+                "invalid drive '{drive}', expected 0 to {}",
+                DRIVES - 1
+            ))?;
+        let block = block
+            .parse()
+            .ok()
+            .filter(|&block| block < MAX_BLOCKS)
+            .ok_or(format!(
+                "invalid block '{block}', expected 0 to {}",
+                MAX_BLOCKS - 1
+            ))?;
+        Ok(Self { drive, block })
+    }
+}
+
+impl fmt::Display for BadBlock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}:{}", self.drive, self.block)
+    }
+}
+
+/// A disk image file backing a drive of the disk controller.
+#[derive(Debug, Clone)]
+pub struct DiskImage {
+    pub path: PathBuf,
+    pub mode: DiskMode,
+    /// Drive the image is in.
+    pub drive: usize,
+}
+
+impl DiskImage {
+    /// The image at `path` in drive 0.
+    #[must_use]
+    pub fn new(path: PathBuf, mode: DiskMode) -> Self {
+        Self {
+            path,
+            mode,
+            drive: 0,
+        }
+    }
+
+    /// Put the image in its drive. In read-write mode a missing file is created
+    /// from the drive as it is, so the files `boot` put on drive 0 are kept; an
+    /// empty drive gets a blank [`IMAGE_SIZE`] image.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the file cannot be read or written, or is not a
+    /// valid image, see [`read_image`].
+    pub fn load(&self, vm: &mut VirtualMachine) -> Result<(), String> {
+        let drive = vm.devices.disk().drive(self.drive);
+        drive.write_protected = self.mode == DiskMode::ReadOnly;
+        if self.mode == DiskMode::ReadWrite && !self.path.exists() {
+            if drive.dump().is_empty() {
+                drive.set(vec![0; IMAGE_SIZE]);
+            }
+            return write_image(&self.path, drive.dump());
+        }
+
+        drive.set(read_image(&self.path)?);
+        Ok(())
+    }
+
+    /// Save the disk to the file if it is read-write and was written to.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the file cannot be written.
+    pub fn save(&self, vm: &mut VirtualMachine) -> Result<(), String> {
+        let drive = vm.devices.disk().drive(self.drive);
+        if self.mode != DiskMode::ReadWrite || !drive.is_dirty() {
+            return Ok(());
+        }
+        write_image(&self.path, drive.dump())?;
+        drive.mark_clean();
+        Ok(())
+    }
+}
+
+/// Save the `disks` if the guest asked for a flush. Errors are printed.
+pub fn sync(disks: &[DiskImage], vm: &mut VirtualMachine) {
+    if vm.devices.disk().take_flush() {
+        flush(disks, vm);
+    }
+}
+
+/// Save the `disks`, as when the VM stops. Errors are printed.
+pub fn flush(disks: &[DiskImage], vm: &mut VirtualMachine) {
+    for disk in disks {
+        if let Err(err) = disk.save(vm) {
+            eprintln!("{err}");
+        }
+    }
+}
+
+/// Read the disk image file at `path`.
+///
+/// # Errors
+///
+/// Returns a message if the file cannot be read, or its size is not a
+/// non-zero multiple of [`BLOCK_SIZE`] up to [`MAX_DISK_SIZE`] bytes.
+pub fn read_image(path: &Path) -> Result<Vec<u8>, String> {
+    let data = std::fs::read(path)
+        .map_err(|err| format!("Failed to read disk {}: {err}", path.display()))?;
+    if data.is_empty() || data.len() % BLOCK_SIZE != 0 || data.len() > MAX_DISK_SIZE {
+        return Err(format!(
+            "Disk {} is {} bytes, expected a multiple of {BLOCK_SIZE} up to {MAX_DISK_SIZE}",
+            path.display(),
+            data.len()
+        ));
+    }
+    Ok(data)
+}
+
+/// Write a disk image file through a temporary file, so a crash never leaves
+/// half an image.
+///
+/// # Errors
+///
+/// Returns a message if the file cannot be written.
+pub fn write_image(path: &Path, data: &[u8]) -> Result<(), String> {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".tmp");
+    let tmp = path.with_file_name(name);
+    std::fs::write(&tmp, data)
+        .and_then(|()| std::fs::rename(&tmp, path))
+        .map_err(|err| format!("Failed to write disk {}: {err}", path.display()))
+}
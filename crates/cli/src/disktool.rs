@@ -0,0 +1,140 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    diskimage::{read_image, write_image},
+    filesystem::{add_host_file, host_file, Entry, FileSystem, Version},
+};
+
+fn open(image: &Path) -> Result<FileSystem, String> {
+    read_image(image).map(FileSystem::from_image)
+}
+
+/// Create an empty image of `blocks` blocks formatted with `version`, with
+/// the host `files` on it.
+///
+/// # Errors
+///
+/// Returns a message if the image exists and `force` is not set, the size is
+/// invalid, or a file cannot be stored.
+pub fn mkfs(
+    image: &Path,
+    files: &[PathBuf],
+    version: Version,
+    blocks: usize,
+    force: bool,
+) -> Result<(), String> {
+    if image.exists() && !force {
+        return Err(format!(
+            "{} already exists, use --force to overwrite it",
+            image.display()
+        ));
+    }
+    let mut fs = FileSystem::with_blocks(version, blocks)?;
+    for path in files {
+        add_host_file(&mut fs, path).map_err(|err| format!("{}: {err}", path.display()))?;
+    }
+    write_image(image, fs.image())
+}
+
+/// The directory of `image` as a table, with the free space below it.
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read.
+pub fn ls(image: &Path) -> Result<String, String> {
+    let fs = open(image)?;
+    let entries = fs.entries();
+    let mut out = String::from("index  start  blocks  bytes  name\n");
+    for entry in &entries {
+        let _ = writeln!(
+            out,
+            "{:>5}  {:>5}  {:>6}  {:>5}  {}",
+            entry.index, entry.start, entry.blocks, entry.size, entry.name
+        );
+    }
+    let version = match fs.version() {
+        Version::V1 => 1,
+        Version::V2 => 2,
+    };
+    let _ = writeln!(
+        out,
+        "{} files, {} blocks free, version {version}",
+        entries.len(),
+        fs.free_blocks(),
+    );
+    Ok(out)
+}
+
+/// Add the host `files` to `image`, the only file under `name` if given. The
+/// image is only written if every file fits.
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read or written, or a file cannot
+/// be stored.
+pub fn put(image: &Path, files: &[PathBuf], name: Option<&str>) -> Result<Vec<Entry>, String> {
+    if name.is_some() && files.len() != 1 {
+        return Err("--name needs exactly one file".to_string());
+    }
+    let mut fs = open(image)?;
+    let mut added = Vec::new();
+    for path in files {
+        let (stem, data) = host_file(path).map_err(|err| format!("{}: {err}", path.display()))?;
+        let entry = fs
+            .add(name.unwrap_or(&stem), &data)
+            .map_err(|err| format!("{}: {err}", path.display()))?;
+        added.push(entry);
+    }
+    write_image(image, fs.image())?;
+    Ok(added)
+}
+
+/// Extract the file `name` from `image` to `output`.
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read, there is no such file, or
+/// `output` cannot be written.
+pub fn get(image: &Path, name: &str, output: &Path) -> Result<(), String> {
+    let data = open(image)?.read(name)?;
+    std::fs::write(output, data)
+        .map_err(|err| format!("Failed to write {}: {err}", output.display()))
+}
+
+/// Delete the files `names` from `image`. The image is only written if every
+/// file exists.
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read or written, or a file does
+/// not exist.
+pub fn rm(image: &Path, names: &[String]) -> Result<(), String> {
+    let mut fs = open(image)?;
+    for name in names {
+        fs.remove(name)?;
+    }
+    write_image(image, fs.image())
+}
+
+/// Problems with the structure of `image`, see [`FileSystem::check`].
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read.
+pub fn check(image: &Path) -> Result<Vec<String>, String> {
+    Ok(open(image)?.check())
+}
+
+/// Convert the version 1 image `image` to version 2, writing it to `output`.
+///
+/// # Errors
+///
+/// Returns a message if the image cannot be read or written, or is not
+/// version 1.
+pub fn migrate(image: &Path, output: &Path) -> Result<(), String> {
+    let fs = open(image)?.migrate()?;
+    write_image(output, fs.image())
+}
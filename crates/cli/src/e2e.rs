@@ -0,0 +1,373 @@
+//! End-to-end tests against the real kernel: a [`Driver`] types on the keyboard
+//! and waits for the screen, and [`run_script`] drives it from a script file.
+//!
+//! A script has one command per line; `#` starts a comment:
+//!
+//! ```text
+//! kernel ../../kernel/main.bin       # ROM image
+//! disk ../../user/sh.bin ../../user/ls.bin
+//! budget 2000000                     # cycles `wait` and `halt` may take
+//! boot
+//! wait >                             # text or /regex/ on the screen
+//! type ls\n                          # escapes: \n \t \b \e \s \\
+//! wait /^ls$/
+//! expect sh
+//! run 10000                          # run a number of cycles
+//! bitmap 0x1234abcd5678ef00          # FNV-1a hash of the bitmap framebuffer
+//! halt 0                             # wait for HALT, optionally check R0
+//! ```
+//!
+//! Paths are relative to the script, except for the default kernel,
+//! `kernel/main.bin` in the working directory. Regexes match per line (`^`/`$`).
+
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
+
+use regex::Regex;
+
+use mb8::vm::VirtualMachine;
+
+use crate::{headless::screen_text, vmrun::boot};
+
+/// Cycles a wait may take unless the script sets a `budget`.
+pub const DEFAULT_BUDGET: u64 = 2_000_000;
+/// Steps between checks of the screen while waiting.
+const STEPS_PER_CHECK: u32 = 256;
+
+/// What the screen should show: a substring, or a regex that matches anywhere.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Text(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    /// Parse `/regex/` or plain text with escapes.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the regex or an escape is invalid.
+    pub fn parse(value: &str) -> Result<Self, String> {
+        match value
+            .strip_prefix('/')
+            .and_then(|rest| rest.strip_suffix('/'))
+        {
+            Some(regex) => Regex::new(&format!("(?m){regex}"))
+                .map(Self::Regex)
+                .map_err(|err| err.to_string()),
+            None => unescape(value).map(Self::Text),
+        }
+    }
+
+    #[must_use]
+    pub fn matches(&self, screen: &str) -> bool {
+        match self {
+            Self::Text(text) => screen.contains(text.as_str()),
+            Self::Regex(regex) => regex.is_match(screen),
+        }
+    }
+}
+
+impl fmt::Display for Pattern {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Text(text) => write!(f, "{text:?}"),
+            Self::Regex(regex) => {
+                let regex = regex.as_str();
+                write!(f, "/{}/", regex.strip_prefix("(?m)").unwrap_or(regex))
+            }
+        }
+    }
+}
+
+/// Expand `\n`, `\t`, `\b` (backspace), `\e` (escape), `\s` (space) and `\\`.
+///
+/// # Errors
+///
+/// Returns a message for any other escape.
+pub fn unescape(value: &str) -> Result<String, String> {
+    let mut text = String::new();
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            text.push(c);
+            continue;
+        }
+        text.push(match chars.next() {
+            Some('n') => '\n',
+            Some('t') => '\t',
+            Some('b') => '\x08',
+            Some('e') => '\x1b',
+            Some('s') => ' ',
+            Some('\\') => '\\',
+            other => return Err(format!("invalid escape '\\{}'", other.unwrap_or(' '))),
+        });
+    }
+    Ok(text)
+}
+
+/// FNV-1a hash of a framebuffer.
+#[must_use]
+pub fn hash(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Runs a VM and checks what it shows. Failures are messages that end with the
+/// current screen.
+#[derive(Debug)]
+pub struct Driver {
+    pub vm: VirtualMachine,
+    /// Cycles [`Driver::wait`] and [`Driver::wait_halt`] may take.
+    pub budget: u64,
+}
+
+impl Driver {
+    #[must_use]
+    pub fn new(vm: VirtualMachine) -> Self {
+        Self {
+            vm,
+            budget: DEFAULT_BUDGET,
+        }
+    }
+
+    /// Boot `kernel` with the `disk` files, as `run` does.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if a file cannot be read.
+    pub fn boot(kernel: &Path, disk: Vec<PathBuf>) -> Result<Self, String> {
+        if let Some(missing) = disk.iter().find(|path| !path.is_file()) {
+            return Err(format!("{} not found", missing.display()));
+        }
+        let mut vm = VirtualMachine::default();
+        boot(&mut vm, kernel, disk, None)?;
+        Ok(Self::new(vm))
+    }
+
+    /// The screen as text, see [`screen_text`].
+    pub fn screen(&mut self) -> String {
+        screen_text(self.vm.devices.gpu())
+    }
+
+    /// Queue `text` on the keyboard.
+    pub fn type_text(&mut self, text: &str) {
+        for byte in text.bytes() {
+            self.vm.devices.keyboard().key_pressed(byte);
+        }
+    }
+
+    /// Run `cycles` cycles, or until the machine halts.
+    pub fn run(&mut self, cycles: u64) {
+        let end = self.vm.cycles + cycles;
+        while !self.vm.halted && self.vm.cycles < end {
+            self.vm.step();
+        }
+    }
+
+    /// Run until the screen matches `pattern`, within the budget.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the budget runs out or the machine halts first.
+    pub fn wait(&mut self, pattern: &Pattern) -> Result<(), String> {
+        let end = self.vm.cycles + self.budget;
+        loop {
+            if pattern.matches(&self.screen()) {
+                return Ok(());
+            }
+            if self.vm.halted {
+                return Err(self.failure(&format!("halted while waiting for {pattern}")));
+            }
+            if self.vm.cycles >= end {
+                return Err(self.failure(&format!(
+                    "{pattern} not on screen after {} cycles",
+                    self.budget
+                )));
+            }
+            for _ in 0..STEPS_PER_CHECK {
+                if self.vm.halted || self.vm.cycles >= end {
+                    break;
+                }
+                self.vm.step();
+            }
+        }
+    }
+
+    /// Check that the screen matches `pattern` now.
+    ///
+    /// # Errors
+    ///
+    /// Fails if it does not.
+    pub fn expect(&mut self, pattern: &Pattern) -> Result<(), String> {
+        if pattern.matches(&self.screen()) {
+            Ok(())
+        } else {
+            Err(self.failure(&format!("{pattern} not on screen")))
+        }
+    }
+
+    /// Hash of the bitmap framebuffer, see [`hash`].
+    pub fn bitmap_hash(&mut self) -> u64 {
+        hash(self.vm.devices.gpu().bitmap_buffer())
+    }
+
+    /// Check the hash of the bitmap framebuffer.
+    ///
+    /// # Errors
+    ///
+    /// Fails with the actual hash if it differs.
+    pub fn expect_bitmap(&mut self, expected: u64) -> Result<(), String> {
+        let actual = self.bitmap_hash();
+        if actual == expected {
+            Ok(())
+        } else {
+            Err(self.failure(&format!(
+                "bitmap hash is {actual:#018x}, expected {expected:#018x}"
+            )))
+        }
+    }
+
+    /// Run until the machine halts, within the budget, and return its exit status.
+    ///
+    /// # Errors
+    ///
+    /// Fails if the budget runs out first.
+    pub fn wait_halt(&mut self) -> Result<Option<u8>, String> {
+        self.run(self.budget);
+        if self.vm.halted {
+            Ok(self.vm.exit_status)
+        } else {
+            Err(self.failure(&format!("still running after {} cycles", self.budget)))
+        }
+    }
+
+    fn failure(&mut self, message: &str) -> String {
+        let screen = self.screen();
+        format!("{message}\n--- screen ---\n{screen}--------------")
+    }
+}
+
+/// Run the script at `path`.
+///
+/// # Errors
+///
+/// Returns `path:line: message` for the first command that fails.
+pub fn run_script(path: &Path) -> Result<(), String> {
+    let source = std::fs::read_to_string(path)
+        .map_err(|err| format!("Failed to read {}: {err}", path.display()))?;
+    let mut script = Script::new(path.parent().unwrap_or(Path::new("")));
+    for (number, line) in source.lines().enumerate() {
+        script
+            .execute(line)
+            .map_err(|err| format!("{}:{}: {err}", path.display(), number + 1))?;
+    }
+    Ok(())
+}
+
+#[derive(Debug)]
+struct Script {
+    dir: PathBuf,
+    kernel: PathBuf,
+    disk: Vec<PathBuf>,
+    budget: u64,
+    driver: Option<Driver>,
+}
+
+impl Script {
+    fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+            kernel: PathBuf::from("kernel/main.bin"),
+            disk: Vec::new(),
+            budget: DEFAULT_BUDGET,
+            driver: None,
+        }
+    }
+
+    fn execute(&mut self, line: &str) -> Result<(), String> {
+        let line = match line.find(" #") {
+            Some(comment) => &line[..comment],
+            None if line.trim_start().starts_with('#') => "",
+            None => line,
+        };
+        let line = line.trim();
+        if line.is_empty() {
+            return Ok(());
+        }
+        let (command, argument) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        let argument = argument.trim();
+
+        match command {
+            "kernel" => self.kernel = self.dir.join(argument),
+            "disk" => {
+                let files = argument.split_whitespace();
+                self.disk.extend(files.map(|file| self.dir.join(file)));
+            }
+            "budget" => {
+                self.budget = parse_number(argument)?;
+                if let Some(driver) = &mut self.driver {
+                    driver.budget = self.budget;
+                }
+            }
+            "boot" => {
+                let mut driver = Driver::boot(&self.kernel, self.disk.clone())?;
+                driver.budget = self.budget;
+                self.driver = Some(driver);
+            }
+            "type" => self.driver()?.type_text(&unescape(argument)?),
+            "wait" => {
+                let pattern = Pattern::parse(argument)?;
+                self.driver()?.wait(&pattern)?;
+            }
+            "expect" => {
+                let pattern = Pattern::parse(argument)?;
+                self.driver()?.expect(&pattern)?;
+            }
+            "run" => {
+                let cycles = parse_number(argument)?;
+                self.driver()?.run(cycles);
+            }
+            "bitmap" => {
+                let expected = parse_number(argument)?;
+                self.driver()?.expect_bitmap(expected)?;
+            }
+            "halt" => {
+                let expected = (!argument.is_empty())
+                    .then(|| parse_number(argument))
+                    .transpose()?;
+                let driver = self.driver()?;
+                let status = driver.wait_halt()?;
+                if let Some(expected) = expected {
+                    if status.map(u64::from) != Some(expected) {
+                        let status = status.map_or("none".to_string(), |s| s.to_string());
+                        return Err(driver
+                            .failure(&format!("exit status is {status}, expected {expected}")));
+                    }
+                }
+            }
+            _ => return Err(format!("unknown command '{command}'")),
+        }
+        Ok(())
+    }
+
+    fn driver(&mut self) -> Result<&mut Driver, String> {
+        self.driver
+            .as_mut()
+            .ok_or_else(|| "the machine is not booted yet, use `boot`".to_string())
+    }
+}
+
+fn parse_number(value: &str) -> Result<u64, String> {
+    let parsed = match value
+        .strip_prefix("0x")
+        .or_else(|| value.strip_prefix("0X"))
+    {
+        Some(hex) => u64::from_str_radix(hex, 16),
+        None => value.replace('_', "").parse(),
+    };
+    parsed.map_err(|err| format!("invalid number '{value}': {err}"))
+}
@@ -1,64 +1,72 @@
-use mb8::vm::VirtualMachine;
-use std::path::PathBuf;
-
-pub fn makefs(user: Vec<PathBuf>, vm: &mut VirtualMachine) {
-    let mut fs = vec![0u8; 65536];
-    let mut blocks = 1;
-    let mut files = 0;
-    for path in user {
-        let Ok(data) = std::fs::read(&path) else {
-            continue;
-        };
-        let Ok(name) = path.file_stem().ok_or("Failed to get file name") else {
-            continue;
-        };
+//! Host side of the file system: building disks from host files. The layouts
+//! are in [`mb8::fs`].
 
-        let size = (data.len() / 256) + 1;
+use mb8::vm::VirtualMachine;
+use mb8_isa::exec::{is_executable, Executable, USER_BASE};
+use std::path::{Path, PathBuf};
+
+pub use mb8::fs::{Entry, FileSystem, Version, BLOCK_SIZE};
+
+/// Bytes stored on disk for a user file. Executables are validated, raw images
+/// (`image`) get a header loading them at `USER_BASE`, other files are stored as-is.
+///
+/// # Errors
+///
+/// Returns an error if an executable is invalid or an image does not fit in memory.
+pub fn disk_file(data: Vec<u8>, image: bool) -> Result<Vec<u8>, String> {
+    if is_executable(&data) {
+        Executable::from_bytes(&data)?;
+        Ok(data)
+    } else if image {
+        Ok(Executable::from_image(&data, USER_BASE, USER_BASE)?.to_bytes())
+    } else {
+        Ok(data)
+    }
+}
 
-        // Add to zero block
-        let zero_block_start = files * 16;
-        fs[zero_block_start] = 1;
-        fs[zero_block_start + 1] = blocks;
-        fs[zero_block_start + 2] = size as u8;
+/// Name and contents of the host file at `path` as stored on disk: the name is
+/// the file stem, and `.bin` files are raw images, see [`disk_file`].
+///
+/// # Errors
+///
+/// Returns a message if the file cannot be read or is not a valid executable.
+pub fn host_file(path: &Path) -> Result<(String, Vec<u8>), String> {
+    let data = std::fs::read(path).map_err(|err| format!("Failed to read: {err}"))?;
+    let name = path
+        .file_stem()
+        .ok_or("Failed to get file name")?
+        .to_string_lossy()
+        .into_owned();
+    let image = path.extension().is_some_and(|ext| ext == "bin");
+    Ok((name, disk_file(data, image)?))
+}
 
-        let chars = name.as_encoded_bytes();
-        if chars.len() > 8 {
-            eprintln!(
-                "Error: File name {} is too long. Max 8 characters allowed.",
-                name.to_string_lossy()
-            );
-            return;
-        }
-        for (i, c) in chars.iter().enumerate() {
-            fs[zero_block_start + 3 + i] = *c;
-        }
+/// Store the host file at `path`, see [`host_file`].
+///
+/// # Errors
+///
+/// Returns a message if the file cannot be read or stored.
+pub fn add_host_file(fs: &mut FileSystem, path: &Path) -> Result<Entry, String> {
+    let (name, data) = host_file(path)?;
+    fs.add(&name, &data)
+}
 
-        let block_start = blocks as usize * 256;
-        for (i, d) in data.iter().enumerate() {
-            fs[block_start + i] = *d;
+/// Put the `user` files on the disk of `vm`. Files that cannot be stored are
+/// reported and left out.
+pub fn makefs(user: Vec<PathBuf>, vm: &mut VirtualMachine) {
+    let mut fs = FileSystem::new();
+    for path in user {
+        if let Err(err) = add_host_file(&mut fs, &path) {
+            eprintln!("Error: {}: {err}", path.display());
         }
-
-        blocks += size as u8;
-        files += 1;
     }
-
-    let Ok(fs) = fs.try_into() else {
-        eprintln!("Failed to convert file system");
-        return;
-    };
-    vm.devices.disk().set(fs);
+    vm.devices.disk().set(fs.into_image());
 }
 
 //you cannot access local memory that I know of, they way you would commiicate with
 // a file system on a desktop,
 #[cfg(feature = "wasm")]
 pub fn makefs_wasm(vm: &mut VirtualMachine) {
-    use std::convert::TryInto;
-
-    let mut fs = vec![0u8; 65536];
-    let mut start_block = 1;
-    let mut file_index = 0;
-
     let user_bins: &[(&[u8], &str)] = &[
         (include_bytes!("../../../user/sh.bin"), "sh"),
         (include_bytes!("../../../user/ls.bin"), "ls"),
@@ -67,35 +75,19 @@ pub fn makefs_wasm(vm: &mut VirtualMachine) {
         (include_bytes!("../../../user/help.bin"), "help"),
     ];
 
+    let mut fs = FileSystem::new();
     for (bin, name) in user_bins {
-        let blocks_needed: usize = (bin.len() / 256) + 1;
-
-        let block_start = start_block * 256;
-        fs[block_start..block_start + bin.len()].copy_from_slice(bin);
-
-        let zero_block_start = file_index * 16;
-        fs[zero_block_start] = 1;
-        fs[zero_block_start + 1] = start_block as u8;
-        fs[zero_block_start + 2] = blocks_needed as u8;
-
-        fs[zero_block_start + 3..zero_block_start + 11].fill(0);
-        for (i, b) in name.as_bytes().iter().enumerate() {
-            fs[zero_block_start + 3 + i] = *b;
+        if let Err(err) = disk_file(bin.to_vec(), true).and_then(|bin| fs.add(name, &bin)) {
+            web_sys::console::log_1(&format!("makefs_wasm: {name}: {err}").into());
         }
-
-        start_block += blocks_needed;
-        file_index += 1;
     }
 
     web_sys::console::log_1(
         &format!(
-            "makefs_wasm: filesystem initialized, zero-block[0..{}]: {:?}",
-            file_index * 16,
-            &fs[0..file_index * 16]
+            "makefs_wasm: filesystem initialized with {} files",
+            fs.entries().len()
         )
         .into(),
     );
-
-    let boxed_fs: Box<[u8; 65536]> = fs.try_into().expect("Failed to convert FS to fixed size");
-    vm.devices.disk().set(boxed_fs);
+    vm.devices.disk().set(fs.into_image());
 }
@@ -0,0 +1,187 @@
+use std::{
+    io::{self, Read, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use mb8::{
+    dev::gpu::{registers::TTY_COLS, Mode, GPU},
+    vm::VirtualMachine,
+};
+
+use crate::{
+    diskimage::{self, DiskImage},
+    serial::{spawn_reader, Serial},
+    terminal::half_blocks,
+    wav::save_recording,
+};
+
+/// Steps between checks of the input, output and time limit.
+const STEPS_PER_POLL: u32 = 1024;
+
+/// The screen as text: in TTY mode one line per row with trailing blanks
+/// removed, in bitmap mode the pixels drawn with half blocks.
+#[must_use]
+pub fn screen_text(gpu: &GPU) -> String {
+    let mut screen = String::new();
+    match gpu.current_mode() {
+        Mode::Off => {}
+        Mode::Tty => {
+            for row in gpu.tty_buffer().chunks(usize::from(TTY_COLS)) {
+                let line: String = row
+                    .iter()
+                    .map(|&c| {
+                        if c.is_ascii_graphic() {
+                            char::from(c)
+                        } else {
+                            ' '
+                        }
+                    })
+                    .collect();
+                screen.push_str(line.trim_end());
+                screen.push('\n');
+            }
+        }
+        Mode::Bitmap => {
+            for line in half_blocks(gpu.bitmap_buffer()) {
+                screen.push_str(line.trim_end());
+                screen.push('\n');
+            }
+        }
+    }
+    screen
+}
+
+/// Why a headless run stopped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Stop {
+    /// The machine halted, with its exit status if it ran `HALT`.
+    Halted(Option<u8>),
+    /// `--max-cycles` was reached.
+    CycleLimit,
+    /// `--timeout` was reached.
+    TimeLimit,
+}
+
+impl Stop {
+    /// Process exit code: the status the machine halted with, 1 if it stopped on an
+    /// error, and 124 (as `timeout` uses) if a limit ended the run.
+    #[must_use]
+    pub fn exit_code(self) -> i32 {
+        match self {
+            Self::Halted(Some(status)) => i32::from(status),
+            Self::Halted(None) => 1,
+            Self::CycleLimit | Self::TimeLimit => 124,
+        }
+    }
+}
+
+/// Runs the VM without a window: the TTY goes to a writer as text and bytes from
+/// a reader are typed on the keyboard.
+#[derive(Debug)]
+pub struct Headless {
+    pub vm: VirtualMachine,
+    /// Host end of the UART.
+    pub serial: Option<Serial>,
+    /// File the recorded sound is written to when the run ends.
+    pub wav: Option<PathBuf>,
+    /// Image files the drives are saved to.
+    pub disks: Vec<DiskImage>,
+    /// Stop after this many CPU cycles.
+    pub max_cycles: Option<u64>,
+    /// Stop after this much host time.
+    pub timeout: Option<Duration>,
+}
+
+impl Headless {
+    #[must_use]
+    pub fn new(mut vm: VirtualMachine) -> Self {
+        vm.devices.gpu().capture_tty = true;
+        Self {
+            vm,
+            serial: None,
+            wav: None,
+            disks: Vec::new(),
+            max_cycles: None,
+            timeout: None,
+        }
+    }
+
+    /// Run until the machine halts or a limit is reached. `input` is read on a
+    /// background thread, so the VM keeps running while it blocks.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if writing to `output` fails.
+    pub fn run(
+        &mut self,
+        input: impl Read + Send + 'static,
+        output: &mut impl Write,
+    ) -> io::Result<Stop> {
+        let keys = spawn_reader(input);
+        let start = Instant::now();
+        let stop = loop {
+            if let Some(stop) = self.stopped(start) {
+                break stop;
+            }
+            for _ in 0..STEPS_PER_POLL {
+                if self.vm.halted || self.max_cycles.is_some_and(|max| self.vm.cycles >= max) {
+                    break;
+                }
+                self.vm.step();
+                if let Some(serial) = &mut self.serial {
+                    serial.pump(self.vm.devices.uart());
+                }
+                diskimage::sync(&self.disks, &mut self.vm);
+            }
+            for key in keys.try_iter() {
+                self.vm.devices.keyboard().key_pressed(key);
+            }
+            self.flush(output)?;
+        };
+        self.flush(output)?;
+
+        if let Some(path) = &self.wav {
+            save_recording(&mut self.vm, path);
+        }
+        diskimage::flush(&self.disks, &mut self.vm);
+        Ok(stop)
+    }
+
+    /// The screen as text, see [`screen_text`].
+    #[must_use]
+    pub fn screen(&mut self) -> String {
+        screen_text(self.vm.devices.gpu())
+    }
+
+    fn stopped(&self, start: Instant) -> Option<Stop> {
+        if self.vm.halted {
+            Some(Stop::Halted(self.vm.exit_status))
+        } else if self.max_cycles.is_some_and(|max| self.vm.cycles >= max) {
+            Some(Stop::CycleLimit)
+        } else if self
+            .timeout
+            .is_some_and(|timeout| start.elapsed() >= timeout)
+        {
+            Some(Stop::TimeLimit)
+        } else {
+            None
+        }
+    }
+
+    /// Write what the TTY printed since the last flush. Backspaces also erase
+    /// the character on the host terminal, as they do on the screen.
+    fn flush(&mut self, output: &mut impl Write) -> io::Result<()> {
+        let text = self.vm.devices.gpu().take_tty_output();
+        if text.is_empty() {
+            return Ok(());
+        }
+        for byte in text {
+            match byte {
+                b'\x08' => output.write_all(b"\x08 \x08")?,
+                _ => output.write_all(&[byte])?,
+            }
+        }
+        output.flush()
+    }
+}
@@ -4,12 +4,24 @@ pub mod config;
 pub const PIXEL_ON_COLOR: u32 = 0x006a_bfc6;
 pub const PIXEL_OFF_COLOR: u32 = 0x0050_459b;
 
+pub mod asm;
+pub mod bench;
 pub mod bitmap;
+pub mod clock;
 pub mod debug;
+pub mod disasm;
+pub mod diskimage;
+pub mod disktool;
+pub mod e2e;
 pub mod filesystem;
+pub mod headless;
 pub mod keyboard;
+pub mod serial;
+pub mod symbols;
+pub mod terminal;
 pub mod tty;
 pub mod vmrun;
+pub mod wav;
 
 #[cfg(feature = "wasm")]
 use wasm_bindgen::prelude::*;
@@ -0,0 +1,132 @@
+use std::{
+    collections::VecDeque,
+    fmt,
+    fs::OpenOptions,
+    io::{Read, Write},
+    net::TcpListener,
+    path::PathBuf,
+    str::FromStr,
+    sync::mpsc::{self, Receiver},
+    thread,
+};
+
+use mb8::dev::uart::Uart;
+
+/// Host end of the UART, as given to `--serial`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SerialSpec {
+    /// `stdio`: the terminal the VM was started from.
+    Stdio,
+    /// `tcp:HOST:PORT`: listen on a local socket and wait for one client.
+    Tcp(String),
+    /// `pty:PATH`: an existing PTY or serial device, e.g. one end of a `socat` pair.
+    Pty(PathBuf),
+}
+
+impl FromStr for SerialSpec {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.split_once(':') {
+            None if value == "stdio" => Ok(Self::Stdio),
+            Some(("tcp", addr)) if !addr.is_empty() => Ok(Self::Tcp(addr.to_string())),
+            Some(("pty", path)) if !path.is_empty() => Ok(Self::Pty(PathBuf::from(path))),
+            _ => Err(format!(
+                "invalid serial port '{value}', expected stdio, tcp:HOST:PORT or pty:PATH"
+            )),
+        }
+    }
+}
+
+/// A connection between the UART and a host byte stream. Bytes from the host are
+/// read on a background thread so [`Serial::pump`] never blocks the VM.
+pub struct Serial {
+    rx: Receiver<u8>,
+    pending: VecDeque<u8>,
+    tx: Box<dyn Write + Send>,
+}
+
+impl fmt::Debug for Serial {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Serial")
+            .field("pending", &self.pending)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Serial {
+    /// Connect to the host end described by `spec`. A TCP port blocks until a
+    /// client connects.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the port cannot be opened.
+    pub fn open(spec: &SerialSpec) -> Result<Self, String> {
+        match spec {
+            SerialSpec::Stdio => Ok(Self::new(std::io::stdin(), std::io::stdout())),
+            SerialSpec::Tcp(addr) => {
+                let listener = TcpListener::bind(addr)
+                    .map_err(|err| format!("Failed to listen on {addr}: {err}"))?;
+                eprintln!("Waiting for a serial connection on {addr}");
+                let (stream, peer) = listener
+                    .accept()
+                    .map_err(|err| format!("Failed to accept on {addr}: {err}"))?;
+                eprintln!("Serial connected to {peer}");
+                let reader = stream.try_clone().map_err(|err| err.to_string())?;
+                Ok(Self::new(reader, stream))
+            }
+            SerialSpec::Pty(path) => {
+                let file = OpenOptions::new()
+                    .read(true)
+                    .write(true)
+                    .open(path)
+                    .map_err(|err| format!("Failed to open {}: {err}", path.display()))?;
+                let reader = file.try_clone().map_err(|err| err.to_string())?;
+                Ok(Self::new(reader, file))
+            }
+        }
+    }
+
+    /// Bridge the UART to `reader` and `writer`.
+    pub fn new(reader: impl Read + Send + 'static, writer: impl Write + Send + 'static) -> Self {
+        Self {
+            rx: spawn_reader(reader),
+            pending: VecDeque::new(),
+            tx: Box::new(writer),
+        }
+    }
+
+    /// Move received bytes into the RX FIFO, as far as it has room, and send
+    /// everything the program transmitted.
+    pub fn pump(&mut self, uart: &mut Uart) {
+        self.pending.extend(self.rx.try_iter());
+        while let Some(&byte) = self.pending.front() {
+            if !uart.receive(byte) {
+                break;
+            }
+            self.pending.pop_front();
+        }
+
+        let out = uart.transmit();
+        if !out.is_empty() {
+            // A closed connection only loses output; the VM keeps running.
+            let _ = self.tx.write_all(&out).and_then(|()| self.tx.flush());
+        }
+    }
+}
+
+/// Read `reader` on a background thread, passing on its bytes until it ends.
+pub(crate) fn spawn_reader(mut reader: impl Read + Send + 'static) -> Receiver<u8> {
+    let (sender, rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut buf = [0; 256];
+        while let Ok(read @ 1..) = reader.read(&mut buf) {
+            for &byte in &buf[..read] {
+                if sender.send(byte).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+    rx
+}
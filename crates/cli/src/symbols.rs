@@ -0,0 +1,32 @@
+use std::path::{Path, PathBuf};
+
+use mb8_isa::symbols::SymbolMap;
+
+/// Load and merge symbol files (native or customasm format). Earlier files win
+/// when two of them name the same address.
+///
+/// # Errors
+///
+/// Returns a message naming the file that could not be read or parsed.
+pub fn load_symbols(paths: &[PathBuf]) -> Result<SymbolMap, String> {
+    let mut map = SymbolMap::new();
+    for path in paths {
+        let text = std::fs::read_to_string(path)
+            .map_err(|err| format!("Failed to read symbols {}: {err}", path.display()))?;
+        let symbols = SymbolMap::parse(&text).map_err(|(line, message)| {
+            format!("Invalid symbol file {}:{line}: {message}", path.display())
+        })?;
+        map.merge(symbols);
+    }
+    Ok(map)
+}
+
+/// Write `map` to `path`. Returns `false` if the file could not be written.
+#[must_use]
+pub fn write_symbols(path: &Path, map: &SymbolMap) -> bool {
+    if let Err(err) = std::fs::write(path, map.to_string()) {
+        eprintln!("Failed to write {}: {err}", path.display());
+        return false;
+    }
+    true
+}
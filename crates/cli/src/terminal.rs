@@ -0,0 +1,227 @@
+use std::{
+    fmt::Write as _,
+    io::{self, Write},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use crossterm::{
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    terminal,
+};
+use mb8::{
+    dev::gpu::{
+        registers::{BITMAP_HEIGHT, BITMAP_WIDTH, TTY_COLS, TTY_ROWS},
+        Mode, GPU,
+    },
+    vm::VirtualMachine,
+};
+
+use mb8_isa::CPU_FREQUENCY;
+
+use crate::{
+    clock::Clock,
+    diskimage::{self, DiskImage},
+    serial::Serial,
+    wav::save_recording,
+};
+
+const FRAME_DURATION_MS: u64 = 16;
+
+/// Map a key press to the byte the keyboard device queues. `None` for keys the
+/// machine has no code for.
+#[must_use]
+pub fn key_byte(key: &KeyEvent) -> Option<u8> {
+    if key.kind == KeyEventKind::Release {
+        return None;
+    }
+    match key.code {
+        KeyCode::Enter => Some(b'\n'),
+        KeyCode::Backspace => Some(0x08),
+        KeyCode::Tab => Some(0x09),
+        KeyCode::Esc => Some(0x1B),
+        KeyCode::Char(c) if c.is_ascii() => u8::try_from(c).ok(),
+        _ => None,
+    }
+}
+
+/// The bitmap as text, one line for every two pixel rows drawn with the upper
+/// and lower half block characters.
+#[must_use]
+pub fn half_blocks(bitmap: &[u8]) -> Vec<String> {
+    let pixel = |x: usize, y: usize| bitmap[(y * BITMAP_WIDTH + x) / 8] & (0x80 >> (x % 8)) != 0;
+    (0..BITMAP_HEIGHT)
+        .step_by(2)
+        .map(|y| {
+            (0..BITMAP_WIDTH)
+                .map(|x| match (pixel(x, y), pixel(x, y + 1)) {
+                    (true, true) => '█',
+                    (true, false) => '▀',
+                    (false, true) => '▄',
+                    (false, false) => ' ',
+                })
+                .collect()
+        })
+        .collect()
+}
+
+/// Escape sequences that draw the screen of `gpu` at the top left of the terminal
+/// and place the cursor. Lines are separated by `\r\n`, as the terminal is in raw mode.
+#[must_use]
+pub fn frame(gpu: &GPU) -> String {
+    let mut out = String::from("\x1b[H");
+    match gpu.current_mode() {
+        Mode::Off => {}
+        Mode::Tty => {
+            for (y, row) in gpu.tty_buffer().chunks(usize::from(TTY_COLS)).enumerate() {
+                if y > 0 {
+                    out.push_str("\r\n");
+                }
+                out.extend(row.iter().map(|&c| {
+                    if c == b' ' || c.is_ascii_graphic() {
+                        char::from(c)
+                    } else {
+                        ' '
+                    }
+                }));
+                out.push_str("\x1b[K");
+            }
+        }
+        Mode::Bitmap => {
+            for (y, row) in half_blocks(gpu.bitmap_buffer()).iter().enumerate() {
+                if y > 0 {
+                    out.push_str("\r\n");
+                }
+                out.push_str(row);
+                out.push_str("\x1b[K");
+            }
+        }
+    }
+    out.push_str("\x1b[J");
+    if gpu.current_mode() == Mode::Tty {
+        let (x, y) = gpu.tty_cursor();
+        let _ = write!(
+            out,
+            "\x1b[{};{}H\x1b[?25h",
+            u16::from(y) + 1,
+            u16::from(x) + 1
+        );
+    } else {
+        out.push_str("\x1b[?25l");
+    }
+    out
+}
+
+/// Puts the terminal in raw mode and restores it when dropped.
+struct RawMode;
+
+impl RawMode {
+    fn enter() -> io::Result<Self> {
+        terminal::enable_raw_mode()?;
+        print!("\x1b[2J");
+        Ok(Self)
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        // Leave the last screen visible and continue below it.
+        print!("\x1b[{};1H\x1b[?25h", u16::from(TTY_ROWS) + 1);
+        let _ = io::stdout().flush();
+        let _ = terminal::disable_raw_mode();
+    }
+}
+
+/// Runs the VM in the host terminal: the screen is drawn with ANSI escape codes
+/// and keystrokes go to the keyboard. Ctrl+C leaves.
+#[derive(Debug)]
+pub struct TerminalRun {
+    pub vm: VirtualMachine,
+    /// Host end of the UART.
+    pub serial: Option<Serial>,
+    /// File the recorded sound is written to when the run ends.
+    pub wav: Option<PathBuf>,
+    /// Image files the drives are saved to.
+    pub disks: Vec<DiskImage>,
+    /// Pacing of the CPU.
+    pub clock: Clock,
+}
+
+impl TerminalRun {
+    #[must_use]
+    pub fn new(vm: VirtualMachine) -> Self {
+        Self {
+            vm,
+            serial: None,
+            wav: None,
+            disks: Vec::new(),
+            clock: Clock::new(CPU_FREQUENCY),
+        }
+    }
+
+    /// Run until the machine halts or the user presses Ctrl+C.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the terminal cannot be put in raw mode, read or written.
+    pub fn run(&mut self) -> io::Result<()> {
+        let raw_mode = RawMode::enter()?;
+        let mut stdout = io::stdout();
+        let mut shown = String::new();
+        let mut last_frame = Instant::now();
+        let mut quit = false;
+
+        while !self.vm.halted && !quit {
+            let budget = self.clock.budget(self.vm.cycles, Instant::now());
+            let end = self.vm.cycles + budget;
+            for _ in 0..budget {
+                if self.vm.halted || self.vm.cycles >= end {
+                    break;
+                }
+                self.vm.step();
+                if let Some(serial) = &mut self.serial {
+                    serial.pump(self.vm.devices.uart());
+                }
+                diskimage::sync(&self.disks, &mut self.vm);
+            }
+            if budget == 0 {
+                std::thread::sleep(self.clock.idle());
+            }
+
+            while event::poll(Duration::ZERO)? {
+                let Event::Key(key) = event::read()? else {
+                    continue;
+                };
+                if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+                    quit = true;
+                } else if let Some(byte) = key_byte(&key) {
+                    self.vm.devices.keyboard().key_pressed(byte);
+                }
+            }
+
+            if last_frame.elapsed() >= Duration::from_millis(FRAME_DURATION_MS) {
+                self.draw(&mut stdout, &mut shown)?;
+                last_frame = Instant::now();
+            }
+        }
+        self.draw(&mut stdout, &mut shown)?;
+        drop(raw_mode);
+
+        if let Some(path) = &self.wav {
+            save_recording(&mut self.vm, path);
+        }
+        diskimage::flush(&self.disks, &mut self.vm);
+        Ok(())
+    }
+
+    /// Draw the screen if it changed since `shown`.
+    fn draw(&mut self, out: &mut impl Write, shown: &mut String) -> io::Result<()> {
+        let frame = frame(self.vm.devices.gpu());
+        if frame != *shown {
+            out.write_all(frame.as_bytes())?;
+            out.flush()?;
+            *shown = frame;
+        }
+        Ok(())
+    }
+}
@@ -1,23 +1,51 @@
 use crate::bitmap::Bitmap;
+use crate::clock::Clock;
+use crate::diskimage::{self, DiskImage};
+use crate::serial::Serial;
 use crate::{filesystem::makefs, keyboard::Keyboard};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 use mb8::dev::gpu::Mode;
 use mb8::vm;
-use minifb::{Window, WindowOptions};
+use mb8_isa::CPU_FREQUENCY;
+use minifb::{Key, KeyRepeat, Window, WindowOptions};
 
 use crate::debug::{Debug, DebugCmd};
 use crate::tty::Tty;
+use crate::wav::save_recording;
 
 use std::io::{self, Write};
 use std::time::{Duration, Instant};
 
-const OPS_PER_FRAME: u32 = 1024;
 const RENDER_INTERVAL: u32 = 1000;
 const WIDTH: usize = 320;
 const HEIGHT: usize = 200;
 const FRAME_DURATION_MS: u64 = 16;
 
+/// Load the kernel ROM, seed the random number generator and put the `user`
+/// files on the disk.
+///
+/// # Errors
+///
+/// Returns a message if the kernel cannot be read.
+pub fn boot(
+    vm: &mut vm::VirtualMachine,
+    kernel: &Path,
+    user: Vec<PathBuf>,
+    seed: Option<u16>,
+) -> Result<(), String> {
+    let rom = std::fs::read(kernel)
+        .map_err(|err| format!("Failed to read kernel {}: {err}", kernel.display()))?;
+    vm.load_rom(&rom);
+
+    let seed = seed.unwrap_or(1);
+
+    vm.devices.rand().number = (seed as u8).max(1);
+
+    makefs(user, vm);
+    Ok(())
+}
+
 #[derive(Debug)]
 pub struct VmRun {
     pub vm: vm::VirtualMachine,
@@ -30,6 +58,15 @@ pub struct VmRun {
     pub debug_enabled: bool,
     pub hit_entry_break: bool,
     pub paused: bool,
+    /// Host end of the UART.
+    pub serial: Option<Serial>,
+    /// File the recorded sound is written to when the run ends.
+    pub wav: Option<PathBuf>,
+    /// Image files the drives are loaded from and saved to.
+    pub disks: Vec<DiskImage>,
+    /// Pacing of the CPU: F5 pauses, F6 advances a frame while paused, F7 toggles turbo.
+    pub clock: Clock,
+    title: String,
 }
 
 impl VmRun {
@@ -57,20 +94,25 @@ impl VmRun {
             debug_enabled: false,
             hit_entry_break: false,
             paused: false,
+            serial: None,
+            wav: None,
+            disks: Vec::new(),
+            clock: Clock::new(CPU_FREQUENCY),
+            title: String::new(),
         })
     }
 
-    pub fn run_desktop(&mut self, kernel: PathBuf, user: Vec<PathBuf>, seed: Option<u16>) {
-        let Ok(rom) = std::fs::read(kernel) else {
+    pub fn run_desktop(&mut self, kernel: &Path, user: Vec<PathBuf>, seed: Option<u16>) {
+        if let Err(err) = boot(&mut self.vm, kernel, user, seed) {
+            eprintln!("{err}");
             return;
-        };
-        self.vm.load_rom(&rom);
-
-        let seed = seed.unwrap_or(1);
-
-        self.vm.devices.rand().number = (seed as u8).max(1);
-
-        makefs(user, &mut self.vm);
+        }
+        for disk in &self.disks {
+            if let Err(err) = disk.load(&mut self.vm) {
+                eprintln!("{err}");
+                return;
+            }
+        }
 
         self.ticks = RENDER_INTERVAL - 1;
         let l_shift = false;
@@ -85,6 +127,7 @@ impl VmRun {
         while self.window.is_open() && !self.vm.halted {
             Keyboard::key_pressed(key, &self.window, &mut self.vm);
             Keyboard::key_released(key, &self.window);
+            self.poll_clock_keys();
             if self.debug_enabled {
                 // Step VM once to finish printing any pending TTY output
                 self.vm_step();
@@ -95,8 +138,8 @@ impl VmRun {
                     self.poll_debug_keys_stdout();
                     continue; // skip rest of loop while paused
                 }
-            } else {
-                self.vm_step();
+            } else if !self.vm_step() {
+                std::thread::sleep(self.clock.idle());
             }
 
             if last_frame.elapsed() >= Duration::from_millis(FRAME_DURATION_MS) {
@@ -106,28 +149,76 @@ impl VmRun {
         }
 
         self.render(&mut buf);
+        if let Some(path) = &self.wav {
+            save_recording(&mut self.vm, path);
+        }
+        diskimage::flush(&self.disks, &mut self.vm);
     }
 
-    fn vm_step(&mut self) {
+    /// Run the CPU for as many cycles as the clock allows, or one instruction in
+    /// debug mode. Returns whether it was due to run at all.
+    fn vm_step(&mut self) -> bool {
         if self.debug_enabled {
             if !self.vm.halted {
                 self.vm.step();
+                self.pump_host();
             }
-            return;
+            return true;
         }
-        for _ in 0..OPS_PER_FRAME {
-            if self.vm.halted {
+        let budget = self.clock.budget(self.vm.cycles, Instant::now());
+        let end = self.vm.cycles + budget;
+        // Every instruction takes at least one cycle, so this bounds the loop
+        // even if an instruction does not count any.
+        for _ in 0..budget {
+            if self.vm.halted || self.vm.cycles >= end {
                 break;
             }
 
             self.vm.step();
-            println!("PC = {:04X}", self.vm.program_counter);
+            self.pump_host();
         }
+        budget > 0
+    }
+
+    fn poll_clock_keys(&mut self) {
+        if self.window.is_key_pressed(Key::F5, KeyRepeat::No) {
+            self.clock.set_paused(!self.clock.paused());
+        }
+        if self.window.is_key_pressed(Key::F6, KeyRepeat::Yes) {
+            self.clock.advance();
+        }
+        if self.window.is_key_pressed(Key::F7, KeyRepeat::No) {
+            self.clock.set_turbo(!self.clock.turbo());
+        }
+    }
+
+    /// Show the clock status in the title bar.
+    fn update_title(&mut self) {
+        self.clock.measure(self.vm.cycles, Instant::now());
+        let title = format!("MB8 - {}", self.clock.status(self.vm.halted));
+        if title != self.title {
+            self.window.set_title(&title);
+            self.title = title;
+        }
+    }
+
+    /// Move UART bytes and save the disks if the guest flushed them.
+    fn pump_host(&mut self) {
+        if let Some(serial) = &mut self.serial {
+            serial.pump(self.vm.devices.uart());
+        }
+        diskimage::sync(&self.disks, &mut self.vm);
     }
 
     fn run_debug(&mut self) -> bool {
         const USER_ENTRY: u16 = 0xE100;
 
+        let pc = self.vm.program_counter;
+        if !self.paused && self.debug.breakpoints.contains(&pc) {
+            println!("--- Breakpoint @ {} ---", self.debug.describe(pc));
+            self.run_stdout_debugger();
+        }
+
         // Break at user entry exactly once
         if self.debug_enabled && !self.hit_entry_break && self.vm.program_counter == USER_ENTRY {
             self.hit_entry_break = true;
@@ -145,6 +236,7 @@ impl VmRun {
     }
 
     fn render(&mut self, buf: &mut [u32]) {
+        self.update_title();
         let gpu = self.vm.devices.gpu();
         match gpu.current_mode() {
             Mode::Off => {}
@@ -234,6 +326,14 @@ impl VmRun {
                 }
             }
 
+            DebugCmd::Break(arg) => {
+                self.debug.toggle_breakpoint(arg.as_deref());
+            }
+
+            DebugCmd::Backtrace => {
+                self.debug.print_backtrace(&mut self.vm);
+            }
+
             DebugCmd::Registers => {
                 print!("\x1B[2J\x1B[H");
                 let _ = io::stdout().flush();
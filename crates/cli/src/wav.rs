@@ -0,0 +1,58 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use mb8::{dev::sound::registers::SAMPLE_RATE, vm::VirtualMachine};
+
+/// Write `samples` as a mono 16-bit PCM WAV stream.
+///
+/// # Errors
+///
+/// Returns an error if writing to `out` fails or the samples do not fit in a WAV file.
+pub fn write_wav(out: &mut impl Write, sample_rate: u32, samples: &[i16]) -> io::Result<()> {
+    const CHANNELS: u16 = 1;
+    const BITS: u16 = 16;
+    let block_align = CHANNELS * BITS / 8;
+    let data_size = u32::try_from(samples.len() * usize::from(block_align))
+        .ok()
+        .filter(|size| *size <= u32::MAX - 36)
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidInput, "too many samples for WAV"))?;
+
+    out.write_all(b"RIFF")?;
+    out.write_all(&(36 + data_size).to_le_bytes())?;
+    out.write_all(b"WAVEfmt ")?;
+    out.write_all(&16u32.to_le_bytes())?;
+    out.write_all(&1u16.to_le_bytes())?; // PCM
+    out.write_all(&CHANNELS.to_le_bytes())?;
+    out.write_all(&sample_rate.to_le_bytes())?;
+    out.write_all(&(sample_rate * u32::from(block_align)).to_le_bytes())?;
+    out.write_all(&block_align.to_le_bytes())?;
+    out.write_all(&BITS.to_le_bytes())?;
+    out.write_all(b"data")?;
+    out.write_all(&data_size.to_le_bytes())?;
+    for sample in samples {
+        out.write_all(&sample.to_le_bytes())?;
+    }
+    out.flush()
+}
+
+/// Save `samples` to a WAV file at `path`.
+///
+/// # Errors
+///
+/// Returns an error message if the file cannot be written.
+pub fn save_wav(path: &Path, sample_rate: u32, samples: &[i16]) -> Result<(), String> {
+    File::create(path)
+        .and_then(|file| write_wav(&mut BufWriter::new(file), sample_rate, samples))
+        .map_err(|err| format!("Failed to write {}: {err}", path.display()))
+}
+
+/// Save what the sound generator of `vm` recorded, reporting a failure on stderr.
+pub fn save_recording(vm: &mut VirtualMachine, path: &Path) {
+    let samples = vm.devices.sound().take_samples();
+    if let Err(err) = save_wav(path, SAMPLE_RATE, &samples) {
+        eprintln!("{err}");
+    }
+}
@@ -0,0 +1,49 @@
+use std::{path::PathBuf, time::Duration};
+
+use mb8_cli::bench::{bench, load_rom, BenchResult};
+
+fn repo(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .join(path)
+}
+
+#[test]
+fn test_bench_programs_halt() {
+    for name in ["alu", "memcpy", "recursion"] {
+        let rom = load_rom(&repo(&format!("bench/{name}.asm"))).unwrap();
+        let result = bench(&rom, 20_000_000);
+        assert!(result.halted, "{name} did not halt");
+        assert!(result.instructions > 1_000_000, "{name} is too short");
+        assert!(result.cycles >= result.instructions);
+    }
+}
+
+#[test]
+fn test_bench_instruction_limit() {
+    let rom = load_rom(&repo("bench/alu.asm")).unwrap();
+    let result = bench(&rom, 1000);
+    assert!(!result.halted);
+    assert_eq!(result.instructions, 1000);
+}
+
+#[test]
+fn test_bench_load_rom_errors() {
+    assert!(load_rom(&repo("bench/missing.bin")).is_err());
+}
+
+#[test]
+fn test_bench_rates() {
+    let result = BenchResult {
+        instructions: 2_000_000,
+        cycles: 3_000_000,
+        elapsed: Duration::from_millis(500),
+        halted: true,
+    };
+    assert!((result.instructions_per_second() - 4e6).abs() < 1.0);
+    assert!((result.cycles_per_second() - 6e6).abs() < 1.0);
+    assert_eq!(
+        result.to_string(),
+        "2000000 instructions, 3000000 cycles (halted) in 0.500 s: 4.00 MIPS, 6.00 M cycles/s"
+    );
+}
@@ -0,0 +1,84 @@
+use std::time::{Duration, Instant};
+
+use mb8_cli::clock::{format_hz, Clock};
+
+#[test]
+fn test_clock_paces_cycles() {
+    let mut clock = Clock::new(1_000_000);
+    let start = Instant::now();
+    assert_eq!(clock.budget(0, start), 0);
+    assert_eq!(clock.budget(0, start + Duration::from_millis(10)), 10_000);
+    // Cycles already run count against the budget.
+    assert_eq!(
+        clock.budget(8_000, start + Duration::from_millis(10)),
+        2_000
+    );
+    assert_eq!(clock.budget(12_000, start + Duration::from_millis(10)), 0);
+}
+
+#[test]
+fn test_clock_drops_lag() {
+    let mut clock = Clock::new(1_000_000);
+    let start = Instant::now();
+    clock.budget(0, start);
+    // A second behind: only 100 ms worth is run.
+    assert_eq!(clock.budget(0, start + Duration::from_secs(1)), 100_000);
+    assert_eq!(clock.budget(100_000, start + Duration::from_secs(1)), 0);
+    assert_eq!(
+        clock.budget(100_000, start + Duration::from_millis(1010)),
+        10_000
+    );
+}
+
+#[test]
+fn test_clock_pause_and_advance() {
+    let mut clock = Clock::new(1_000_000);
+    let start = Instant::now();
+    clock.set_paused(true);
+    assert_eq!(clock.budget(0, start + Duration::from_secs(1)), 0);
+    clock.advance();
+    assert_eq!(clock.budget(0, start + Duration::from_secs(1)), 16_000);
+    assert_eq!(clock.budget(16_000, start + Duration::from_secs(1)), 0);
+    assert_eq!(clock.status(false), "paused");
+
+    // Time spent paused is not made up after resuming.
+    clock.set_paused(false);
+    let resume = start + Duration::from_secs(5);
+    assert_eq!(clock.budget(16_000, resume), 0);
+    assert_eq!(
+        clock.budget(16_000, resume + Duration::from_millis(1)),
+        1_000
+    );
+}
+
+#[test]
+fn test_clock_turbo() {
+    let mut clock = Clock::new(1_000);
+    clock.set_turbo(true);
+    assert!(clock.budget(0, Instant::now()) > 1_000);
+    assert_eq!(clock.idle(), Duration::ZERO);
+}
+
+#[test]
+fn test_clock_status() {
+    let mut clock = Clock::new(2_000_000);
+    let start = Instant::now();
+    assert_eq!(clock.status(false), "- (0%)");
+    clock.measure(0, start);
+    clock.measure(500_000, start + Duration::from_millis(500));
+    assert_eq!(clock.speed(), Some(1_000_000));
+    assert_eq!(clock.status(false), "1.00 MHz (50%)");
+    assert_eq!(clock.status(true), "halted");
+    clock.set_turbo(true);
+    clock.measure(0, start);
+    clock.measure(21_050_000, start + Duration::from_secs(1));
+    assert_eq!(clock.status(false), "turbo 21.05 MHz");
+}
+
+#[test]
+fn test_format_hz() {
+    assert_eq!(format_hz(1_000_000), "1.00 MHz");
+    assert_eq!(format_hz(4_194_304), "4.19 MHz");
+    assert_eq!(format_hz(32_768), "32.76 kHz");
+    assert_eq!(format_hz(60), "60 Hz");
+}
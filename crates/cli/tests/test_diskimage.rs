@@ -0,0 +1,131 @@
+use mb8::{
+    dev::disk::registers::{DISK_CMD_FLUSH, DISK_CMD_WRITE},
+    vm::VirtualMachine,
+};
+use mb8_cli::diskimage::{self, BadBlock, DiskImage, DiskMode, IMAGE_SIZE};
+use tempfile::tempdir;
+
+const DISK: u16 = 0xF200;
+
+/// Write `value` to the first byte of `block` through the disk registers.
+fn write_block(vm: &mut VirtualMachine, block: u8, value: u8) {
+    vm.devices.write(DISK, block);
+    vm.devices.write(DISK + 2, value);
+    vm.devices.write(DISK + 1, DISK_CMD_WRITE);
+}
+
+#[test]
+fn test_disk_image_created_and_saved() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("disk.mb8d");
+    let image = DiskImage::new(path.clone(), DiskMode::ReadWrite);
+
+    let mut vm = VirtualMachine::default();
+    write_block(&mut vm, 1, 0x11);
+    vm.devices.disk().drive(0).mark_clean();
+    image.load(&mut vm).unwrap();
+    let saved = std::fs::read(&path).unwrap();
+    assert_eq!(saved.len(), IMAGE_SIZE);
+    assert_eq!(saved[256], 0x11);
+
+    write_block(&mut vm, 2, 0x22);
+    diskimage::flush(std::slice::from_ref(&image), &mut vm);
+    assert!(!vm.devices.disk().drive(0).is_dirty());
+
+    let mut vm = VirtualMachine::default();
+    image.load(&mut vm).unwrap();
+    assert_eq!(vm.devices.disk().dump()[256], 0x11);
+    assert_eq!(vm.devices.disk().dump()[512], 0x22);
+}
+
+#[test]
+fn test_disk_image_flush_command() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("disk.mb8d");
+    std::fs::write(&path, vec![0; IMAGE_SIZE]).unwrap();
+    let image = DiskImage::new(path.clone(), DiskMode::ReadWrite);
+
+    let mut vm = VirtualMachine::default();
+    image.load(&mut vm).unwrap();
+    write_block(&mut vm, 4, 0x44);
+    diskimage::sync(std::slice::from_ref(&image), &mut vm);
+    assert_eq!(std::fs::read(&path).unwrap()[4 * 256], 0);
+
+    vm.devices.write(DISK + 1, DISK_CMD_FLUSH);
+    diskimage::sync(std::slice::from_ref(&image), &mut vm);
+    assert_eq!(std::fs::read(&path).unwrap()[4 * 256], 0x44);
+}
+
+#[test]
+fn test_disk_image_read_only_and_overlay() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("disk.mb8d");
+    let mut data = vec![0; IMAGE_SIZE];
+    data[256] = 0x11;
+    std::fs::write(&path, &data).unwrap();
+
+    let read_only = DiskImage::new(path.clone(), DiskMode::ReadOnly);
+    let mut vm = VirtualMachine::default();
+    read_only.load(&mut vm).unwrap();
+    write_block(&mut vm, 1, 0x99);
+    assert_eq!(vm.devices.disk().dump()[256], 0x11);
+
+    let overlay = DiskImage::new(path.clone(), DiskMode::Overlay);
+    let mut vm = VirtualMachine::default();
+    overlay.load(&mut vm).unwrap();
+    write_block(&mut vm, 1, 0x99);
+    assert_eq!(vm.devices.disk().dump()[256], 0x99);
+    diskimage::flush(&[overlay], &mut vm);
+    assert_eq!(std::fs::read(&path).unwrap(), data);
+}
+
+#[test]
+fn test_disk_image_errors() {
+    let dir = tempdir().unwrap();
+    let missing = DiskImage::new(dir.path().join("missing.mb8d"), DiskMode::ReadOnly);
+    assert!(missing.load(&mut VirtualMachine::default()).is_err());
+
+    let path = dir.path().join("short.mb8d");
+    std::fs::write(&path, [0; 100]).unwrap();
+    let short = DiskImage::new(path, DiskMode::ReadWrite);
+    let err = short.load(&mut VirtualMachine::default()).unwrap_err();
+    assert!(err.contains("100 bytes"), "{err}");
+
+    assert_eq!("ro".parse(), Ok(DiskMode::ReadOnly));
+    assert_eq!("overlay".parse(), Ok(DiskMode::Overlay));
+    assert!("rwx".parse::<DiskMode>().is_err());
+}
+
+#[test]
+fn test_disk_image_second_drive() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("big.mb8d");
+    std::fs::write(&path, vec![0; 1024 * 256]).unwrap();
+    let mut image = DiskImage::new(path.clone(), DiskMode::ReadWrite);
+    image.drive = 1;
+
+    let mut vm = VirtualMachine::default();
+    image.load(&mut vm).unwrap();
+    vm.devices.write(DISK + 0x111, 1);
+    vm.devices.write(DISK + 0x110, 3);
+    write_block(&mut vm, 0xE8, 0x55);
+    diskimage::flush(std::slice::from_ref(&image), &mut vm);
+
+    assert_eq!(std::fs::read(&path).unwrap()[1000 * 256], 0x55);
+    assert_eq!(vm.devices.disk().dump()[0xE8 * 256], 0);
+}
+
+#[test]
+fn test_bad_block_parse() {
+    assert_eq!("7".parse(), Ok(BadBlock { drive: 0, block: 7 }));
+    assert_eq!(
+        "2:300".parse(),
+        Ok(BadBlock {
+            drive: 2,
+            block: 300
+        })
+    );
+    assert!("4:1".parse::<BadBlock>().is_err());
+    assert!("65536".parse::<BadBlock>().is_err());
+    assert!("1:".parse::<BadBlock>().is_err());
+}
@@ -0,0 +1,102 @@
+use std::fs;
+
+use mb8_cli::{
+    disktool::{check, get, ls, migrate, mkfs, put, rm},
+    filesystem::Version,
+};
+use tempfile::tempdir;
+
+#[test]
+fn test_disktool_round_trip() {
+    let dir = tempdir().unwrap();
+    let image = dir.path().join("disk.mb8d");
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, b"remember").unwrap();
+
+    mkfs(
+        &image,
+        std::slice::from_ref(&notes),
+        Version::V1,
+        256,
+        false,
+    )
+    .unwrap();
+    assert!(mkfs(&image, &[], Version::V1, 256, false).is_err());
+    let added = put(&image, std::slice::from_ref(&notes), Some("copy")).unwrap();
+    assert_eq!(added[0].name, "copy");
+
+    let table = ls(&image).unwrap();
+    assert!(
+        table.contains("    0      1       1    256  notes\n"),
+        "{table}"
+    );
+    assert!(
+        table.contains("    1      2       1    256  copy\n"),
+        "{table}"
+    );
+    assert!(
+        table.ends_with("2 files, 253 blocks free, version 1\n"),
+        "{table}"
+    );
+
+    let out = dir.path().join("out.txt");
+    get(&image, "copy", &out).unwrap();
+    let data = fs::read(&out).unwrap();
+    assert_eq!(data.len(), 256);
+    assert_eq!(&data[..8], b"remember");
+
+    rm(&image, &["notes".to_string()]).unwrap();
+    assert!(get(&image, "notes", &out).is_err());
+    assert!(check(&image).unwrap().is_empty());
+}
+
+#[test]
+fn test_disktool_changes_nothing_on_error() {
+    let dir = tempdir().unwrap();
+    let image = dir.path().join("disk.mb8d");
+    let a = dir.path().join("a.txt");
+    fs::write(&a, b"a").unwrap();
+    mkfs(&image, &[], Version::V1, 256, false).unwrap();
+    let before = fs::read(&image).unwrap();
+
+    assert!(put(&image, &[a.clone(), dir.path().join("missing.txt")], None).is_err());
+    assert!(put(&image, &[a.clone(), a.clone()], Some("x")).is_err());
+    assert!(rm(&image, &["a".to_string()]).is_err());
+    assert_eq!(fs::read(&image).unwrap(), before);
+    assert!(ls(&dir.path().join("missing.mb8d")).is_err());
+}
+
+#[test]
+fn test_disktool_migrate() {
+    let dir = tempdir().unwrap();
+    let image = dir.path().join("disk.mb8d");
+    let notes = dir.path().join("notes.txt");
+    fs::write(&notes, b"remember").unwrap();
+    mkfs(&image, &[notes], Version::V1, 256, false).unwrap();
+
+    let v2 = dir.path().join("v2.mb8d");
+    migrate(&image, &v2).unwrap();
+    let table = ls(&v2).unwrap();
+    assert!(
+        table.contains("    0      6       1    256  notes\n"),
+        "{table}"
+    );
+    assert!(
+        table.ends_with("1 files, 249 blocks free, version 2\n"),
+        "{table}"
+    );
+    assert!(check(&v2).unwrap().is_empty());
+    assert!(migrate(&v2, &v2).is_err());
+
+    let fresh = dir.path().join("fresh.mb8d");
+    mkfs(&fresh, &[], Version::V2, 4096, false).unwrap();
+    put(
+        &fresh,
+        &[dir.path().join("notes.txt")],
+        Some("a_long_file_name.txt"),
+    )
+    .unwrap();
+    let out = dir.path().join("out.txt");
+    get(&fresh, "a_long_file_name.txt", &out).unwrap();
+    assert_eq!(fs::read(&out).unwrap(), b"remember");
+}
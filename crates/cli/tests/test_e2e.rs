@@ -0,0 +1,57 @@
+use std::path::PathBuf;
+
+use mb8_cli::e2e::{run_script, unescape, Driver, Pattern};
+
+fn repo(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .join(path)
+}
+
+#[test]
+fn test_e2e_scripts() {
+    let mut scripts: Vec<PathBuf> = std::fs::read_dir(repo("tests/e2e"))
+        .unwrap()
+        .map(|entry| entry.unwrap().path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "script"))
+        .collect();
+    scripts.sort();
+    assert!(!scripts.is_empty());
+    for script in scripts {
+        if let Err(err) = run_script(&script) {
+            panic!("{err}");
+        }
+    }
+}
+
+#[test]
+fn test_patterns() {
+    assert_eq!(unescape(r"ls\n\s\\").unwrap(), "ls\n \\");
+    assert!(unescape(r"\q").is_err());
+
+    let screen = ">ls\nsh\nls\n>\n";
+    assert!(Pattern::parse("ls").unwrap().matches(screen));
+    assert!(Pattern::parse(r"sh\nls").unwrap().matches(screen));
+    assert!(Pattern::parse("/^sh$/").unwrap().matches(screen));
+    assert!(!Pattern::parse("/^s$/").unwrap().matches(screen));
+    assert!(Pattern::parse("/(/").is_err());
+    assert_eq!(Pattern::parse("/^sh$/").unwrap().to_string(), "/^sh$/");
+}
+
+#[test]
+fn test_driver_failure_shows_screen() {
+    let disk = vec![repo("user/sh.bin")];
+    let mut driver = Driver::boot(&repo("kernel/main.bin"), disk).unwrap();
+    driver.wait(&Pattern::parse("/^>$/").unwrap()).unwrap();
+
+    driver.budget = 10_000;
+    let err = driver.wait(&Pattern::parse("hello").unwrap()).unwrap_err();
+    assert!(
+        err.starts_with("\"hello\" not on screen after 10000 cycles\n--- screen ---\nMB8 kernel"),
+        "{err}"
+    );
+    assert!(driver.wait_halt().is_err());
+
+    let missing = Driver::boot(&repo("kernel/main.bin"), vec![repo("user/nope.bin")]);
+    assert!(missing.unwrap_err().ends_with("user/nope.bin not found"));
+}
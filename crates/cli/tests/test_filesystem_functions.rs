@@ -1,5 +1,7 @@
+use mb8::fs::v1::DIR_ENTRIES;
 use mb8::vm::VirtualMachine;
-use mb8_cli::filesystem::makefs;
+use mb8_cli::filesystem::{makefs, FileSystem, Version};
+use mb8_isa::exec::ExecHeader;
 use std::fs;
 use tempfile::tempdir;
 
@@ -66,3 +68,207 @@ fn test_two_files() {
     assert_eq!(start_block_b, 2);
     assert_eq!(size_blocks_b, 1);
 }
+
+#[test]
+fn test_raw_image_gets_exec_header() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("prog.bin");
+    let mut image = vec![0x21, 0x42, 0x01, 0x00];
+    image.resize(0x1000, 0);
+    fs::write(&path, &image).unwrap();
+
+    let mut vm = VirtualMachine::default();
+    makefs(vec![path], &mut vm);
+
+    let disk_img = vm.devices.disk().dump();
+    assert_eq!(disk_img[2], 1);
+    let header = ExecHeader::parse(&disk_img[256..]).unwrap();
+    assert_eq!(header.load, 0x1000);
+    assert_eq!(header.entry, 0x1000);
+    assert_eq!(header.code_size, 4);
+    assert_eq!(header.bss_size, 0x0FFC);
+    assert_eq!(&disk_img[256 + 16..256 + 20], &image[..4]);
+}
+
+#[test]
+fn test_invalid_executable_is_rejected() {
+    let dir = tempdir().unwrap();
+    let path = dir.path().join("bad.bin");
+    let header = ExecHeader {
+        load: 0x0800,
+        entry: 0x0800,
+        code_size: 2,
+        bss_size: 0,
+        stack_size: 0x100,
+    };
+    let mut data = header.to_bytes().to_vec();
+    data.extend([0x01, 0x00]);
+    fs::write(&path, &data).unwrap();
+
+    let mut vm = VirtualMachine::default();
+    makefs(vec![path], &mut vm);
+
+    assert_eq!(vm.devices.disk().dump()[0], 0);
+}
+
+#[test]
+fn test_makefs_skips_bad_files_and_continues() {
+    let dir = tempdir().unwrap();
+    let long = dir.path().join("muchtoolong.txt");
+    let ok = dir.path().join("ok.txt");
+    fs::write(&long, b"long").unwrap();
+    fs::write(&ok, b"ok").unwrap();
+
+    let mut vm = VirtualMachine::default();
+    makefs(vec![dir.path().join("missing.txt"), long, ok], &mut vm);
+
+    let disk_img = vm.devices.disk().dump();
+    assert_eq!(disk_img[0], 1);
+    assert_eq!(&disk_img[3..6], b"ok\0");
+    assert_eq!(disk_img[16], 0);
+}
+
+#[test]
+fn test_filesystem_add_and_read() {
+    let mut fs = FileSystem::new();
+    let a = fs.add("a", &[1; 300]).unwrap();
+    assert_eq!((a.index, a.start, a.blocks), (0, 1, 2));
+    let b = fs.add("b", &[2; 256]).unwrap();
+    assert_eq!((b.index, b.start, b.blocks), (1, 3, 1));
+    let empty = fs.add("empty", &[]).unwrap();
+    assert_eq!(empty.blocks, 1);
+
+    let data = fs.read("a").unwrap();
+    assert_eq!(data.len(), 512);
+    assert_eq!(&data[..300], &[1; 300]);
+    assert!(data[300..].iter().all(|&byte| byte == 0));
+    assert_eq!(fs.entries().len(), 3);
+    assert_eq!(fs.find("b"), Some(b));
+    assert!(fs.read("c").is_err());
+}
+
+#[test]
+fn test_filesystem_rejects_bad_names() {
+    let mut fs = FileSystem::new();
+    assert!(fs.add("", b"x").is_err());
+    assert!(fs.add("ninechars", b"x").is_err());
+    assert!(fs.add("a b", b"x").is_err());
+    fs.add("a", b"x").unwrap();
+    assert!(fs.add("a", b"y").is_err());
+}
+
+#[test]
+fn test_filesystem_remove_reuses_space() {
+    let mut fs = FileSystem::new();
+    fs.add("a", &[1; 512]).unwrap();
+    fs.add("b", &[2; 256]).unwrap();
+    let removed = fs.remove("a").unwrap();
+    assert_eq!(removed.start, 1);
+    assert!(fs.remove("a").is_err());
+
+    // A one block file fits in the hole, a three block one goes after `b`.
+    let c = fs.add("c", &[3; 10]).unwrap();
+    assert_eq!((c.index, c.start), (0, 1));
+    let d = fs.add("d", &[4; 700]).unwrap();
+    assert_eq!((d.index, d.start), (2, 4));
+    assert!(fs.check().is_empty());
+}
+
+#[test]
+fn test_filesystem_full() {
+    let mut fs = FileSystem::new();
+    for i in 0..DIR_ENTRIES {
+        fs.add(&format!("f{i}"), b"x").unwrap();
+    }
+    assert!(fs.add("more", b"x").is_err());
+
+    let mut fs = FileSystem::new();
+    assert!(fs.add("big", &vec![0; 255 * 256]).is_ok());
+    assert!(fs.add("more", b"x").is_err());
+}
+
+#[test]
+fn test_filesystem_check() {
+    let mut image = FileSystem::new().into_image();
+    // `a` at blocks 1..3, `b` overlapping it at 2, `c` in the directory.
+    image[..3].copy_from_slice(&[1, 1, 2]);
+    image[3] = b'a';
+    image[16..19].copy_from_slice(&[1, 2, 1]);
+    image[19] = b'b';
+    image[32..35].copy_from_slice(&[1, 0, 1]);
+    image[35] = b'c';
+    image[48..51].copy_from_slice(&[7, 250, 10]);
+    image[51] = b'd';
+    let problems = FileSystem::from_image(image).check();
+    assert_eq!(problems.len(), 4, "{problems:?}");
+    assert!(problems[0].contains("invalid flag"));
+    assert!(problems[1].contains("block 2 is also used by a"));
+    assert!(problems[2].contains("starts in the file system metadata"));
+    assert!(problems[3].contains("past the end"));
+}
+
+#[test]
+fn test_filesystem_v2() {
+    let mut fs = FileSystem::format(Version::V2);
+    assert!(fs.check().is_empty());
+    let free = fs.free_blocks();
+
+    let entry = fs.add("a_long_file_name.txt", &[7; 300]).unwrap();
+    assert_eq!((entry.start, entry.blocks, entry.size), (6, 2, 300));
+    assert_eq!(fs.read("a_long_file_name.txt").unwrap(), vec![7; 300]);
+    assert_eq!(fs.free_blocks(), free - 2);
+    assert!(fs.add(&"x".repeat(23), b"x").is_err());
+
+    fs.remove("a_long_file_name.txt").unwrap();
+    assert_eq!(fs.free_blocks(), free);
+    assert!(fs.check().is_empty());
+
+    fs.add("b", b"b").unwrap();
+    let mut image = fs.into_image();
+    // Free `b`'s block in the bitmap.
+    image[256] &= !0x02;
+    let problems = FileSystem::from_image(image).check();
+    assert_eq!(problems.len(), 1, "{problems:?}");
+    assert!(problems[0].contains("block 6: used by b but free in the bitmap"));
+}
+
+#[test]
+fn test_filesystem_migrate() {
+    let mut fs = FileSystem::new();
+    let exe = ExecHeader {
+        load: 0x2000,
+        entry: 0x2000,
+        code_size: 3,
+        bss_size: 0,
+        stack_size: 0,
+    };
+    let mut bin = exe.to_bytes().to_vec();
+    bin.extend([1, 2, 3]);
+    fs.add("exe", &bin).unwrap();
+    fs.add("raw", b"raw").unwrap();
+
+    let v2 = fs.migrate().unwrap();
+    assert_eq!(v2.version(), Version::V2);
+    assert!(v2.check().is_empty());
+    assert_eq!(v2.read("exe").unwrap(), bin);
+    assert_eq!(v2.read("raw").unwrap().len(), 256);
+    assert!(v2.migrate().is_err());
+}
+
+#[test]
+fn test_filesystem_large_v2() {
+    let mut fs = FileSystem::with_blocks(Version::V2, 8192).unwrap();
+    assert_eq!(fs.image().len(), 8192 * 256);
+    assert!(fs.check().is_empty());
+    // Four bitmap blocks push the directory to blocks 5..9.
+    assert_eq!(fs.free_blocks(), 8192 - 9);
+
+    let big = fs.add("big", &vec![1; 300 * 256]).unwrap();
+    assert_eq!((big.start, big.blocks), (9, 300));
+    let after = fs.add("after", b"x").unwrap();
+    assert_eq!(after.start, 309);
+    assert!(fs.check().is_empty());
+
+    assert!(FileSystem::with_blocks(Version::V2, 4).is_err());
+    assert!(FileSystem::with_blocks(Version::V1, 0x10001).is_err());
+}
@@ -0,0 +1,91 @@
+use std::{io::Cursor, path::PathBuf, time::Duration};
+
+use mb8::{hle::Hle, vm::VirtualMachine};
+use mb8_cli::{
+    headless::{Headless, Stop},
+    vmrun::boot,
+};
+
+fn repo(path: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../..")
+        .join(path)
+}
+
+/// A headless machine running the kernel with `user` programs on the disk.
+fn headless(user: &[&str]) -> Headless {
+    let mut vm = VirtualMachine::default();
+    let user = user.iter().map(|name| repo(&format!("user/{name}.bin")));
+    boot(&mut vm, &repo("kernel/main.bin"), user.collect(), None).unwrap();
+    Headless::new(vm)
+}
+
+#[test]
+fn test_headless_shell_session() {
+    let mut headless = headless(&["sh", "ls", "exit"]);
+    headless.timeout = Some(Duration::from_secs(30));
+    let mut output = Vec::new();
+    let stop = headless
+        .run(Cursor::new(b"ls\nexit\n".to_vec()), &mut output)
+        .unwrap();
+
+    assert_eq!(stop, Stop::Halted(Some(0)));
+    assert_eq!(stop.exit_code(), 0);
+    assert_eq!(
+        String::from_utf8(output).unwrap(),
+        "MB8 kernel is starting...\nType 'help' for more information\n\
+         >ls\nsh\nls\nexit\n>exit\n"
+    );
+    let screen = headless.screen();
+    assert_eq!(screen.lines().count(), 25);
+    assert!(screen.starts_with("MB8 kernel is starting...\n"));
+    assert!(screen.contains("\n>ls\nsh\nls\nexit\n>exit\n"), "{screen}");
+}
+
+#[test]
+fn test_headless_cycle_limit() {
+    let mut headless = headless(&["sh"]);
+    headless.max_cycles = Some(200_000);
+    let mut output = Vec::new();
+    let stop = headless.run(Cursor::new(Vec::new()), &mut output).unwrap();
+
+    assert_eq!(stop, Stop::CycleLimit);
+    assert_eq!(stop.exit_code(), 124);
+    assert!(headless.vm.cycles >= 200_000);
+    assert!(output.ends_with(b">"), "waits at the prompt");
+}
+
+#[test]
+fn test_headless_cycle_limit_while_waiting_for_key_under_hle() {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.load_program(&std::fs::read(repo("user/sh.bin")).unwrap())
+        .unwrap();
+    let mut headless = Headless::new(vm);
+    headless.max_cycles = Some(200_000);
+    headless.timeout = Some(Duration::from_secs(10));
+    let mut output = Vec::new();
+    let stop = headless.run(Cursor::new(Vec::new()), &mut output).unwrap();
+
+    assert_eq!(stop, Stop::CycleLimit);
+    assert!(headless.vm.cycles >= 200_000);
+    assert!(output.ends_with(b">"), "waits at the prompt");
+}
+
+#[test]
+fn test_headless_host_files() {
+    let dir = tempfile::tempdir().unwrap();
+    std::fs::write(dir.path().join("notes.txt"), b"notes").unwrap();
+    std::fs::write(dir.path().join("data.bin"), [0; 300]).unwrap();
+    let mut headless = headless(&["sh", "hls"]);
+    headless.vm.devices.hostfs().set_root(dir.path()).unwrap();
+    headless.timeout = Some(Duration::from_secs(30));
+    headless.max_cycles = Some(2_000_000);
+    let mut output = Vec::new();
+    headless
+        .run(Cursor::new(b"hls\n".to_vec()), &mut output)
+        .unwrap();
+
+    let output = String::from_utf8(output).unwrap();
+    assert!(output.ends_with(">hls\ndata.bin\nnotes.txt\n>"), "{output}");
+}
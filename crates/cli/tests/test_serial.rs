@@ -0,0 +1,95 @@
+use std::{
+    io::{Cursor, Read, Write},
+    net::TcpStream,
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use mb8::{dev::uart::Uart, dev::Device};
+use mb8_cli::serial::{Serial, SerialSpec};
+
+/// A writer whose output can be inspected after it was moved into a `Serial`.
+#[derive(Clone, Default)]
+struct Shared(Arc<Mutex<Vec<u8>>>);
+
+impl Write for Shared {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        self.0.lock().unwrap().extend_from_slice(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Pump until the UART has received `count` bytes, reading them back.
+fn receive(serial: &mut Serial, uart: &mut Uart, count: usize) -> Vec<u8> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    let mut received = Vec::new();
+    while received.len() < count && Instant::now() < deadline {
+        serial.pump(uart);
+        while uart.read(0) & 0x01 != 0 {
+            received.push(uart.read(1));
+        }
+    }
+    received
+}
+
+#[test]
+fn test_serial_spec_parse() {
+    assert_eq!("stdio".parse(), Ok(SerialSpec::Stdio));
+    assert_eq!(
+        "tcp:127.0.0.1:5555".parse(),
+        Ok(SerialSpec::Tcp("127.0.0.1:5555".to_string()))
+    );
+    assert_eq!(
+        "pty:/dev/pts/3".parse(),
+        Ok(SerialSpec::Pty("/dev/pts/3".into()))
+    );
+    assert!("tcp:".parse::<SerialSpec>().is_err());
+    assert!("com1".parse::<SerialSpec>().is_err());
+}
+
+#[test]
+fn test_serial_pump_respects_fifo() {
+    let input: Vec<u8> = (0..40).collect();
+    let output = Shared::default();
+    let mut serial = Serial::new(Cursor::new(input.clone()), output.clone());
+    let mut uart = Uart::default();
+
+    // The RX FIFO holds 16 bytes; the rest waits on the host side.
+    assert_eq!(receive(&mut serial, &mut uart, 40), input);
+
+    for byte in b"ok\n" {
+        uart.write(1, *byte);
+    }
+    serial.pump(&mut uart);
+    assert_eq!(*output.0.lock().unwrap(), b"ok\n");
+}
+
+#[test]
+fn test_serial_over_tcp() {
+    let addr = "127.0.0.1:45871";
+    let server = thread::spawn(move || Serial::open(&SerialSpec::Tcp(addr.to_string())));
+    let mut client = loop {
+        if let Ok(stream) = TcpStream::connect(addr) {
+            break stream;
+        }
+        thread::sleep(Duration::from_millis(10));
+    };
+    let mut serial = server.join().unwrap().unwrap();
+    let mut uart = Uart::default();
+
+    client.write_all(b"ls\n").unwrap();
+    assert_eq!(receive(&mut serial, &mut uart, 3), b"ls\n");
+
+    for byte in b"sh\n" {
+        uart.write(1, *byte);
+    }
+    serial.pump(&mut uart);
+    let mut reply = [0; 3];
+    client.read_exact(&mut reply).unwrap();
+    assert_eq!(&reply, b"sh\n");
+}
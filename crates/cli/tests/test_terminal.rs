@@ -0,0 +1,57 @@
+use crossterm::event::{KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use mb8::vm::VirtualMachine;
+use mb8_cli::terminal::{frame, key_byte};
+
+#[test]
+fn test_key_byte() {
+    let key = |code| KeyEvent::new(code, KeyModifiers::NONE);
+    assert_eq!(key_byte(&key(KeyCode::Char('a'))), Some(b'a'));
+    assert_eq!(key_byte(&key(KeyCode::Enter)), Some(b'\n'));
+    assert_eq!(key_byte(&key(KeyCode::Backspace)), Some(0x08));
+    assert_eq!(key_byte(&key(KeyCode::Esc)), Some(0x1B));
+    assert_eq!(key_byte(&key(KeyCode::Char('é'))), None);
+    assert_eq!(key_byte(&key(KeyCode::F(1))), None);
+
+    let mut release = key(KeyCode::Char('a'));
+    release.kind = KeyEventKind::Release;
+    assert_eq!(key_byte(&release), None);
+}
+
+#[test]
+fn test_tty_frame() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.write(0xF000, 0x01);
+    for byte in b"hi\n>" {
+        vm.devices.write(0xF001, *byte);
+    }
+    let frame = frame(vm.devices.gpu());
+
+    let lines: Vec<&str> = frame.split("\r\n").collect();
+    assert_eq!(lines.len(), 25);
+    assert_eq!(lines[0], format!("\x1b[Hhi{}\x1b[K", " ".repeat(38)));
+    assert_eq!(lines[1], format!(">{}\x1b[K", " ".repeat(39)));
+    assert!(
+        lines[24].ends_with("\x1b[J\x1b[2;2H\x1b[?25h"),
+        "cursor after the prompt"
+    );
+}
+
+#[test]
+fn test_bitmap_frame() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.write(0xF000, 0x02);
+    // Pixel (0, 0) and (1, 0)-(1, 1) on the first row pair; (63, 31) at the bottom right.
+    vm.devices.write(0xF001, 0xC0);
+    vm.devices.write(0xF001 + 8, 0x40);
+    vm.devices.write(0xF001 + 255, 0x01);
+    let frame = frame(vm.devices.gpu());
+
+    let lines: Vec<&str> = frame.split("\r\n").collect();
+    assert_eq!(lines.len(), 16);
+    assert!(lines[0].starts_with("\x1b[H▀█ "));
+    assert!(lines[15].starts_with(&format!("{}▄\x1b[K", " ".repeat(63))));
+    assert!(
+        frame.ends_with("\x1b[J\x1b[?25l"),
+        "no cursor in bitmap mode"
+    );
+}
@@ -0,0 +1,19 @@
+use mb8_cli::wav::write_wav;
+
+#[test]
+fn test_write_wav() {
+    let mut out = Vec::new();
+    write_wav(&mut out, 22_050, &[0, 1, -1]).unwrap();
+
+    assert_eq!(out.len(), 44 + 6);
+    assert_eq!(&out[0..4], b"RIFF");
+    assert_eq!(out[4..8], 42u32.to_le_bytes());
+    assert_eq!(&out[8..16], b"WAVEfmt ");
+    assert_eq!(out[22..24], 1u16.to_le_bytes(), "mono");
+    assert_eq!(out[24..28], 22_050u32.to_le_bytes());
+    assert_eq!(out[28..32], 44_100u32.to_le_bytes(), "byte rate");
+    assert_eq!(out[34..36], 16u16.to_le_bytes(), "bits per sample");
+    assert_eq!(&out[36..40], b"data");
+    assert_eq!(out[40..44], 6u32.to_le_bytes());
+    assert_eq!(&out[44..], [0x00, 0x00, 0x01, 0x00, 0xFF, 0xFF]);
+}
@@ -0,0 +1,1036 @@
+//! Multi-pass assembly of a parsed [`Program`] into banks of output bytes.
+//!
+//! Every pass walks all items with the symbol values of the previous pass.
+//! Assembly is finished once a pass does not change any symbol; only the
+//! diagnostics of that final pass are reported.
+//!
+//! In object mode every bank becomes a section of an [`Object`]. Banks without
+//! an `#addr` are relocatable: their labels are link-time values, unknown
+//! symbols are treated as references to other objects, and every place such a
+//! value is written is recorded as a relocation.
+
+use std::collections::{BTreeMap, HashMap};
+
+use mb8_isa::symbols::SymbolMap;
+
+use crate::{
+    error::{AsmError, Location},
+    expr::{
+        check_type, eval, int_bytes, Env, Expr, Fixup, LinkOp, Local, Locals, Output, Symbolic,
+        Value,
+    },
+    lexer::{Token, TokenKind},
+    object::{Object, RelocKind, Relocation, Section, Symbol, Target},
+    parser::{BankDef, Item, Program},
+    rules::{Arg, Binding, ParamKind},
+    source::Sources,
+};
+
+/// Upper bound on the number of passes before giving up on resolving addresses.
+const MAX_PASSES: usize = 16;
+
+/// Output of a successful assembly.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Assembly {
+    /// Output image, banks placed at their `#outp` offsets.
+    pub binary: Vec<u8>,
+    /// Final value of every label and constant, keyed by its full name (e.g. `parent.sub`).
+    pub symbols: BTreeMap<String, i128>,
+    /// Label and line table for debuggers and the disassembler.
+    pub map: SymbolMap,
+}
+
+/// Assemble `program` into a binary image.
+///
+/// # Errors
+/// Returns every diagnostic of the final pass.
+pub fn assemble(program: &Program, sources: &Sources) -> Result<Assembly, Vec<AsmError>> {
+    run(program, sources, false)?.finish()
+}
+
+/// Assemble `program` into a relocatable object.
+///
+/// # Errors
+/// Returns every diagnostic of the final pass.
+pub fn assemble_object(program: &Program, sources: &Sources) -> Result<Object, Vec<AsmError>> {
+    run(program, sources, true)?.finish_object()
+}
+
+/// Run passes until all symbols are stable.
+fn run<'a>(
+    program: &'a Program,
+    sources: &'a Sources,
+    object: bool,
+) -> Result<Pass<'a>, Vec<AsmError>> {
+    let mut known = HashMap::new();
+    for _ in 0..MAX_PASSES {
+        let mut pass = Pass::new(program, sources, known, object);
+        pass.run();
+        if pass.symbols == pass.known {
+            return Ok(pass);
+        }
+        known = pass.symbols;
+    }
+    Err(vec![AsmError::new(format!(
+        "addresses did not settle after {MAX_PASSES} passes"
+    ))])
+}
+
+/// Value of a label or constant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Sym {
+    /// Relocatable bank the value is an offset into.
+    bank: Option<usize>,
+    value: i128,
+    /// Defined by a label rather than a constant.
+    label: bool,
+}
+
+#[derive(Debug)]
+struct Bank {
+    name: String,
+    addr: i128,
+    size: Option<i128>,
+    outp: Option<i128>,
+    fill: bool,
+    /// Created implicitly because output appeared before any `#bankdef`.
+    implicit: bool,
+    /// Placed by the linker (object mode, no `#addr`).
+    relocatable: bool,
+    pc: i128,
+    data: Vec<Option<u8>>,
+}
+
+impl Bank {
+    fn implicit(object: bool) -> Self {
+        Self {
+            name: if object { "text" } else { "default" }.to_string(),
+            addr: 0,
+            size: None,
+            outp: Some(0),
+            fill: false,
+            implicit: true,
+            relocatable: object,
+            pc: 0,
+            data: Vec::new(),
+        }
+    }
+}
+
+/// Labels of one `asm` block expansion.
+#[derive(Debug, Default)]
+struct LocalScope {
+    known: HashMap<String, i128>,
+    current: HashMap<String, i128>,
+}
+
+struct Pass<'a> {
+    program: &'a Program,
+    sources: &'a Sources,
+    object: bool,
+    known: HashMap<String, Sym>,
+    symbols: HashMap<String, Sym>,
+    errors: Vec<AsmError>,
+    banks: Vec<Bank>,
+    bank: usize,
+    /// Link-time values written so far: bank, location and fixup with its offset into the bank.
+    fixups: Vec<(usize, Location, Fixup)>,
+    /// Start address and source location of everything emitted into fixed banks.
+    lines: Vec<(i128, Location)>,
+    /// Current label hierarchy, used to resolve `.sublabels`.
+    path: Vec<String>,
+    loc: Location,
+}
+
+impl<'a> Pass<'a> {
+    fn new(
+        program: &'a Program,
+        sources: &'a Sources,
+        known: HashMap<String, Sym>,
+        object: bool,
+    ) -> Self {
+        Self {
+            program,
+            sources,
+            object,
+            known,
+            symbols: HashMap::new(),
+            errors: Vec::new(),
+            banks: vec![Bank::implicit(object)],
+            bank: 0,
+            fixups: Vec::new(),
+            lines: Vec::new(),
+            path: Vec::new(),
+            loc: Location::default(),
+        }
+    }
+
+    fn error(&mut self, message: impl Into<String>) {
+        let error = self.sources.error_at(self.loc.file, self.loc.line, message);
+        self.errors.push(error);
+    }
+
+    fn pc(&self) -> i128 {
+        self.banks[self.bank].pc
+    }
+
+    /// Value of an address in the current bank, a link-time value if the bank is relocatable.
+    fn address(&self, addr: i128) -> Value {
+        if self.banks[self.bank].relocatable {
+            Value::Symbolic(Symbolic::address(Target::Section(self.bank), addr))
+        } else {
+            Value::Int {
+                value: addr,
+                size: None,
+            }
+        }
+    }
+
+    fn run(&mut self) {
+        for located in &self.program.items {
+            self.loc = located.loc;
+            match &located.item {
+                Item::Label { depth, name } => {
+                    let bank = &self.banks[self.bank];
+                    let sym = Sym {
+                        bank: bank.relocatable.then_some(self.bank),
+                        value: bank.pc,
+                        label: true,
+                    };
+                    self.define(*depth, name, sym);
+                }
+                Item::Constant { depth, name, value } => {
+                    let value = self.eval_int(value);
+                    let sym = Sym {
+                        bank: None,
+                        value,
+                        label: false,
+                    };
+                    self.define(*depth, name, sym);
+                }
+                Item::Instruction(tokens) => {
+                    let pc = self.pc();
+                    let mut scopes = Vec::new();
+                    let output = match self.assemble_line(tokens, pc, &mut scopes, false) {
+                        Ok(output) => output,
+                        Err(message) => {
+                            self.error(message);
+                            self.assemble_line(tokens, pc, &mut scopes, true)
+                                .unwrap_or_default()
+                        }
+                    };
+                    self.emit(output);
+                }
+                Item::Addr(expr) => {
+                    let addr = self.eval_int(expr);
+                    self.banks[self.bank].pc = addr;
+                }
+                Item::Data { size, values } => {
+                    for value in values {
+                        let value = self.eval_value(value);
+                        match data_output(&value, *size) {
+                            Ok(output) => self.emit(output),
+                            Err(message) => self.error(message),
+                        }
+                    }
+                }
+                Item::Bank(name) => match self.banks.iter().position(|bank| &bank.name == name) {
+                    Some(index) => self.bank = index,
+                    None => self.error(format!("unknown bank '{name}'")),
+                },
+                Item::BankDef(def) => self.define_bank(def),
+            }
+        }
+    }
+
+    fn define_bank(&mut self, def: &BankDef) {
+        let mut field = |expr: &Option<Expr>| expr.as_ref().map(|expr| self.eval_int(expr));
+        let addr = field(&def.addr);
+        let size = field(&def.size);
+        let outp = field(&def.outp);
+        if def.fill && size.is_none() {
+            self.error(format!("bank '{}' uses #fill without #size", def.name));
+        }
+        if self.banks.iter().any(|bank| bank.name == def.name) {
+            self.error(format!("bank '{}' is already defined", def.name));
+        }
+        let bank = Bank {
+            name: def.name.clone(),
+            addr: addr.unwrap_or(0),
+            size,
+            outp,
+            fill: def.fill,
+            implicit: false,
+            relocatable: self.object && addr.is_none(),
+            pc: addr.unwrap_or(0),
+            data: Vec::new(),
+        };
+        if self.banks.len() == 1 && self.banks[0].implicit && self.banks[0].data.is_empty() {
+            self.banks[0] = bank;
+            self.bank = 0;
+        } else {
+            self.banks.push(bank);
+            self.bank = self.banks.len() - 1;
+        }
+    }
+
+    fn emit(&mut self, output: Output) {
+        let bytes = output.bytes;
+        if bytes.is_empty() {
+            return;
+        }
+        let bank = &self.banks[self.bank];
+        let pc = bank.pc;
+        let start = pc - bank.addr;
+        let end = start + bytes.len() as i128;
+        if start < 0 || bank.size.is_some_and(|size| end > size) {
+            let name = bank.name.clone();
+            self.error(format!("output at 0x{pc:04X} is outside bank '{name}'"));
+            self.banks[self.bank].pc += bytes.len() as i128;
+            return;
+        }
+
+        let bank = &mut self.banks[self.bank];
+        let start = start as usize;
+        if bank.data.len() < start + bytes.len() {
+            bank.data.resize(start + bytes.len(), None);
+        }
+        let overlap = bank.data[start..start + bytes.len()]
+            .iter()
+            .any(Option::is_some);
+        for (slot, byte) in bank.data[start..].iter_mut().zip(&bytes) {
+            *slot = Some(*byte);
+        }
+        bank.pc += bytes.len() as i128;
+        if !bank.relocatable {
+            self.lines.push((pc, self.loc));
+        }
+        for fixup in output.fixups {
+            let fixup = Fixup {
+                offset: fixup.offset + start,
+                ..fixup
+            };
+            self.fixups.push((self.bank, self.loc, fixup));
+        }
+        if overlap {
+            self.error(format!("output at 0x{pc:04X} overlaps earlier output"));
+        }
+    }
+
+    fn define(&mut self, depth: usize, name: &str, sym: Sym) {
+        if depth > self.path.len() {
+            self.error(format!("sublabel '{name}' has no parent label"));
+            return;
+        }
+        self.path.truncate(depth);
+        self.path.push(name.to_string());
+        let full = self.path.join(".");
+        if self.symbols.insert(full.clone(), sym).is_some() {
+            self.error(format!("duplicate symbol '{full}'"));
+        }
+    }
+
+    fn full_name(&self, depth: usize, name: &str) -> Option<String> {
+        if depth > self.path.len() {
+            return None;
+        }
+        let mut parts = self.path[..depth].to_vec();
+        parts.push(name.to_string());
+        Some(parts.join("."))
+    }
+
+    fn lookup(&self, depth: usize, name: &str) -> Option<Value> {
+        let full = self.full_name(depth, name)?;
+        let sym = self.symbols.get(&full).or_else(|| self.known.get(&full))?;
+        Some(match sym.bank {
+            Some(bank) => Value::Symbolic(Symbolic::address(Target::Section(bank), sym.value)),
+            None => Value::Int {
+                value: sym.value,
+                size: None,
+            },
+        })
+    }
+    /// Evaluate a top-level expression, falling back to a lenient evaluation on error.
+    fn eval_value(&mut self, expr: &Expr) -> Value {
+        let pc = self.pc();
+        let mut scopes = Vec::new();
+        let strict = eval(
+            expr,
+            &mut Ctx::new(self, pc, &mut scopes, false),
+            &mut Locals::new(),
+        );
+        match strict {
+            Ok(value) => value,
+            Err(message) => {
+                self.error(message);
+                eval(
+                    expr,
+                    &mut Ctx::new(self, pc, &mut scopes, true),
+                    &mut Locals::new(),
+                )
+                .unwrap_or(Value::Int {
+                    value: 0,
+                    size: None,
+                })
+            }
+        }
+    }
+
+    fn eval_int(&mut self, expr: &Expr) -> i128 {
+        match self.eval_value(expr).as_int() {
+            Ok((value, _)) => value,
+            Err(message) => {
+                self.error(message);
+                0
+            }
+        }
+    }
+
+    /// Assemble one instruction line at `pc`, picking the smallest encoding among matching rules.
+    fn assemble_line(
+        &mut self,
+        tokens: &[Token],
+        pc: i128,
+        scopes: &mut Vec<LocalScope>,
+        lenient: bool,
+    ) -> Result<Output, String> {
+        let matches = self.program.rules.matches(tokens);
+        if matches.is_empty() {
+            let text: Vec<String> = tokens.iter().map(|token| token.kind.to_string()).collect();
+            return Err(format!("no rule matches '{}'", text.join(" ")));
+        }
+
+        let mut best: Option<Output> = None;
+        let mut first_error = None;
+        for m in matches {
+            let rule = &self.program.rules.rules[m.rule];
+            match self
+                .bind(&m.args, pc, scopes, lenient)
+                .and_then(|mut locals| {
+                    eval(
+                        &rule.body,
+                        &mut Ctx::new(self, pc, scopes, lenient),
+                        &mut locals,
+                    )
+                })
+                .and_then(|value| value.to_output())
+            {
+                Ok(output) => {
+                    if best
+                        .as_ref()
+                        .is_none_or(|best| output.bytes.len() < best.bytes.len())
+                    {
+                        best = Some(output);
+                    }
+                }
+                Err(message) => {
+                    first_error.get_or_insert(message);
+                }
+            }
+        }
+        best.ok_or_else(|| first_error.unwrap_or_default())
+    }
+
+    /// Evaluate the arguments of a matched rule into its local variables.
+    fn bind(
+        &mut self,
+        args: &[Binding],
+        pc: i128,
+        scopes: &mut Vec<LocalScope>,
+        lenient: bool,
+    ) -> Result<Locals, String> {
+        let mut locals = Locals::new();
+        for binding in args {
+            let local = match &binding.arg {
+                Arg::Expr { expr, kind } => {
+                    let value = eval(
+                        expr,
+                        &mut Ctx::new(self, pc, scopes, lenient),
+                        &mut Locals::new(),
+                    )?;
+                    let value = match kind {
+                        ParamKind::Unsigned(size) => {
+                            check_type(&value, Some(false), *size, lenient)?
+                        }
+                        ParamKind::Signed(size) => check_type(&value, Some(true), *size, lenient)?,
+                        ParamKind::Integer(size) => check_type(&value, None, *size, lenient)?,
+                        ParamKind::Any | ParamKind::Subrule(_) => value,
+                    };
+                    Local {
+                        value,
+                        tokens: None,
+                    }
+                }
+                Arg::Subrule {
+                    rule,
+                    subrule,
+                    args,
+                    tokens,
+                } => {
+                    let program = self.program;
+                    let Some(sub) = program
+                        .rules
+                        .subrules
+                        .get(subrule)
+                        .and_then(|rules| rules.get(*rule))
+                    else {
+                        return Err(format!("unknown subrule '{subrule}'"));
+                    };
+                    let mut sub_locals = self.bind(args, pc, scopes, lenient)?;
+                    let value = eval(
+                        &sub.body,
+                        &mut Ctx::new(self, pc, scopes, lenient),
+                        &mut sub_locals,
+                    )?;
+                    Local {
+                        value,
+                        tokens: Some(tokens.clone()),
+                    }
+                }
+            };
+            locals.insert(binding.name.clone(), local);
+        }
+        Ok(locals)
+    }
+
+    /// Assemble the lines of an `asm` block once, with `known` as the guess for its local labels.
+    fn expand_once(
+        &mut self,
+        lines: &[Vec<Token>],
+        pc: i128,
+        scopes: &mut Vec<LocalScope>,
+        known: HashMap<String, i128>,
+        lenient: bool,
+    ) -> (Result<Output, String>, HashMap<String, i128>) {
+        scopes.push(LocalScope {
+            known,
+            current: HashMap::new(),
+        });
+        let mut output = Output::default();
+        let mut result = Ok(());
+        for line in lines {
+            let mut line = line.as_slice();
+            if let [Token {
+                kind: TokenKind::Ident(name),
+                ..
+            }, colon, rest @ ..] = line
+            {
+                if colon.is_punct(":") {
+                    if let Some(scope) = scopes.last_mut() {
+                        scope
+                            .current
+                            .insert(name.clone(), pc + output.bytes.len() as i128);
+                    }
+                    line = rest;
+                }
+            }
+            if line.is_empty() {
+                continue;
+            }
+            match self.assemble_line(line, pc + output.bytes.len() as i128, scopes, lenient) {
+                Ok(out) => output.extend(out),
+                Err(message) => {
+                    result = Err(message);
+                    break;
+                }
+            }
+        }
+        let labels = scopes.pop().map(|scope| scope.current).unwrap_or_default();
+        (result.map(|()| output), labels)
+    }
+
+    fn finish(self) -> Result<Assembly, Vec<AsmError>> {
+        let map = self.symbol_map();
+        let mut errors = self.errors;
+        let mut binary = Vec::new();
+        for bank in &self.banks {
+            let Some(outp) = bank.outp else {
+                continue;
+            };
+            if bank.implicit && bank.data.is_empty() {
+                continue;
+            }
+            let len = match (bank.fill, bank.size) {
+                (true, Some(size)) => size as usize,
+                _ => bank.data.len(),
+            };
+            let start = (outp / 8) as usize;
+            if binary.len() < start + len {
+                binary.resize(start + len, 0);
+            }
+            for (slot, byte) in binary[start..start + len].iter_mut().zip(&bank.data) {
+                *slot = byte.unwrap_or(0);
+            }
+        }
+        if bank_overlap(&self.banks) {
+            errors.push(AsmError::new("output banks overlap"));
+        }
+
+        if errors.is_empty() {
+            Ok(Assembly {
+                binary,
+                map,
+                symbols: self
+                    .symbols
+                    .into_iter()
+                    .map(|(name, sym)| (name, sym.value))
+                    .collect(),
+            })
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Labels and line table of the fixed banks.
+    fn symbol_map(&self) -> SymbolMap {
+        let mut map = SymbolMap::new();
+        let mut labels: Vec<(&String, &Sym)> = self
+            .symbols
+            .iter()
+            .filter(|(_, sym)| sym.label && sym.bank.is_none())
+            .collect();
+        // Prefer `parent` over `parent.sub` when both sit at the same address.
+        labels.sort_by_key(|(name, sym)| (sym.value, name.matches('.').count(), *name));
+        for (name, sym) in labels {
+            if let Ok(addr) = u16::try_from(sym.value) {
+                map.add_label(addr, name.as_str());
+            }
+        }
+
+        let cwd = std::env::current_dir().unwrap_or_default();
+        for (pc, loc) in &self.lines {
+            let (Ok(addr), Some(path)) = (u16::try_from(*pc), self.sources.path(loc.file)) else {
+                continue;
+            };
+            let path = path.strip_prefix(&cwd).unwrap_or(path);
+            map.add_line(addr, path.display().to_string(), loc.line);
+        }
+        map
+    }
+
+    fn finish_object(self) -> Result<Object, Vec<AsmError>> {
+        let mut errors = self.errors;
+        let mut object = Object::default();
+        let mut sections = HashMap::new();
+        for (index, bank) in self.banks.iter().enumerate() {
+            if bank.implicit && bank.data.is_empty() {
+                continue;
+            }
+            let len = match (bank.fill, bank.size) {
+                (true, Some(size)) => size as usize,
+                _ => bank.data.len(),
+            };
+            let mut data: Vec<u8> = bank.data.iter().map(|byte| byte.unwrap_or(0)).collect();
+            data.resize(len, 0);
+            if data.len() > 0x1_0000 || !(0..=0xFFFF).contains(&bank.addr) {
+                errors.push(AsmError::new(format!(
+                    "bank '{}' does not fit in the 16-bit address space",
+                    bank.name
+                )));
+            }
+            sections.insert(index, object.sections.len());
+            object.sections.push(Section {
+                name: bank.name.clone(),
+                addr: (!bank.relocatable).then_some(bank.addr as u16),
+                data,
+            });
+        }
+
+        let mut symbols: Vec<(&String, &Sym)> = self.symbols.iter().collect();
+        symbols.sort_by_key(|(name, _)| *name);
+        for (name, sym) in symbols {
+            object.symbols.push(Symbol {
+                name: name.clone(),
+                section: sym.bank.and_then(|bank| sections.get(&bank).copied()),
+                value: sym.value as i64,
+                global: !name.contains('.'),
+            });
+        }
+
+        for (bank, loc, fixup) in &self.fixups {
+            let section = sections.get(bank).copied().unwrap_or_default();
+            match relocation(*bank, fixup) {
+                Ok((kind, target, addend)) => object.relocations.push(Relocation {
+                    section,
+                    offset: fixup.offset as u16,
+                    kind,
+                    target: match target {
+                        Target::Section(bank) => {
+                            Target::Section(sections.get(&bank).copied().unwrap_or_default())
+                        }
+                        symbol @ Target::Symbol(_) => symbol,
+                    },
+                    addend: addend as i64,
+                }),
+                Err(message) => errors.push(self.sources.error_at(loc.file, loc.line, message)),
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(object)
+        } else {
+            Err(errors)
+        }
+    }
+}
+
+/// Classify a link-time value written in `bank` as a relocation.
+fn relocation(bank: usize, fixup: &Fixup) -> Result<(RelocKind, Target, i128), String> {
+    let value = &fixup.value;
+    let mut target = None;
+    let mut relative = false;
+    for (term, coefficient) in &value.terms {
+        match coefficient {
+            1 if target.is_none() => target = Some(term.clone()),
+            -1 if *term == Target::Section(bank) && !relative => relative = true,
+            _ => return Err("expression is too complex to be relocated".to_string()),
+        }
+    }
+    let Some(target) = target else {
+        return Err("expression is too complex to be relocated".to_string());
+    };
+    let kind = match (value.op, fixup.width, relative) {
+        (LinkOp::None, 16, false) => RelocKind::Abs16,
+        (LinkOp::None, 8, false) => RelocKind::Abs8,
+        (LinkOp::Hi8, 8, false) => RelocKind::Hi8,
+        (LinkOp::Lo8, 8, false) => RelocKind::Lo8,
+        (LinkOp::None, 8, true) => RelocKind::Rel8,
+        (_, width, _) => {
+            return Err(format!(
+                "cannot relocate a {width}-bit field with this expression"
+            ))
+        }
+    };
+    Ok((kind, target, value.addend))
+}
+
+fn bank_overlap(banks: &[Bank]) -> bool {
+    let ranges: Vec<(i128, i128)> = banks
+        .iter()
+        .filter(|bank| !bank.data.is_empty() || bank.fill)
+        .filter_map(|bank| {
+            let outp = bank.outp? / 8;
+            let len = match (bank.fill, bank.size) {
+                (true, Some(size)) => size,
+                _ => bank.data.len() as i128,
+            };
+            Some((outp, outp + len))
+        })
+        .collect();
+    ranges
+        .iter()
+        .enumerate()
+        .any(|(i, a)| ranges[i + 1..].iter().any(|b| a.0 < b.1 && b.0 < a.1))
+}
+
+/// Output of one `#d` / `#dN` value.
+fn data_output(value: &Value, size: Option<u32>) -> Result<Output, String> {
+    let Some(size) = size else {
+        return value.to_output();
+    };
+    if let Value::Symbolic(symbolic) = value {
+        return Value::Symbolic(Symbolic {
+            size: Some(size),
+            ..symbolic.clone()
+        })
+        .to_output();
+    }
+    let (number, _) = value.as_int()?;
+    if size < 127 && (number < -(1i128 << (size - 1)) || number >= (1i128 << size)) {
+        return Err(format!("value {number} does not fit in {size} bits"));
+    }
+    Ok(Output {
+        bytes: int_bytes(number, size),
+        fixups: Vec::new(),
+    })
+}
+
+/// Replace `{name}` placeholders of an `asm` block line with the rule's arguments.
+fn substitute(line: &[Token], locals: &Locals) -> Result<Vec<Token>, String> {
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < line.len() {
+        let name = line.get(i + 1).and_then(Token::ident);
+        let closed = line.get(i + 2).is_some_and(|token| token.is_punct("}"));
+        if let (true, Some(name), true) = (line[i].is_punct("{"), name, closed) {
+            let Some(local) = locals.get(name) else {
+                return Err(format!("unknown argument '{{{name}}}' in asm block"));
+            };
+            let loc = line[i].loc;
+            match (&local.tokens, &local.value) {
+                (Some(tokens), _) => out.extend(tokens.iter().cloned()),
+                (None, value) => out.push(Token {
+                    kind: TokenKind::Value(Box::new(value.clone())),
+                    loc,
+                }),
+            }
+            i += 3;
+        } else {
+            out.push(line[i].clone());
+            i += 1;
+        }
+    }
+    Ok(out)
+}
+
+/// Evaluation context of one instruction or directive.
+struct Ctx<'p, 'a> {
+    pass: &'p mut Pass<'a>,
+    pc: i128,
+    scopes: &'p mut Vec<LocalScope>,
+    lenient: bool,
+}
+
+impl<'p, 'a> Ctx<'p, 'a> {
+    fn new(
+        pass: &'p mut Pass<'a>,
+        pc: i128,
+        scopes: &'p mut Vec<LocalScope>,
+        lenient: bool,
+    ) -> Self {
+        Self {
+            pass,
+            pc,
+            scopes,
+            lenient,
+        }
+    }
+}
+
+impl Env for Ctx<'_, '_> {
+    fn pc(&self) -> Value {
+        self.pass.address(self.pc)
+    }
+
+    fn symbol(&mut self, depth: usize, name: &str) -> Result<Value, String> {
+        if depth == 0 {
+            for scope in self.scopes.iter().rev() {
+                if let Some(value) = scope.current.get(name).or_else(|| scope.known.get(name)) {
+                    return Ok(self.pass.address(*value));
+                }
+            }
+        }
+        match self.pass.lookup(depth, name) {
+            Some(value) => Ok(value),
+            None if self.pass.object && depth == 0 => Ok(Value::Symbolic(Symbolic::address(
+                Target::Symbol(name.to_string()),
+                0,
+            ))),
+            None if self.lenient => Ok(Value::Int {
+                value: 0,
+                size: None,
+            }),
+            None => Err(format!("unknown symbol '{}{name}'", ".".repeat(depth))),
+        }
+    }
+
+    fn asm(&mut self, lines: &[Vec<Token>], locals: &Locals) -> Result<Output, String> {
+        let lines = lines
+            .iter()
+            .map(|line| substitute(line, locals))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let mut known = HashMap::new();
+        let mut settled = false;
+        for _ in 0..MAX_PASSES {
+            let (_, labels) =
+                self.pass
+                    .expand_once(&lines, self.pc, self.scopes, known.clone(), true);
+            settled = labels == known;
+            known = labels;
+            if settled {
+                break;
+            }
+        }
+        if !settled {
+            return Err("labels of asm block did not settle".to_string());
+        }
+        self.pass
+            .expand_once(&lines, self.pc, self.scopes, known, self.lenient)
+            .0
+    }
+
+    fn lenient(&self) -> bool {
+        self.lenient
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use crate::{
+        assemble_object_str, assemble_str,
+        object::{RelocKind, Target},
+    };
+
+    pub(crate) const ISA: &str = "
+#subruledef register
+{
+    R0 => 0x0
+    R1 => 0x1
+    R6 => 0x6
+    R7 => 0x7
+}
+#ruledef
+{
+    NOP => 0x0000
+    LDI { dst: register } { value: u8 } => 0x2 @ dst @ value
+    JR { offset: i8 } => 0x31 @ offset
+    JR [{ addr: u16 }] => {
+        offset = addr - $ - 2
+        assert(offset <= 127)
+        assert(offset >= -128)
+        0x31 @ offset`8
+    }
+    CALL [{ hi: register }:{ lo: register }] => 0x40 @ hi @ lo
+    CALL [{ addr: u16 }] => {
+        hi = addr >> 8;
+        lo = addr & 0xFF;
+        asm {
+            LDI R6 {hi}
+            LDI R7 {lo}
+            CALL [R6:R7]
+        }
+    }
+    SPIN { reg: register } => asm {
+        again:
+        LDI {reg} 0
+        JR [again]
+    }
+}
+";
+
+    fn assemble(body: &str) -> Vec<u8> {
+        match assemble_str(&format!("{ISA}\n{body}")) {
+            Ok(assembly) => assembly.binary,
+            Err(errors) => panic!("{errors:?}"),
+        }
+    }
+
+    #[test]
+    fn test_forward_reference() {
+        assert_eq!(
+            assemble("CALL [target]\nNOP\ntarget:\nJR [target]"),
+            vec![0x26, 0x00, 0x27, 0x08, 0x40, 0x67, 0x00, 0x00, 0x31, 0xFE]
+        );
+    }
+
+    #[test]
+    fn test_asm_block_local_labels() {
+        assert_eq!(
+            assemble("NOP\nSPIN R1\nSPIN R0"),
+            vec![0x00, 0x00, 0x21, 0x00, 0x31, 0xFC, 0x20, 0x00, 0x31, 0xFC]
+        );
+    }
+
+    #[test]
+    fn test_sublabels_and_data() {
+        let source = format!(
+            "{ISA}\n#bankdef rom {{ #addr 0x10 \n #size 8 \n #outp 0 \n #fill }}\n\
+             top:\n.loop:\nJR [.loop]\nother:\n#d8 1, 2\n#d \"A\""
+        );
+        let Ok(assembly) = assemble_str(&source) else {
+            panic!("assembly failed");
+        };
+        assert_eq!(
+            assembly.binary,
+            vec![0x31, 0xFE, 0x01, 0x02, 0x41, 0x00, 0x00, 0x00]
+        );
+        assert_eq!(assembly.symbols.get("top.loop"), Some(&0x10));
+        assert_eq!(assembly.symbols.get("other"), Some(&0x12));
+        assert_eq!(assembly.map.describe(0x13), "other+0x1");
+        assert_eq!(
+            assembly.map.labels.get(&0x10).map(String::as_str),
+            Some("top")
+        );
+        let line = |addr| assembly.map.source(addr).map(|source| source.line);
+        assert_eq!(line(0x11), line(0x10));
+        assert_eq!(line(0x14), line(0x12).map(|line| line + 1));
+    }
+
+    #[test]
+    fn test_errors_are_reported() {
+        let Err(errors) = assemble_str(&format!("{ISA}\nJR [missing]\nLDI R0 0x100\nFOO")) else {
+            panic!("expected errors");
+        };
+        assert_eq!(errors.len(), 3);
+        assert!(errors[0].message.contains("unknown symbol 'missing'"));
+        assert!(errors[1].message.contains("out of range"));
+        assert!(errors[2].message.contains("no rule matches"));
+    }
+
+    #[test]
+    fn test_relative_jump_out_of_range() {
+        let source = format!("{ISA}\nJR [far]\n#d1024 0\nfar:");
+        let Err(errors) = assemble_str(&source) else {
+            panic!("expected an error");
+        };
+        assert!(errors[0].message.contains("assertion failed"));
+    }
+
+    #[test]
+    fn test_object_relocations() {
+        let source = format!("{ISA}\nstart:\nCALL [puts]\nJR [start]\nJR [puts]\n#d16 start");
+        let Ok(object) = assemble_object_str(&source) else {
+            panic!("assembly failed");
+        };
+        assert_eq!(object.sections.len(), 1);
+        assert_eq!(object.sections[0].addr, None);
+        assert_eq!(object.sections[0].data.len(), 12);
+        let relocs: Vec<_> = object
+            .relocations
+            .iter()
+            .map(|reloc| (reloc.offset, reloc.kind, reloc.target.clone(), reloc.addend))
+            .collect();
+        let puts = Target::Symbol("puts".to_string());
+        assert_eq!(
+            relocs,
+            vec![
+                (1, RelocKind::Hi8, puts.clone(), 0),
+                (3, RelocKind::Lo8, puts.clone(), 0),
+                (9, RelocKind::Rel8, puts, -10),
+                (10, RelocKind::Abs16, Target::Section(0), 0),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_object_rejects_complex_relocations() {
+        let source = format!("{ISA}\nstart:\n#d16 puts * 2\n#d16 puts - start");
+        let Err(errors) = assemble_object_str(&source) else {
+            panic!("expected an error");
+        };
+        assert_eq!(errors.len(), 2);
+        assert!(errors[0].message.contains("not supported"));
+        assert!(errors[1].message.contains("cannot relocate"));
+    }
+
+    fn errors(body: &str) -> Vec<String> {
+        match assemble_str(&format!("{ISA}\n{body}")) {
+            Ok(assembly) => panic!("`{body}` assembled to {:?}", assembly.binary),
+            Err(errors) => errors.into_iter().map(|error| error.message).collect(),
+        }
+    }
+
+    #[test]
+    fn test_symbol_errors() {
+        assert!(errors("a:\nNOP\na:")[0].contains("duplicate symbol 'a'"));
+        assert!(errors(".loop:\nNOP")[0].contains("no parent label"));
+        assert!(errors("x = 1\nx = 2")[0].contains("duplicate symbol 'x'"));
+    }
+
+    #[test]
+    fn test_bank_errors() {
+        let bank = "#bankdef rom { #addr 0x10 \n #size 2 \n #outp 0 }";
+        assert!(errors(&format!("{bank}\nNOP\nNOP"))[0].contains("outside bank 'rom'"));
+        assert!(errors(&format!("{bank}\n{bank}"))[0].contains("already defined"));
+        assert!(errors("#bank missing")[0].contains("unknown bank 'missing'"));
+        assert!(errors("NOP\n#addr 0\nNOP")[0].contains("overlaps"));
+    }
+
+    #[test]
+    fn test_source_errors() {
+        assert!(errors("#include \"missing.asm\"")[0].contains("not found"));
+        assert!(errors("#frobnicate")[0].contains("unknown directive #frobnicate"));
+        assert!(errors("#d8 0x100")[0].contains("does not fit in 8 bits"));
+    }
+}
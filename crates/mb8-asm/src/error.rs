@@ -0,0 +1,47 @@
+use std::{fmt::Display, path::PathBuf};
+
+pub type AsmResult<T, E = AsmError> = Result<T, E>;
+
+/// Position of a token in the assembled sources.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Location {
+    /// Index of the file in the assembler's file list.
+    pub file: usize,
+    /// One-based line number.
+    pub line: usize,
+}
+
+/// A diagnostic produced while assembling.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AsmError {
+    pub message: String,
+    pub file: Option<PathBuf>,
+    pub line: usize,
+}
+
+impl AsmError {
+    #[must_use]
+    pub fn new(message: impl Into<String>) -> Self {
+        Self {
+            message: message.into(),
+            file: None,
+            line: 0,
+        }
+    }
+
+    #[must_use]
+    pub fn at(mut self, file: PathBuf, line: usize) -> Self {
+        self.file = Some(file);
+        self.line = line;
+        self
+    }
+}
+
+impl Display for AsmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.file {
+            Some(file) => write!(f, "{}:{}: {}", file.display(), self.line, self.message),
+            None => f.write_str(&self.message),
+        }
+    }
+}
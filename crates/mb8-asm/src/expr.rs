@@ -0,0 +1,1004 @@
+//! Expressions used in instruction operands, directives and rule bodies.
+
+use std::collections::HashMap;
+
+use crate::{
+    lexer::{Token, TokenKind},
+    object::Target,
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number {
+        value: i128,
+        size: Option<u32>,
+    },
+    Str(String),
+    /// Address of the current instruction (`$`).
+    Pc,
+    /// Symbol reference. `depth` is the number of leading dots of a sublabel.
+    Symbol {
+        depth: usize,
+        name: String,
+    },
+    Unary {
+        op: &'static str,
+        expr: Box<Expr>,
+    },
+    Binary {
+        op: &'static str,
+        lhs: Box<Expr>,
+        rhs: Box<Expr>,
+    },
+    /// Bit size cast, e.g. ``offset`8``.
+    Size {
+        expr: Box<Expr>,
+        size: u32,
+    },
+    Call {
+        name: String,
+        args: Vec<Expr>,
+    },
+    /// Statement block of a rule body. Evaluates to its last expression.
+    Block(Vec<Stmt>),
+    /// `asm { ... }` block of a rule body, one token line per instruction.
+    Asm(Vec<Vec<Token>>),
+    /// Already evaluated rule argument substituted into an `asm` block.
+    Value(Box<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Stmt {
+    Assign(String, Expr),
+    Expr(Expr),
+}
+
+/// Result of evaluating an expression.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Void,
+    Bool(bool),
+    /// Integer with an optional width in bits.
+    Int {
+        value: i128,
+        size: Option<u32>,
+    },
+    Str(String),
+    /// Output of an `asm` block.
+    Bytes(Output),
+    /// Address that is only known once the linker has placed every section.
+    Symbolic(Symbolic),
+    /// Sized value with link-time holes, e.g. the result of ``0x31 @ offset`8``.
+    Patched {
+        value: i128,
+        size: u32,
+        holes: Vec<Hole>,
+    },
+}
+
+/// Link-time value: `op(sum of coefficient * address + addend)`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbolic {
+    pub terms: Vec<(Target, i128)>,
+    pub addend: i128,
+    pub op: LinkOp,
+    pub size: Option<u32>,
+}
+
+/// Operation applied to a link-time address.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkOp {
+    None,
+    /// `addr >> 8`
+    Hi8,
+    /// `addr & 0xFF`
+    Lo8,
+}
+
+/// A link-time value occupying `width` bits starting at `bit` (counted from the least significant bit).
+#[derive(Debug, Clone, PartialEq)]
+pub struct Hole {
+    pub bit: u32,
+    pub width: u32,
+    pub value: Symbolic,
+}
+
+/// Bytes of an instruction or `asm` block and the places the linker must patch.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Output {
+    pub bytes: Vec<u8>,
+    pub fixups: Vec<Fixup>,
+}
+
+/// A link-time value covering `width` bits at byte `offset` of an [`Output`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Fixup {
+    pub offset: usize,
+    pub width: u32,
+    pub value: Symbolic,
+}
+
+impl Output {
+    /// Append `other`, moving its fixups behind the current bytes.
+    pub fn extend(&mut self, other: Output) {
+        let base = self.bytes.len();
+        self.bytes.extend(other.bytes);
+        self.fixups
+            .extend(other.fixups.into_iter().map(|fixup| Fixup {
+                offset: fixup.offset + base,
+                ..fixup
+            }));
+    }
+}
+
+impl Symbolic {
+    /// Address `offset` bytes past `target`.
+    #[must_use]
+    pub fn address(target: Target, offset: i128) -> Self {
+        Self {
+            terms: vec![(target, 1)],
+            addend: offset,
+            op: LinkOp::None,
+            size: None,
+        }
+    }
+
+    fn plain(&self) -> Result<(), String> {
+        if self.op == LinkOp::None {
+            Ok(())
+        } else {
+            Err("cannot do arithmetic on the high or low byte of a link-time address".to_string())
+        }
+    }
+
+    fn add(mut self, value: i128) -> Result<Value, String> {
+        self.plain()?;
+        self.addend = self.addend.wrapping_add(value);
+        self.size = None;
+        Ok(Value::Symbolic(self))
+    }
+
+    fn scale(mut self, factor: i128) -> Result<Self, String> {
+        self.plain()?;
+        for (_, coefficient) in &mut self.terms {
+            *coefficient *= factor;
+        }
+        self.addend = self.addend.wrapping_mul(factor);
+        Ok(self)
+    }
+
+    /// `self + other`, collapsing to a plain number when all addresses cancel out.
+    fn combine(mut self, other: Symbolic) -> Result<Value, String> {
+        self.plain()?;
+        other.plain()?;
+        for (target, coefficient) in other.terms {
+            match self.terms.iter_mut().find(|(t, _)| *t == target) {
+                Some((_, c)) => *c += coefficient,
+                None => self.terms.push((target, coefficient)),
+            }
+        }
+        self.terms.retain(|(_, coefficient)| *coefficient != 0);
+        let addend = self.addend.wrapping_add(other.addend);
+        if self.terms.is_empty() {
+            return Ok(Value::Int {
+                value: addend,
+                size: None,
+            });
+        }
+        Ok(Value::Symbolic(Symbolic {
+            addend,
+            size: None,
+            ..self
+        }))
+    }
+}
+
+impl Value {
+    /// Interpret the value as an integer, converting strings to their big-endian bytes.
+    ///
+    /// # Errors
+    /// Returns an error for booleans, blocks, link-time addresses and strings longer than 16 bytes.
+    pub fn as_int(&self) -> Result<(i128, Option<u32>), String> {
+        match self {
+            Value::Int { value, size } => Ok((*value, *size)),
+            Value::Str(text) if text.len() <= 16 => {
+                let value = text
+                    .bytes()
+                    .fold(0i128, |acc, byte| (acc << 8) | byte as i128);
+                Ok((value, Some(text.len() as u32 * 8)))
+            }
+            Value::Str(_) => Err("string is too long to be used as a number".to_string()),
+            Value::Bool(_) => Err("expected a number, found a boolean".to_string()),
+            Value::Bytes(_) => Err("expected a number, found an asm block".to_string()),
+            Value::Symbolic(_) | Value::Patched { .. } => {
+                Err("value is only known after linking".to_string())
+            }
+            Value::Void => Err("expected a number, found nothing".to_string()),
+        }
+    }
+
+    /// Convert the value to output bytes.
+    ///
+    /// # Errors
+    /// Returns an error if the value has no known size or is not a whole number of bytes.
+    pub fn to_output(&self) -> Result<Output, String> {
+        let whole_bytes = |size: u32| {
+            if size.is_multiple_of(8) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "output of {size} bits is not a whole number of bytes"
+                ))
+            }
+        };
+        match self {
+            Value::Bytes(output) => Ok(output.clone()),
+            Value::Str(text) => Ok(Output {
+                bytes: text.as_bytes().to_vec(),
+                fixups: Vec::new(),
+            }),
+            Value::Int {
+                value,
+                size: Some(size),
+            } => {
+                whole_bytes(*size)?;
+                Ok(Output {
+                    bytes: int_bytes(*value, *size),
+                    fixups: Vec::new(),
+                })
+            }
+            Value::Symbolic(Symbolic { size: Some(_), .. }) | Value::Patched { .. } => {
+                let (value, size, holes) = self.sized_parts()?;
+                whole_bytes(size)?;
+                let mut fixups = Vec::new();
+                for hole in holes {
+                    if hole.bit % 8 != 0 || hole.width % 8 != 0 {
+                        return Err("link-time values must occupy whole bytes".to_string());
+                    }
+                    fixups.push(Fixup {
+                        offset: ((size - hole.bit - hole.width) / 8) as usize,
+                        width: hole.width,
+                        value: hole.value,
+                    });
+                }
+                Ok(Output {
+                    bytes: int_bytes(value, size),
+                    fixups,
+                })
+            }
+            Value::Int { size: None, .. } | Value::Symbolic(_) => {
+                Err("output value has no explicit size, use a sized literal or `x`8".to_string())
+            }
+            Value::Bool(_) | Value::Void => Err("rule does not produce any output".to_string()),
+        }
+    }
+
+    /// Value, size and link-time holes of a sized value, e.g. an operand of `@`.
+    fn sized_parts(&self) -> Result<(i128, u32, Vec<Hole>), String> {
+        match self {
+            Value::Symbolic(symbolic) => match symbolic.size {
+                Some(size) => Ok((
+                    0,
+                    size,
+                    vec![Hole {
+                        bit: 0,
+                        width: size,
+                        value: symbolic.clone(),
+                    }],
+                )),
+                None => Err("both sides of `@` need an explicit size".to_string()),
+            },
+            Value::Patched { value, size, holes } => Ok((*value, *size, holes.clone())),
+            _ => match self.as_int() {
+                Ok((value, Some(size))) => Ok((mask(value, size), size, Vec::new())),
+                _ => Err("both sides of `@` need an explicit size".to_string()),
+            },
+        }
+    }
+}
+
+/// Big-endian two's complement bytes of `value` truncated to `size` bits.
+#[must_use]
+pub fn int_bytes(value: i128, size: u32) -> Vec<u8> {
+    (0..size / 8)
+        .rev()
+        .map(|i| {
+            let shift = i * 8;
+            if shift >= 128 {
+                if value < 0 {
+                    0xFF
+                } else {
+                    0x00
+                }
+            } else {
+                (value >> shift) as u8
+            }
+        })
+        .collect()
+}
+
+fn mask(value: i128, size: u32) -> i128 {
+    if size >= 127 {
+        value
+    } else {
+        value & ((1i128 << size) - 1)
+    }
+}
+
+/// Hooks the evaluator needs from the assembler.
+pub trait Env {
+    /// Address of the instruction being assembled.
+    fn pc(&self) -> Value;
+    /// Resolve a label or constant.
+    ///
+    /// # Errors
+    /// Returns an error for unknown symbols.
+    fn symbol(&mut self, depth: usize, name: &str) -> Result<Value, String>;
+    /// Assemble an `asm` block with placeholders substituted from `locals`.
+    ///
+    /// # Errors
+    /// Returns an error if any line of the block fails to assemble.
+    fn asm(&mut self, lines: &[Vec<Token>], locals: &Locals) -> Result<Output, String>;
+    /// Whether assertions and range checks are relaxed because addresses are not final yet.
+    fn lenient(&self) -> bool;
+}
+
+/// A rule-local variable: its value and, for register-like arguments, the source tokens.
+#[derive(Debug, Clone)]
+pub struct Local {
+    pub value: Value,
+    pub tokens: Option<Vec<Token>>,
+}
+
+pub type Locals = HashMap<String, Local>;
+
+/// Evaluate `expr` with the given rule-local variables.
+///
+/// # Errors
+/// Returns an error on type mismatches, failed assertions or unknown symbols.
+pub fn eval(expr: &Expr, env: &mut dyn Env, locals: &mut Locals) -> Result<Value, String> {
+    match expr {
+        Expr::Number { value, size } => Ok(Value::Int {
+            value: *value,
+            size: *size,
+        }),
+        Expr::Str(text) => Ok(Value::Str(text.clone())),
+        Expr::Pc => Ok(env.pc()),
+        Expr::Symbol { depth, name } => {
+            if *depth == 0 {
+                if let Some(local) = locals.get(name) {
+                    return Ok(local.value.clone());
+                }
+            }
+            env.symbol(*depth, name)
+        }
+        Expr::Unary { op, expr } => {
+            let value = eval(expr, env, locals)?;
+            match (*op, value) {
+                ("!", Value::Bool(value)) => Ok(Value::Bool(!value)),
+                ("-", Value::Symbolic(symbolic)) => Ok(Value::Symbolic(symbolic.scale(-1)?)),
+                ("!" | "~", value) => Ok(Value::Int {
+                    value: !value.as_int()?.0,
+                    size: None,
+                }),
+                (_, value) => Ok(Value::Int {
+                    value: value.as_int()?.0.wrapping_neg(),
+                    size: None,
+                }),
+            }
+        }
+        Expr::Binary { op, lhs, rhs } => eval_binary(op, lhs, rhs, env, locals),
+        Expr::Size { expr, size } => match eval(expr, env, locals)? {
+            Value::Symbolic(symbolic) => Ok(Value::Symbolic(Symbolic {
+                size: Some(*size),
+                ..symbolic
+            })),
+            value => Ok(Value::Int {
+                value: mask(value.as_int()?.0, *size),
+                size: Some(*size),
+            }),
+        },
+        Expr::Call { name, args } => eval_call(name, args, env, locals),
+        Expr::Block(stmts) => {
+            let mut result = Value::Void;
+            for stmt in stmts {
+                result = match stmt {
+                    Stmt::Assign(name, expr) => {
+                        let value = eval(expr, env, locals)?;
+                        locals.insert(
+                            name.clone(),
+                            Local {
+                                value,
+                                tokens: None,
+                            },
+                        );
+                        Value::Void
+                    }
+                    Stmt::Expr(expr) => eval(expr, env, locals)?,
+                };
+            }
+            Ok(result)
+        }
+        Expr::Asm(lines) => Ok(Value::Bytes(env.asm(lines, locals)?)),
+        Expr::Value(value) => Ok((**value).clone()),
+    }
+}
+
+fn eval_binary(
+    op: &str,
+    lhs: &Expr,
+    rhs: &Expr,
+    env: &mut dyn Env,
+    locals: &mut Locals,
+) -> Result<Value, String> {
+    let lhs = eval(lhs, env, locals)?;
+    let rhs = eval(rhs, env, locals)?;
+
+    if let ("&&" | "||", Value::Bool(a), Value::Bool(b)) = (op, &lhs, &rhs) {
+        return Ok(Value::Bool(if op == "&&" { *a && *b } else { *a || *b }));
+    }
+    if let ("==" | "!=", Value::Bool(a), Value::Bool(b)) = (op, &lhs, &rhs) {
+        return Ok(Value::Bool((a == b) == (op == "==")));
+    }
+    if op == "@" {
+        let (a, size_a, holes_a) = lhs.sized_parts()?;
+        let (b, size_b, holes_b) = rhs.sized_parts()?;
+        let value = (a << size_b) | b;
+        let size = size_a + size_b;
+        let mut holes: Vec<Hole> = holes_a
+            .into_iter()
+            .map(|hole| Hole {
+                bit: hole.bit + size_b,
+                ..hole
+            })
+            .collect();
+        holes.extend(holes_b);
+        if holes.is_empty() {
+            return Ok(Value::Int {
+                value,
+                size: Some(size),
+            });
+        }
+        return Ok(Value::Patched { value, size, holes });
+    }
+    if matches!(lhs, Value::Symbolic(_)) || matches!(rhs, Value::Symbolic(_)) {
+        return eval_symbolic(op, lhs, rhs);
+    }
+
+    let (a, _) = lhs.as_int()?;
+    let (b, _) = rhs.as_int()?;
+    let int = |value: i128| Ok(Value::Int { value, size: None });
+    match op {
+        "+" => int(a.wrapping_add(b)),
+        "-" => int(a.wrapping_sub(b)),
+        "*" => int(a.wrapping_mul(b)),
+        "/" | "%" if b == 0 => Err("division by zero".to_string()),
+        "/" => int(a / b),
+        "%" => int(a % b),
+        "&" => int(a & b),
+        "|" => int(a | b),
+        "^" => int(a ^ b),
+        "<<" => int(a.checked_shl(b as u32).unwrap_or(0)),
+        ">>" => int(a
+            .checked_shr(b as u32)
+            .unwrap_or(if a < 0 { -1 } else { 0 })),
+        "==" => Ok(Value::Bool(a == b)),
+        "!=" => Ok(Value::Bool(a != b)),
+        "<" => Ok(Value::Bool(a < b)),
+        "<=" => Ok(Value::Bool(a <= b)),
+        ">" => Ok(Value::Bool(a > b)),
+        ">=" => Ok(Value::Bool(a >= b)),
+        _ => Err(format!("operator `{op}` is not supported on numbers")),
+    }
+}
+
+/// Binary operators with at least one link-time operand.
+fn eval_symbolic(op: &str, lhs: Value, rhs: Value) -> Result<Value, String> {
+    match (op, lhs, rhs) {
+        ("+", Value::Symbolic(a), Value::Symbolic(b)) => a.combine(b),
+        ("-", Value::Symbolic(a), Value::Symbolic(b)) => a.combine(b.scale(-1)?),
+        ("+", Value::Symbolic(a), n) | ("+", n, Value::Symbolic(a)) => a.add(n.as_int()?.0),
+        ("-", Value::Symbolic(a), n) => a.add(n.as_int()?.0.wrapping_neg()),
+        ("-", n, Value::Symbolic(b)) => b.scale(-1)?.add(n.as_int()?.0),
+        (">>", Value::Symbolic(a), n) if n.as_int()?.0 == 8 => {
+            a.plain()?;
+            Ok(Value::Symbolic(Symbolic {
+                op: LinkOp::Hi8,
+                size: None,
+                ..a
+            }))
+        }
+        ("&", Value::Symbolic(a), n) | ("&", n, Value::Symbolic(a)) if n.as_int()?.0 == 0xFF => {
+            let op = match a.op {
+                LinkOp::None | LinkOp::Lo8 => LinkOp::Lo8,
+                LinkOp::Hi8 => LinkOp::Hi8,
+            };
+            Ok(Value::Symbolic(Symbolic {
+                op,
+                size: None,
+                ..a
+            }))
+        }
+        // Range checks on link-time values are done by the linker when it applies the relocation.
+        ("==" | "!=" | "<" | "<=" | ">" | ">=", _, _) => Ok(Value::Bool(true)),
+        _ => Err(format!(
+            "operator `{op}` is not supported on addresses resolved by the linker"
+        )),
+    }
+}
+
+fn eval_call(
+    name: &str,
+    args: &[Expr],
+    env: &mut dyn Env,
+    locals: &mut Locals,
+) -> Result<Value, String> {
+    match name {
+        "assert" => {
+            let Some(condition) = args.first() else {
+                return Err("assert expects a condition".to_string());
+            };
+            let condition = eval(condition, env, locals)?;
+            if condition == Value::Bool(true) || env.lenient() {
+                return Ok(Value::Void);
+            }
+            match args.get(1).map(|message| eval(message, env, locals)) {
+                Some(Ok(Value::Str(message))) => Err(format!("assertion failed: {message}")),
+                _ => Err("assertion failed".to_string()),
+            }
+        }
+        _ => Err(format!("unknown function '{name}'")),
+    }
+}
+
+/// Check an argument against a parameter type such as `u8` or `i8` and give it that size.
+///
+/// # Errors
+/// Returns an error if the value is out of range for the type.
+pub fn check_type(
+    value: &Value,
+    signed: Option<bool>,
+    size: u32,
+    lenient: bool,
+) -> Result<Value, String> {
+    if let Value::Symbolic(symbolic) = value {
+        return Ok(Value::Symbolic(Symbolic {
+            size: Some(size),
+            ..symbolic.clone()
+        }));
+    }
+    let (number, _) = value.as_int()?;
+    let (min, max) = match signed {
+        Some(false) => (0, (1i128 << size) - 1),
+        Some(true) => (-(1i128 << (size - 1)), (1i128 << (size - 1)) - 1),
+        None => (-(1i128 << (size - 1)), (1i128 << size) - 1),
+    };
+    if !lenient && (number < min || number > max) {
+        let ty = match signed {
+            Some(false) => "u",
+            Some(true) => "i",
+            None => "s",
+        };
+        return Err(format!("value {number} is out of range for {ty}{size}"));
+    }
+    Ok(Value::Int {
+        value: mask(number, size),
+        size: Some(size),
+    })
+}
+
+/// Recursive-descent parser over a token slice.
+#[derive(Debug)]
+pub struct ExprParser<'t> {
+    tokens: &'t [Token],
+    pub pos: usize,
+}
+
+const BINARY_OPS: &[&[&str]] = &[
+    &["||"],
+    &["&&"],
+    &["==", "!=", "<", "<=", ">", ">="],
+    &["|"],
+    &["^"],
+    &["&"],
+    &["<<", ">>"],
+    &["@"],
+    &["+", "-"],
+    &["*", "/", "%"],
+];
+
+impl<'t> ExprParser<'t> {
+    #[must_use]
+    pub fn new(tokens: &'t [Token], pos: usize) -> Self {
+        Self { tokens, pos }
+    }
+
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_punct(&self, punct: &str) -> bool {
+        self.peek().is_some_and(|token| token.is_punct(punct))
+    }
+
+    fn expect_punct(&mut self, punct: &str) -> Result<(), String> {
+        if self.peek_punct(punct) {
+            self.pos += 1;
+            Ok(())
+        } else {
+            Err(format!("expected `{punct}`, found {}", self.describe()))
+        }
+    }
+
+    fn describe(&self) -> String {
+        match self.peek() {
+            Some(token) => format!("`{}`", token.kind),
+            None => "end of input".to_string(),
+        }
+    }
+
+    /// Parse a full expression starting at the current position.
+    ///
+    /// # Errors
+    /// Returns an error if the tokens do not form an expression.
+    pub fn parse_expr(&mut self) -> Result<Expr, String> {
+        self.parse_binary(0)
+    }
+
+    fn parse_binary(&mut self, level: usize) -> Result<Expr, String> {
+        if level == BINARY_OPS.len() {
+            return self.parse_unary();
+        }
+        let mut lhs = self.parse_binary(level + 1)?;
+        loop {
+            let op = match self.peek().map(|token| &token.kind) {
+                Some(TokenKind::Punct(op)) if BINARY_OPS[level].contains(op) => *op,
+                _ => return Ok(lhs),
+            };
+            self.pos += 1;
+            let rhs = self.parse_binary(level + 1)?;
+            lhs = Expr::Binary {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+        }
+    }
+
+    fn parse_unary(&mut self) -> Result<Expr, String> {
+        for op in ["-", "!", "~"] {
+            if self.peek_punct(op) {
+                self.pos += 1;
+                let expr = self.parse_unary()?;
+                return Ok(Expr::Unary {
+                    op,
+                    expr: Box::new(expr),
+                });
+            }
+        }
+        let mut expr = self.parse_primary()?;
+        while self.peek_punct("`") {
+            self.pos += 1;
+            let Some(TokenKind::Number { value, .. }) = self.peek().map(|token| &token.kind) else {
+                return Err(format!("expected a bit size, found {}", self.describe()));
+            };
+            self.pos += 1;
+            expr = Expr::Size {
+                expr: Box::new(expr),
+                size: *value as u32,
+            };
+        }
+        Ok(expr)
+    }
+
+    fn parse_primary(&mut self) -> Result<Expr, String> {
+        let Some(token) = self.peek() else {
+            return Err("expected an expression, found end of input".to_string());
+        };
+        match &token.kind {
+            TokenKind::Number { value, size } => {
+                self.pos += 1;
+                Ok(Expr::Number {
+                    value: *value,
+                    size: *size,
+                })
+            }
+            TokenKind::Str(text) => {
+                self.pos += 1;
+                Ok(Expr::Str(text.clone()))
+            }
+            TokenKind::Value(value) => {
+                self.pos += 1;
+                Ok(Expr::Value(value.clone()))
+            }
+            TokenKind::Punct("$") => {
+                self.pos += 1;
+                Ok(Expr::Pc)
+            }
+            TokenKind::Punct("(") => {
+                self.pos += 1;
+                let expr = self.parse_expr()?;
+                self.expect_punct(")")?;
+                Ok(expr)
+            }
+            TokenKind::Punct(".") => {
+                let mut depth = 0;
+                while self.peek_punct(".") {
+                    depth += 1;
+                    self.pos += 1;
+                }
+                match self.peek().and_then(Token::ident) {
+                    Some(name) => {
+                        self.pos += 1;
+                        Ok(Expr::Symbol {
+                            depth,
+                            name: name.to_string(),
+                        })
+                    }
+                    None => Err(format!("expected a label name, found {}", self.describe())),
+                }
+            }
+            TokenKind::Ident(name) if name == "asm" && self.next_is_punct("{") => {
+                self.pos += 1;
+                let inner = self.capture_braces()?;
+                Ok(Expr::Asm(split_lines(inner)))
+            }
+            TokenKind::Ident(name) => {
+                self.pos += 1;
+                if self.peek_punct("(") {
+                    self.pos += 1;
+                    let mut args = Vec::new();
+                    while !self.peek_punct(")") {
+                        args.push(self.parse_expr()?);
+                        if !self.peek_punct(",") {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                    self.expect_punct(")")?;
+                    return Ok(Expr::Call {
+                        name: name.clone(),
+                        args,
+                    });
+                }
+                Ok(Expr::Symbol {
+                    depth: 0,
+                    name: name.clone(),
+                })
+            }
+            _ => Err(format!("expected an expression, found {}", self.describe())),
+        }
+    }
+
+    fn next_is_punct(&self, punct: &str) -> bool {
+        self.tokens
+            .get(self.pos + 1)
+            .is_some_and(|token| token.is_punct(punct))
+    }
+
+    /// Consume a `{ ... }` group and return the tokens between the braces.
+    ///
+    /// # Errors
+    /// Returns an error if the braces are not balanced.
+    pub fn capture_braces(&mut self) -> Result<&'t [Token], String> {
+        self.expect_punct("{")?;
+        let start = self.pos;
+        let mut depth = 1;
+        while let Some(token) = self.peek() {
+            if token.is_punct("{") {
+                depth += 1;
+            } else if token.is_punct("}") {
+                depth -= 1;
+                if depth == 0 {
+                    let inner = &self.tokens[start..self.pos];
+                    self.pos += 1;
+                    return Ok(inner);
+                }
+            }
+            self.pos += 1;
+        }
+        Err("unterminated `{` block".to_string())
+    }
+
+    /// Parse the statements of a `{ ... }` rule body.
+    ///
+    /// # Errors
+    /// Returns an error if a statement is malformed.
+    pub fn parse_block(&mut self) -> Result<Expr, String> {
+        let inner = self.capture_braces()?;
+        let mut parser = ExprParser::new(inner, 0);
+        let mut stmts = Vec::new();
+        loop {
+            while parser.peek().is_some_and(Token::is_newline) {
+                parser.pos += 1;
+            }
+            let Some(token) = parser.peek() else {
+                break;
+            };
+            let stmt = match token.ident() {
+                Some(name) if parser.next_is_punct("=") => {
+                    parser.pos += 2;
+                    Stmt::Assign(name.to_string(), parser.parse_expr()?)
+                }
+                _ => Stmt::Expr(parser.parse_expr()?),
+            };
+            stmts.push(stmt);
+            if let Some(token) = parser.peek() {
+                if !token.is_newline() {
+                    return Err(format!("unexpected `{}` after statement", token.kind));
+                }
+            }
+        }
+        Ok(Expr::Block(stmts))
+    }
+}
+
+/// Split tokens into non-empty lines.
+#[must_use]
+pub fn split_lines(tokens: &[Token]) -> Vec<Vec<Token>> {
+    tokens
+        .split(Token::is_newline)
+        .filter(|line| !line.is_empty())
+        .map(<[Token]>::to_vec)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::lexer::lex;
+
+    struct TestEnv;
+
+    impl Env for TestEnv {
+        fn pc(&self) -> Value {
+            Value::Int {
+                value: 0x1000,
+                size: None,
+            }
+        }
+
+        fn symbol(&mut self, _depth: usize, name: &str) -> Result<Value, String> {
+            match name {
+                "target" => Ok(Value::Int {
+                    value: 0x0FF0,
+                    size: None,
+                }),
+                "external" => Ok(Value::Symbolic(Symbolic::address(
+                    Target::Symbol(name.to_string()),
+                    0,
+                ))),
+                _ => Err(format!("unknown symbol '{name}'")),
+            }
+        }
+
+        fn asm(&mut self, _lines: &[Vec<Token>], _locals: &Locals) -> Result<Output, String> {
+            Ok(Output::default())
+        }
+
+        fn lenient(&self) -> bool {
+            false
+        }
+    }
+
+    fn eval_str(source: &str) -> Result<Value, String> {
+        let tokens = lex(source, 0).map_err(|(_, message)| message)?;
+        let expr = ExprParser::new(&tokens, 0).parse_expr()?;
+        eval(&expr, &mut TestEnv, &mut Locals::new())
+    }
+
+    #[test]
+    fn test_eval_precedence() {
+        assert_eq!(
+            eval_str("1 + 2 * 3"),
+            Ok(Value::Int {
+                value: 7,
+                size: None
+            })
+        );
+        assert_eq!(
+            eval_str("0x1234 >> 8 & 0xFF"),
+            Ok(Value::Int {
+                value: 0x12,
+                size: None
+            })
+        );
+    }
+
+    #[test]
+    fn test_eval_concat() {
+        assert_eq!(
+            eval_str("0x31 @ (target - $ - 2)`8"),
+            Ok(Value::Int {
+                value: 0x31EE,
+                size: Some(16)
+            })
+        );
+        assert!(eval_str("0x31 @ 5").is_err());
+    }
+
+    #[test]
+    fn test_eval_link_time_address() {
+        let Ok(Value::Symbolic(hi)) = eval_str("(external + 4) >> 8") else {
+            panic!("expected a link-time value");
+        };
+        assert_eq!(hi.op, LinkOp::Hi8);
+        assert_eq!(hi.addend, 4);
+        assert_eq!(
+            eval_str("external - external + 1"),
+            Ok(Value::Int {
+                value: 1,
+                size: None
+            })
+        );
+        let Ok(Value::Patched { value, size, holes }) = eval_str("0x31 @ (external - 2)`8") else {
+            panic!("expected a patched value");
+        };
+        assert_eq!((value, size, holes.len(), holes[0].bit), (0x3100, 16, 1, 0));
+        assert!(eval_str("external * 2").is_err());
+    }
+
+    #[test]
+    fn test_string_as_number() {
+        assert_eq!(eval_str("\"\\n\""), Ok(Value::Str("\n".to_string())));
+        assert_eq!(Value::Str("\n".to_string()).as_int(), Ok((10, Some(8))));
+    }
+
+    #[test]
+    fn test_check_type() {
+        let value = Value::Int {
+            value: -4,
+            size: None,
+        };
+        assert_eq!(
+            check_type(&value, Some(true), 8, false),
+            Ok(Value::Int {
+                value: 0xFC,
+                size: Some(8)
+            })
+        );
+        assert!(check_type(&value, Some(false), 8, false).is_err());
+    }
+
+    fn int(value: i128) -> Value {
+        Value::Int { value, size: None }
+    }
+
+    #[test]
+    fn test_eval_operators() {
+        assert_eq!(eval_str("-3 + 10 % 4"), Ok(int(-1)));
+        assert_eq!(eval_str("(1 << 4) | 1"), Ok(int(0x11)));
+        assert_eq!(eval_str("0xF0 ^ 0xFF"), Ok(int(0x0F)));
+        assert_eq!(eval_str("7 / 2"), Ok(int(3)));
+        assert_eq!(eval_str("$ - target"), Ok(int(0x10)));
+        assert_eq!(eval_str("2 > 1 && 1 == 1"), Ok(Value::Bool(true)));
+        assert_eq!(eval_str("2 < 1 || 1 != 1"), Ok(Value::Bool(false)));
+    }
+
+    #[test]
+    fn test_eval_errors() {
+        let error = |source| match eval_str(source) {
+            Ok(value) => panic!("`{source}` evaluated to {value:?}"),
+            Err(message) => message,
+        };
+        assert!(error("1 / 0").contains("division by zero"));
+        assert!(error("4 % 0").contains("division by zero"));
+        assert!(error("missing + 1").contains("unknown symbol 'missing'"));
+        assert!(error("nope(1)").contains("unknown function 'nope'"));
+        assert_eq!(error("assert(1 > 2)"), "assertion failed");
+        assert!(error("1 +").contains("expected an expression"));
+        assert!(error("(1 + 2").contains("expected `)`"));
+        assert!(error("1 @ 2").contains("explicit size"));
+    }
+
+    #[test]
+    fn test_check_type_ranges() {
+        let value = |value| Value::Int { value, size: None };
+        assert!(check_type(&value(0xFF), Some(false), 8, false).is_ok());
+        assert!(check_type(&value(0x100), Some(false), 8, false).is_err());
+        assert!(check_type(&value(-128), Some(true), 8, false).is_ok());
+        assert!(check_type(&value(-129), Some(true), 8, false).is_err());
+        assert!(check_type(&value(0xFFFF), None, 16, false).is_ok());
+        assert!(check_type(&value(0x1_0000), None, 16, false).is_err());
+    }
+}
@@ -0,0 +1,314 @@
+//! Tokenizer for the customasm-style dialect used by `asm/`, `kernel/` and `user/`.
+
+use std::fmt::Display;
+
+use crate::{error::Location, expr::Value};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenKind {
+    /// Identifier, e.g. `LDI`, `R0` or `sys_exec`.
+    Ident(String),
+    /// Integer literal. Hex and binary literals carry their width in bits.
+    Number { value: i128, size: Option<u32> },
+    /// String literal with escapes already resolved.
+    Str(String),
+    /// Directive name without the leading `#`, e.g. `include` or `d8`.
+    Directive(String),
+    /// Operator or punctuation.
+    Punct(&'static str),
+    /// End of a line.
+    Newline,
+    /// Rule argument substituted into an `asm` block.
+    Value(Box<Value>),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    pub loc: Location,
+}
+
+impl Token {
+    #[must_use]
+    pub fn is_punct(&self, punct: &str) -> bool {
+        matches!(self.kind, TokenKind::Punct(p) if p == punct)
+    }
+
+    #[must_use]
+    pub fn is_newline(&self) -> bool {
+        matches!(self.kind, TokenKind::Newline)
+    }
+
+    #[must_use]
+    pub fn ident(&self) -> Option<&str> {
+        match &self.kind {
+            TokenKind::Ident(name) => Some(name),
+            _ => None,
+        }
+    }
+}
+
+impl Display for TokenKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TokenKind::Ident(name) => f.write_str(name),
+            TokenKind::Number { value, .. } => write!(f, "{value}"),
+            TokenKind::Str(value) => write!(f, "{value:?}"),
+            TokenKind::Directive(name) => write!(f, "#{name}"),
+            TokenKind::Punct(punct) => f.write_str(punct),
+            TokenKind::Newline => f.write_str("end of line"),
+            TokenKind::Value(value) => match value.as_int() {
+                Ok((value, _)) => write!(f, "{value}"),
+                Err(_) => f.write_str("<link-time value>"),
+            },
+        }
+    }
+}
+
+const PUNCTS: &[&str] = &[
+    "=>", "<<", ">>", "<=", ">=", "==", "!=", "&&", "||", "{", "}", "[", "]", "(", ")", ":", ",",
+    "=", "+", "-", "*", "/", "%", "&", "|", "^", "~", "!", "<", ">", "@", "$", "`", ".", "?",
+];
+
+/// Split a source file into tokens.
+///
+/// # Errors
+/// Returns the offending line and a message on malformed literals or unknown characters.
+pub fn lex(source: &str, file: usize) -> Result<Vec<Token>, (usize, String)> {
+    let mut tokens = Vec::new();
+    for (index, line) in source.lines().enumerate() {
+        let loc = Location {
+            file,
+            line: index + 1,
+        };
+        lex_line(line, loc, &mut tokens).map_err(|message| (loc.line, message))?;
+        tokens.push(Token {
+            kind: TokenKind::Newline,
+            loc,
+        });
+    }
+    Ok(tokens)
+}
+
+fn lex_line(line: &str, loc: Location, tokens: &mut Vec<Token>) -> Result<(), String> {
+    let chars: Vec<char> = line.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        if c.is_whitespace() {
+            i += 1;
+            continue;
+        }
+        if c == ';' {
+            break;
+        }
+
+        let kind = if c == '"' {
+            let (value, end) = lex_string(&chars, i + 1)?;
+            i = end;
+            TokenKind::Str(value)
+        } else if c.is_ascii_digit() {
+            let start = i;
+            while i < chars.len() && (chars[i].is_ascii_alphanumeric() || chars[i] == '_') {
+                i += 1;
+            }
+            let text: String = chars[start..i].iter().collect();
+            lex_number(&text)?
+        } else if is_ident_start(c) {
+            let start = i;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            TokenKind::Ident(chars[start..i].iter().collect())
+        } else if c == '#' && chars.get(i + 1).is_some_and(|&c| is_ident_start(c)) {
+            let start = i + 1;
+            i += 1;
+            while i < chars.len() && is_ident_char(chars[i]) {
+                i += 1;
+            }
+            TokenKind::Directive(chars[start..i].iter().collect())
+        } else {
+            let rest: String = chars[i..chars.len().min(i + 2)].iter().collect();
+            let Some(punct) = PUNCTS.iter().find(|punct| rest.starts_with(**punct)) else {
+                return Err(format!("unexpected character '{c}'"));
+            };
+            i += punct.len();
+            TokenKind::Punct(punct)
+        };
+        tokens.push(Token { kind, loc });
+    }
+    Ok(())
+}
+
+fn is_ident_start(c: char) -> bool {
+    c.is_ascii_alphabetic() || c == '_'
+}
+
+fn is_ident_char(c: char) -> bool {
+    c.is_ascii_alphanumeric() || c == '_'
+}
+
+fn lex_number(text: &str) -> Result<TokenKind, String> {
+    let digits = |s: &str| s.chars().filter(|&c| c != '_').collect::<String>();
+    let (value, size) = if let Some(hex) = text.strip_prefix("0x") {
+        let hex = digits(hex);
+        (i128::from_str_radix(&hex, 16), Some(hex.len() as u32 * 4))
+    } else if let Some(bin) = text.strip_prefix("0b") {
+        let bin = digits(bin);
+        (i128::from_str_radix(&bin, 2), Some(bin.len() as u32))
+    } else {
+        (digits(text).parse::<i128>(), None)
+    };
+    let value = value.map_err(|_| format!("invalid number '{text}'"))?;
+    Ok(TokenKind::Number { value, size })
+}
+
+fn lex_string(chars: &[char], mut i: usize) -> Result<(String, usize), String> {
+    let mut value = String::new();
+    while i < chars.len() {
+        match chars[i] {
+            '"' => return Ok((value, i + 1)),
+            '\\' => {
+                let escaped = chars.get(i + 1).ok_or("unterminated escape sequence")?;
+                match escaped {
+                    'n' => value.push('\n'),
+                    'r' => value.push('\r'),
+                    't' => value.push('\t'),
+                    '0' => value.push('\0'),
+                    '\\' => value.push('\\'),
+                    '"' => value.push('"'),
+                    '\'' => value.push('\''),
+                    'x' => {
+                        let hex: String = chars.get(i + 2..i + 4).unwrap_or(&[]).iter().collect();
+                        let byte = u8::from_str_radix(&hex, 16)
+                            .map_err(|_| format!("invalid escape '\\x{hex}'"))?;
+                        value.push(byte as char);
+                        i += 2;
+                    }
+                    other => return Err(format!("unknown escape sequence '\\{other}'")),
+                }
+                i += 2;
+            }
+            c => {
+                value.push(c);
+                i += 1;
+            }
+        }
+    }
+    Err("unterminated string".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(source: &str) -> Vec<TokenKind> {
+        match lex(source, 0) {
+            Ok(tokens) => tokens.into_iter().map(|token| token.kind).collect(),
+            Err((line, message)) => panic!("{line}: {message}"),
+        }
+    }
+
+    #[test]
+    fn test_lex_instruction() {
+        assert_eq!(
+            kinds("LD R5 [R1:R2] ; comment"),
+            vec![
+                TokenKind::Ident("LD".to_string()),
+                TokenKind::Ident("R5".to_string()),
+                TokenKind::Punct("["),
+                TokenKind::Ident("R1".to_string()),
+                TokenKind::Punct(":"),
+                TokenKind::Ident("R2".to_string()),
+                TokenKind::Punct("]"),
+                TokenKind::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_number_sizes() {
+        assert_eq!(
+            kinds("0x0100 0b1111_0000 42"),
+            vec![
+                TokenKind::Number {
+                    value: 0x100,
+                    size: Some(16)
+                },
+                TokenKind::Number {
+                    value: 0xF0,
+                    size: Some(8)
+                },
+                TokenKind::Number {
+                    value: 42,
+                    size: None
+                },
+                TokenKind::Newline,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_lex_string_and_directive() {
+        assert_eq!(
+            kinds("#d \"hi;\\n\\0\""),
+            vec![
+                TokenKind::Directive("d".to_string()),
+                TokenKind::Str("hi;\n\0".to_string()),
+                TokenKind::Newline,
+            ]
+        );
+    }
+
+    fn error(source: &str) -> String {
+        match lex(source, 0) {
+            Ok(tokens) => panic!("`{source}` lexed to {tokens:?}"),
+            Err((_, message)) => message,
+        }
+    }
+
+    #[test]
+    fn test_lex_errors() {
+        assert!(error("#d \"open").contains("unterminated string"));
+        assert!(error("#d \"\\q\"").contains("unknown escape"));
+        assert!(error("#d \"\\xZZ\"").contains("invalid escape"));
+        assert!(error("0xZZ").contains("invalid number"));
+        assert!(error("LDI R0 \u{e9}").contains("unexpected character"));
+    }
+
+    #[test]
+    fn test_lex_error_line() {
+        let Err((line, _)) = lex("NOP\nNOP\n\"open", 0) else {
+            panic!("expected an error");
+        };
+        assert_eq!(line, 3);
+    }
+
+    #[test]
+    fn test_lex_operators_and_labels() {
+        assert_eq!(
+            kinds("start: .loop: x >= 0x10 && y`8"),
+            vec![
+                TokenKind::Ident("start".to_string()),
+                TokenKind::Punct(":"),
+                TokenKind::Punct("."),
+                TokenKind::Ident("loop".to_string()),
+                TokenKind::Punct(":"),
+                TokenKind::Ident("x".to_string()),
+                TokenKind::Punct(">="),
+                TokenKind::Number {
+                    value: 0x10,
+                    size: Some(8)
+                },
+                TokenKind::Punct("&&"),
+                TokenKind::Ident("y".to_string()),
+                TokenKind::Punct("`"),
+                TokenKind::Number {
+                    value: 8,
+                    size: None
+                },
+                TokenKind::Newline,
+            ]
+        );
+    }
+}
@@ -0,0 +1,82 @@
+//! Assembler for the MB8 VM.
+//!
+//! Understands the customasm dialect used by `asm/`, `kernel/` and `user/`:
+//! `#ruledef`/`#subruledef` (including `asm { ... }` bodies), `#bankdef`,
+//! `#addr`, `#d`/`#dN`, `#include`/`#once`, labels, sublabels and constants.
+//!
+//! Sources can also be assembled into relocatable [`Object`]s and combined
+//! with [`linker::link`].
+
+use std::path::{Path, PathBuf};
+
+use error::AsmError;
+use source::Sources;
+
+pub mod assembler;
+pub mod error;
+pub mod expr;
+pub mod lexer;
+pub mod linker;
+pub mod object;
+pub mod parser;
+pub mod rules;
+pub mod source;
+
+pub use assembler::Assembly;
+pub use object::Object;
+
+/// Assemble the file at `path`, resolving includes relative to each including file.
+///
+/// # Errors
+/// Returns every error found in the sources.
+pub fn assemble_file(path: &Path) -> Result<Assembly, Vec<AsmError>> {
+    assemble_sources(Sources::new(), path)
+}
+
+/// Assemble source text that is not backed by a file. Includes are resolved
+/// relative to the current directory.
+///
+/// # Errors
+/// Returns every error found in the sources.
+pub fn assemble_str(source: &str) -> Result<Assembly, Vec<AsmError>> {
+    let path = PathBuf::from("<input>");
+    let mut sources = Sources::new();
+    sources.add_virtual(&path, source);
+    assemble_sources(sources, &path)
+}
+
+/// Assemble the file at `path` into a relocatable object.
+///
+/// # Errors
+/// Returns every error found in the sources.
+pub fn assemble_object_file(path: &Path) -> Result<Object, Vec<AsmError>> {
+    let (program, sources) = parse_sources(Sources::new(), path)?;
+    assembler::assemble_object(&program, &sources)
+}
+
+/// Assemble source text that is not backed by a file into a relocatable object.
+///
+/// # Errors
+/// Returns every error found in the sources.
+pub fn assemble_object_str(source: &str) -> Result<Object, Vec<AsmError>> {
+    let path = PathBuf::from("<input>");
+    let mut sources = Sources::new();
+    sources.add_virtual(&path, source);
+    let (program, sources) = parse_sources(sources, &path)?;
+    assembler::assemble_object(&program, &sources)
+}
+
+fn assemble_sources(sources: Sources, path: &Path) -> Result<Assembly, Vec<AsmError>> {
+    let (program, sources) = parse_sources(sources, path)?;
+    assembler::assemble(&program, &sources)
+}
+
+fn parse_sources(
+    mut sources: Sources,
+    path: &Path,
+) -> Result<(parser::Program, Sources), Vec<AsmError>> {
+    let tokens = sources.load(path).map_err(|error| vec![error])?;
+    let program = parser::parse(&tokens)
+        .map_err(|(loc, message)| vec![sources.error_at(loc.file, loc.line, message)])?;
+    Ok((program, sources))
+}
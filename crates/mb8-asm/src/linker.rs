@@ -0,0 +1,556 @@
+//! Combines relocatable [`Object`]s into a single image.
+//!
+//! Placement is described by a link script with one directive per line
+//! (`;` starts a comment):
+//!
+//! ```text
+//! region <name> <origin> <size> [fill]
+//! section <name> <region>
+//! ```
+//!
+//! Regions are written to the output in script order. Relocatable sections go
+//! to the region of their `section` line, in script order, and sections not
+//! listed go to the first region. Sections with a fixed address must lie inside
+//! a region.
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    fmt::Display,
+};
+
+use mb8_isa::symbols::SymbolMap;
+
+use crate::{
+    error::AsmError,
+    object::{Object, RelocKind, Target},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub name: String,
+    pub origin: u16,
+    pub size: u32,
+    /// Pad the region to its full size in the output.
+    pub fill: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct LinkScript {
+    pub regions: Vec<Region>,
+    /// Section name and the region it is placed in.
+    pub sections: Vec<(String, String)>,
+}
+
+impl Default for LinkScript {
+    /// User program layout: everything in RAM at `0x1000`.
+    fn default() -> Self {
+        Self {
+            regions: vec![Region {
+                name: "ram".to_string(),
+                origin: 0x1000,
+                size: 0x1000,
+                fill: false,
+            }],
+            sections: Vec::new(),
+        }
+    }
+}
+
+impl LinkScript {
+    /// Parse a link script.
+    ///
+    /// # Errors
+    /// Returns the line number and message of the first invalid line.
+    pub fn parse(source: &str) -> Result<Self, (usize, String)> {
+        let mut script = Self {
+            regions: Vec::new(),
+            sections: Vec::new(),
+        };
+        for (index, line) in source.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.split(';').next().unwrap_or_default();
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                [] => {}
+                ["region", name, origin, size, rest @ ..] => {
+                    let fill = match rest {
+                        [] => false,
+                        ["fill"] => true,
+                        _ => return Err((line_no, format!("unexpected `{}`", rest.join(" ")))),
+                    };
+                    let origin = parse_number(origin).map_err(|e| (line_no, e))?;
+                    let size = parse_number(size).map_err(|e| (line_no, e))?;
+                    let Ok(origin) = u16::try_from(origin) else {
+                        return Err((line_no, format!("region '{name}' starts above 0xFFFF")));
+                    };
+                    if u32::from(origin) + size > 0x1_0000 {
+                        return Err((line_no, format!("region '{name}' ends above 0xFFFF")));
+                    }
+                    if script.region(name).is_some() {
+                        return Err((line_no, format!("region '{name}' is already defined")));
+                    }
+                    script.regions.push(Region {
+                        name: (*name).to_string(),
+                        origin,
+                        size,
+                        fill,
+                    });
+                }
+                ["section", name, region] => {
+                    if script.region(region).is_none() {
+                        return Err((line_no, format!("unknown region '{region}'")));
+                    }
+                    script
+                        .sections
+                        .push(((*name).to_string(), (*region).to_string()));
+                }
+                _ => {
+                    return Err((
+                        line_no,
+                        format!("invalid link script line `{}`", line.trim()),
+                    ))
+                }
+            }
+        }
+        if script.regions.is_empty() {
+            return Err((0, "link script defines no regions".to_string()));
+        }
+        Ok(script)
+    }
+
+    fn region(&self, name: &str) -> Option<usize> {
+        self.regions.iter().position(|region| region.name == name)
+    }
+}
+
+fn parse_number(text: &str) -> Result<u32, String> {
+    let parsed = match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(hex) => u32::from_str_radix(&hex.replace('_', ""), 16),
+        None => text.replace('_', "").parse(),
+    };
+    parsed.map_err(|_| format!("invalid number '{text}'"))
+}
+
+/// Output of a successful link.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Linked {
+    /// Image of every region, concatenated in script order.
+    pub binary: Vec<u8>,
+    /// Final value of every global symbol.
+    pub symbols: BTreeMap<String, i64>,
+    /// Final address of every label, for debuggers and the disassembler.
+    pub map: SymbolMap,
+}
+
+/// A section placed at its final address.
+#[derive(Debug, Clone, Copy)]
+struct Placed {
+    object: usize,
+    section: usize,
+    region: usize,
+    base: u32,
+    len: u32,
+}
+
+impl Placed {
+    fn end(&self) -> u32 {
+        self.base + self.len
+    }
+}
+
+/// Link `objects` (each with a name used in diagnostics) according to `script`.
+///
+/// # Errors
+/// Returns every placement, symbol and relocation error.
+pub fn link(objects: &[(String, Object)], script: &LinkScript) -> Result<Linked, Vec<AsmError>> {
+    let mut linker = Linker {
+        objects,
+        script,
+        placed: Vec::new(),
+        bases: HashMap::new(),
+        globals: BTreeMap::new(),
+        images: HashMap::new(),
+        errors: Vec::new(),
+    };
+    linker.place();
+    linker.collect_globals();
+    linker.relocate();
+    if !linker.errors.is_empty() {
+        return Err(linker.errors);
+    }
+    Ok(Linked {
+        binary: linker.write(),
+        map: linker.symbol_map(),
+        symbols: linker
+            .globals
+            .into_iter()
+            .map(|(name, global)| (name, global.value))
+            .collect(),
+    })
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Global {
+    object: usize,
+    absolute: bool,
+    value: i64,
+}
+
+struct Linker<'a> {
+    objects: &'a [(String, Object)],
+    script: &'a LinkScript,
+    placed: Vec<Placed>,
+    /// Base address of every placed section, keyed by object and section index.
+    bases: HashMap<(usize, usize), u32>,
+    globals: BTreeMap<String, Global>,
+    /// Section data with relocations applied.
+    images: HashMap<(usize, usize), Vec<u8>>,
+    errors: Vec<AsmError>,
+}
+
+impl Linker<'_> {
+    fn error(&mut self, object: usize, message: impl Display) {
+        let name = &self.objects[object].0;
+        self.errors
+            .push(AsmError::new(format!("{name}: {message}")));
+    }
+
+    /// Assign every section a base address.
+    fn place(&mut self) {
+        let mut floating = Vec::new();
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            for (section_index, section) in object.sections.iter().enumerate() {
+                let len = section.data.len() as u32;
+                let Some(addr) = section.addr else {
+                    let rank = self
+                        .script
+                        .sections
+                        .iter()
+                        .position(|(name, _)| *name == section.name);
+                    let region = rank
+                        .and_then(|rank| self.script.region(&self.script.sections[rank].1))
+                        .unwrap_or(0);
+                    floating.push((rank.unwrap_or(usize::MAX), index, section_index, region));
+                    continue;
+                };
+                let (start, end) = (u32::from(addr), u32::from(addr) + len);
+                let region = self.script.regions.iter().position(|region| {
+                    let origin = u32::from(region.origin);
+                    start >= origin && end <= origin + region.size
+                });
+                match region {
+                    Some(region) => self.placed.push(Placed {
+                        object: index,
+                        section: section_index,
+                        region,
+                        base: start,
+                        len,
+                    }),
+                    None => self.error(
+                        index,
+                        format!(
+                            "section '{}' at 0x{addr:04X} is outside every region",
+                            section.name
+                        ),
+                    ),
+                }
+            }
+        }
+
+        floating.sort_by_key(|&(rank, object, section, _)| (rank, object, section));
+        let mut cursors: Vec<u32> = self
+            .script
+            .regions
+            .iter()
+            .map(|region| u32::from(region.origin))
+            .collect();
+        for (_, object, section, region) in floating {
+            let len = self.objects[object].1.sections[section].data.len() as u32;
+            let mut base = cursors[region];
+            // Skip over fixed sections already occupying the space.
+            while let Some(end) = self
+                .placed
+                .iter()
+                .find(|p| p.region == region && p.base < base + len && base < p.end())
+                .map(Placed::end)
+            {
+                base = end;
+            }
+            let bounds = &self.script.regions[region];
+            if base + len > u32::from(bounds.origin) + bounds.size {
+                let message = format!(
+                    "section '{}' does not fit in region '{}'",
+                    self.objects[object].1.sections[section].name, bounds.name
+                );
+                self.error(object, message);
+                continue;
+            }
+            cursors[region] = base + len;
+            self.placed.push(Placed {
+                object,
+                section,
+                region,
+                base,
+                len,
+            });
+        }
+
+        let mut spans: Vec<Placed> = self.placed.iter().filter(|p| p.len > 0).copied().collect();
+        spans.sort_unstable_by_key(|p| p.base);
+        for pair in spans.windows(2) {
+            let (first, second) = (pair[0], pair[1]);
+            if second.base < first.end() {
+                let message = format!(
+                    "section '{}' overlaps section '{}' of {} at 0x{:04X}",
+                    self.objects[second.object].1.sections[second.section].name,
+                    self.objects[first.object].1.sections[first.section].name,
+                    self.objects[first.object].0,
+                    second.base
+                );
+                self.error(second.object, message);
+            }
+        }
+        self.bases = self
+            .placed
+            .iter()
+            .map(|p| ((p.object, p.section), p.base))
+            .collect();
+    }
+
+    /// Address of a symbol value, `None` if its section could not be placed.
+    fn symbol_value(&self, object: usize, section: Option<usize>, value: i64) -> Option<i64> {
+        match section {
+            Some(section) => self
+                .bases
+                .get(&(object, section))
+                .map(|base| i64::from(*base) + value),
+            None => Some(value),
+        }
+    }
+
+    fn collect_globals(&mut self) {
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            for symbol in object.symbols.iter().filter(|symbol| symbol.global) {
+                let Some(value) = self.symbol_value(index, symbol.section, symbol.value) else {
+                    continue;
+                };
+                let absolute = symbol.section.is_none();
+                match self.globals.get(&symbol.name) {
+                    // Constants shared through a common include are fine.
+                    Some(other) if other.absolute && absolute && other.value == value => {}
+                    Some(other) => {
+                        let message = format!(
+                            "duplicate symbol '{}' (also defined in {})",
+                            symbol.name, self.objects[other.object].0
+                        );
+                        self.error(index, message);
+                    }
+                    None => {
+                        let global = Global {
+                            object: index,
+                            absolute,
+                            value,
+                        };
+                        self.globals.insert(symbol.name.clone(), global);
+                    }
+                }
+            }
+        }
+    }
+
+    fn relocate(&mut self) {
+        let mut unresolved = HashSet::new();
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            for reloc in &object.relocations {
+                let Some(base) = self.bases.get(&(index, reloc.section)).copied() else {
+                    continue;
+                };
+                let target = match &reloc.target {
+                    Target::Section(section) => self
+                        .bases
+                        .get(&(index, *section))
+                        .map(|base| i64::from(*base)),
+                    Target::Symbol(name) => {
+                        if let Some(local) = object.symbols.iter().find(|s| &s.name == name) {
+                            self.symbol_value(index, local.section, local.value)
+                        } else if let Some(global) = self.globals.get(name) {
+                            Some(global.value)
+                        } else {
+                            if unresolved.insert((index, name)) {
+                                self.error(index, format!("unresolved symbol '{name}'"));
+                            }
+                            continue;
+                        }
+                    }
+                };
+                let Some(target) = target else {
+                    continue;
+                };
+                let value = target + reloc.addend;
+                let patch = match reloc.kind {
+                    RelocKind::Abs16 => u16::try_from(value).map(|v| v.to_be_bytes().to_vec()).ok(),
+                    RelocKind::Abs8 => u8::try_from(value).map(|v| vec![v]).ok(),
+                    RelocKind::Hi8 => u16::try_from(value).map(|v| vec![(v >> 8) as u8]).ok(),
+                    RelocKind::Lo8 => u16::try_from(value).map(|v| vec![v as u8]).ok(),
+                    RelocKind::Rel8 => i8::try_from(value - i64::from(base))
+                        .map(|v| vec![v as u8])
+                        .ok(),
+                };
+                let Some(patch) = patch else {
+                    let message = format!(
+                        "{} relocation to {} is out of range ({value})",
+                        reloc.kind,
+                        describe(&reloc.target)
+                    );
+                    self.error(index, message);
+                    continue;
+                };
+                let data = self
+                    .images
+                    .entry((index, reloc.section))
+                    .or_insert_with(|| object.sections[reloc.section].data.clone());
+                let offset = usize::from(reloc.offset);
+                match data.get_mut(offset..offset + patch.len()) {
+                    Some(slot) => slot.copy_from_slice(&patch),
+                    None => self.error(
+                        index,
+                        format!("relocation at offset {offset} is outside its section"),
+                    ),
+                }
+            }
+        }
+    }
+
+    fn symbol_map(&self) -> SymbolMap {
+        let mut labels: Vec<(u16, &str)> = Vec::new();
+        for (index, (_, object)) in self.objects.iter().enumerate() {
+            for symbol in &object.symbols {
+                if symbol.section.is_none() {
+                    continue;
+                }
+                let value = self.symbol_value(index, symbol.section, symbol.value);
+                if let Some(addr) = value.and_then(|value| u16::try_from(value).ok()) {
+                    labels.push((addr, &symbol.name));
+                }
+            }
+        }
+        // Prefer `parent` over `parent.sub` when both sit at the same address.
+        labels.sort_by_key(|(addr, name)| (*addr, name.matches('.').count()));
+        let mut map = SymbolMap::new();
+        for (addr, name) in labels {
+            map.add_label(addr, name);
+        }
+        map
+    }
+
+    /// Image of every region, concatenated in script order.
+    fn write(&self) -> Vec<u8> {
+        let mut binary = Vec::new();
+        for (index, region) in self.script.regions.iter().enumerate() {
+            let origin = u32::from(region.origin);
+            let placed: Vec<&Placed> = self.placed.iter().filter(|p| p.region == index).collect();
+            let len = if region.fill {
+                region.size
+            } else {
+                placed.iter().map(|p| p.end() - origin).max().unwrap_or(0)
+            };
+            let mut image = vec![0; len as usize];
+            for p in placed {
+                let data = self
+                    .images
+                    .get(&(p.object, p.section))
+                    .unwrap_or(&self.objects[p.object].1.sections[p.section].data);
+                let start = (p.base - origin) as usize;
+                image[start..start + data.len()].copy_from_slice(data);
+            }
+            binary.extend(image);
+        }
+        binary
+    }
+}
+
+fn describe(target: &Target) -> String {
+    match target {
+        Target::Section(section) => format!("section {section}"),
+        Target::Symbol(name) => format!("'{name}'"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{assemble_object_str, assembler::tests::ISA};
+
+    fn object(name: &str, source: &str) -> (String, Object) {
+        match assemble_object_str(&format!("{ISA}\n{source}")) {
+            Ok(object) => (name.to_string(), object),
+            Err(errors) => panic!("{errors:?}"),
+        }
+    }
+
+    #[test]
+    fn test_link_two_objects() {
+        let main = object(
+            "main.o",
+            "
+start:
+    CALL [puts]
+    JR [start]
+    JR [puts]
+",
+        );
+        let lib = object(
+            "lib.o",
+            "
+    NOP
+puts:
+    NOP
+message:
+    #d16 message
+",
+        );
+        let Ok(linked) = link(&[main, lib], &LinkScript::default()) else {
+            panic!("link failed");
+        };
+        assert_eq!(linked.symbols.get("start"), Some(&0x1000));
+        assert_eq!(linked.symbols.get("puts"), Some(&0x100C));
+        assert_eq!(linked.symbols.get("message"), Some(&0x100E));
+        assert_eq!(linked.map.describe(0x100D), "puts+0x1");
+        assert_eq!(
+            linked.binary,
+            [
+                0x26, 0x10, 0x27, 0x0C, 0x40, 0x67, 0x31, 0xF8, 0x31, 0x02, 0x00, 0x00, 0x00, 0x00,
+                0x10, 0x0E,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_link_reports_symbol_errors() {
+        let a = object("a.o", "start:\n CALL [missing]\n");
+        let b = object("b.o", "start:\n NOP\n");
+        let Err(errors) = link(&[a, b], &LinkScript::default()) else {
+            panic!("link should fail");
+        };
+        let messages: Vec<String> = errors.iter().map(ToString::to_string).collect();
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("duplicate symbol 'start'")));
+        assert!(messages
+            .iter()
+            .any(|m| m.contains("unresolved symbol 'missing'")));
+    }
+
+    #[test]
+    fn test_link_script() {
+        let Ok(script) =
+            LinkScript::parse("; kernel layout\nregion rom 0xE000 0x2000 fill\nsection text rom\n")
+        else {
+            panic!("script should parse");
+        };
+        assert_eq!(script.regions[0].origin, 0xE000);
+        assert!(script.regions[0].fill);
+        assert!(LinkScript::parse("section text nowhere\n").is_err());
+        assert!(LinkScript::parse("region big 0xF000 0x2000\n").is_err());
+    }
+}
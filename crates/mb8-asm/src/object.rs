@@ -0,0 +1,344 @@
+//! Relocatable object files produced by `asm --object` and consumed by the linker.
+//!
+//! Layout (all integers big-endian, strings are a `u16` length followed by UTF-8):
+//!
+//! ```text
+//! "MB8O" version:u8
+//! sections:u16    { name:str flags:u8 addr:u16 len:u16 data:[u8; len] }
+//! symbols:u16     { name:str flags:u8 section:u16 value:i64 }
+//! relocations:u16 { section:u16 offset:u16 kind:u8 target addend:i64 }
+//! target          = 0:u8 section:u16 | 1:u8 name:str
+//! ```
+//!
+//! Section flag `1` marks an absolute section placed at `addr`; symbol flag `1`
+//! marks a global symbol. Symbols in section `0xFFFF` are absolute values.
+
+use std::fmt::Display;
+
+const MAGIC: &[u8; 4] = b"MB8O";
+const VERSION: u8 = 1;
+const ABSOLUTE: u16 = 0xFFFF;
+
+/// A relocatable object: sections of code/data, the symbols they define and the
+/// places that must be patched once the final addresses are known.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Object {
+    pub sections: Vec<Section>,
+    pub symbols: Vec<Symbol>,
+    pub relocations: Vec<Relocation>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section {
+    pub name: String,
+    /// Fixed load address, `None` for sections the linker may place anywhere.
+    pub addr: Option<u16>,
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Symbol {
+    pub name: String,
+    /// Section the value is relative to, `None` for absolute values and constants.
+    pub section: Option<usize>,
+    pub value: i64,
+    /// Visible to other objects. Sublabels (`parent.sub`) are local.
+    pub global: bool,
+}
+
+/// What a relocation refers to.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Target {
+    /// Start address of a section of the same object.
+    Section(usize),
+    /// A symbol, resolved in the same object first and then among all globals.
+    Symbol(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelocKind {
+    /// Full 16-bit address.
+    Abs16,
+    /// Address that must fit in an unsigned byte.
+    Abs8,
+    /// High byte of an address (`addr >> 8`).
+    Hi8,
+    /// Low byte of an address (`addr & 0xFF`).
+    Lo8,
+    /// Signed byte relative to the start of the containing section, as used by `JR`.
+    Rel8,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Relocation {
+    /// Section containing the bytes to patch.
+    pub section: usize,
+    /// Offset of the first patched byte inside the section.
+    pub offset: u16,
+    pub kind: RelocKind,
+    pub target: Target,
+    pub addend: i64,
+}
+
+impl RelocKind {
+    /// Number of bytes the relocation patches.
+    #[must_use]
+    pub fn width(self) -> usize {
+        match self {
+            RelocKind::Abs16 => 2,
+            RelocKind::Abs8 | RelocKind::Hi8 | RelocKind::Lo8 | RelocKind::Rel8 => 1,
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            RelocKind::Abs16 => 0,
+            RelocKind::Abs8 => 1,
+            RelocKind::Hi8 => 2,
+            RelocKind::Lo8 => 3,
+            RelocKind::Rel8 => 4,
+        }
+    }
+
+    fn from_code(code: u8) -> Option<Self> {
+        match code {
+            0 => Some(RelocKind::Abs16),
+            1 => Some(RelocKind::Abs8),
+            2 => Some(RelocKind::Hi8),
+            3 => Some(RelocKind::Lo8),
+            4 => Some(RelocKind::Rel8),
+            _ => None,
+        }
+    }
+}
+
+impl Display for RelocKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            RelocKind::Abs16 => "abs16",
+            RelocKind::Abs8 => "abs8",
+            RelocKind::Hi8 => "hi8",
+            RelocKind::Lo8 => "lo8",
+            RelocKind::Rel8 => "rel8",
+        })
+    }
+}
+
+impl Object {
+    /// Serialize the object.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Writer(MAGIC.to_vec());
+        out.u8(VERSION);
+
+        out.u16(self.sections.len() as u16);
+        for section in &self.sections {
+            out.str(&section.name);
+            out.u8(u8::from(section.addr.is_some()));
+            out.u16(section.addr.unwrap_or(0));
+            out.u16(section.data.len() as u16);
+            out.0.extend(&section.data);
+        }
+
+        out.u16(self.symbols.len() as u16);
+        for symbol in &self.symbols {
+            out.str(&symbol.name);
+            out.u8(u8::from(symbol.global));
+            out.u16(symbol.section.map_or(ABSOLUTE, |section| section as u16));
+            out.i64(symbol.value);
+        }
+
+        out.u16(self.relocations.len() as u16);
+        for reloc in &self.relocations {
+            out.u16(reloc.section as u16);
+            out.u16(reloc.offset);
+            out.u8(reloc.kind.code());
+            match &reloc.target {
+                Target::Section(section) => {
+                    out.u8(0);
+                    out.u16(*section as u16);
+                }
+                Target::Symbol(name) => {
+                    out.u8(1);
+                    out.str(name);
+                }
+            }
+            out.i64(reloc.addend);
+        }
+        out.0
+    }
+
+    /// Parse an object produced by [`Object::to_bytes`].
+    ///
+    /// # Errors
+    /// Returns an error if the data is not a valid MB8 object.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let mut input = Reader { data, pos: 0 };
+        if input.take(4)? != MAGIC {
+            return Err("not an MB8 object file".to_string());
+        }
+        let version = input.u8()?;
+        if version != VERSION {
+            return Err(format!("unsupported object version {version}"));
+        }
+
+        let mut object = Object::default();
+        for _ in 0..input.u16()? {
+            let name = input.str()?;
+            let absolute = input.u8()? & 1 != 0;
+            let addr = input.u16()?;
+            let len = input.u16()? as usize;
+            object.sections.push(Section {
+                name,
+                addr: absolute.then_some(addr),
+                data: input.take(len)?.to_vec(),
+            });
+        }
+
+        for _ in 0..input.u16()? {
+            let name = input.str()?;
+            let global = input.u8()? & 1 != 0;
+            let section = input.u16()?;
+            object.symbols.push(Symbol {
+                name,
+                section: (section != ABSOLUTE).then_some(section as usize),
+                value: input.i64()?,
+                global,
+            });
+        }
+
+        for _ in 0..input.u16()? {
+            let section = input.u16()? as usize;
+            let offset = input.u16()?;
+            let kind = input.u8()?;
+            let kind = RelocKind::from_code(kind)
+                .ok_or_else(|| format!("unknown relocation kind {kind}"))?;
+            let target = match input.u8()? {
+                0 => Target::Section(input.u16()? as usize),
+                1 => Target::Symbol(input.str()?),
+                other => return Err(format!("unknown relocation target {other}")),
+            };
+            object.relocations.push(Relocation {
+                section,
+                offset,
+                kind,
+                target,
+                addend: input.i64()?,
+            });
+        }
+
+        if input.pos != data.len() {
+            return Err("trailing data after object".to_string());
+        }
+        Ok(object)
+    }
+}
+
+struct Writer(Vec<u8>);
+
+impl Writer {
+    fn u8(&mut self, value: u8) {
+        self.0.push(value);
+    }
+
+    fn u16(&mut self, value: u16) {
+        self.0.extend(value.to_be_bytes());
+    }
+
+    fn i64(&mut self, value: i64) {
+        self.0.extend(value.to_be_bytes());
+    }
+
+    fn str(&mut self, value: &str) {
+        self.u16(value.len() as u16);
+        self.0.extend(value.as_bytes());
+    }
+}
+
+struct Reader<'d> {
+    data: &'d [u8],
+    pos: usize,
+}
+
+impl<'d> Reader<'d> {
+    fn take(&mut self, len: usize) -> Result<&'d [u8], String> {
+        let bytes = self
+            .data
+            .get(self.pos..self.pos + len)
+            .ok_or("unexpected end of object file")?;
+        self.pos += len;
+        Ok(bytes)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, String> {
+        let bytes = self.take(2)?;
+        Ok(u16::from_be_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i64(&mut self) -> Result<i64, String> {
+        let mut bytes = [0; 8];
+        bytes.copy_from_slice(self.take(8)?);
+        Ok(i64::from_be_bytes(bytes))
+    }
+
+    fn str(&mut self) -> Result<String, String> {
+        let len = self.u16()? as usize;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| "invalid UTF-8 in name".to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_object_roundtrip() {
+        let object = Object {
+            sections: vec![
+                Section {
+                    name: "text".to_string(),
+                    addr: None,
+                    data: vec![0x26, 0x00, 0x27, 0x00],
+                },
+                Section {
+                    name: "ram".to_string(),
+                    addr: Some(0x1000),
+                    data: vec![0x01],
+                },
+            ],
+            symbols: vec![Symbol {
+                name: "start".to_string(),
+                section: Some(0),
+                value: 0,
+                global: true,
+            }],
+            relocations: vec![
+                Relocation {
+                    section: 0,
+                    offset: 1,
+                    kind: RelocKind::Hi8,
+                    target: Target::Symbol("puts".to_string()),
+                    addend: 0,
+                },
+                Relocation {
+                    section: 0,
+                    offset: 3,
+                    kind: RelocKind::Lo8,
+                    target: Target::Section(0),
+                    addend: -2,
+                },
+            ],
+        };
+        assert_eq!(Object::from_bytes(&object.to_bytes()), Ok(object));
+    }
+
+    #[test]
+    fn test_object_rejects_garbage() {
+        assert!(Object::from_bytes(b"MB8").is_err());
+        assert!(Object::from_bytes(b"ELF\x7f\x01").is_err());
+    }
+}
@@ -0,0 +1,313 @@
+//! Splits the token stream into items: labels, constants, directives, rule
+//! definitions and instructions.
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Location,
+    expr::{split_lines, Expr, ExprParser},
+    lexer::{Token, TokenKind},
+    rules::{Rule, Rules},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Item {
+    Label {
+        depth: usize,
+        name: String,
+    },
+    Constant {
+        depth: usize,
+        name: String,
+        value: Expr,
+    },
+    Instruction(Vec<Token>),
+    Addr(Expr),
+    /// `#d` (no size) or `#dN` data.
+    Data {
+        size: Option<u32>,
+        values: Vec<Expr>,
+    },
+    Bank(String),
+    BankDef(BankDef),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BankDef {
+    pub name: String,
+    pub addr: Option<Expr>,
+    pub size: Option<Expr>,
+    pub outp: Option<Expr>,
+    pub fill: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Located {
+    pub item: Item,
+    pub loc: Location,
+}
+
+/// A parsed program: its items in source order and every rule it defines.
+#[derive(Debug, Default)]
+pub struct Program {
+    pub items: Vec<Located>,
+    pub rules: Rules,
+}
+
+struct Parser<'t> {
+    tokens: &'t [Token],
+    pos: usize,
+}
+
+/// Parse a fully expanded token stream.
+///
+/// # Errors
+/// Returns the location and message of the first syntax error.
+pub fn parse(tokens: &[Token]) -> Result<Program, (Location, String)> {
+    let mut parser = Parser { tokens, pos: 0 };
+    let mut program = Program {
+        items: Vec::new(),
+        rules: Rules {
+            rules: Vec::new(),
+            subrules: HashMap::new(),
+        },
+    };
+    while let Some(token) = parser.peek() {
+        let loc = token.loc;
+        parser
+            .parse_item(&mut program)
+            .map_err(|message| (loc, message))?;
+    }
+    Ok(program)
+}
+
+impl<'t> Parser<'t> {
+    fn peek(&self) -> Option<&'t Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn peek_at(&self, offset: usize) -> Option<&'t Token> {
+        self.tokens.get(self.pos + offset)
+    }
+
+    fn skip_newlines(&mut self) {
+        while self.peek().is_some_and(Token::is_newline) {
+            self.pos += 1;
+        }
+    }
+
+    fn line(&mut self) -> &'t [Token] {
+        let start = self.pos;
+        while self.peek().is_some_and(|token| !token.is_newline()) {
+            self.pos += 1;
+        }
+        &self.tokens[start..self.pos]
+    }
+
+    fn expr_line(&mut self) -> Result<Expr, String> {
+        let line = self.line();
+        parse_full_expr(line)
+    }
+
+    fn parse_item(&mut self, program: &mut Program) -> Result<(), String> {
+        let Some(token) = self.peek() else {
+            return Ok(());
+        };
+        let loc = token.loc;
+        let item = match &token.kind {
+            TokenKind::Newline => {
+                self.pos += 1;
+                return Ok(());
+            }
+            TokenKind::Directive(name) => {
+                self.pos += 1;
+                match name.as_str() {
+                    "ruledef" | "subruledef" => {
+                        let rule_name = self.peek().and_then(Token::ident).map(str::to_string);
+                        if rule_name.is_some() {
+                            self.pos += 1;
+                        }
+                        let rules = self.parse_rules()?;
+                        if name == "subruledef" {
+                            let Some(rule_name) = rule_name else {
+                                return Err("#subruledef needs a name".to_string());
+                            };
+                            program
+                                .rules
+                                .subrules
+                                .entry(rule_name)
+                                .or_default()
+                                .extend(rules);
+                        } else {
+                            program.rules.rules.extend(rules);
+                        }
+                        return Ok(());
+                    }
+                    "bankdef" => Item::BankDef(self.parse_bankdef()?),
+                    "bank" => {
+                        let Some(bank) = self.peek().and_then(Token::ident) else {
+                            return Err("expected a bank name after #bank".to_string());
+                        };
+                        self.pos += 1;
+                        Item::Bank(bank.to_string())
+                    }
+                    "addr" => Item::Addr(self.expr_line()?),
+                    "d" => Item::Data {
+                        size: None,
+                        values: self.data_values()?,
+                    },
+                    other => match other.strip_prefix('d').map(str::parse::<u32>) {
+                        Some(Ok(size)) if size > 0 && size % 8 == 0 => Item::Data {
+                            size: Some(size),
+                            values: self.data_values()?,
+                        },
+                        _ => return Err(format!("unknown directive #{other}")),
+                    },
+                }
+            }
+            _ => {
+                let mut depth = 0;
+                while self.peek_at(depth).is_some_and(|token| token.is_punct(".")) {
+                    depth += 1;
+                }
+                let name = self.peek_at(depth).and_then(Token::ident);
+                let next = self.peek_at(depth + 1);
+                match (name, next) {
+                    (Some(name), Some(next)) if next.is_punct(":") => {
+                        self.pos += depth + 2;
+                        Item::Label {
+                            depth,
+                            name: name.to_string(),
+                        }
+                    }
+                    (Some(name), Some(next)) if next.is_punct("=") => {
+                        self.pos += depth + 2;
+                        Item::Constant {
+                            depth,
+                            name: name.to_string(),
+                            value: self.expr_line()?,
+                        }
+                    }
+                    _ => Item::Instruction(self.line().to_vec()),
+                }
+            }
+        };
+        program.items.push(Located { item, loc });
+        Ok(())
+    }
+
+    fn data_values(&mut self) -> Result<Vec<Expr>, String> {
+        let line = self.line();
+        let mut parser = ExprParser::new(line, 0);
+        let mut values = vec![parser.parse_expr()?];
+        while parser.pos < line.len() {
+            if !line[parser.pos].is_punct(",") {
+                return Err(format!("expected `,`, found `{}`", line[parser.pos].kind));
+            }
+            parser.pos += 1;
+            values.push(parser.parse_expr()?);
+        }
+        Ok(values)
+    }
+
+    fn parse_rules(&mut self) -> Result<Vec<Rule>, String> {
+        self.skip_newlines();
+        let mut block = ExprParser::new(self.tokens, self.pos);
+        let inner = block.capture_braces()?;
+        self.pos = block.pos;
+
+        let mut rules = Vec::new();
+        let mut pos = 0;
+        while pos < inner.len() {
+            if inner[pos].is_newline() {
+                pos += 1;
+                continue;
+            }
+            let start = pos;
+            while pos < inner.len() && !inner[pos].is_punct("=>") {
+                if inner[pos].is_newline() {
+                    return Err("expected `=>` in rule".to_string());
+                }
+                pos += 1;
+            }
+            if pos == inner.len() {
+                return Err("expected `=>` in rule".to_string());
+            }
+            let pattern = &inner[start..pos];
+            pos += 1;
+
+            let mut parser = ExprParser::new(inner, pos);
+            let body = if inner.get(pos).is_some_and(|token| token.is_punct("{")) {
+                parser.parse_block()?
+            } else if inner.get(pos).and_then(Token::ident) == Some("asm")
+                && inner.get(pos + 1).is_some_and(|token| token.is_punct("{"))
+            {
+                parser.pos += 1;
+                Expr::Asm(split_lines(parser.capture_braces()?))
+            } else {
+                parser.parse_expr()?
+            };
+            pos = parser.pos;
+            if let Some(token) = inner.get(pos) {
+                if !token.is_newline() {
+                    return Err(format!("unexpected `{}` after rule body", token.kind));
+                }
+            }
+            rules.push(Rule::new(pattern, body, inner[start].loc)?);
+        }
+        Ok(rules)
+    }
+
+    fn parse_bankdef(&mut self) -> Result<BankDef, String> {
+        let Some(name) = self.peek().and_then(Token::ident) else {
+            return Err("expected a bank name after #bankdef".to_string());
+        };
+        self.pos += 1;
+        self.skip_newlines();
+        let mut block = ExprParser::new(self.tokens, self.pos);
+        let inner = block.capture_braces()?;
+        self.pos = block.pos;
+
+        let mut bank = BankDef {
+            name: name.to_string(),
+            addr: None,
+            size: None,
+            outp: None,
+            fill: false,
+        };
+        for line in split_lines(inner) {
+            let Some(TokenKind::Directive(field)) = line.first().map(|token| &token.kind) else {
+                return Err("expected a bank field such as #addr".to_string());
+            };
+            let value = || parse_full_expr(&line[1..]);
+            match field.as_str() {
+                "addr" => bank.addr = Some(value()?),
+                "size" => bank.size = Some(value()?),
+                "outp" => bank.outp = Some(value()?),
+                "fill" => bank.fill = true,
+                "bits" => {
+                    if value()?
+                        != (Expr::Number {
+                            value: 8,
+                            size: None,
+                        })
+                    {
+                        return Err("only 8-bit banks are supported".to_string());
+                    }
+                }
+                other => return Err(format!("unknown bank field #{other}")),
+            }
+        }
+        Ok(bank)
+    }
+}
+
+/// Parse `tokens` as exactly one expression.
+fn parse_full_expr(tokens: &[Token]) -> Result<Expr, String> {
+    let mut parser = ExprParser::new(tokens, 0);
+    let expr = parser.parse_expr()?;
+    match tokens.get(parser.pos) {
+        Some(token) => Err(format!("unexpected `{}` after expression", token.kind)),
+        None => Ok(expr),
+    }
+}
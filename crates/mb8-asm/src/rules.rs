@@ -0,0 +1,309 @@
+//! `#ruledef` / `#subruledef` patterns and the matcher that selects them.
+
+use std::collections::HashMap;
+
+use crate::{
+    error::Location,
+    expr::{Expr, ExprParser},
+    lexer::{Token, TokenKind},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PatternPart {
+    /// Identifier matched case-insensitively, stored lowercase.
+    Word(String),
+    Punct(&'static str),
+    Param {
+        name: String,
+        kind: ParamKind,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParamKind {
+    /// Any expression.
+    Any,
+    /// `uN`.
+    Unsigned(u32),
+    /// `iN`.
+    Signed(u32),
+    /// `sN`, either signed or unsigned.
+    Integer(u32),
+    /// Name of a `#subruledef`.
+    Subrule(String),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Rule {
+    pub pattern: Vec<PatternPart>,
+    pub body: Expr,
+    pub loc: Location,
+}
+
+impl Rule {
+    /// Build a rule from its pattern tokens and parsed body.
+    ///
+    /// # Errors
+    /// Returns an error if the pattern contains a malformed parameter.
+    pub fn new(tokens: &[Token], body: Expr, loc: Location) -> Result<Self, String> {
+        let mut pattern = Vec::new();
+        let mut i = 0;
+        while i < tokens.len() {
+            let part = match &tokens[i].kind {
+                TokenKind::Punct("{") => {
+                    let Some(name) = tokens.get(i + 1).and_then(Token::ident) else {
+                        return Err("expected a parameter name after `{`".to_string());
+                    };
+                    i += 2;
+                    let mut kind = ParamKind::Any;
+                    if tokens.get(i).is_some_and(|token| token.is_punct(":")) {
+                        let Some(ty) = tokens.get(i + 1).and_then(Token::ident) else {
+                            return Err(format!("expected a type for parameter '{name}'"));
+                        };
+                        kind = parse_kind(ty);
+                        i += 2;
+                    }
+                    if !tokens.get(i).is_some_and(|token| token.is_punct("}")) {
+                        return Err(format!("expected `}}` after parameter '{name}'"));
+                    }
+                    PatternPart::Param {
+                        name: name.to_string(),
+                        kind,
+                    }
+                }
+                TokenKind::Ident(word) => PatternPart::Word(word.to_lowercase()),
+                TokenKind::Punct(punct) => PatternPart::Punct(punct),
+                other => return Err(format!("unexpected `{other}` in rule pattern")),
+            };
+            pattern.push(part);
+            i += 1;
+        }
+        Ok(Self { pattern, body, loc })
+    }
+}
+
+fn parse_kind(ty: &str) -> ParamKind {
+    let bits = |prefix: char| {
+        ty.strip_prefix(prefix)
+            .and_then(|bits| bits.parse::<u32>().ok())
+            .filter(|bits| (1..=64).contains(bits))
+    };
+    if let Some(bits) = bits('u') {
+        ParamKind::Unsigned(bits)
+    } else if let Some(bits) = bits('i') {
+        ParamKind::Signed(bits)
+    } else if let Some(bits) = bits('s') {
+        ParamKind::Integer(bits)
+    } else {
+        ParamKind::Subrule(ty.to_string())
+    }
+}
+
+/// An argument captured by a pattern parameter.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Arg {
+    Expr {
+        expr: Expr,
+        kind: ParamKind,
+    },
+    Subrule {
+        rule: usize,
+        subrule: String,
+        args: Vec<Binding>,
+        tokens: Vec<Token>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Binding {
+    pub name: String,
+    pub arg: Arg,
+}
+
+/// A rule whose pattern matched an instruction line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub rule: usize,
+    pub args: Vec<Binding>,
+}
+
+#[derive(Debug, Default)]
+pub struct Rules {
+    pub rules: Vec<Rule>,
+    pub subrules: HashMap<String, Vec<Rule>>,
+}
+
+impl Rules {
+    /// Every rule matching the whole of `tokens`, in declaration order.
+    #[must_use]
+    pub fn matches(&self, tokens: &[Token]) -> Vec<Match> {
+        let mut out = Vec::new();
+        for (index, rule) in self.rules.iter().enumerate() {
+            for (end, args) in self.match_parts(&rule.pattern, tokens, 0) {
+                if end == tokens.len() {
+                    out.push(Match { rule: index, args });
+                }
+            }
+        }
+        out
+    }
+
+    fn match_parts(
+        &self,
+        parts: &[PatternPart],
+        tokens: &[Token],
+        pos: usize,
+    ) -> Vec<(usize, Vec<Binding>)> {
+        let Some((part, rest)) = parts.split_first() else {
+            return vec![(pos, Vec::new())];
+        };
+        let token = tokens.get(pos).map(|token| &token.kind);
+        match part {
+            PatternPart::Word(word) => match token {
+                Some(TokenKind::Ident(ident)) if ident.eq_ignore_ascii_case(word) => {
+                    self.match_parts(rest, tokens, pos + 1)
+                }
+                _ => Vec::new(),
+            },
+            PatternPart::Punct(punct) => match token {
+                Some(TokenKind::Punct(p)) if p == punct => self.match_parts(rest, tokens, pos + 1),
+                _ => Vec::new(),
+            },
+            PatternPart::Param { name, kind } => {
+                let mut heads = Vec::new();
+                if let ParamKind::Subrule(subrule) = kind {
+                    for (rule, sub) in self.subrules.get(subrule).into_iter().flatten().enumerate()
+                    {
+                        for (end, args) in self.match_parts(&sub.pattern, tokens, pos) {
+                            if end > pos {
+                                heads.push((
+                                    end,
+                                    Arg::Subrule {
+                                        rule,
+                                        subrule: subrule.clone(),
+                                        args,
+                                        tokens: tokens[pos..end].to_vec(),
+                                    },
+                                ));
+                            }
+                        }
+                    }
+                } else {
+                    let mut parser = ExprParser::new(tokens, pos);
+                    if let Ok(expr) = parser.parse_expr() {
+                        heads.push((
+                            parser.pos,
+                            Arg::Expr {
+                                expr,
+                                kind: kind.clone(),
+                            },
+                        ));
+                    }
+                }
+
+                let mut out = Vec::new();
+                for (end, arg) in heads {
+                    for (end, mut args) in self.match_parts(rest, tokens, end) {
+                        args.insert(
+                            0,
+                            Binding {
+                                name: name.clone(),
+                                arg: arg.clone(),
+                            },
+                        );
+                        out.push((end, args));
+                    }
+                }
+                out
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{lexer::lex, parser::parse};
+
+    fn rules(source: &str) -> Rules {
+        let tokens = match lex(source, 0) {
+            Ok(tokens) => tokens,
+            Err((line, message)) => panic!("{line}: {message}"),
+        };
+        match parse(&tokens) {
+            Ok(program) => program.rules,
+            Err((_, message)) => panic!("{message}"),
+        }
+    }
+
+    fn line(source: &str) -> Vec<Token> {
+        let Ok(mut tokens) = lex(source, 0) else {
+            panic!("failed to lex {source}");
+        };
+        tokens.retain(|token| !token.is_newline());
+        tokens
+    }
+
+    const ISA: &str = "
+#subruledef register
+{
+    R0 => 0x0
+    R1 => 0x1
+}
+#ruledef
+{
+    LD { dst: register } [{ hi: register }:{ lo: register }] => 0x5 @ dst @ hi @ lo
+    LD { dst: register } [{ addr: u16 }] => 0x00
+    JR { offset: i8 } => 0x31 @ offset
+}
+";
+
+    #[test]
+    fn test_match_subrules() {
+        let rules = rules(ISA);
+        let matches = rules.matches(&line("ld r0 [R1:R0]"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule, 0);
+        assert_eq!(matches[0].args.len(), 3);
+    }
+
+    #[test]
+    fn test_match_expression_param() {
+        let rules = rules(ISA);
+        let matches = rules.matches(&line("LD R0 [label + 2]"));
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].rule, 1);
+        assert!(rules.matches(&line("JR [label]")).is_empty());
+        assert_eq!(rules.matches(&line("JR -4")).len(), 1);
+    }
+
+    fn parse_error(source: &str) -> String {
+        let tokens = match lex(source, 0) {
+            Ok(tokens) => tokens,
+            Err((line, message)) => panic!("{line}: {message}"),
+        };
+        match parse(&tokens) {
+            Ok(_) => panic!("`{source}` parsed"),
+            Err((_, message)) => message,
+        }
+    }
+
+    #[test]
+    fn test_rule_pattern_errors() {
+        assert!(parse_error("#ruledef { FOO { } => 0x00 }").contains("parameter name"));
+        assert!(parse_error("#ruledef { FOO { x: } => 0x00 }").contains("type for parameter"));
+        let message = parse_error("#ruledef { FOO { x: u8 ] } => 0x00 }");
+        assert!(message.contains("expected `}`"), "{message}");
+        assert!(parse_error("#ruledef { FOO 0x00 }").contains("=>"));
+    }
+
+    #[test]
+    fn test_match_typed_params() {
+        let rules = rules(ISA);
+        assert!(rules.matches(&line("LD R0 R1")).is_empty());
+        assert!(rules.matches(&line("LD R0 [R1:R1]")).len() == 1);
+        assert_eq!(rules.matches(&line("JR 0x7F")).len(), 1);
+        assert!(rules.matches(&line("JR")).is_empty());
+        assert!(rules.matches(&line("JMP 4")).is_empty());
+    }
+}
@@ -0,0 +1,140 @@
+//! Loads source files and expands `#include` / `#once`.
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+};
+
+use crate::{
+    error::{AsmError, AsmResult},
+    lexer::{lex, Token, TokenKind},
+};
+
+/// All files taking part in an assembly, in the order they were first loaded.
+#[derive(Debug, Default)]
+pub struct Sources {
+    files: Vec<PathBuf>,
+    included: HashSet<PathBuf>,
+    overrides: HashMap<PathBuf, String>,
+}
+
+impl Sources {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Provide in-memory contents for `path` instead of reading it from disk.
+    pub fn add_virtual(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        self.overrides
+            .insert(normalize(&path.into()), source.into());
+    }
+
+    /// Path of the file with the given index.
+    #[must_use]
+    pub fn path(&self, file: usize) -> Option<&Path> {
+        self.files.get(file).map(PathBuf::as_path)
+    }
+
+    #[must_use]
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+
+    /// Error located at `line` of `file`.
+    #[must_use]
+    pub fn error_at(&self, file: usize, line: usize, message: impl Into<String>) -> AsmError {
+        let error = AsmError::new(message);
+        match self.path(file) {
+            Some(path) => error.at(path.to_path_buf(), line),
+            None => error,
+        }
+    }
+
+    /// Load `path` and return its tokens with every `#include` expanded in place.
+    ///
+    /// # Errors
+    /// Returns an error if a file cannot be read or tokenized.
+    pub fn load(&mut self, path: &Path) -> AsmResult<Vec<Token>> {
+        let mut tokens = Vec::new();
+        self.expand(&normalize(path), &mut tokens)?;
+        Ok(tokens)
+    }
+
+    fn read(&self, path: &Path) -> std::io::Result<String> {
+        match self.overrides.get(path) {
+            Some(source) => Ok(source.clone()),
+            None => fs::read_to_string(path),
+        }
+    }
+
+    fn expand(&mut self, path: &Path, out: &mut Vec<Token>) -> AsmResult<()> {
+        let source = self
+            .read(path)
+            .map_err(|e| AsmError::new(format!("cannot read {}: {e}", path.display())))?;
+        let file = self.files.len();
+        self.files.push(path.to_path_buf());
+        let tokens =
+            lex(&source, file).map_err(|(line, message)| self.error_at(file, line, message))?;
+
+        let once = tokens
+            .iter()
+            .any(|token| matches!(&token.kind, TokenKind::Directive(name) if name == "once"));
+        if !self.included.insert(path.to_path_buf()) && once {
+            return Ok(());
+        }
+
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            match &token.kind {
+                TokenKind::Directive(name) if name == "once" => {
+                    i += 1;
+                }
+                TokenKind::Directive(name) if name == "include" => {
+                    let Some(TokenKind::Str(include)) = tokens.get(i + 1).map(|t| &t.kind) else {
+                        return Err(self.error_at(
+                            file,
+                            token.loc.line,
+                            "expected a file name after #include",
+                        ));
+                    };
+                    let base = path.parent().unwrap_or(Path::new(""));
+                    let target = normalize(&base.join(include));
+                    if !self.overrides.contains_key(&target) && !target.exists() {
+                        return Err(self.error_at(
+                            file,
+                            token.loc.line,
+                            format!("included file not found: {}", target.display()),
+                        ));
+                    }
+                    self.expand(&target, out)?;
+                    i += 2;
+                }
+                _ => {
+                    out.push(token.clone());
+                    i += 1;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Canonical form of `path` used to recognise files included more than once.
+fn normalize(path: &Path) -> PathBuf {
+    fs::canonicalize(path).unwrap_or_else(|_| {
+        let mut out = PathBuf::new();
+        for component in path.components() {
+            match component {
+                std::path::Component::CurDir => {}
+                std::path::Component::ParentDir if out.file_name().is_some() => {
+                    out.pop();
+                }
+                other => out.push(other),
+            }
+        }
+        out
+    })
+}
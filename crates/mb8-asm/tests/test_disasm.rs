@@ -0,0 +1,46 @@
+use mb8_asm::assemble_str;
+use mb8_isa::{decode::decode, disasm::Disassembler, encode::encode, table::INSTRUCTIONS};
+
+/// A program with every instruction of the ISA table, followed by each
+/// relative jump pointing backwards and forwards.
+fn program() -> Vec<u8> {
+    let mut words = Vec::new();
+    for spec in INSTRUCTIONS {
+        for operands in [0x0000, 0x0A5C] {
+            if let Some(opcode) = decode(spec.opcode | (operands & !spec.mask)) {
+                words.push(encode(&opcode));
+            }
+        }
+    }
+    for opcode in 0x31..=0x35u16 {
+        words.push(opcode << 8 | 0xFC);
+        words.push(opcode << 8 | 0x02);
+    }
+    words.push(0x0000);
+    words.push(0x0000);
+    words.iter().flat_map(|word| word.to_be_bytes()).collect()
+}
+
+/// Disassembled output assembles back to the same binary, with and without
+/// labels.
+#[test]
+fn test_disassembly_reassembles() {
+    let binary = program();
+    for labels in [false, true] {
+        for aliases in [false, true] {
+            let text = Disassembler::new(0)
+                .with_labels(labels)
+                .with_aliases(aliases)
+                .disassemble(&binary, 0);
+            let source = format!("#include \"../../asm/ext.asm\"\n{text}");
+            let assembly = match assemble_str(&source) {
+                Ok(assembly) => assembly,
+                Err(errors) => panic!("labels {labels}, aliases {aliases}: {errors:?}\n{text}"),
+            };
+            assert_eq!(
+                assembly.binary, binary,
+                "labels {labels}, aliases {aliases}"
+            );
+        }
+    }
+}
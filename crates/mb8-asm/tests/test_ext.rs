@@ -0,0 +1,108 @@
+//! The macros of `asm/ext.asm` expand to the instructions they stand for.
+
+use mb8_asm::{assemble_str, error::AsmError};
+
+fn try_assemble(body: &str) -> Result<Vec<u8>, Vec<AsmError>> {
+    assemble_str(&format!("#include \"../../asm/ext.asm\"\n{body}\n"))
+        .map(|assembly| assembly.binary)
+}
+
+fn assemble(body: &str) -> Vec<u8> {
+    match try_assemble(body) {
+        Ok(binary) => binary,
+        Err(errors) => panic!("`{body}`: {errors:?}"),
+    }
+}
+
+fn assert_expands(macro_: &str, expansion: &str) {
+    assert_eq!(assemble(macro_), assemble(expansion), "`{macro_}`");
+}
+
+#[test]
+fn test_absolute_addresses() {
+    assert_expands("LDI R1 R2 0x1234", "LDI R1 0x12\nLDI R2 0x34");
+    assert_expands("CALL [0x1234]", "LDI R6 0x12\nLDI R7 0x34\nCALL [R6:R7]");
+    assert_expands("JMP [0xE500]", "LDI R6 0xE5\nLDI R7 0x00\nJMP [R6:R7]");
+    assert_expands("ST [0xF201] R3", "LDI IH 0xF2\nLDI IL 0x01\nST [IH:IL] R3");
+    assert_expands("LD R3 [0xF201]", "LDI IH 0xF2\nLDI IL 0x01\nLD R3 [IH:IL]");
+    assert_expands("CALL [target]\ntarget:", "CALL [0x0006]");
+}
+
+#[test]
+fn test_relative_jumps() {
+    for (mnemonic, opcode) in [
+        ("JR", 0x31),
+        ("JZR", 0x32),
+        ("JNZR", 0x33),
+        ("JCR", 0x34),
+        ("JNCR", 0x35),
+    ] {
+        assert_eq!(
+            assemble(&format!(
+                "back:\n{mnemonic} [back]\n{mnemonic} [next]\nnext:"
+            )),
+            [opcode, 0xFE, opcode, 0x00],
+            "{mnemonic}"
+        );
+    }
+}
+
+#[test]
+fn test_relative_jump_out_of_range() {
+    assert!(try_assemble("JZR [far]\n#d8 0\n#d128 0\nfar:").is_ok());
+    let Err(errors) = try_assemble("JZR [far]\n#d1024 0\nfar:") else {
+        panic!("expected an error");
+    };
+    assert!(errors[0].message.contains("assertion failed"), "{errors:?}");
+    assert!(try_assemble("back:\n#d1024 0\nJNCR [back]").is_err());
+}
+
+#[test]
+fn test_register_macros() {
+    assert_expands("ZERO R3", "LDI R3 0");
+    assert_expands("INC R2", "PUSH R7\nLDI R7 1\nADD R2 R7\nPOP R7");
+    assert_expands("DEC R2", "PUSH R7\nLDI R7 1\nSUB R2 R7\nPOP R7");
+    assert_expands("NOT R2", "PUSH R7\nLDI R7 0xFF\nXOR R2 R7\nPOP R7");
+    assert_expands("CMPI R2 0x10", "PUSH R7\nLDI R7 0x10\nSUB R7 R2\nPOP R7");
+    assert_expands("SHRI R2 3", "PUSH R7\nLDI R7 3\nSHR R2 R7\nPOP R7");
+    assert_expands("SHLI R2 3", "PUSH R7\nLDI R7 3\nSHL R2 R7\nPOP R7");
+    assert_expands("SWAP R1 R2", "PUSH R1\nMOV R1 R2\nPOP R2");
+}
+
+#[test]
+fn test_macros_with_local_labels() {
+    let inc16 = |hi: &str, lo: &str, n: u8| {
+        format!(
+            "CMPI {lo} 0xFF\nJZR [inc_hi{n}]\nINC {lo}\nJR [end{n}]\n\
+             inc_hi{n}:\nLDI {lo} 0\nINC {hi}\nend{n}:\nNOP"
+        )
+    };
+    assert_expands(
+        "INC16 R1 R2\nINC16 R3 R4",
+        &format!("{}\n{}", inc16("R1", "R2", 0), inc16("R3", "R4", 1)),
+    );
+    assert_expands(
+        "MUL R0 R1 R2",
+        "ZERO R0\nPUSH R2\niter:\nADD R0 R1\nDEC R2\nCMPI R2 0\nJNZR [iter]\nPOP R2",
+    );
+    assert_expands(
+        "LD R3 [R1:R2 - 4]",
+        "LDI R0 4\nSUB R2 R0\nJNCR [no_borrow]\nDEC R1\nno_borrow:\nLD R3 [R1:R2]",
+    );
+}
+
+#[test]
+fn test_macro_operands_out_of_range() {
+    for body in [
+        "LDI R1 R2 0x10000",
+        "CMPI R1 0x100",
+        "SHLI R1 -1",
+        "ST [0x10000] R1",
+    ] {
+        assert!(try_assemble(body).is_err(), "`{body}` assembled");
+    }
+    let Err(errors) = try_assemble("INC R16") else {
+        panic!("expected an error");
+    };
+    assert!(errors[0].message.contains("no rule matches"), "{errors:?}");
+}
@@ -0,0 +1,38 @@
+use mb8_asm::assemble_str;
+use mb8_isa::{decode::decode, encode::encode, table::INSTRUCTIONS};
+
+/// Every instruction of the ISA table assembles, through `asm/isa.asm`, to the
+/// word the table encodes.
+#[test]
+fn test_rules_match_table() {
+    let isa = include_str!("../../../asm/isa.asm");
+    for spec in INSTRUCTIONS {
+        for operands in [0x0000, 0x0A5C, 0x0F80] {
+            let Some(opcode) = decode(spec.opcode | (operands & !spec.mask)) else {
+                panic!("{} does not decode", spec.mnemonic);
+            };
+            for opcode in [opcode, opcode.with_aliases()] {
+                let source = format!("{isa}\n{opcode}\n");
+                let Ok(assembly) = assemble_str(&source) else {
+                    panic!("failed to assemble `{opcode}`");
+                };
+                assert_eq!(
+                    assembly.binary,
+                    encode(&opcode).to_be_bytes(),
+                    "`{opcode}` assembled differently"
+                );
+            }
+        }
+    }
+}
+
+/// `HALT code` keeps assembling; the CPU ignores the code.
+#[test]
+fn test_halt_with_code() {
+    let isa = include_str!("../../../asm/isa.asm");
+    let Ok(assembly) = assemble_str(&format!("{isa}\nHALT 0x42\n")) else {
+        panic!("failed to assemble `HALT 0x42`");
+    };
+    assert_eq!(assembly.binary, [0x01, 0x42]);
+    assert_eq!(decode(0x0142), decode(0x0100));
+}
@@ -0,0 +1,67 @@
+//! Snapshot tests: the assembler's output for the kernel and the user programs
+//! matches the binaries it produced before, in `tests/snapshots`, so changes to
+//! the assembler cannot alter them unnoticed. `make snapshots` rebuilds them
+//! after the sources change, and `make customasm-check` compares them with
+//! customasm's output.
+
+use std::path::{Path, PathBuf};
+
+use mb8_asm::assemble_file;
+
+fn repo() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).join("../..")
+}
+
+/// `kernel/main.asm` and every hand-written `user/*.asm`. Programs compiled
+/// from `.mc8` sources are left out, as their assembly is generated.
+fn sources() -> Vec<PathBuf> {
+    let mut sources = vec![repo().join("kernel/main.asm")];
+    let mut user: Vec<PathBuf> = std::fs::read_dir(repo().join("user"))
+        .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    user.retain(|path| {
+        path.extension().is_some_and(|ext| ext == "asm") && !path.with_extension("mc8").exists()
+    });
+    user.sort();
+    sources.extend(user);
+    sources
+}
+
+/// `kernel/main.asm` is compared with `tests/snapshots/kernel-main.bin`.
+fn reference(source: &Path) -> PathBuf {
+    let dir = source
+        .parent()
+        .and_then(Path::file_name)
+        .unwrap_or_default()
+        .to_string_lossy();
+    let name = source.file_stem().unwrap_or_default().to_string_lossy();
+    Path::new(env!("CARGO_MANIFEST_DIR")).join(format!("tests/snapshots/{dir}-{name}.bin"))
+}
+
+#[test]
+fn test_output_matches_snapshots() {
+    let sources = sources();
+    assert!(sources.len() > 1, "no user programs found");
+    for source in sources {
+        let expected = match std::fs::read(reference(&source)) {
+            Ok(expected) => expected,
+            Err(err) => panic!(
+                "{}: {err}, run `make snapshots`",
+                reference(&source).display()
+            ),
+        };
+        let binary = match assemble_file(&source) {
+            Ok(assembly) => assembly.binary,
+            Err(errors) => panic!("{}: {errors:?}", source.display()),
+        };
+        assert_eq!(binary.len(), expected.len(), "{}", source.display());
+        if let Some(offset) = binary.iter().zip(&expected).position(|(a, b)| a != b) {
+            panic!(
+                "{} differs from its snapshot at byte 0x{offset:04X}: {:02X} instead of {:02X}",
+                source.display(),
+                binary[offset],
+                expected[offset]
+            );
+        }
+    }
+}
@@ -0,0 +1,329 @@
+//! Disassembler for MB8 binaries.
+//! Renders instructions in the syntax accepted by `asm/isa.asm` and `asm/ext.asm`,
+//! so the output can be fed back into the assembler.
+
+use std::{collections::BTreeSet, fmt::Write};
+
+use crate::{decode::decode, opcodes::Opcode, registers::Register, symbols::SymbolMap};
+
+/// A single decoded instruction word.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Instruction {
+    /// Address of the first byte of the instruction.
+    pub address: u16,
+    /// Raw 16-bit instruction word.
+    pub word: u16,
+    /// Decoded opcode, `None` if the word is not a valid instruction.
+    pub opcode: Option<Opcode>,
+}
+
+impl Instruction {
+    /// Absolute target of a relative jump instruction.
+    #[must_use]
+    pub fn relative_target(&self) -> Option<u16> {
+        let offset = self.opcode?.relative_offset()?;
+        Some(
+            self.address
+                .wrapping_add(2)
+                .wrapping_add_signed(offset as i16),
+        )
+    }
+}
+
+/// Walks a binary image and renders it as assembly source.
+#[derive(Debug, Clone)]
+pub struct Disassembler {
+    base: u16,
+    aliases: bool,
+    labels: bool,
+    symbols: SymbolMap,
+}
+
+impl Disassembler {
+    /// Create a disassembler for an image loaded at `base`.
+    #[must_use]
+    pub fn new(base: u16) -> Self {
+        Self {
+            base,
+            aliases: false,
+            labels: false,
+            symbols: SymbolMap::new(),
+        }
+    }
+
+    /// Render registers with their aliases (`A`, `IH`, `SPL`, ...).
+    #[must_use]
+    pub fn with_aliases(mut self, aliases: bool) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Emit `L_XXXX` labels for jump and call targets inside the image.
+    #[must_use]
+    pub fn with_labels(mut self, labels: bool) -> Self {
+        self.labels = labels;
+        self
+    }
+
+    /// Name addresses after the labels of a symbol file instead of `L_XXXX`.
+    #[must_use]
+    pub fn with_symbols(mut self, symbols: SymbolMap) -> Self {
+        self.symbols = symbols;
+        self
+    }
+
+    /// Decode every instruction word from `start` to the end of the image.
+    #[must_use]
+    pub fn decode(&self, binary: &[u8], start: u16) -> Vec<Instruction> {
+        let offset = start.wrapping_sub(self.base) as usize;
+        let Some(code) = binary.get(offset..) else {
+            return Vec::new();
+        };
+
+        code.chunks_exact(2)
+            .enumerate()
+            .map(|(i, bytes)| {
+                let word = u16::from_be_bytes([bytes[0], bytes[1]]);
+                Instruction {
+                    address: start.wrapping_add((i * 2) as u16),
+                    word,
+                    opcode: decode(word),
+                }
+            })
+            .collect()
+    }
+
+    /// Render the image starting at `start` as assembly source.
+    #[must_use]
+    pub fn disassemble(&self, binary: &[u8], start: u16) -> String {
+        let instructions = self.decode(binary, start);
+        let absolute = absolute_targets(&instructions);
+        let mut targets = if self.labels {
+            self.targets(binary, &instructions, &absolute)
+        } else {
+            BTreeSet::new()
+        };
+        targets.extend(self.symbols.labels.keys());
+
+        let mut out = String::new();
+        for (index, instruction) in instructions.iter().enumerate() {
+            if targets.contains(&instruction.address) {
+                let _ = writeln!(out, "{}:", self.label(instruction.address));
+            }
+            let text = self.render(*instruction, index, &absolute, &targets);
+            let _ = writeln!(
+                out,
+                "    {text:<28}; {:04X}: {:04X}",
+                instruction.address, instruction.word
+            );
+        }
+
+        let offset = start.wrapping_sub(self.base) as usize;
+        if binary.len() > offset && (binary.len() - offset) % 2 == 1 {
+            if let Some(byte) = binary.last() {
+                let address = start.wrapping_add((binary.len() - offset - 1) as u16);
+                let text = format!("#d8 0x{byte:02X}");
+                let _ = writeln!(out, "    {text:<28}; {address:04X}: {byte:02X}");
+            }
+        }
+
+        out
+    }
+
+    fn targets(
+        &self,
+        binary: &[u8],
+        instructions: &[Instruction],
+        absolute: &[AbsoluteTarget],
+    ) -> BTreeSet<u16> {
+        let end = self.base as usize + binary.len();
+        instructions
+            .iter()
+            .filter_map(Instruction::relative_target)
+            .chain(absolute.iter().map(|target| target.address))
+            .filter(|&address| address >= self.base && (address as usize) < end)
+            .collect()
+    }
+
+    fn render(
+        &self,
+        instruction: Instruction,
+        index: usize,
+        absolute: &[AbsoluteTarget],
+        targets: &BTreeSet<u16>,
+    ) -> String {
+        let Some(opcode) = instruction.opcode else {
+            return format!("#d16 0x{:04X}", instruction.word);
+        };
+        let opcode = if self.aliases {
+            opcode.with_aliases()
+        } else {
+            opcode
+        };
+
+        if let Some(target) = instruction.relative_target() {
+            return format!("{} [{}]", opcode.mnemonic(), self.operand(target, targets));
+        }
+
+        if let Opcode::Ldi { dst, .. } = opcode {
+            for target in absolute {
+                if !targets.contains(&target.address) {
+                    continue;
+                }
+                if target.hi == index {
+                    return format!("LDI {dst} {} >> 8", self.label(target.address));
+                }
+                if target.lo == index {
+                    return format!("LDI {dst} {} & 0xFF", self.label(target.address));
+                }
+            }
+        }
+
+        opcode.to_string()
+    }
+
+    fn label(&self, address: u16) -> String {
+        match self.symbols.labels.get(&address) {
+            Some(name) => name.clone(),
+            None => format!("L_{address:04X}"),
+        }
+    }
+
+    fn operand(&self, address: u16, targets: &BTreeSet<u16>) -> String {
+        if targets.contains(&address) {
+            return self.label(address);
+        }
+        match self.symbols.lookup(address) {
+            Some((name, offset)) => format!("{name} + 0x{offset:X}"),
+            None => format!("0x{address:04X}"),
+        }
+    }
+}
+
+/// An absolute `JMP`/`CALL` target built from a pair of `LDI` instructions.
+#[derive(Debug, Clone, Copy)]
+struct AbsoluteTarget {
+    address: u16,
+    hi: usize,
+    lo: usize,
+}
+
+/// Find `LDI hi; LDI lo; JMP/CALL [hi:lo]` sequences, as emitted by the `JMP`/`CALL` macros.
+fn absolute_targets(instructions: &[Instruction]) -> Vec<AbsoluteTarget> {
+    let mut targets = Vec::new();
+    for (index, instruction) in instructions.iter().enumerate().skip(2) {
+        let Some(Opcode::Jmp { hi, lo } | Opcode::Call { hi, lo }) = instruction.opcode else {
+            continue;
+        };
+        let (Some(hi_index), Some(lo_index)) = (
+            loaded_by(instructions, index, hi),
+            loaded_by(instructions, index, lo),
+        ) else {
+            continue;
+        };
+        let (
+            Some(Opcode::Ldi {
+                value: hi_value, ..
+            }),
+            Some(Opcode::Ldi {
+                value: lo_value, ..
+            }),
+        ) = (instructions[hi_index].opcode, instructions[lo_index].opcode)
+        else {
+            continue;
+        };
+        targets.push(AbsoluteTarget {
+            address: u16::from_be_bytes([hi_value, lo_value]),
+            hi: hi_index,
+            lo: lo_index,
+        });
+    }
+    targets
+}
+
+/// Index of the `LDI` among the two preceding instructions that loads `register`.
+fn loaded_by(instructions: &[Instruction], index: usize, register: Register) -> Option<usize> {
+    (index - 2..index).rev().find(
+        |&i| matches!(instructions[i].opcode, Some(Opcode::Ldi { dst, .. }) if dst == register),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_walks_from_start() {
+        let binary = [0x00, 0x00, 0x01, 0x00, 0x41, 0x00];
+        let instructions = Disassembler::new(0xE000).decode(&binary, 0xE002);
+        assert_eq!(instructions.len(), 2);
+        assert_eq!(instructions[0].address, 0xE002);
+        assert_eq!(instructions[0].opcode, Some(Opcode::Halt));
+        assert_eq!(instructions[1].opcode, Some(Opcode::Ret));
+    }
+
+    #[test]
+    fn test_relative_target() {
+        let instruction = Instruction {
+            address: 0xE010,
+            word: 0x31FC,
+            opcode: Some(Opcode::Jr { offset: -4 }),
+        };
+        assert_eq!(instruction.relative_target(), Some(0xE00E));
+    }
+
+    #[test]
+    fn test_disassemble_relative_jump() {
+        // NOP; JNZR -4
+        let binary = [0x00, 0x00, 0x33, 0xFC];
+        let out = Disassembler::new(0x1000).disassemble(&binary, 0x1000);
+        assert!(out.contains("NOP"));
+        assert!(out.contains("JNZR [0x1000]"));
+    }
+
+    #[test]
+    fn test_disassemble_labels() {
+        // LDI R6 0x10; LDI R7 0x06; CALL [R6:R7]; RET
+        let binary = [0x26, 0x10, 0x27, 0x06, 0x40, 0x67, 0x41, 0x00];
+        let out = Disassembler::new(0x1000)
+            .with_labels(true)
+            .disassemble(&binary, 0x1000);
+        assert!(out.contains("LDI R6 L_1006 >> 8"));
+        assert!(out.contains("LDI R7 L_1006 & 0xFF"));
+        assert!(out.contains("L_1006:\n    RET"));
+    }
+
+    #[test]
+    fn test_disassemble_symbols() {
+        // LDI R6 0x10; LDI R7 0x06; CALL [R6:R7]; RET; JR [0x1006]
+        let binary = [0x26, 0x10, 0x27, 0x06, 0x40, 0x67, 0x41, 0x00, 0x31, 0xFC];
+        let mut symbols = SymbolMap::new();
+        symbols.add_label(0x1000, "main");
+        symbols.add_label(0x1006, "done");
+        let out = Disassembler::new(0x1000)
+            .with_symbols(symbols)
+            .disassemble(&binary, 0x1000);
+        assert!(out.starts_with("main:\n"));
+        assert!(out.contains("LDI R6 done >> 8"));
+        assert!(out.contains("done:\n    RET"));
+        assert!(out.contains("JR [done]"));
+    }
+
+    #[test]
+    fn test_disassemble_aliases() {
+        // PUSH R15
+        let binary = [0x42, 0xF0];
+        let out = Disassembler::new(0)
+            .with_aliases(true)
+            .disassemble(&binary, 0);
+        assert!(out.contains("PUSH F"));
+    }
+
+    #[test]
+    fn test_disassemble_invalid_word() {
+        let binary = [0xFF, 0xFF, 0x12];
+        let out = Disassembler::new(0).disassemble(&binary, 0);
+        assert!(out.contains("#d16 0xFFFF"));
+        assert!(out.contains("#d8 0x12"));
+    }
+}
@@ -1,4 +1,4 @@
-use crate::{opcodes::Opcode, registers::Register};
+use crate::{opcodes::Opcode, registers::Register, table};
 
 /// Encode a Register into a 4-bit value.
 #[must_use]
@@ -34,98 +34,8 @@ pub fn encode_program(program: &[Opcode]) -> Vec<u8> {
 
 /// Encode an Opcode into a 16-bit instruction.
 #[must_use]
-#[allow(clippy::too_many_lines)]
 pub fn encode(opcode: &Opcode) -> u16 {
-    match opcode {
-        Opcode::Nop => 0x0000,
-        Opcode::Halt => 0x0100,
-        Opcode::Sys => 0x0200,
-        Opcode::Mov { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1000 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Add { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1100 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Sub { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1200 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::And { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1300 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Or { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1400 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Xor { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1500 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Shr { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1600 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Shl { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1700 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Cmp { dst, src } => {
-            let dst = encode_register(*dst);
-            let src = encode_register(*src);
-            0x1800 | (dst as u16) << 4 | src as u16
-        }
-        Opcode::Ldi { dst, value } => {
-            let dst = encode_register(*dst);
-            0x2000 | (dst as u16) << 4 | *value as u16
-        }
-        Opcode::Jmp { hi, lo } => {
-            let hi = encode_register(*hi);
-            let lo = encode_register(*lo);
-            0x3000 | (hi as u16) << 4 | lo as u16
-        }
-        Opcode::Jr { offset } => 0x3100 | (*offset as u8) as u16,
-        Opcode::Jzr { offset } => 0x3200 | (*offset as u8) as u16,
-        Opcode::Jnzr { offset } => 0x3300 | (*offset as u8) as u16,
-        Opcode::Jcr { offset } => 0x3400 | (*offset as u8) as u16,
-        Opcode::Jncr { offset } => 0x3500 | (*offset as u8) as u16,
-        Opcode::Call { hi, lo } => {
-            let hi = encode_register(*hi);
-            let lo = encode_register(*lo);
-            0x4000 | (hi as u16) << 4 | lo as u16
-        }
-        Opcode::Ret => 0x4100,
-        Opcode::Push { src } => {
-            let src = encode_register(*src);
-            0x4200 | (src as u16) << 4
-        }
-        Opcode::Pop { dst } => {
-            let dst = encode_register(*dst);
-            0x4300 | (dst as u16) << 4
-        }
-        Opcode::Ld { dst, hi, lo } => {
-            let dst = encode_register(*dst);
-            let hi = encode_register(*hi);
-            let lo = encode_register(*lo);
-            0x5000 | (dst as u16) << 8 | (hi as u16) << 4 | lo as u16
-        }
-        Opcode::St { src, hi, lo } => {
-            let src = encode_register(*src);
-            let hi = encode_register(*hi);
-            let lo = encode_register(*lo);
-            0x6000 | (src as u16) << 8 | (hi as u16) << 4 | lo as u16
-        }
-    }
+    table::encode_word(opcode)
 }
 
 #[cfg(test)]
@@ -0,0 +1,253 @@
+//! MB8 executable files, as loaded by the kernel's `SYS_EXEC`.
+//!
+//! An executable is a 16-byte header followed by the code image. All 16-bit
+//! fields are big-endian:
+//!
+//! ```text
+//! 0  "MB8X"      magic
+//! 4  version:u8  EXEC_VERSION
+//! 5  flags:u8    reserved, must be 0
+//! 6  load:u16    address the code is copied to
+//! 8  entry:u16   address execution starts at
+//! 10 code:u16    number of code bytes following the header
+//! 12 bss:u16     number of zeroed bytes placed right after the code
+//! 14 stack:u16   stack space the program needs below `STACK_TOP`
+//! ```
+
+use crate::{RAM_SIZE, STACK_BOTTOM, STACK_TOP};
+
+pub const EXEC_MAGIC: &[u8; 4] = b"MB8X";
+pub const EXEC_VERSION: u8 = 1;
+/// Size of the header in bytes.
+pub const EXEC_HEADER_SIZE: usize = 16;
+/// Lowest load address; memory below it belongs to the kernel.
+pub const USER_BASE: u16 = 0x1000;
+/// Stack size requested by executables built from raw images.
+pub const DEFAULT_STACK: u16 = (STACK_TOP - STACK_BOTTOM + 1) as u16;
+
+/// Fields of an executable header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ExecHeader {
+    pub load: u16,
+    pub entry: u16,
+    pub code_size: u16,
+    pub bss_size: u16,
+    pub stack_size: u16,
+}
+
+impl ExecHeader {
+    /// Serialize the header.
+    #[must_use]
+    pub fn to_bytes(&self) -> [u8; EXEC_HEADER_SIZE] {
+        let mut bytes = [0; EXEC_HEADER_SIZE];
+        bytes[..4].copy_from_slice(EXEC_MAGIC);
+        bytes[4] = EXEC_VERSION;
+        let fields = [
+            self.load,
+            self.entry,
+            self.code_size,
+            self.bss_size,
+            self.stack_size,
+        ];
+        for (i, field) in fields.iter().enumerate() {
+            bytes[6 + i * 2..8 + i * 2].copy_from_slice(&field.to_be_bytes());
+        }
+        bytes
+    }
+
+    /// Parse and validate the header at the start of `data`.
+    ///
+    /// # Errors
+    /// Returns an error if `data` does not start with a valid header.
+    pub fn parse(data: &[u8]) -> Result<Self, String> {
+        let Some(bytes) = data.get(..EXEC_HEADER_SIZE) else {
+            return Err("file is shorter than an executable header".to_string());
+        };
+        if &bytes[..4] != EXEC_MAGIC {
+            return Err("not an MB8 executable".to_string());
+        }
+        if bytes[4] != EXEC_VERSION {
+            return Err(format!("unsupported executable version {}", bytes[4]));
+        }
+        if bytes[5] != 0 {
+            return Err(format!("unknown executable flags 0x{:02X}", bytes[5]));
+        }
+        let field = |i: usize| u16::from_be_bytes([bytes[6 + i * 2], bytes[7 + i * 2]]);
+        let header = Self {
+            load: field(0),
+            entry: field(1),
+            code_size: field(2),
+            bss_size: field(3),
+            stack_size: field(4),
+        };
+        header.validate()?;
+        Ok(header)
+    }
+
+    /// Check that the program fits in user memory and starts inside its code.
+    ///
+    /// # Errors
+    /// Returns a description of the first violated constraint.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.load < USER_BASE {
+            return Err(format!(
+                "load address 0x{:04X} is below user memory at 0x{USER_BASE:04X}",
+                self.load
+            ));
+        }
+        let end = usize::from(self.load)
+            + usize::from(self.code_size)
+            + usize::from(self.bss_size)
+            + usize::from(self.stack_size);
+        if end > RAM_SIZE {
+            return Err(format!(
+                "program needs 0x{:X} bytes from 0x{:04X} including stack, which does not fit in RAM",
+                end - usize::from(self.load),
+                self.load
+            ));
+        }
+        let code = self.load..self.load + self.code_size;
+        if !code.contains(&self.entry) {
+            return Err(format!(
+                "entry point 0x{:04X} is outside the code at 0x{:04X}..0x{:04X}",
+                self.entry, code.start, code.end
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An executable: header and code image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Executable {
+    pub header: ExecHeader,
+    pub code: Vec<u8>,
+}
+
+impl Executable {
+    /// Build an executable from a raw image loaded at `load`. Trailing zero bytes
+    /// (such as `#fill` padding) become BSS instead of being stored.
+    ///
+    /// # Errors
+    /// Returns an error if the program does not fit in user memory or `entry`
+    /// is outside the code.
+    pub fn from_image(image: &[u8], load: u16, entry: u16) -> Result<Self, String> {
+        let len = image
+            .iter()
+            .rposition(|&byte| byte != 0)
+            .map_or(0, |last| (last + 2) & !1);
+        // Keep at least the entry instruction so the entry point stays inside the code.
+        let len = len
+            .max(usize::from(entry.saturating_sub(load)) + 2)
+            .min(image.len());
+        let (Ok(code_size), Ok(bss_size)) = (u16::try_from(len), u16::try_from(image.len() - len))
+        else {
+            return Err(format!("image of {} bytes is too large", image.len()));
+        };
+        let header = ExecHeader {
+            load,
+            entry,
+            code_size,
+            bss_size,
+            stack_size: DEFAULT_STACK,
+        };
+        header.validate()?;
+        Ok(Self {
+            header,
+            code: image[..len].to_vec(),
+        })
+    }
+
+    /// Serialize the executable.
+    #[must_use]
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = self.header.to_bytes().to_vec();
+        bytes.extend(&self.code);
+        bytes
+    }
+
+    /// Parse and validate an executable file.
+    ///
+    /// # Errors
+    /// Returns an error if the header is invalid or the file length does not match it.
+    pub fn from_bytes(data: &[u8]) -> Result<Self, String> {
+        let header = ExecHeader::parse(data)?;
+        let code = &data[EXEC_HEADER_SIZE..];
+        if code.len() != usize::from(header.code_size) {
+            return Err(format!(
+                "header declares {} code bytes but the file has {}",
+                header.code_size,
+                code.len()
+            ));
+        }
+        Ok(Self {
+            header,
+            code: code.to_vec(),
+        })
+    }
+}
+
+/// Whether `data` starts with the executable magic.
+#[must_use]
+pub fn is_executable(data: &[u8]) -> bool {
+    data.starts_with(EXEC_MAGIC)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_executable_roundtrip() {
+        let mut image = vec![0x21, 0x42, 0x01, 0x00];
+        image.resize(0x1000, 0);
+        let Ok(exec) = Executable::from_image(&image, 0x1000, 0x1000) else {
+            panic!("failed to build executable");
+        };
+        assert_eq!(exec.header.code_size, 4);
+        assert_eq!(exec.header.bss_size, 0x0FFC);
+        assert_eq!(exec.header.stack_size, 0x100);
+
+        let bytes = exec.to_bytes();
+        assert_eq!(&bytes[..6], b"MB8X\x01\x00");
+        assert_eq!(&bytes[6..10], &[0x10, 0x00, 0x10, 0x00]);
+        assert_eq!(Executable::from_bytes(&bytes), Ok(exec));
+    }
+
+    #[test]
+    fn test_executable_validation() {
+        let header = ExecHeader {
+            load: 0x1000,
+            entry: 0x1000,
+            code_size: 0x10,
+            bss_size: 0,
+            stack_size: 0x100,
+        };
+        assert_eq!(header.validate(), Ok(()));
+        for invalid in [
+            ExecHeader {
+                load: 0x0F00,
+                entry: 0x0F00,
+                ..header
+            },
+            ExecHeader {
+                entry: 0x1010,
+                ..header
+            },
+            ExecHeader {
+                bss_size: 0xAF00,
+                ..header
+            },
+        ] {
+            assert!(invalid.validate().is_err(), "{invalid:?}");
+            assert!(ExecHeader::parse(&invalid.to_bytes()).is_err());
+        }
+
+        let mut bytes = header.to_bytes().to_vec();
+        assert!(Executable::from_bytes(&bytes).is_err(), "missing code");
+        bytes.extend([0; 0x10]);
+        assert!(Executable::from_bytes(&bytes).is_ok());
+        bytes[4] = 2;
+        assert!(Executable::from_bytes(&bytes).is_err(), "version");
+    }
+}
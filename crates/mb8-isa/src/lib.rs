@@ -1,7 +1,11 @@
 pub mod decode;
+pub mod disasm;
 pub mod encode;
+pub mod exec;
 pub mod opcodes;
 pub mod registers;
+pub mod symbols;
+pub mod table;
 
 /// Represents the size of the RAM in bytes.
 pub const RAM_SIZE: usize = 0xC000;
@@ -13,3 +17,5 @@ pub const STACK_BOTTOM: usize = 0xBF00;
 pub const ROM_SIZE: usize = 0x1000;
 /// Represents the general purpose registers count of the CPU.
 pub const REGISTERS_COUNT: usize = 16;
+/// Represents the nominal clock rate of the CPU in cycles per second.
+pub const CPU_FREQUENCY: u64 = 1_000_000;
@@ -1,10 +1,12 @@
 //! Opcodes for the MB8 ISA.
 //! This module defines the opcodes used by the MB8 ISA.
 
+use std::fmt::Display;
+
 use crate::registers::Register;
 
 /// Full list of MB8 opcodes used in VM.
-#[derive(Debug, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Opcode {
     /* Control group */
     /// No operation. Instruction does nothing.
@@ -126,3 +128,178 @@ pub enum Opcode {
         lo: Register,
     },
 }
+
+impl Opcode {
+    /// Returns the same opcode with every register operand replaced by its alias.
+    #[must_use]
+    pub fn with_aliases(self) -> Opcode {
+        match self {
+            Opcode::Mov { dst, src } => Opcode::Mov {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Add { dst, src } => Opcode::Add {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Sub { dst, src } => Opcode::Sub {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::And { dst, src } => Opcode::And {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Or { dst, src } => Opcode::Or {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Xor { dst, src } => Opcode::Xor {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Shr { dst, src } => Opcode::Shr {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Shl { dst, src } => Opcode::Shl {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Cmp { dst, src } => Opcode::Cmp {
+                dst: dst.alias(),
+                src: src.alias(),
+            },
+            Opcode::Ldi { dst, value } => Opcode::Ldi {
+                dst: dst.alias(),
+                value,
+            },
+            Opcode::Jmp { hi, lo } => Opcode::Jmp {
+                hi: hi.alias(),
+                lo: lo.alias(),
+            },
+            Opcode::Call { hi, lo } => Opcode::Call {
+                hi: hi.alias(),
+                lo: lo.alias(),
+            },
+            Opcode::Push { src } => Opcode::Push { src: src.alias() },
+            Opcode::Pop { dst } => Opcode::Pop { dst: dst.alias() },
+            Opcode::Ld { dst, hi, lo } => Opcode::Ld {
+                dst: dst.alias(),
+                hi: hi.alias(),
+                lo: lo.alias(),
+            },
+            Opcode::St { src, hi, lo } => Opcode::St {
+                src: src.alias(),
+                hi: hi.alias(),
+                lo: lo.alias(),
+            },
+            opcode => opcode,
+        }
+    }
+
+    /// Returns the signed offset of a relative jump instruction.
+    #[must_use]
+    pub fn relative_offset(&self) -> Option<i8> {
+        match self {
+            Opcode::Jr { offset }
+            | Opcode::Jzr { offset }
+            | Opcode::Jnzr { offset }
+            | Opcode::Jcr { offset }
+            | Opcode::Jncr { offset } => Some(*offset),
+            _ => None,
+        }
+    }
+
+    /// Returns the mnemonic of the instruction as accepted by the assembler.
+    #[must_use]
+    pub fn mnemonic(&self) -> &'static str {
+        crate::table::spec(self).mnemonic
+    }
+}
+
+/// Formats the opcode in `asm/isa.asm` syntax.
+impl Display for Opcode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mnemonic = self.mnemonic();
+        match self {
+            Opcode::Nop | Opcode::Halt | Opcode::Sys | Opcode::Ret => f.write_str(mnemonic),
+            Opcode::Mov { dst, src }
+            | Opcode::Add { dst, src }
+            | Opcode::Sub { dst, src }
+            | Opcode::And { dst, src }
+            | Opcode::Or { dst, src }
+            | Opcode::Xor { dst, src }
+            | Opcode::Shr { dst, src }
+            | Opcode::Shl { dst, src }
+            | Opcode::Cmp { dst, src } => write!(f, "{mnemonic} {dst} {src}"),
+            Opcode::Ldi { dst, value } => write!(f, "{mnemonic} {dst} 0x{value:02X}"),
+            Opcode::Jmp { hi, lo } | Opcode::Call { hi, lo } => {
+                write!(f, "{mnemonic} [{hi}:{lo}]")
+            }
+            Opcode::Jr { offset }
+            | Opcode::Jzr { offset }
+            | Opcode::Jnzr { offset }
+            | Opcode::Jcr { offset }
+            | Opcode::Jncr { offset } => write!(f, "{mnemonic} {offset}"),
+            Opcode::Push { src } => write!(f, "{mnemonic} {src}"),
+            Opcode::Pop { dst } => write!(f, "{mnemonic} {dst}"),
+            Opcode::Ld { dst, hi, lo } => write!(f, "{mnemonic} {dst} [{hi}:{lo}]"),
+            Opcode::St { src, hi, lo } => write!(f, "{mnemonic} [{hi}:{lo}] {src}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_display_opcode() {
+        assert_eq!(Opcode::Nop.to_string(), "NOP");
+        assert_eq!(
+            Opcode::Mov {
+                dst: Register::R0,
+                src: Register::R1
+            }
+            .to_string(),
+            "MOV R0 R1"
+        );
+        assert_eq!(
+            Opcode::Ldi {
+                dst: Register::R6,
+                value: 0xE5
+            }
+            .to_string(),
+            "LDI R6 0xE5"
+        );
+        assert_eq!(Opcode::Jnzr { offset: -4 }.to_string(), "JNZR -4");
+        assert_eq!(
+            Opcode::Call {
+                hi: Register::R6,
+                lo: Register::R7
+            }
+            .to_string(),
+            "CALL [R6:R7]"
+        );
+        assert_eq!(
+            Opcode::St {
+                src: Register::R5,
+                hi: Register::R6,
+                lo: Register::R7
+            }
+            .to_string(),
+            "ST [R6:R7] R5"
+        );
+    }
+
+    #[test]
+    fn test_display_aliases() {
+        let opcode = Opcode::Ld {
+            dst: Register::R0,
+            hi: Register::R9,
+            lo: Register::R10,
+        };
+        assert_eq!(opcode.with_aliases().to_string(), "LD A [IH:IL]");
+    }
+}
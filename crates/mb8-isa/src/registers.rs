@@ -1,5 +1,7 @@
 //! Register definitions for the MB8 VM.
 
+use std::fmt::Display;
+
 pub mod flags {
     /// Zero flag for the flag register
     pub const Z_FLAG: u8 = 0b0000_0001;
@@ -66,3 +68,55 @@ pub enum Register {
     /// Flag register
     F,
 }
+
+impl Register {
+    /// Returns the alias for a general-purpose register, if it has one.
+    ///
+    /// Aliases themselves are returned unchanged.
+    #[must_use]
+    pub fn alias(self) -> Register {
+        match self {
+            Register::R0 => Register::A,
+            Register::R9 => Register::IH,
+            Register::R10 => Register::IL,
+            Register::R11 => Register::FPH,
+            Register::R12 => Register::FPL,
+            Register::R13 => Register::SPH,
+            Register::R14 => Register::SPL,
+            Register::R15 => Register::F,
+            register => register,
+        }
+    }
+}
+
+impl Display for Register {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Register::R0 => "R0",
+            Register::R1 => "R1",
+            Register::R2 => "R2",
+            Register::R3 => "R3",
+            Register::R4 => "R4",
+            Register::R5 => "R5",
+            Register::R6 => "R6",
+            Register::R7 => "R7",
+            Register::R8 => "R8",
+            Register::R9 => "R9",
+            Register::R10 => "R10",
+            Register::R11 => "R11",
+            Register::R12 => "R12",
+            Register::R13 => "R13",
+            Register::R14 => "R14",
+            Register::R15 => "R15",
+            Register::A => "A",
+            Register::IH => "IH",
+            Register::IL => "IL",
+            Register::FPH => "FPH",
+            Register::FPL => "FPL",
+            Register::SPH => "SPH",
+            Register::SPL => "SPL",
+            Register::F => "F",
+        };
+        f.write_str(name)
+    }
+}
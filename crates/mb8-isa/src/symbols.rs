@@ -0,0 +1,235 @@
+//! Symbol and line-table files shared by the assembler, linker, disassembler and debugger.
+//!
+//! The native format is line based, addresses are hex and `;` starts a comment:
+//!
+//! ```text
+//! label E6A4 sys_fs_find
+//! line E6A4 kernel/fs.asm:120
+//! ```
+//!
+//! The `name = 0xE6A4` lines written by customasm's symbol output are accepted as well.
+
+use std::{collections::BTreeMap, fmt::Display};
+
+/// Position in a source file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SourceLine {
+    pub file: String,
+    /// One-based line number.
+    pub line: usize,
+}
+
+impl Display for SourceLine {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.file, self.line)
+    }
+}
+
+/// Address → label and address → source line tables.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct SymbolMap {
+    /// First label defined at each address.
+    pub labels: BTreeMap<u16, String>,
+    /// Source line of the instruction or data starting at each address.
+    pub lines: BTreeMap<u16, SourceLine>,
+}
+
+impl SymbolMap {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record a label. Earlier labels at the same address win.
+    pub fn add_label(&mut self, addr: u16, name: impl Into<String>) {
+        self.labels.entry(addr).or_insert_with(|| name.into());
+    }
+
+    /// Record the source line of the code at `addr`.
+    pub fn add_line(&mut self, addr: u16, file: impl Into<String>, line: usize) {
+        self.lines.insert(
+            addr,
+            SourceLine {
+                file: file.into(),
+                line,
+            },
+        );
+    }
+
+    /// Add every entry of `other` that does not clash with an existing one.
+    pub fn merge(&mut self, other: SymbolMap) {
+        for (addr, name) in other.labels {
+            self.add_label(addr, name);
+        }
+        for (addr, line) in other.lines {
+            self.lines.entry(addr).or_insert(line);
+        }
+    }
+
+    /// Parse a symbol file in the native or customasm format.
+    ///
+    /// # Errors
+    /// Returns the line number and message of the first invalid line.
+    pub fn parse(text: &str) -> Result<Self, (usize, String)> {
+        let mut map = Self::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_no = index + 1;
+            let line = line.split(';').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            if let Some((name, value)) = line.split_once('=') {
+                let addr = parse_addr(value.trim()).map_err(|e| (line_no, e))?;
+                map.add_label(addr, name.trim());
+                continue;
+            }
+            let mut words = line.split_whitespace();
+            match (words.next(), words.next(), words.next(), words.next()) {
+                (Some("label"), Some(addr), Some(name), None) => {
+                    let addr = parse_hex(addr).map_err(|e| (line_no, e))?;
+                    map.add_label(addr, name);
+                }
+                (Some("line"), Some(addr), Some(location), None) => {
+                    let addr = parse_hex(addr).map_err(|e| (line_no, e))?;
+                    let parsed = location
+                        .rsplit_once(':')
+                        .and_then(|(file, line)| Some((file, line.parse().ok()?)));
+                    let Some((file, line)) = parsed else {
+                        return Err((line_no, format!("expected file:line, found '{location}'")));
+                    };
+                    map.add_line(addr, file, line);
+                }
+                _ => return Err((line_no, format!("invalid symbol line `{line}`"))),
+            }
+        }
+        Ok(map)
+    }
+
+    /// Nearest label at or below `addr` and the offset from it.
+    #[must_use]
+    pub fn lookup(&self, addr: u16) -> Option<(&str, u16)> {
+        self.labels
+            .range(..=addr)
+            .next_back()
+            .map(|(base, name)| (name.as_str(), addr - base))
+    }
+
+    /// Address of the label `name`.
+    #[must_use]
+    pub fn address(&self, name: &str) -> Option<u16> {
+        self.labels
+            .iter()
+            .find(|(_, label)| label.as_str() == name)
+            .map(|(addr, _)| *addr)
+    }
+
+    /// Source line of the code containing `addr`.
+    #[must_use]
+    pub fn source(&self, addr: u16) -> Option<&SourceLine> {
+        self.lines.range(..=addr).next_back().map(|(_, line)| line)
+    }
+
+    /// `addr` as `label+0xNN`, or as plain hex when no label precedes it.
+    #[must_use]
+    pub fn describe(&self, addr: u16) -> String {
+        match self.lookup(addr) {
+            Some((name, 0)) => name.to_string(),
+            Some((name, offset)) => format!("{name}+0x{offset:X}"),
+            None => format!("{addr:04X}"),
+        }
+    }
+
+    /// Resolve a debugger argument: a label name, optionally with `+offset`, or a hex address.
+    #[must_use]
+    pub fn resolve(&self, text: &str) -> Option<u16> {
+        let (name, offset) = match text.split_once('+') {
+            Some((name, offset)) => (name, parse_addr(offset).ok()?),
+            None => (text, 0),
+        };
+        match self.address(name) {
+            Some(addr) => Some(addr.wrapping_add(offset)),
+            None if offset == 0 => parse_hex(name).ok(),
+            None => None,
+        }
+    }
+}
+
+impl Display for SymbolMap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for (addr, name) in &self.labels {
+            writeln!(f, "label {addr:04X} {name}")?;
+        }
+        for (addr, line) in &self.lines {
+            writeln!(f, "line {addr:04X} {line}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Parse a hex address with an optional `0x` prefix.
+fn parse_hex(text: &str) -> Result<u16, String> {
+    let digits = text
+        .strip_prefix("0x")
+        .or_else(|| text.strip_prefix("0X"))
+        .unwrap_or(text);
+    u16::from_str_radix(digits, 16).map_err(|_| format!("invalid address '{text}'"))
+}
+
+/// Parse an address written as `0x` hex or decimal.
+fn parse_addr(text: &str) -> Result<u16, String> {
+    match text.strip_prefix("0x").or_else(|| text.strip_prefix("0X")) {
+        Some(_) => parse_hex(text),
+        None => text
+            .parse()
+            .map_err(|_| format!("invalid address '{text}'")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn map() -> SymbolMap {
+        let mut map = SymbolMap::new();
+        map.add_label(0xE000, "start");
+        map.add_label(0xE6A4, "sys_fs_find");
+        map.add_label(0xE6A4, "sys_fs_find.loop");
+        map.add_line(0xE6A4, "kernel/fs.asm", 120);
+        map
+    }
+
+    #[test]
+    fn test_describe() {
+        let map = map();
+        assert_eq!(map.describe(0xE6A4), "sys_fs_find");
+        assert_eq!(map.describe(0xE6B6), "sys_fs_find+0x12");
+        assert_eq!(map.describe(0x1000), "1000");
+        assert_eq!(
+            map.source(0xE6B6).map(ToString::to_string),
+            Some("kernel/fs.asm:120".to_string())
+        );
+    }
+
+    #[test]
+    fn test_resolve() {
+        let map = map();
+        assert_eq!(map.resolve("sys_fs_find+0x12"), Some(0xE6B6));
+        assert_eq!(map.resolve("E000"), Some(0xE000));
+        assert_eq!(map.resolve("missing+2"), None);
+    }
+
+    #[test]
+    fn test_roundtrip() {
+        let map = map();
+        assert_eq!(SymbolMap::parse(&map.to_string()), Ok(map));
+    }
+
+    #[test]
+    fn test_parse_customasm_symbols() {
+        let Ok(map) = SymbolMap::parse("start = 0xe000\nstart.loop = 0xe004\n") else {
+            panic!("failed to parse");
+        };
+        assert_eq!(map.describe(0xE006), "start.loop+0x2");
+        assert!(SymbolMap::parse("label nowhere").is_err());
+    }
+}
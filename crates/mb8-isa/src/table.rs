@@ -0,0 +1,368 @@
+//! Declarative description of the MB8 instruction set.
+//!
+//! The `isa!` invocation below is the single source of truth for instruction
+//! encodings: it generates [`INSTRUCTIONS`], the word encoder and decoder used by
+//! [`crate::encode`] and [`crate::decode`], and [`ruledef`] renders the same table
+//! as the customasm rules in `asm/isa.asm`.
+
+use std::fmt::Write;
+
+use crate::{
+    decode::decode_register,
+    encode::encode_register,
+    opcodes::Opcode,
+    registers::{
+        flags::{C_FLAG, N_FLAG, Z_FLAG},
+        Register,
+    },
+};
+
+/// Where an operand is stored in the instruction word.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Field {
+    /// Register in bits 11..8.
+    A,
+    /// Register in bits 7..4.
+    B,
+    /// Register in bits 3..0.
+    C,
+    /// Unsigned 8-bit immediate in bits 7..0.
+    Imm8,
+    /// Signed 8-bit offset in bits 7..0.
+    Rel8,
+}
+
+impl Field {
+    fn shift(self) -> u16 {
+        match self {
+            Field::A => 8,
+            Field::B => 4,
+            Field::C | Field::Imm8 | Field::Rel8 => 0,
+        }
+    }
+
+    /// Bits of the instruction word covered by the field.
+    #[must_use]
+    pub fn mask(self) -> u16 {
+        match self {
+            Field::A | Field::B | Field::C => 0xF << self.shift(),
+            Field::Imm8 | Field::Rel8 => 0xFF,
+        }
+    }
+
+    fn extract(self, word: u16) -> u16 {
+        (word & self.mask()) >> self.shift()
+    }
+
+    fn insert(self, bits: u16) -> u16 {
+        (bits << self.shift()) & self.mask()
+    }
+
+    /// customasm parameter type of the field.
+    #[must_use]
+    pub fn asm_type(self) -> &'static str {
+        match self {
+            Field::A | Field::B | Field::C => "register",
+            Field::Imm8 => "u8",
+            Field::Rel8 => "i8",
+        }
+    }
+}
+
+/// A named operand of an instruction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Operand {
+    pub name: &'static str,
+    pub field: Field,
+}
+
+/// One row of the instruction table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Spec {
+    pub mnemonic: &'static str,
+    /// Fixed bits of the instruction word.
+    pub opcode: u16,
+    /// Bits that identify the instruction. Bits outside the mask and the
+    /// operand fields are ignored when decoding and encoded as zero.
+    pub mask: u16,
+    /// Assembler syntax after the mnemonic, operands written as `{name}`.
+    pub syntax: &'static str,
+    pub operands: &'static [Operand],
+    /// Flags the instruction writes (`Z_FLAG | N_FLAG | C_FLAG`).
+    pub flags: u8,
+    /// Cost of the instruction in CPU cycles.
+    pub cycles: u8,
+}
+
+/// Conversion between an operand value and its bits in the instruction word.
+trait FieldValue: Sized {
+    fn from_bits(bits: u16) -> Option<Self>;
+    fn to_bits(self) -> u16;
+}
+
+impl FieldValue for Register {
+    fn from_bits(bits: u16) -> Option<Self> {
+        decode_register(bits)
+    }
+
+    fn to_bits(self) -> u16 {
+        u16::from(encode_register(self))
+    }
+}
+
+impl FieldValue for u8 {
+    fn from_bits(bits: u16) -> Option<Self> {
+        Some(bits as u8)
+    }
+
+    fn to_bits(self) -> u16 {
+        u16::from(self)
+    }
+}
+
+impl FieldValue for i8 {
+    fn from_bits(bits: u16) -> Option<Self> {
+        Some(bits as u8 as i8)
+    }
+
+    fn to_bits(self) -> u16 {
+        u16::from(self as u8)
+    }
+}
+
+macro_rules! isa {
+    ($(
+        $variant:ident { $($field:ident: $pos:ident),* } =>
+            $mnemonic:literal $syntax:literal,
+            $opcode:literal / $mask:literal,
+            flags: $flags:expr,
+            cycles: $cycles:literal;
+    )*) => {
+        /// Every instruction of the ISA, in opcode order.
+        pub const INSTRUCTIONS: &[Spec] = &[$(
+            Spec {
+                mnemonic: $mnemonic,
+                opcode: $opcode,
+                mask: $mask,
+                syntax: $syntax,
+                operands: &[$(Operand { name: stringify!($field), field: Field::$pos }),*],
+                flags: $flags,
+                cycles: $cycles,
+            },
+        )*];
+
+        /// Table row describing `opcode`.
+        #[must_use]
+        pub fn spec(opcode: &Opcode) -> &'static Spec {
+            let mut index = 0;
+            $(
+                if matches!(opcode, Opcode::$variant { .. }) {
+                    return &INSTRUCTIONS[index];
+                }
+                index += 1;
+            )*
+            unreachable!("instruction {index} is missing from the table")
+        }
+
+        pub(crate) fn encode_word(opcode: &Opcode) -> u16 {
+            match *opcode {
+                $(Opcode::$variant { $($field),* } => {
+                    $opcode $(| Field::$pos.insert($field.to_bits()))*
+                })*
+            }
+        }
+
+        pub(crate) fn decode_word(word: u16) -> Option<Opcode> {
+            $(
+                if word & $mask == $opcode {
+                    return Some(Opcode::$variant {
+                        $($field: FieldValue::from_bits(Field::$pos.extract(word))?),*
+                    });
+                }
+            )*
+            None
+        }
+    };
+}
+
+const ALU: u8 = Z_FLAG | N_FLAG | C_FLAG;
+const LOGIC: u8 = Z_FLAG | N_FLAG;
+
+isa! {
+    Nop {} => "NOP" "", 0x0000 / 0xFF00, flags: 0, cycles: 1;
+    Halt {} => "HALT" "", 0x0100 / 0xFF00, flags: 0, cycles: 1;
+    Sys {} => "SYS" "", 0x0200 / 0xFF00, flags: 0, cycles: 1;
+    Mov { dst: B, src: C } => "MOV" "{dst} {src}", 0x1000 / 0xFF00, flags: 0, cycles: 1;
+    Add { dst: B, src: C } => "ADD" "{dst} {src}", 0x1100 / 0xFF00, flags: ALU, cycles: 1;
+    Sub { dst: B, src: C } => "SUB" "{dst} {src}", 0x1200 / 0xFF00, flags: ALU, cycles: 1;
+    And { dst: B, src: C } => "AND" "{dst} {src}", 0x1300 / 0xFF00, flags: LOGIC, cycles: 1;
+    Or { dst: B, src: C } => "OR" "{dst} {src}", 0x1400 / 0xFF00, flags: LOGIC, cycles: 1;
+    Xor { dst: B, src: C } => "XOR" "{dst} {src}", 0x1500 / 0xFF00, flags: LOGIC, cycles: 1;
+    Shr { dst: B, src: C } => "SHR" "{dst} {src}", 0x1600 / 0xFF00, flags: ALU, cycles: 1;
+    Shl { dst: B, src: C } => "SHL" "{dst} {src}", 0x1700 / 0xFF00, flags: ALU, cycles: 1;
+    Cmp { dst: B, src: C } => "CMP" "{dst} {src}", 0x1800 / 0xFF00, flags: ALU, cycles: 1;
+    Ldi { dst: A, value: Imm8 } => "LDI" "{dst} {value}", 0x2000 / 0xF000, flags: 0, cycles: 1;
+    Jmp { hi: B, lo: C } => "JMP" "[{hi}:{lo}]", 0x3000 / 0xFF00, flags: 0, cycles: 2;
+    Jr { offset: Rel8 } => "JR" "{offset}", 0x3100 / 0xFF00, flags: 0, cycles: 2;
+    Jzr { offset: Rel8 } => "JZR" "{offset}", 0x3200 / 0xFF00, flags: 0, cycles: 2;
+    Jnzr { offset: Rel8 } => "JNZR" "{offset}", 0x3300 / 0xFF00, flags: 0, cycles: 2;
+    Jcr { offset: Rel8 } => "JCR" "{offset}", 0x3400 / 0xFF00, flags: 0, cycles: 2;
+    Jncr { offset: Rel8 } => "JNCR" "{offset}", 0x3500 / 0xFF00, flags: 0, cycles: 2;
+    Call { hi: B, lo: C } => "CALL" "[{hi}:{lo}]", 0x4000 / 0xFF00, flags: 0, cycles: 4;
+    Ret {} => "RET" "", 0x4100 / 0xFF00, flags: 0, cycles: 4;
+    Push { src: B } => "PUSH" "{src}", 0x4200 / 0xFF00, flags: 0, cycles: 2;
+    Pop { dst: B } => "POP" "{dst}", 0x4300 / 0xFF00, flags: 0, cycles: 2;
+    Ld { dst: A, hi: B, lo: C } => "LD" "{dst} [{hi}:{lo}]", 0x5000 / 0xF000, flags: 0, cycles: 2;
+    St { src: A, hi: B, lo: C } => "ST" "[{hi}:{lo}] {src}", 0x6000 / 0xF000, flags: 0, cycles: 2;
+}
+
+/// Extra assembler forms as `(pattern, body)`: they fill bits the CPU ignores,
+/// so they decode to the instruction of the table they start with.
+pub const EXTRA_RULES: &[(&str, &str)] = &[("HALT { code: u8 }", "0x01 @ code")];
+
+/// Every register name accepted by the assembler, aliases last.
+pub const REGISTERS: [Register; 24] = [
+    Register::R0,
+    Register::R1,
+    Register::R2,
+    Register::R3,
+    Register::R4,
+    Register::R5,
+    Register::R6,
+    Register::R7,
+    Register::R8,
+    Register::R9,
+    Register::R10,
+    Register::R11,
+    Register::R12,
+    Register::R13,
+    Register::R14,
+    Register::R15,
+    Register::A,
+    Register::IH,
+    Register::IL,
+    Register::FPH,
+    Register::FPL,
+    Register::SPH,
+    Register::SPL,
+    Register::F,
+];
+
+impl Spec {
+    /// customasm rule body, e.g. `0x10 @ dst @ src`.
+    fn rule_body(&self) -> String {
+        let mut parts: Vec<String> = Vec::new();
+        let mut literal = String::new();
+        let mut previous = None;
+        for nibble in (0..4).rev() {
+            let bits = 0xF << (nibble * 4);
+            let Some(operand) = self.operands.iter().find(|op| op.field.mask() & bits != 0) else {
+                let digit = (self.opcode & self.mask & bits) >> (nibble * 4);
+                let _ = write!(literal, "{digit:X}");
+                previous = None;
+                continue;
+            };
+            if !literal.is_empty() {
+                parts.push(format!("0x{literal}"));
+                literal.clear();
+            }
+            // 8-bit fields span two nibbles but are a single rule parameter.
+            if previous != Some(operand.name) {
+                parts.push(operand.name.to_string());
+            }
+            previous = Some(operand.name);
+        }
+        if !literal.is_empty() {
+            parts.push(format!("0x{literal}"));
+        }
+        parts.join(" @ ")
+    }
+
+    /// customasm rule pattern, e.g. `LD { dst: register } [{ hi: register }:{ lo: register }]`.
+    fn rule_pattern(&self) -> String {
+        let mut pattern = self.syntax.to_string();
+        for operand in self.operands {
+            pattern = pattern.replace(
+                &format!("{{{}}}", operand.name),
+                &format!("{{ {}: {} }}", operand.name, operand.field.asm_type()),
+            );
+        }
+        if pattern.is_empty() {
+            self.mnemonic.to_string()
+        } else {
+            format!("{} {pattern}", self.mnemonic)
+        }
+    }
+}
+
+/// The contents of `asm/isa.asm`: register names and one rule per instruction.
+#[must_use]
+pub fn ruledef() -> String {
+    let mut out = String::new();
+    out.push_str("#once\n\n");
+    out.push_str("; Generated from crates/mb8-isa/src/table.rs by `make isa`. Do not edit.\n\n");
+    out.push_str("#subruledef register\n{\n");
+    for register in REGISTERS {
+        let name = register.to_string();
+        let _ = writeln!(out, "    {name:<3} => 0x{:X}", encode_register(register));
+        if register == Register::R15 {
+            out.push('\n');
+        }
+    }
+    out.push_str("}\n\n#ruledef mb8_isa\n{\n");
+    for spec in INSTRUCTIONS {
+        let _ = writeln!(out, "    {} => {}", spec.rule_pattern(), spec.rule_body());
+        for (pattern, body) in EXTRA_RULES {
+            if pattern.split_whitespace().next() == Some(spec.mnemonic) {
+                let _ = writeln!(out, "    {pattern} => {body}");
+            }
+        }
+    }
+    out.push_str("}\n");
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_spec_lookup() {
+        let spec = spec(&Opcode::Ret);
+        assert_eq!(spec.mnemonic, "RET");
+        assert_eq!(spec.cycles, 4);
+        assert_eq!(
+            crate::table::spec(&Opcode::Add {
+                dst: Register::R0,
+                src: Register::R1
+            })
+            .flags,
+            ALU
+        );
+    }
+
+    #[test]
+    fn test_rule_generation() {
+        let rule = |mnemonic: &str| {
+            let Some(spec) = INSTRUCTIONS.iter().find(|spec| spec.mnemonic == mnemonic) else {
+                panic!("missing {mnemonic}");
+            };
+            format!("{} => {}", spec.rule_pattern(), spec.rule_body())
+        };
+        assert_eq!(rule("NOP"), "NOP => 0x0000");
+        assert_eq!(
+            rule("MOV"),
+            "MOV { dst: register } { src: register } => 0x10 @ dst @ src"
+        );
+        assert_eq!(
+            rule("LDI"),
+            "LDI { dst: register } { value: u8 } => 0x2 @ dst @ value"
+        );
+        assert_eq!(rule("JR"), "JR { offset: i8 } => 0x31 @ offset");
+        assert_eq!(rule("PUSH"), "PUSH { src: register } => 0x42 @ src @ 0x0");
+        assert_eq!(
+            rule("ST"),
+            "ST [{ hi: register }:{ lo: register }] { src: register } => 0x6 @ src @ hi @ lo"
+        );
+    }
+}
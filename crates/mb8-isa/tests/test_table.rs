@@ -0,0 +1,62 @@
+use mb8_isa::{
+    decode::{decode, decode_register},
+    encode::encode,
+    table::{ruledef, spec, INSTRUCTIONS, REGISTERS},
+};
+
+#[test]
+fn test_isa_asm_is_generated() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/../../asm/isa.asm");
+    let Ok(isa) = std::fs::read_to_string(path) else {
+        panic!("failed to read {path}");
+    };
+    assert_eq!(isa, ruledef(), "asm/isa.asm is out of date, run `make isa`");
+}
+
+#[test]
+fn test_every_word_roundtrips() {
+    for word in 0..=u16::MAX {
+        let Some(opcode) = decode(word) else {
+            continue;
+        };
+        let canonical = encode(&opcode);
+        let spec = spec(&opcode);
+        assert_eq!(
+            word & spec.mask,
+            spec.opcode,
+            "{word:04X} decoded as {opcode}"
+        );
+        assert_eq!(
+            decode(canonical),
+            Some(opcode),
+            "{word:04X} -> {canonical:04X}"
+        );
+    }
+}
+
+#[test]
+fn test_encodings_do_not_overlap() {
+    for (i, a) in INSTRUCTIONS.iter().enumerate() {
+        for b in &INSTRUCTIONS[i + 1..] {
+            let common = a.mask & b.mask;
+            assert_ne!(
+                a.opcode & common,
+                b.opcode & common,
+                "{} and {} share an encoding",
+                a.mnemonic,
+                b.mnemonic
+            );
+        }
+    }
+}
+
+#[test]
+fn test_register_aliases() {
+    for register in REGISTERS {
+        let bits = u16::from(mb8_isa::encode::encode_register(register));
+        let Some(decoded) = decode_register(bits) else {
+            panic!("{register} does not decode");
+        };
+        assert_eq!(decoded.alias(), register.alias(), "{register}");
+    }
+}
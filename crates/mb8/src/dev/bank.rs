@@ -0,0 +1,163 @@
+use super::Device;
+
+pub mod registers {
+    /// Selected bank (read/write).
+    pub const BANK: u16 = 0x00;
+    /// Number of RAM banks (read-only).
+    pub const RAM_BANKS: u16 = 0x01;
+    /// Number of cartridge pages (read-only).
+    pub const CART_PAGES: u16 = 0x02;
+
+    /// Size of the window, a RAM bank and a cartridge page.
+    pub const BANK_SIZE: usize = 0x2000;
+    /// Bank numbers with this bit set select a cartridge page.
+    pub const CART_BANK: u8 = 0x80;
+    /// RAM banks available by default.
+    pub const DEFAULT_RAM_BANKS: usize = 8;
+    /// Largest cartridge image (128 pages, 1 MiB).
+    pub const MAX_CART_PAGES: usize = 0x80;
+}
+
+use registers::{
+    BANK, BANK_SIZE, CART_BANK, CART_PAGES, DEFAULT_RAM_BANKS, MAX_CART_PAGES, RAM_BANKS,
+};
+
+/// Bank controller: maps one of several 8 KiB RAM banks or read-only cartridge
+/// pages into the window at `0xC000..=0xDFFF`.
+///
+/// Bank `n` below `0x80` is RAM bank `n`; `0x80 | n` is cartridge page `n`.
+/// Selecting a bank that does not exist leaves the window unmapped: reads return
+/// `0xFF` and writes are ignored.
+#[derive(Debug)]
+pub struct Banks {
+    selected: u8,
+    ram: Vec<u8>,
+    cartridge: Vec<u8>,
+}
+
+impl Default for Banks {
+    fn default() -> Self {
+        Self::new(DEFAULT_RAM_BANKS)
+    }
+}
+
+impl Banks {
+    /// A controller with `ram_banks` zeroed RAM banks (at most 128) and no cartridge.
+    #[must_use]
+    pub fn new(ram_banks: usize) -> Self {
+        Self {
+            selected: 0,
+            ram: vec![0; ram_banks.min(usize::from(CART_BANK)) * BANK_SIZE],
+            cartridge: Vec::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn selected(&self) -> u8 {
+        self.selected
+    }
+
+    pub fn select(&mut self, bank: u8) {
+        self.selected = bank;
+    }
+
+    #[must_use]
+    pub fn ram_banks(&self) -> usize {
+        self.ram.len() / BANK_SIZE
+    }
+
+    #[must_use]
+    pub fn cartridge_pages(&self) -> usize {
+        self.cartridge.len() / BANK_SIZE
+    }
+
+    /// Insert a cartridge image. It is split into 8 KiB pages, the last one
+    /// padded with `0xFF`.
+    ///
+    /// # Errors
+    /// Returns an error if the image is empty or larger than 128 pages.
+    pub fn load_cartridge(&mut self, image: &[u8]) -> Result<(), String> {
+        let pages = image.len().div_ceil(BANK_SIZE);
+        if pages == 0 {
+            return Err("cartridge image is empty".to_string());
+        }
+        if pages > MAX_CART_PAGES {
+            return Err(format!(
+                "cartridge image of {} bytes has more than {MAX_CART_PAGES} pages of {BANK_SIZE} bytes",
+                image.len()
+            ));
+        }
+        self.cartridge = image.to_vec();
+        self.cartridge.resize(pages * BANK_SIZE, 0xFF);
+        Ok(())
+    }
+
+    /// Serialize the selected bank and the contents of the RAM banks. Cartridge
+    /// pages are read-only and are not part of the state.
+    #[must_use]
+    pub fn save_state(&self) -> Vec<u8> {
+        let mut state = vec![self.selected, self.ram_banks() as u8];
+        state.extend(&self.ram);
+        state
+    }
+
+    /// Restore a state written by [`Banks::save_state`].
+    ///
+    /// # Errors
+    /// Returns an error if `state` is malformed or holds a different number of RAM banks.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        let [selected, banks, ram @ ..] = state else {
+            return Err("bank state is truncated".to_string());
+        };
+        if usize::from(*banks) != self.ram_banks() || ram.len() != self.ram.len() {
+            return Err(format!(
+                "bank state holds {banks} RAM banks, the controller has {}",
+                self.ram_banks()
+            ));
+        }
+        self.selected = *selected;
+        self.ram.copy_from_slice(ram);
+        Ok(())
+    }
+
+    /// Offset of `addr` in the window within the RAM (`true`) or cartridge image.
+    fn window_offset(&self, addr: u16) -> (bool, usize) {
+        let bank = usize::from(self.selected & !CART_BANK);
+        (
+            self.selected & CART_BANK == 0,
+            bank * BANK_SIZE + usize::from(addr),
+        )
+    }
+
+    /// Read `addr`, relative to the start of the window.
+    pub fn read_window(&mut self, addr: u16) -> u8 {
+        let (ram, offset) = self.window_offset(addr);
+        let memory = if ram { &self.ram } else { &self.cartridge };
+        memory.get(offset).copied().unwrap_or(0xFF)
+    }
+
+    /// Write `addr`, relative to the start of the window. Cartridge pages ignore writes.
+    pub fn write_window(&mut self, addr: u16, value: u8) {
+        let (ram, offset) = self.window_offset(addr);
+        if let Some(byte) = self.ram.get_mut(offset).filter(|_| ram) {
+            *byte = value;
+        }
+    }
+}
+
+impl Device for Banks {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            BANK => self.selected,
+            RAM_BANKS => self.ram_banks() as u8,
+            CART_PAGES => self.cartridge_pages() as u8,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr == BANK {
+            self.selected = value;
+        }
+    }
+}
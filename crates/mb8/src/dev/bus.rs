@@ -1,4 +1,7 @@
-use super::{disk::Disk, gpu::GPU, keyboard::Keyboard, ram::RAM, rand::Rand, rom::ROM, Device};
+use super::{
+    bank::Banks, debug::DebugPort, disk::Disk, dma::Dma, gpu::GPU, hostfs::HostFs,
+    keyboard::Keyboard, ram::RAM, rand::Rand, rom::ROM, rtc::Rtc, sound::Sound, uart::Uart, Device,
+};
 
 #[derive(Debug, Default)]
 pub struct Bus {
@@ -8,6 +11,13 @@ pub struct Bus {
     keyboard: Keyboard,
     disk: Disk,
     rand: Rand,
+    banks: Banks,
+    dma: Dma,
+    uart: Uart,
+    rtc: Rtc,
+    sound: Sound,
+    debug_port: DebugPort,
+    hostfs: HostFs,
 }
 
 impl Bus {
@@ -28,30 +38,97 @@ impl Bus {
         &mut self.rand
     }
 
+    pub fn banks(&mut self) -> &mut Banks {
+        &mut self.banks
+    }
+
+    pub fn dma(&mut self) -> &mut Dma {
+        &mut self.dma
+    }
+
+    pub fn uart(&mut self) -> &mut Uart {
+        &mut self.uart
+    }
+
+    pub fn rtc(&mut self) -> &mut Rtc {
+        &mut self.rtc
+    }
+
+    pub fn sound(&mut self) -> &mut Sound {
+        &mut self.sound
+    }
+
+    pub fn debug_port(&mut self) -> &mut DebugPort {
+        &mut self.debug_port
+    }
+
+    pub fn hostfs(&mut self) -> &mut HostFs {
+        &mut self.hostfs
+    }
+
+    /// Advance the devices by `cycles` CPU cycles.
+    pub fn tick(&mut self, cycles: u64) {
+        for _ in 0..self.dma.budget(cycles) {
+            let (src, dst) = self.dma.advance();
+            let value = self.read(src);
+            self.write(dst, value);
+        }
+        self.sound.tick(cycles);
+        self.disk.tick(cycles);
+    }
+
     #[must_use]
     pub fn read(&mut self, addr: u16) -> u8 {
         match addr {
             0x0000..=0xBFFF => self.ram.read(addr),
-            0xC000..=0xDFFF => unimplemented!(),
+            0xC000..=0xDFFF => self.banks.read_window(addr - 0xC000),
             0xE000..=0xEFFF => self.rom.read(addr - 0xE000),
             0xF000..=0xF100 => self.gpu.read(addr - 0xF000),
             0xF101..=0xF1FF => self.keyboard.read(addr - 0xF101),
             0xF200..=0xF3FF => self.disk.read(addr - 0xF200),
             0xF400 => self.rand.read(addr - 0xF400),
-            0xF401..=0xFFFF => unimplemented!(),
+            0xF401..=0xF4FF => unimplemented!(),
+            0xF500..=0xF502 => self.banks.read(addr - 0xF500),
+            0xF503..=0xF5FF => unimplemented!(),
+            0xF600..=0xF607 => self.dma.read(addr - 0xF600),
+            0xF608..=0xF6FF => unimplemented!(),
+            0xF700..=0xF701 => self.uart.read(addr - 0xF700),
+            0xF702..=0xF7FF => unimplemented!(),
+            0xF800..=0xF807 => self.rtc.read(addr - 0xF800),
+            0xF808..=0xF8FF => unimplemented!(),
+            0xF900..=0xF90F => self.sound.read(addr - 0xF900),
+            0xF910..=0xF9FF => unimplemented!(),
+            0xFA00..=0xFA04 => self.debug_port.read(addr - 0xFA00),
+            0xFA05..=0xFAFF => unimplemented!(),
+            0xFB00..=0xFBFF => self.hostfs.read(addr - 0xFB00),
+            0xFC00..=0xFFFF => unimplemented!(),
         }
     }
 
     pub fn write(&mut self, addr: u16, value: u8) {
         match addr {
             0x0000..=0xBFFF => self.ram.write(addr, value),
-            0xC000..=0xDFFF => unimplemented!(),
+            0xC000..=0xDFFF => self.banks.write_window(addr - 0xC000, value),
             0xE000..=0xEFFF => self.rom.write(addr - 0xE000, value),
             0xF000..=0xF100 => self.gpu.write(addr - 0xF000, value),
             0xF101..=0xF1FF => self.keyboard.write(addr - 0xF101, value),
             0xF200..=0xF3FF => self.disk.write(addr - 0xF200, value),
             0xF400 => self.rand.write(addr - 0xF400, value),
-            0xF401..=0xFFFF => unimplemented!(),
+            0xF401..=0xF4FF => unimplemented!(),
+            0xF500..=0xF502 => self.banks.write(addr - 0xF500, value),
+            0xF503..=0xF5FF => unimplemented!(),
+            0xF600..=0xF607 => self.dma.write(addr - 0xF600, value),
+            0xF608..=0xF6FF => unimplemented!(),
+            0xF700..=0xF701 => self.uart.write(addr - 0xF700, value),
+            0xF702..=0xF7FF => unimplemented!(),
+            0xF800..=0xF807 => self.rtc.write(addr - 0xF800, value),
+            0xF808..=0xF8FF => unimplemented!(),
+            0xF900..=0xF90F => self.sound.write(addr - 0xF900, value),
+            0xF910..=0xF9FF => unimplemented!(),
+            0xFA00..=0xFA04 => self.debug_port.write(addr - 0xFA00, value),
+            0xFA05..=0xFAFF => unimplemented!(),
+            0xFB00..=0xFBFF => self.hostfs.write(addr - 0xFB00, value),
+            0xFC00..=0xFFFF => unimplemented!(),
         }
     }
 }
@@ -0,0 +1,80 @@
+use super::Device;
+
+pub mod registers {
+    /// Write-only: append the byte to the log.
+    pub const LOG: u16 = 0x00;
+    /// Write-only: append the value to the log as two hex digits.
+    pub const HEX: u16 = 0x01;
+    /// Write-only: the assertion with this ID passed.
+    pub const PASS: u16 = 0x02;
+    /// Write-only: the assertion with this ID failed.
+    pub const FAIL: u16 = 0x03;
+    /// Write-only: stop the machine with this exit status.
+    pub const EXIT: u16 = 0x04;
+}
+
+use registers::{EXIT, FAIL, HEX, LOG, PASS};
+
+/// Result of an assertion reported by guest code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Assertion {
+    pub id: u8,
+    pub passed: bool,
+}
+
+/// Lets test programs report to the host: a text log, assertion results and an
+/// exit status. Reads return 0.
+#[derive(Debug, Default)]
+pub struct DebugPort {
+    log: Vec<u8>,
+    assertions: Vec<Assertion>,
+    exit: Option<u8>,
+    used: bool,
+}
+
+impl DebugPort {
+    /// Everything written to `LOG` and `HEX`.
+    #[must_use]
+    pub fn log(&self) -> &[u8] {
+        &self.log
+    }
+
+    /// Reported assertions, in order.
+    #[must_use]
+    pub fn assertions(&self) -> &[Assertion] {
+        &self.assertions
+    }
+
+    /// Whether the program wrote to any register.
+    #[must_use]
+    pub fn used(&self) -> bool {
+        self.used
+    }
+
+    /// Take a pending exit request.
+    pub fn take_exit(&mut self) -> Option<u8> {
+        self.exit.take()
+    }
+}
+
+impl Device for DebugPort {
+    fn read(&mut self, _addr: u16) -> u8 {
+        0
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        self.used = true;
+        match addr {
+            LOG => self.log.push(value),
+            HEX => self
+                .log
+                .extend_from_slice(format!("{value:02X}").as_bytes()),
+            PASS | FAIL => self.assertions.push(Assertion {
+                id: value,
+                passed: addr == PASS,
+            }),
+            EXIT => self.exit = Some(value),
+            _ => {}
+        }
+    }
+}
@@ -1,48 +1,253 @@
+use std::collections::BTreeSet;
+
 use super::{utils::empty_memory, Device};
 
 pub mod registers {
+    /// Low byte of the block number.
     pub const DISK_BLOCK: u16 = 0x0000;
     pub const DISK_CMD: u16 = 0x0001;
     pub const DISK_BUFFER_START: u16 = 0x0002;
     pub const DISK_BUFFER_END: u16 = 0x0002 + 256;
+    /// High byte of the block number.
+    pub const DISK_BLOCK_HIGH: u16 = 0x0110;
+    /// Drive the commands operate on.
+    pub const DISK_DRIVE: u16 = 0x0111;
+    /// State of the controller (read-only): `DISK_STATUS_BUSY` while a read or
+    /// write runs, then its result.
+    pub const DISK_STATUS: u16 = 0x0112;
 
     pub const DISK_CMD_NOP: u8 = 0x00;
     pub const DISK_CMD_READ: u8 = 0x01;
     pub const DISK_CMD_WRITE: u8 = 0x02;
+    /// Ask the host to save the image to its backing file now.
+    pub const DISK_CMD_FLUSH: u8 = 0x03;
+
+    pub const DISK_STATUS_OK: u8 = 0x00;
+    /// The drive does not exist or has no image, or the block is past its end.
+    pub const DISK_STATUS_OUT_OF_RANGE: u8 = 0x01;
+    /// A write to a write-protected drive.
+    pub const DISK_STATUS_WRITE_PROTECTED: u8 = 0x02;
+    /// `DISK_CMD` got an unknown command.
+    pub const DISK_STATUS_INVALID_COMMAND: u8 = 0x03;
+    /// The block is marked bad.
+    pub const DISK_STATUS_BAD_BLOCK: u8 = 0x04;
+    /// A command is in progress. The other bits are clear and the registers
+    /// and buffer ignore writes until it completes.
+    pub const DISK_STATUS_BUSY: u8 = 0x80;
 }
 
+use registers::{
+    DISK_STATUS_BAD_BLOCK, DISK_STATUS_BUSY, DISK_STATUS_INVALID_COMMAND, DISK_STATUS_OK,
+    DISK_STATUS_OUT_OF_RANGE, DISK_STATUS_WRITE_PROTECTED,
+};
+
+pub const BLOCK_SIZE: usize = 256;
+/// Size of the default disk image: 256 blocks of 256 bytes.
+pub const DISK_SIZE: usize = 65536;
+/// Blocks a 16-bit block number reaches.
+pub const MAX_BLOCKS: usize = 0x10000;
+/// Size of the largest disk image, 16 MiB.
+pub const MAX_DISK_SIZE: usize = MAX_BLOCKS * BLOCK_SIZE;
+/// Drives on the controller.
+pub const DRIVES: usize = 4;
+
+/// A drive and the image in it.
+#[derive(Debug, Default)]
+pub struct Drive {
+    /// Empty when there is no image. A partial last block cannot be read.
+    img: Vec<u8>,
+    /// Fail `DISK_CMD_WRITE` with `DISK_STATUS_WRITE_PROTECTED`.
+    pub write_protected: bool,
+    /// Blocks that fail reads and writes with `DISK_STATUS_BAD_BLOCK`, to test
+    /// error handling.
+    pub bad_blocks: BTreeSet<usize>,
+    /// Written since the image was set or last saved.
+    dirty: bool,
+}
+
+impl Drive {
+    pub fn set(&mut self, img: Vec<u8>) {
+        self.img = img;
+        self.dirty = false;
+    }
+
+    /// Blocks the guest can address.
+    #[must_use]
+    pub fn blocks(&self) -> usize {
+        (self.img.len() / BLOCK_SIZE).min(MAX_BLOCKS)
+    }
+
+    /// Whether blocks were written since the image was set or marked clean.
+    #[must_use]
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Call once the image has been saved.
+    pub fn mark_clean(&mut self) {
+        self.dirty = false;
+    }
+
+    #[must_use]
+    pub fn dump(&self) -> &[u8] {
+        &self.img
+    }
+
+    fn block(&mut self, block: usize) -> Option<&mut [u8]> {
+        (block < self.blocks()).then(|| &mut self.img[block * BLOCK_SIZE..][..BLOCK_SIZE])
+    }
+}
+
+/// Emulated time a read or write takes, in CPU cycles. Zero by default, so
+/// commands complete as soon as they are written.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct Latency {
+    /// Added when the command is for another block than the previous one.
+    pub seek: u64,
+    /// Added to every command.
+    pub transfer: u64,
+}
+
+/// The disk controller. Drive 0 holds a 64 KiB image from the start, the other
+/// drives are empty until the host sets one.
 #[derive(Debug)]
 pub struct Disk {
-    img: Box<[u8; 65536]>,
+    drives: [Drive; DRIVES],
     buffer: Box<[u8; 256]>,
-    block: u8,
+    block: u16,
+    drive: u8,
+    status: u8,
+    pub latency: Latency,
+    /// Command in progress and the cycles left until it completes.
+    pending: Option<(u8, u64)>,
+    /// Drive and block of the previous command, for the seek time.
+    head: (u8, u16),
+    /// The guest sent `DISK_CMD_FLUSH`.
+    flush: bool,
 }
 
 impl Default for Disk {
     fn default() -> Self {
+        let mut drives: [Drive; DRIVES] = Default::default();
+        drives[0].set(vec![0; DISK_SIZE]);
         Disk {
-            img: empty_memory(),
+            drives,
             buffer: empty_memory(),
             block: Default::default(),
+            drive: 0,
+            status: DISK_STATUS_OK,
+            latency: Latency::default(),
+            pending: None,
+            head: (0, 0),
+            flush: false,
         }
     }
 }
 
 impl Disk {
-    pub fn set(&mut self, img: Box<[u8; 65536]>) {
-        self.img = img;
+    /// Drive `index`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is not below [`DRIVES`].
+    pub fn drive(&mut self, index: usize) -> &mut Drive {
+        &mut self.drives[index]
     }
 
+    /// Put `img` in drive 0, the one the kernel boots from.
+    pub fn set(&mut self, img: Vec<u8>) {
+        self.drives[0].set(img);
+    }
+
+    /// Image in drive 0.
     #[must_use]
     pub fn dump(&self) -> &[u8] {
-        self.img.as_slice()
+        self.drives[0].dump()
+    }
+
+    /// Whether the guest asked for a flush since the last call.
+    pub fn take_flush(&mut self) -> bool {
+        std::mem::take(&mut self.flush)
+    }
+
+    #[must_use]
+    pub fn busy(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Advance the command in progress by `cycles` CPU cycles.
+    pub fn tick(&mut self, cycles: u64) {
+        if let Some((command, left)) = &mut self.pending {
+            *left = left.saturating_sub(cycles);
+            if *left == 0 {
+                let command = *command;
+                self.complete(command);
+            }
+        }
+    }
+
+    /// Complete the command in progress now.
+    pub fn finish(&mut self) {
+        if let Some((command, _)) = self.pending {
+            self.complete(command);
+        }
+    }
+
+    /// Start a read or write. It completes after the latency, at once without one.
+    fn start(&mut self, command: u8) {
+        let mut cycles = self.latency.transfer;
+        if self.head != (self.drive, self.block) {
+            cycles += self.latency.seek;
+        }
+        self.head = (self.drive, self.block);
+        if cycles == 0 {
+            self.complete(command);
+        } else {
+            self.pending = Some((command, cycles));
+            self.status = DISK_STATUS_BUSY;
+        }
+    }
+
+    fn complete(&mut self, command: u8) {
+        self.pending = None;
+        self.status = match self.transfer(command == registers::DISK_CMD_WRITE) {
+            Ok(()) => DISK_STATUS_OK,
+            Err(status) => status,
+        };
+    }
+
+    /// Move the selected block into the buffer, or the buffer into the block
+    /// for a `write`.
+    fn transfer(&mut self, write: bool) -> Result<(), u8> {
+        let block = usize::from(self.block);
+        let drive = self
+            .drives
+            .get_mut(usize::from(self.drive))
+            .ok_or(DISK_STATUS_OUT_OF_RANGE)?;
+        if drive.bad_blocks.contains(&block) {
+            return Err(DISK_STATUS_BAD_BLOCK);
+        }
+        if write && drive.write_protected {
+            return Err(DISK_STATUS_WRITE_PROTECTED);
+        }
+        let data = drive.block(block).ok_or(DISK_STATUS_OUT_OF_RANGE)?;
+        if write {
+            data.copy_from_slice(self.buffer.as_slice());
+            drive.dirty = true;
+        } else {
+            self.buffer.copy_from_slice(data);
+        }
+        Ok(())
     }
 }
 
 impl Device for Disk {
     fn read(&mut self, addr: u16) -> u8 {
         match addr {
-            registers::DISK_BLOCK => self.block,
+            registers::DISK_BLOCK => self.block.to_be_bytes()[1],
+            registers::DISK_BLOCK_HIGH => self.block.to_be_bytes()[0],
+            registers::DISK_DRIVE => self.drive,
+            registers::DISK_STATUS => self.status,
             registers::DISK_BUFFER_START..=registers::DISK_BUFFER_END => {
                 self.buffer[(addr - registers::DISK_BUFFER_START) as usize]
             }
@@ -51,19 +256,21 @@ impl Device for Disk {
     }
 
     fn write(&mut self, addr: u16, value: u8) {
+        if self.busy() {
+            return;
+        }
         match addr {
-            registers::DISK_BLOCK => self.block = value,
+            registers::DISK_BLOCK => self.block = self.block & 0xFF00 | u16::from(value),
+            registers::DISK_BLOCK_HIGH => {
+                self.block = u16::from(value) << 8 | self.block & 0x00FF;
+            }
+            registers::DISK_DRIVE => self.drive = value,
+            registers::DISK_STATUS => {}
             registers::DISK_CMD => match value {
                 registers::DISK_CMD_NOP => {}
-                registers::DISK_CMD_READ => {
-                    let offset = self.block as usize * 256;
-                    self.buffer.copy_from_slice(&self.img[offset..offset + 256]);
-                }
-                registers::DISK_CMD_WRITE => {
-                    let offset = self.block as usize * 256;
-                    self.img[offset..offset + 256].copy_from_slice(self.buffer.as_slice());
-                }
-                _ => unimplemented!(),
+                registers::DISK_CMD_READ | registers::DISK_CMD_WRITE => self.start(value),
+                registers::DISK_CMD_FLUSH => self.flush = true,
+                _ => self.status = DISK_STATUS_INVALID_COMMAND,
             },
             registers::DISK_BUFFER_START..=registers::DISK_BUFFER_END => {
                 self.buffer[(addr - registers::DISK_BUFFER_START) as usize] = value;
@@ -0,0 +1,130 @@
+use super::Device;
+
+pub mod registers {
+    pub const SRC_HI: u16 = 0x00;
+    pub const SRC_LO: u16 = 0x01;
+    pub const DST_HI: u16 = 0x02;
+    pub const DST_LO: u16 = 0x03;
+    pub const LEN_HI: u16 = 0x04;
+    pub const LEN_LO: u16 = 0x05;
+    /// Control (write-only): start a transfer and select its addressing mode.
+    pub const CTRL: u16 = 0x06;
+    /// Status (read-only).
+    pub const STATUS: u16 = 0x07;
+
+    /// `CTRL`: start the transfer.
+    pub const CTRL_START: u8 = 0x01;
+    /// `CTRL`: keep reading the same source address (a device port).
+    pub const CTRL_FIXED_SRC: u8 = 0x02;
+    /// `CTRL`: keep writing the same destination address, such as the TTY register.
+    pub const CTRL_FIXED_DST: u8 = 0x04;
+
+    /// `STATUS`: a transfer is in progress.
+    pub const STATUS_BUSY: u8 = 0x01;
+    /// `STATUS`: the last transfer completed. Cleared by starting another one.
+    pub const STATUS_DONE: u8 = 0x02;
+
+    /// Bytes moved per CPU cycle.
+    pub const BYTES_PER_CYCLE: u64 = 1;
+}
+
+use registers::{
+    BYTES_PER_CYCLE, CTRL, CTRL_FIXED_DST, CTRL_FIXED_SRC, CTRL_START, DST_HI, DST_LO, LEN_HI,
+    LEN_LO, SRC_HI, SRC_LO, STATUS, STATUS_BUSY, STATUS_DONE,
+};
+
+/// DMA controller: copies `len` bytes from `src` to `dst` on the bus while the CPU
+/// keeps running. The address and length registers count along with the transfer.
+#[derive(Debug, Default)]
+pub struct Dma {
+    pub src: u16,
+    pub dst: u16,
+    pub len: u16,
+    ctrl: u8,
+    status: u8,
+}
+
+impl Dma {
+    #[must_use]
+    pub fn busy(&self) -> bool {
+        self.status & STATUS_BUSY != 0
+    }
+
+    #[must_use]
+    pub fn status(&self) -> u8 {
+        self.status
+    }
+
+    /// Number of bytes the transfer may move in `cycles` CPU cycles.
+    #[must_use]
+    pub fn budget(&self, cycles: u64) -> u16 {
+        if self.busy() {
+            let bytes = cycles.saturating_mul(BYTES_PER_CYCLE);
+            u16::try_from(bytes).unwrap_or(u16::MAX).min(self.len)
+        } else {
+            0
+        }
+    }
+
+    /// Source and destination of the next byte. Advances the transfer.
+    pub fn advance(&mut self) -> (u16, u16) {
+        let addresses = (self.src, self.dst);
+        if self.ctrl & CTRL_FIXED_SRC == 0 {
+            self.src = self.src.wrapping_add(1);
+        }
+        if self.ctrl & CTRL_FIXED_DST == 0 {
+            self.dst = self.dst.wrapping_add(1);
+        }
+        self.len -= 1;
+        if self.len == 0 {
+            self.status = STATUS_DONE;
+        }
+        addresses
+    }
+
+    fn set_byte(word: &mut u16, hi: bool, value: u8) {
+        let [mut high, mut low] = word.to_be_bytes();
+        if hi {
+            high = value;
+        } else {
+            low = value;
+        }
+        *word = u16::from_be_bytes([high, low]);
+    }
+}
+
+impl Device for Dma {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            SRC_HI => self.src.to_be_bytes()[0],
+            SRC_LO => self.src.to_be_bytes()[1],
+            DST_HI => self.dst.to_be_bytes()[0],
+            DST_LO => self.dst.to_be_bytes()[1],
+            LEN_HI => self.len.to_be_bytes()[0],
+            LEN_LO => self.len.to_be_bytes()[1],
+            STATUS => self.status,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        // The registers are locked while a transfer runs.
+        if self.busy() {
+            return;
+        }
+        match addr {
+            SRC_HI | SRC_LO => Self::set_byte(&mut self.src, addr == SRC_HI, value),
+            DST_HI | DST_LO => Self::set_byte(&mut self.dst, addr == DST_HI, value),
+            LEN_HI | LEN_LO => Self::set_byte(&mut self.len, addr == LEN_HI, value),
+            CTRL if value & CTRL_START != 0 => {
+                self.ctrl = value;
+                self.status = if self.len == 0 {
+                    STATUS_DONE
+                } else {
+                    STATUS_BUSY
+                };
+            }
+            _ => {}
+        }
+    }
+}
@@ -62,6 +62,9 @@ pub struct GPU {
     tty_vram: Box<[u8; registers::TTY_CELLS + 2]>,
     bitmap_vram: Box<[u8; (registers::BITMAP_WIDTH * registers::BITMAP_HEIGHT) / 8]>,
     redraw: bool,
+    /// Keep a copy of the characters written to the TTY for [`GPU::take_tty_output`].
+    pub capture_tty: bool,
+    tty_output: Vec<u8>,
 }
 
 impl Default for GPU {
@@ -71,6 +74,8 @@ impl Default for GPU {
             tty_vram: empty_memory(),
             bitmap_vram: empty_memory(),
             redraw: false,
+            capture_tty: false,
+            tty_output: Vec::new(),
         }
     }
 }
@@ -81,6 +86,15 @@ impl GPU {
         &self.tty_vram[registers::VRAM_TTY_START..registers::VRAM_TTY_END]
     }
 
+    /// Column and row the next TTY character is written to.
+    #[must_use]
+    pub fn tty_cursor(&self) -> (u8, u8) {
+        (
+            self.tty_vram[registers::VRAM_CURSOR_X],
+            self.tty_vram[registers::VRAM_CURSOR_Y],
+        )
+    }
+
     #[must_use]
     pub fn bitmap_buffer(&self) -> &[u8] {
         &self.bitmap_vram[0..(registers::BITMAP_WIDTH * registers::BITMAP_HEIGHT) / 8]
@@ -91,6 +105,12 @@ impl GPU {
         self.mode
     }
 
+    /// Take the characters written to the TTY since the last call, including
+    /// newlines and backspaces. Empty unless [`GPU::capture_tty`] is set.
+    pub fn take_tty_output(&mut self) -> Vec<u8> {
+        std::mem::take(&mut self.tty_output)
+    }
+
     pub fn redraw(&mut self) -> bool {
         if self.redraw {
             self.redraw = false;
@@ -117,6 +137,9 @@ impl Device for GPU {
             }
             registers::GPU_REG_TTY if self.mode == Mode::Tty => {
                 self.redraw = true;
+                if self.capture_tty {
+                    self.tty_output.push(value);
+                }
                 let (mut cursor_x, mut cursor_y) = (
                     self.tty_vram[registers::VRAM_CURSOR_X],
                     self.tty_vram[registers::VRAM_CURSOR_Y],
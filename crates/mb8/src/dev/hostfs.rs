@@ -0,0 +1,322 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use super::Device;
+
+pub mod registers {
+    /// Command (write-only), run as soon as it is written.
+    pub const CMD: u16 = 0x00;
+    /// Result of the last command (read-only).
+    pub const STATUS: u16 = 0x01;
+    /// File handle: set by the open commands, used by read, write and close.
+    pub const HANDLE: u16 = 0x02;
+    /// Bytes to read or write, then the bytes transferred. `LIST` sets it to
+    /// the length of the name.
+    pub const LEN: u16 = 0x03;
+    /// Directory entry for `LIST`.
+    pub const INDEX: u16 = 0x04;
+    /// Size of the file in bytes, big-endian over four registers, set by the
+    /// open commands, `WRITE` and `LIST`. Saturates at `0xFFFF_FFFF`.
+    pub const SIZE: u16 = 0x05;
+    /// Names for the open commands and `LIST`, data for read and write.
+    pub const BUFFER_START: u16 = 0x80;
+    pub const BUFFER_END: u16 = 0xFF;
+
+    pub const CMD_NOP: u8 = 0x00;
+    /// Open the file named in the buffer for reading.
+    pub const CMD_OPEN_READ: u8 = 0x01;
+    /// Open the file named in the buffer for writing, creating or truncating it.
+    pub const CMD_OPEN_WRITE: u8 = 0x02;
+    /// Read up to `LEN` bytes from `HANDLE` into the buffer. `LEN` is 0 at the
+    /// end of the file.
+    pub const CMD_READ: u8 = 0x03;
+    /// Write `LEN` bytes of the buffer to `HANDLE`.
+    pub const CMD_WRITE: u8 = 0x04;
+    pub const CMD_CLOSE: u8 = 0x05;
+    /// Put the name of file `INDEX` in the buffer. Files are sorted by name.
+    pub const CMD_LIST: u8 = 0x06;
+
+    pub const STATUS_OK: u8 = 0x00;
+    /// No such file, or `INDEX` is past the last file.
+    pub const STATUS_NOT_FOUND: u8 = 0x01;
+    /// The name is empty, too long, starts with `.` or holds a path separator,
+    /// a `:` or a non-printable character.
+    pub const STATUS_INVALID_NAME: u8 = 0x02;
+    /// `HANDLE` is not an open file, or not open for this command.
+    pub const STATUS_BAD_HANDLE: u8 = 0x03;
+    /// Every handle is in use.
+    pub const STATUS_NO_HANDLES: u8 = 0x04;
+    /// The host failed the operation.
+    pub const STATUS_IO_ERROR: u8 = 0x05;
+    /// `CMD` got an unknown command.
+    pub const STATUS_INVALID_COMMAND: u8 = 0x06;
+    /// The host shares no directory.
+    pub const STATUS_DISABLED: u8 = 0x07;
+}
+
+use registers::{
+    BUFFER_END, BUFFER_START, CMD, CMD_CLOSE, CMD_LIST, CMD_NOP, CMD_OPEN_READ, CMD_OPEN_WRITE,
+    CMD_READ, CMD_WRITE, HANDLE, INDEX, LEN, SIZE, STATUS, STATUS_BAD_HANDLE, STATUS_DISABLED,
+    STATUS_INVALID_COMMAND, STATUS_INVALID_NAME, STATUS_IO_ERROR, STATUS_NOT_FOUND,
+    STATUS_NO_HANDLES, STATUS_OK,
+};
+
+/// Size of the buffer, and so of a chunk or a name.
+pub const BUFFER_SIZE: usize = 128;
+/// Files open at once.
+pub const MAX_FILES: usize = 4;
+
+#[derive(Debug)]
+struct OpenFile {
+    file: File,
+    write: bool,
+}
+
+/// Gives the guest the regular files of one host directory. Names are flat, so
+/// subdirectories and files outside the directory cannot be reached, and
+/// symbolic links are only followed to files inside it.
+#[derive(Debug)]
+pub struct HostFs {
+    /// Canonical path of the shared directory.
+    root: Option<PathBuf>,
+    files: [Option<OpenFile>; MAX_FILES],
+    buffer: [u8; BUFFER_SIZE],
+    status: u8,
+    handle: u8,
+    len: u8,
+    index: u8,
+    size: u32,
+}
+
+impl Default for HostFs {
+    fn default() -> Self {
+        HostFs {
+            root: None,
+            files: Default::default(),
+            buffer: [0; BUFFER_SIZE],
+            status: STATUS_OK,
+            handle: 0,
+            len: 0,
+            index: 0,
+            size: 0,
+        }
+    }
+}
+
+impl HostFs {
+    /// Share `dir` with the guest, closing the files open in the previous one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if `dir` is not a directory.
+    pub fn set_root(&mut self, dir: &Path) -> io::Result<()> {
+        let root = dir.canonicalize()?;
+        if !root.is_dir() {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "not a directory",
+            ));
+        }
+        self.root = Some(root);
+        self.files = Default::default();
+        Ok(())
+    }
+
+    /// The shared directory.
+    #[must_use]
+    pub fn root(&self) -> Option<&Path> {
+        self.root.as_deref()
+    }
+
+    fn run(&mut self, command: u8) -> Result<(), u8> {
+        match command {
+            CMD_NOP => Ok(()),
+            CMD_OPEN_READ | CMD_OPEN_WRITE => self.open(command == CMD_OPEN_WRITE),
+            CMD_READ => self.read_chunk(),
+            CMD_WRITE => self.write_chunk(),
+            CMD_CLOSE => {
+                open_file(&mut self.files, self.handle)?;
+                self.files[usize::from(self.handle)] = None;
+                Ok(())
+            }
+            CMD_LIST => self.list(),
+            _ => Err(STATUS_INVALID_COMMAND),
+        }
+    }
+
+    fn root_dir(&self) -> Result<&Path, u8> {
+        self.root.as_deref().ok_or(STATUS_DISABLED)
+    }
+
+    /// The name in the buffer, up to the first zero byte.
+    fn name(&self) -> Result<String, u8> {
+        let end = self
+            .buffer
+            .iter()
+            .position(|&byte| byte == 0)
+            .unwrap_or(BUFFER_SIZE);
+        let name = &self.buffer[..end];
+        if !valid_name(name) {
+            return Err(STATUS_INVALID_NAME);
+        }
+        Ok(String::from_utf8_lossy(name).into_owned())
+    }
+
+    /// Path of file `name`, if it stays inside the shared directory.
+    fn path(&self, name: &str) -> Result<PathBuf, u8> {
+        let root = self.root_dir()?;
+        // Whatever the host's path syntax, `name` must be one plain component
+        // naming an entry of the directory, whether it exists yet or not.
+        let mut components = Path::new(name).components();
+        if !matches!(
+            (components.next(), components.next()),
+            (Some(Component::Normal(_)), None)
+        ) {
+            return Err(STATUS_INVALID_NAME);
+        }
+        let path = root.join(name);
+        if path.parent() != Some(root) {
+            return Err(STATUS_INVALID_NAME);
+        }
+        if fs::symlink_metadata(&path).is_ok() {
+            let target = path.canonicalize().map_err(|_| STATUS_NOT_FOUND)?;
+            if target.parent() != Some(root) || !target.is_file() {
+                return Err(STATUS_NOT_FOUND);
+            }
+        }
+        Ok(path)
+    }
+
+    fn open(&mut self, write: bool) -> Result<(), u8> {
+        self.root_dir()?;
+        let path = self.path(&self.name()?)?;
+        let slot = self
+            .files
+            .iter()
+            .position(Option::is_none)
+            .ok_or(STATUS_NO_HANDLES)?;
+        let file = if write {
+            OpenOptions::new()
+                .write(true)
+                .create(true)
+                .truncate(true)
+                .open(&path)
+        } else {
+            File::open(&path)
+        }
+        .map_err(|err| match err.kind() {
+            io::ErrorKind::NotFound => STATUS_NOT_FOUND,
+            _ => STATUS_IO_ERROR,
+        })?;
+        self.size = file_size(&file);
+        self.files[slot] = Some(OpenFile { file, write });
+        self.handle = slot as u8;
+        Ok(())
+    }
+
+    fn read_chunk(&mut self) -> Result<(), u8> {
+        let len = usize::from(self.len).min(BUFFER_SIZE);
+        self.len = 0;
+        let open = open_file(&mut self.files, self.handle)?;
+        if open.write {
+            return Err(STATUS_BAD_HANDLE);
+        }
+        let read = open
+            .file
+            .read(&mut self.buffer[..len])
+            .map_err(|_| STATUS_IO_ERROR)?;
+        self.len = read as u8;
+        Ok(())
+    }
+
+    fn write_chunk(&mut self) -> Result<(), u8> {
+        let len = usize::from(self.len).min(BUFFER_SIZE);
+        let open = open_file(&mut self.files, self.handle)?;
+        if !open.write {
+            return Err(STATUS_BAD_HANDLE);
+        }
+        open.file
+            .write_all(&self.buffer[..len])
+            .map_err(|_| STATUS_IO_ERROR)?;
+        self.size = file_size(&open.file);
+        Ok(())
+    }
+
+    fn list(&mut self) -> Result<(), u8> {
+        let root = self.root_dir()?;
+        let mut names: Vec<String> = fs::read_dir(root)
+            .map_err(|_| STATUS_IO_ERROR)?
+            .filter_map(Result::ok)
+            .filter_map(|entry| entry.file_name().into_string().ok())
+            .filter(|name| valid_name(name.as_bytes()) && self.path(name).is_ok())
+            .collect();
+        names.sort();
+        let name = names.get(usize::from(self.index)).ok_or(STATUS_NOT_FOUND)?;
+        let size = fs::metadata(root.join(name)).map_or(0, |meta| meta.len());
+        self.size = u32::try_from(size).unwrap_or(u32::MAX);
+        self.len = name.len() as u8;
+        self.buffer.fill(0);
+        self.buffer[..name.len()].copy_from_slice(name.as_bytes());
+        Ok(())
+    }
+}
+
+/// Whether the guest may use `name`: 1 to 127 printable ASCII characters, no
+/// path separator or drive colon and no leading `.`, which also rules out `.`
+/// and `..`.
+fn valid_name(name: &[u8]) -> bool {
+    !name.is_empty()
+        && name.len() < BUFFER_SIZE
+        && name[0] != b'.'
+        && name
+            .iter()
+            .all(|&byte| (0x20..0x7F).contains(&byte) && !b"/\\:".contains(&byte))
+}
+
+/// The file open under `handle`.
+fn open_file(files: &mut [Option<OpenFile>], handle: u8) -> Result<&mut OpenFile, u8> {
+    files
+        .get_mut(usize::from(handle))
+        .and_then(Option::as_mut)
+        .ok_or(STATUS_BAD_HANDLE)
+}
+
+fn file_size(file: &File) -> u32 {
+    file.metadata()
+        .map_or(0, |meta| u32::try_from(meta.len()).unwrap_or(u32::MAX))
+}
+
+impl Device for HostFs {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            STATUS => self.status,
+            HANDLE => self.handle,
+            LEN => self.len,
+            INDEX => self.index,
+            BUFFER_START..=BUFFER_END => self.buffer[usize::from(addr - BUFFER_START)],
+            _ if (SIZE..SIZE + 4).contains(&addr) => {
+                self.size.to_be_bytes()[usize::from(addr - SIZE)]
+            }
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        match addr {
+            CMD => {
+                self.status = match self.run(value) {
+                    Ok(()) => STATUS_OK,
+                    Err(status) => status,
+                };
+            }
+            HANDLE => self.handle = value,
+            LEN => self.len = value,
+            INDEX => self.index = value,
+            BUFFER_START..=BUFFER_END => self.buffer[usize::from(addr - BUFFER_START)] = value,
+            _ => {}
+        }
+    }
+}
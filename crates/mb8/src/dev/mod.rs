@@ -1,10 +1,17 @@
+pub mod bank;
 pub mod bus;
+pub mod debug;
 pub mod disk;
+pub mod dma;
 pub mod gpu;
+pub mod hostfs;
 pub mod keyboard;
 pub mod ram;
 pub mod rand;
 pub mod rom;
+pub mod rtc;
+pub mod sound;
+pub mod uart;
 pub mod utils;
 
 pub trait Device {
@@ -0,0 +1,127 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::Device;
+
+pub mod registers {
+    /// Control (write-only): writing `CTRL_LATCH` captures the current time.
+    pub const CTRL: u16 = 0x00;
+    pub const YEAR_HI: u16 = 0x01;
+    pub const YEAR_LO: u16 = 0x02;
+    /// 1 to 12.
+    pub const MONTH: u16 = 0x03;
+    /// 1 to 31.
+    pub const DAY: u16 = 0x04;
+    pub const HOUR: u16 = 0x05;
+    pub const MINUTE: u16 = 0x06;
+    pub const SECOND: u16 = 0x07;
+
+    pub const CTRL_LATCH: u8 = 0x01;
+}
+
+use registers::{CTRL, CTRL_LATCH, SECOND, YEAR_HI};
+
+/// Where the clock takes the time from.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum TimeSource {
+    /// The host clock.
+    #[default]
+    Host,
+    /// A fixed time in seconds since the Unix epoch, for deterministic runs.
+    Fixed(u64),
+}
+
+/// Calendar date and time in UTC.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct DateTime {
+    pub year: u16,
+    pub month: u8,
+    pub day: u8,
+    pub hour: u8,
+    pub minute: u8,
+    pub second: u8,
+}
+
+impl DateTime {
+    /// Convert seconds since the Unix epoch. Years past 65535 saturate.
+    #[must_use]
+    #[allow(clippy::cast_possible_truncation, clippy::cast_possible_wrap)]
+    pub fn from_unix(seconds: u64) -> Self {
+        let days = (seconds / 86_400) as i64;
+        let time = seconds % 86_400;
+
+        // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm).
+        let z = days + 719_468;
+        let era = z / 146_097;
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = doy - (153 * mp + 2) / 5 + 1;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 };
+        let year = yoe + era * 400 + i64::from(month <= 2);
+
+        Self {
+            year: u16::try_from(year).unwrap_or(u16::MAX),
+            month: month as u8,
+            day: day as u8,
+            hour: (time / 3600) as u8,
+            minute: (time / 60 % 60) as u8,
+            second: (time % 60) as u8,
+        }
+    }
+
+    fn to_registers(self) -> [u8; 7] {
+        let [year_hi, year_lo] = self.year.to_be_bytes();
+        [
+            year_hi,
+            year_lo,
+            self.month,
+            self.day,
+            self.hour,
+            self.minute,
+            self.second,
+        ]
+    }
+}
+
+/// Real-time clock. The time registers hold the value captured by the last latch,
+/// so a program reads a consistent time even if a second passes in between.
+#[derive(Debug, Default)]
+pub struct Rtc {
+    pub source: TimeSource,
+    latched: [u8; 7],
+}
+
+impl Rtc {
+    /// Current time of the source.
+    #[must_use]
+    pub fn now(&self) -> DateTime {
+        let seconds = match self.source {
+            TimeSource::Host => SystemTime::now()
+                .duration_since(UNIX_EPOCH)
+                .map_or(0, |elapsed| elapsed.as_secs()),
+            TimeSource::Fixed(seconds) => seconds,
+        };
+        DateTime::from_unix(seconds)
+    }
+
+    /// Capture the current time into the registers.
+    pub fn latch(&mut self) {
+        self.latched = self.now().to_registers();
+    }
+}
+
+impl Device for Rtc {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            YEAR_HI..=SECOND => self.latched[usize::from(addr - YEAR_HI)],
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr == CTRL && value & CTRL_LATCH != 0 {
+            self.latch();
+        }
+    }
+}
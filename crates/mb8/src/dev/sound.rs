@@ -0,0 +1,182 @@
+use mb8_isa::CPU_FREQUENCY;
+
+use super::Device;
+
+pub mod registers {
+    /// Registers of channel `n` start at `n * CHANNEL_SIZE`.
+    pub const CHANNEL_SIZE: u16 = 4;
+    /// Frequency in Hz, high and low byte. For the noise channel this is the rate
+    /// the noise generator is clocked at.
+    pub const FREQ_HI: u16 = 0x00;
+    pub const FREQ_LO: u16 = 0x01;
+    /// Volume, 0 (silent) to 15. Writing it restarts the envelope.
+    pub const VOLUME: u16 = 0x02;
+    /// Envelope: 0 holds the volume, `n` lowers it by one every `n` 64ths of a second.
+    pub const ENVELOPE: u16 = 0x03;
+
+    /// Channels 0 to 2 are square waves, channel 3 is noise.
+    pub const CHANNELS: usize = 4;
+    pub const NOISE_CHANNEL: usize = 3;
+    pub const MAX_VOLUME: u8 = 15;
+
+    /// Rate samples are rendered at.
+    pub const SAMPLE_RATE: u32 = 22_050;
+}
+
+use registers::{
+    CHANNELS, CHANNEL_SIZE, ENVELOPE, FREQ_HI, FREQ_LO, MAX_VOLUME, NOISE_CHANNEL, SAMPLE_RATE,
+    VOLUME,
+};
+
+/// Samples per envelope step of length 1.
+const ENVELOPE_UNIT: u32 = SAMPLE_RATE / 64;
+
+#[derive(Debug, Clone, Copy)]
+struct Channel {
+    frequency: u16,
+    envelope: u8,
+    /// Current volume after the envelope.
+    level: u8,
+    /// Samples until the envelope lowers the level.
+    countdown: u32,
+    /// Position in the waveform period, scaled by the sample rate.
+    phase: u32,
+    /// Noise shift register.
+    lfsr: u16,
+}
+
+impl Default for Channel {
+    fn default() -> Self {
+        Self {
+            frequency: 0,
+            envelope: 0,
+            level: 0,
+            countdown: 0,
+            phase: 0,
+            lfsr: 1,
+        }
+    }
+}
+
+impl Channel {
+    fn trigger(&mut self, volume: u8) {
+        self.level = volume.min(MAX_VOLUME);
+        self.countdown = u32::from(self.envelope) * ENVELOPE_UNIT;
+    }
+
+    /// Output of the channel for the next sample, from `-level` to `level`.
+    fn sample(&mut self, noise: bool) -> i32 {
+        if self.level == 0 || self.frequency == 0 {
+            return 0;
+        }
+        let high = if noise {
+            // Clock the 15-bit LFSR once for every period that elapsed.
+            self.phase += u32::from(self.frequency);
+            while self.phase >= SAMPLE_RATE {
+                self.phase -= SAMPLE_RATE;
+                let bit = (self.lfsr ^ (self.lfsr >> 1)) & 1;
+                self.lfsr = (self.lfsr >> 1) | (bit << 14);
+            }
+            self.lfsr & 1 != 0
+        } else {
+            let high = self.phase < SAMPLE_RATE / 2;
+            self.phase = (self.phase + u32::from(self.frequency)) % SAMPLE_RATE;
+            high
+        };
+        let level = i32::from(self.level);
+
+        if self.envelope != 0 {
+            self.countdown = self.countdown.saturating_sub(1);
+            if self.countdown == 0 {
+                self.level -= 1;
+                self.countdown = u32::from(self.envelope) * ENVELOPE_UNIT;
+            }
+        }
+
+        if high {
+            level
+        } else {
+            -level
+        }
+    }
+}
+
+/// Programmable sound generator: three square wave channels and one noise
+/// channel. Samples are rendered as the CPU spends cycles and kept while
+/// [`Sound::recording`] is set.
+#[derive(Debug, Default)]
+pub struct Sound {
+    channels: [Channel; CHANNELS],
+    /// Cycles not yet turned into a sample, scaled by the sample rate.
+    remainder: u64,
+    pub recording: bool,
+    samples: Vec<i16>,
+}
+
+impl Sound {
+    /// Render the samples that fall into `cycles` CPU cycles.
+    pub fn tick(&mut self, cycles: u64) {
+        self.remainder += cycles * u64::from(SAMPLE_RATE);
+        while self.remainder >= CPU_FREQUENCY {
+            self.remainder -= CPU_FREQUENCY;
+            let sample = self.mix();
+            if self.recording {
+                self.samples.push(sample);
+            }
+        }
+    }
+
+    /// Take the recorded samples.
+    pub fn take_samples(&mut self) -> Vec<i16> {
+        std::mem::take(&mut self.samples)
+    }
+
+    #[allow(clippy::cast_possible_truncation)]
+    fn mix(&mut self) -> i16 {
+        let sum: i32 = self
+            .channels
+            .iter_mut()
+            .enumerate()
+            .map(|(i, channel)| channel.sample(i == NOISE_CHANNEL))
+            .sum();
+        // Full volume on every channel uses the whole 16-bit range.
+        let scale = i32::from(i16::MAX) / (CHANNELS as i32 * i32::from(MAX_VOLUME));
+        (sum * scale) as i16
+    }
+
+    fn register(addr: u16) -> Option<(usize, u16)> {
+        let channel = usize::from(addr / CHANNEL_SIZE);
+        (channel < CHANNELS).then_some((channel, addr % CHANNEL_SIZE))
+    }
+}
+
+impl Device for Sound {
+    fn read(&mut self, addr: u16) -> u8 {
+        let Some((channel, register)) = Self::register(addr) else {
+            return 0;
+        };
+        let channel = &self.channels[channel];
+        match register {
+            FREQ_HI => channel.frequency.to_be_bytes()[0],
+            FREQ_LO => channel.frequency.to_be_bytes()[1],
+            VOLUME => channel.level,
+            ENVELOPE => channel.envelope,
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        let Some((channel, register)) = Self::register(addr) else {
+            return;
+        };
+        let channel = &mut self.channels[channel];
+        let [hi, lo] = channel.frequency.to_be_bytes();
+        match register {
+            FREQ_HI => channel.frequency = u16::from_be_bytes([value, lo]),
+            FREQ_LO => channel.frequency = u16::from_be_bytes([hi, value]),
+            VOLUME => channel.trigger(value),
+            ENVELOPE => channel.envelope = value,
+            _ => {}
+        }
+    }
+}
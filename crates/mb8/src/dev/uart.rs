@@ -0,0 +1,76 @@
+use std::collections::VecDeque;
+
+use super::Device;
+
+pub mod registers {
+    /// Status (read-only).
+    pub const STATUS: u16 = 0x00;
+    /// Data: reading pops the RX FIFO, writing pushes to the TX FIFO.
+    pub const DATA: u16 = 0x01;
+
+    /// `STATUS`: the RX FIFO holds a byte.
+    pub const STATUS_RX_READY: u8 = 0x01;
+    /// `STATUS`: the TX FIFO is full; further writes are dropped.
+    pub const STATUS_TX_FULL: u8 = 0x02;
+
+    /// Capacity of each FIFO in bytes.
+    pub const FIFO_SIZE: usize = 16;
+}
+
+use registers::{DATA, FIFO_SIZE, STATUS, STATUS_RX_READY, STATUS_TX_FULL};
+
+/// Serial port. The host feeds the RX FIFO with [`Uart::receive`] and drains the
+/// TX FIFO with [`Uart::transmit`].
+#[derive(Debug, Default)]
+pub struct Uart {
+    rx: VecDeque<u8>,
+    tx: VecDeque<u8>,
+}
+
+impl Uart {
+    /// Free space in the RX FIFO.
+    #[must_use]
+    pub fn rx_space(&self) -> usize {
+        FIFO_SIZE - self.rx.len()
+    }
+
+    /// Queue a byte from the host. Returns `false` if the RX FIFO is full.
+    pub fn receive(&mut self, byte: u8) -> bool {
+        if self.rx.len() < FIFO_SIZE {
+            self.rx.push_back(byte);
+            true
+        } else {
+            false
+        }
+    }
+
+    /// Take every byte the program has written.
+    pub fn transmit(&mut self) -> Vec<u8> {
+        self.tx.drain(..).collect()
+    }
+}
+
+impl Device for Uart {
+    fn read(&mut self, addr: u16) -> u8 {
+        match addr {
+            STATUS => {
+                let mut status = 0;
+                if !self.rx.is_empty() {
+                    status |= STATUS_RX_READY;
+                }
+                if self.tx.len() >= FIFO_SIZE {
+                    status |= STATUS_TX_FULL;
+                }
+                status
+            }
+            DATA => self.rx.pop_front().unwrap_or(0),
+            _ => 0,
+        }
+    }
+
+    fn write(&mut self, addr: u16, value: u8) {
+        if addr == DATA && self.tx.len() < FIFO_SIZE {
+            self.tx.push_back(value);
+        }
+    }
+}
@@ -0,0 +1,687 @@
+//! The file system on the disk, in both layouts (see `docs/filesystem.md`).
+//!
+//! Version 1 is what the ROM kernel reads: block 0 is the directory, 16
+//! entries of 16 bytes, each a used flag, the start block, the size in blocks
+//! and a name of up to 8 bytes padded with zeros.
+//!
+//! Version 2 starts with a superblock, keeps a bitmap of the used blocks and a
+//! directory of 32 entries with byte sizes and names of up to 22 characters.
+//! It manages up to 65535 blocks, so it fills larger disks.
+//! The free functions read either layout from a disk image; [`FileSystem`]
+//! owns an image and changes it.
+
+pub use crate::dev::disk::BLOCK_SIZE;
+use crate::dev::disk::{DISK_SIZE, MAX_BLOCKS};
+
+/// Layout of version 1.
+pub mod v1 {
+    /// Blocks the 8-bit start blocks reach. Larger disks are only used this far.
+    pub const BLOCKS: usize = 256;
+    pub const DIR_ENTRIES: usize = 16;
+    pub const DIR_ENTRY_SIZE: usize = 16;
+    pub const NAME_LEN: usize = 8;
+}
+
+/// Layout of version 2. Numbers are big-endian.
+pub mod v2 {
+    /// Superblock fields in block 0.
+    pub const MAGIC: &[u8; 4] = b"MB8F";
+    pub const VERSION: u8 = 2;
+    pub const SB_MAGIC: usize = 0;
+    pub const SB_VERSION: usize = 4;
+    /// Blocks on the disk, 16 bits.
+    pub const SB_BLOCKS: usize = 5;
+    /// First block of the free-block bitmap, 16 bits. It takes as many blocks
+    /// as one bit per block needs.
+    pub const SB_BITMAP: usize = 7;
+    /// First block of the directory and its length in blocks, 16 bits each.
+    pub const SB_DIR: usize = 9;
+    pub const SB_DIR_BLOCKS: usize = 11;
+
+    /// Most blocks the superblock counts.
+    pub const MAX_BLOCKS: usize = u16::MAX as usize;
+
+    /// Where `format` puts the bitmap, followed by the directory.
+    pub const BITMAP_BLOCK: usize = 1;
+    pub const DIR_BLOCKS: usize = 4;
+
+    /// Directory entry fields.
+    pub const DIR_ENTRY_SIZE: usize = 32;
+    pub const DIR_ENTRIES: usize = DIR_BLOCKS * super::BLOCK_SIZE / DIR_ENTRY_SIZE;
+    pub const ENTRY_FLAG: usize = 0;
+    pub const ENTRY_START: usize = 1;
+    pub const ENTRY_BLOCKS: usize = 3;
+    /// Size in bytes, 32 bits.
+    pub const ENTRY_SIZE: usize = 5;
+    /// Name, zero padded and always zero terminated.
+    pub const ENTRY_NAME: usize = 9;
+    pub const NAME_LEN: usize = DIR_ENTRY_SIZE - ENTRY_NAME - 1;
+}
+
+/// Layout of a disk image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Version {
+    V1,
+    V2,
+}
+
+impl Version {
+    /// Longest file name.
+    #[must_use]
+    pub fn name_len(self) -> usize {
+        match self {
+            Self::V1 => v1::NAME_LEN,
+            Self::V2 => v2::NAME_LEN,
+        }
+    }
+
+    #[must_use]
+    pub fn dir_entries(self) -> usize {
+        match self {
+            Self::V1 => v1::DIR_ENTRIES,
+            Self::V2 => v2::DIR_ENTRIES,
+        }
+    }
+}
+
+/// Layout of `image`: version 2 if block 0 starts with the magic, version 1 otherwise.
+#[must_use]
+pub fn version(image: &[u8]) -> Version {
+    if image.starts_with(v2::MAGIC) {
+        Version::V2
+    } else {
+        Version::V1
+    }
+}
+
+/// Blocks the free-block bitmap of a disk of `blocks` blocks takes.
+#[must_use]
+pub fn bitmap_blocks(blocks: usize) -> usize {
+    blocks.div_ceil(BLOCK_SIZE * 8)
+}
+
+/// A used directory entry.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Entry {
+    /// Position in the directory.
+    pub index: usize,
+    pub start: u16,
+    /// Size in blocks.
+    pub blocks: u16,
+    /// Size in bytes. Version 1 has no byte sizes, so this is whole blocks.
+    pub size: u32,
+    pub name: String,
+}
+
+impl Entry {
+    /// Blocks the file takes.
+    #[must_use]
+    pub fn block_range(&self) -> std::ops::Range<usize> {
+        let start = usize::from(self.start);
+        start..start + usize::from(self.blocks)
+    }
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> u16 {
+    u16::from_be_bytes([bytes[offset], bytes[offset + 1]])
+}
+
+fn write_u16(bytes: &mut [u8], offset: usize, value: u16) {
+    bytes[offset..offset + 2].copy_from_slice(&value.to_be_bytes());
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+    u32::from_be_bytes([
+        bytes[offset],
+        bytes[offset + 1],
+        bytes[offset + 2],
+        bytes[offset + 3],
+    ])
+}
+
+/// Bytes of the directory of `image`, as the superblock describes it.
+fn directory(image: &[u8]) -> &[u8] {
+    match version(image) {
+        Version::V1 => &image[..v1::DIR_ENTRIES * v1::DIR_ENTRY_SIZE],
+        Version::V2 => {
+            let start = usize::from(read_u16(image, v2::SB_DIR)) * BLOCK_SIZE;
+            let len = usize::from(read_u16(image, v2::SB_DIR_BLOCKS)) * BLOCK_SIZE;
+            image
+                .get(start..start + len)
+                .or_else(|| image.get(start..))
+                .unwrap_or_default()
+        }
+    }
+}
+
+/// Block `index` of the directory of `image`. Version 1 has one directory block.
+#[must_use]
+pub fn directory_block(image: &[u8], index: usize) -> Option<&[u8]> {
+    directory(image)
+        .chunks(BLOCK_SIZE)
+        .nth(index)
+        .filter(|block| block.len() == BLOCK_SIZE)
+}
+
+/// Raw directory entries of `image`, used or not.
+fn raw_entries(image: &[u8]) -> std::slice::Chunks<'_, u8> {
+    let size = match version(image) {
+        Version::V1 => v1::DIR_ENTRY_SIZE,
+        Version::V2 => v2::DIR_ENTRY_SIZE,
+    };
+    directory(image).chunks(size)
+}
+
+/// Stored name bytes of a raw entry.
+fn raw_name(version: Version, raw: &[u8]) -> &[u8] {
+    let name = match version {
+        Version::V1 => &raw[3..],
+        Version::V2 => &raw[v2::ENTRY_NAME..],
+    };
+    &name[..version.name_len().min(name.len())]
+}
+
+fn parse_entry(version: Version, index: usize, raw: &[u8]) -> Option<Entry> {
+    if raw.len() < 3 || raw[0] == 0 {
+        return None;
+    }
+    let name = raw_name(version, raw);
+    let len = name.iter().position(|&c| c == 0).unwrap_or(name.len());
+    let name = String::from_utf8_lossy(&name[..len]).into_owned();
+    Some(match version {
+        Version::V1 => Entry {
+            index,
+            start: u16::from(raw[1]),
+            blocks: u16::from(raw[2]),
+            size: u32::from(raw[2]) * BLOCK_SIZE as u32,
+            name,
+        },
+        Version::V2 => Entry {
+            index,
+            start: read_u16(raw, v2::ENTRY_START),
+            blocks: read_u16(raw, v2::ENTRY_BLOCKS),
+            size: read_u32(raw, v2::ENTRY_SIZE),
+            name,
+        },
+    })
+}
+
+/// The used directory entries of `image`.
+#[must_use]
+pub fn entries(image: &[u8]) -> Vec<Entry> {
+    let version = version(image);
+    raw_entries(image)
+        .enumerate()
+        .filter_map(|(index, raw)| parse_entry(version, index, raw))
+        .collect()
+}
+
+/// The entry of the file `name` on `image`.
+#[must_use]
+pub fn find(image: &[u8], name: &[u8]) -> Option<Entry> {
+    entries(image)
+        .into_iter()
+        .find(|entry| entry.name.as_bytes() == name)
+}
+
+/// Contents of the file `name` on `image`: its exact size in version 2, every
+/// block in version 1.
+#[must_use]
+pub fn read(image: &[u8], name: &[u8]) -> Option<Vec<u8>> {
+    let entry = find(image, name)?;
+    let start = usize::from(entry.start) * BLOCK_SIZE;
+    let len = (entry.size as usize).min(usize::from(entry.blocks) * BLOCK_SIZE);
+    image.get(start..start + len).map(<[u8]>::to_vec)
+}
+
+/// A disk image with the file system on it.
+#[derive(Debug, Clone)]
+pub struct FileSystem {
+    image: Vec<u8>,
+}
+
+impl Default for FileSystem {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FileSystem {
+    /// An empty version 1 disk, the layout the ROM kernel reads.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::format(Version::V1)
+    }
+
+    /// An empty 64 KiB disk with the layout `version`.
+    #[must_use]
+    pub fn format(version: Version) -> Self {
+        Self::formatted(version, DISK_SIZE / BLOCK_SIZE)
+    }
+
+    /// An empty disk of `blocks` blocks with the layout `version`.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the disk is too small for the file system metadata
+    /// or larger than 16-bit block numbers reach.
+    pub fn with_blocks(version: Version, blocks: usize) -> Result<Self, String> {
+        let min = match version {
+            Version::V1 => 2,
+            Version::V2 => v2::BITMAP_BLOCK + bitmap_blocks(blocks) + v2::DIR_BLOCKS + 1,
+        };
+        if !(min..=MAX_BLOCKS).contains(&blocks) {
+            return Err(format!("A disk must have {min} to {MAX_BLOCKS} blocks"));
+        }
+        Ok(Self::formatted(version, blocks))
+    }
+
+    fn formatted(version: Version, blocks: usize) -> Self {
+        let mut fs = Self::from_image(vec![0; blocks * BLOCK_SIZE]);
+        if version == Version::V2 {
+            let blocks = fs.blocks_on_image().min(v2::MAX_BLOCKS);
+            let dir = v2::BITMAP_BLOCK + bitmap_blocks(blocks);
+            let image = &mut fs.image;
+            image[v2::SB_MAGIC..v2::SB_MAGIC + 4].copy_from_slice(v2::MAGIC);
+            image[v2::SB_VERSION] = v2::VERSION;
+            let fields = [
+                (v2::SB_BLOCKS, blocks),
+                (v2::SB_BITMAP, v2::BITMAP_BLOCK),
+                (v2::SB_DIR, dir),
+                (v2::SB_DIR_BLOCKS, v2::DIR_BLOCKS),
+            ];
+            for (offset, value) in fields {
+                write_u16(image, offset, value as u16);
+            }
+            for block in 0..dir + v2::DIR_BLOCKS {
+                fs.set_used(block, true);
+            }
+        }
+        fs
+    }
+
+    /// The file system on `image`, whose size should be a multiple of [`BLOCK_SIZE`].
+    #[must_use]
+    pub fn from_image(image: Vec<u8>) -> Self {
+        Self { image }
+    }
+
+    #[must_use]
+    pub fn image(&self) -> &[u8] {
+        &self.image
+    }
+
+    #[must_use]
+    pub fn into_image(self) -> Vec<u8> {
+        self.image
+    }
+
+    /// Blocks the file system manages: at most 256 in version 1 and what the
+    /// superblock counts in version 2, but never more than the image holds.
+    #[must_use]
+    pub fn blocks(&self) -> usize {
+        let blocks = match self.version() {
+            Version::V1 => v1::BLOCKS,
+            Version::V2 => usize::from(read_u16(&self.image, v2::SB_BLOCKS)),
+        };
+        blocks.min(self.blocks_on_image())
+    }
+
+    fn blocks_on_image(&self) -> usize {
+        self.image.len() / BLOCK_SIZE
+    }
+
+    #[must_use]
+    pub fn version(&self) -> Version {
+        version(&self.image)
+    }
+
+    /// The used directory entries.
+    #[must_use]
+    pub fn entries(&self) -> Vec<Entry> {
+        entries(&self.image)
+    }
+
+    #[must_use]
+    pub fn find(&self, name: &str) -> Option<Entry> {
+        find(&self.image, name.as_bytes())
+    }
+
+    /// Contents of the file `name`, see [`read`].
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if there is no such file or it runs past the disk.
+    pub fn read(&self, name: &str) -> Result<Vec<u8>, String> {
+        self.find(name).ok_or(format!("{name}: no such file"))?;
+        read(&self.image, name.as_bytes()).ok_or(format!("{name}: runs past the end of the disk"))
+    }
+
+    /// Blocks neither the file system nor a file uses.
+    #[must_use]
+    pub fn free_blocks(&self) -> usize {
+        self.used_blocks().iter().filter(|&&used| !used).count()
+    }
+
+    /// Store `data` as the file `name` in the first free directory entry and
+    /// the first free run of blocks large enough for it.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the name is invalid or taken, or the directory or
+    /// disk is full.
+    pub fn add(&mut self, name: &str, data: &[u8]) -> Result<Entry, String> {
+        let version = self.version();
+        let name_len = version.name_len();
+        if name.is_empty() || name.len() > name_len {
+            return Err(format!(
+                "File name {name} must be 1 to {name_len} characters long"
+            ));
+        }
+        if name.bytes().any(|c| !c.is_ascii_graphic()) {
+            return Err(format!("File name {name} must be printable ASCII"));
+        }
+        if self.find(name).is_some() {
+            return Err(format!("{name} already exists"));
+        }
+        let index = raw_entries(&self.image)
+            .position(|raw| raw[0] == 0)
+            .ok_or("The directory is full")?;
+        let blocks = data.len().div_ceil(BLOCK_SIZE).max(1);
+        let max_blocks = match version {
+            Version::V1 => usize::from(u8::MAX),
+            Version::V2 => usize::from(u16::MAX),
+        };
+        let start = self
+            .free_run(blocks)
+            .filter(|_| blocks <= max_blocks)
+            .ok_or(format!("No room for {name} ({blocks} blocks) on the disk"))?;
+
+        let offset = start * BLOCK_SIZE;
+        self.image[offset..offset + blocks * BLOCK_SIZE].fill(0);
+        self.image[offset..offset + data.len()].copy_from_slice(data);
+
+        let entry = Entry {
+            index,
+            start: start as u16,
+            blocks: blocks as u16,
+            size: data.len() as u32,
+            name: name.to_string(),
+        };
+        let raw = self.raw_entry_mut(index);
+        raw.fill(0);
+        raw[0] = 1;
+        match version {
+            Version::V1 => {
+                raw[1] = start as u8;
+                raw[2] = blocks as u8;
+                raw[3..3 + name.len()].copy_from_slice(name.as_bytes());
+            }
+            Version::V2 => {
+                write_u16(raw, v2::ENTRY_START, entry.start);
+                write_u16(raw, v2::ENTRY_BLOCKS, entry.blocks);
+                raw[v2::ENTRY_SIZE..v2::ENTRY_SIZE + 4].copy_from_slice(&entry.size.to_be_bytes());
+                raw[v2::ENTRY_NAME..v2::ENTRY_NAME + name.len()].copy_from_slice(name.as_bytes());
+                for block in start..start + blocks {
+                    self.set_used(block, true);
+                }
+            }
+        }
+        Ok(entry)
+    }
+
+    /// Delete the file `name`. Its blocks are free for the next file.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if there is no such file.
+    pub fn remove(&mut self, name: &str) -> Result<Entry, String> {
+        let entry = self.find(name).ok_or(format!("{name}: no such file"))?;
+        self.raw_entry_mut(entry.index).fill(0);
+        if self.version() == Version::V2 {
+            for block in entry.block_range() {
+                self.set_used(block, false);
+            }
+        }
+        Ok(entry)
+    }
+
+    /// The same files on a version 2 disk. Version 1 stores no byte sizes, so
+    /// executables are cut to the size their header gives and other files keep
+    /// every block.
+    ///
+    /// # Errors
+    ///
+    /// Returns a message if the disk is not version 1 or a file cannot be read.
+    pub fn migrate(&self) -> Result<Self, String> {
+        if self.version() != Version::V1 {
+            return Err("The disk is not version 1".to_string());
+        }
+        let mut fs = Self::formatted(Version::V2, self.blocks_on_image());
+        for entry in self.entries() {
+            let mut data = self.read(&entry.name)?;
+            if let Ok(header) = mb8_isa::exec::ExecHeader::parse(&data) {
+                data.truncate(mb8_isa::exec::EXEC_HEADER_SIZE + usize::from(header.code_size));
+            }
+            fs.add(&entry.name, &data)?;
+        }
+        Ok(fs)
+    }
+
+    /// Problems with the structure: a bad superblock, flags and names, duplicate
+    /// names, files that overlap the metadata, each other or the end of the disk,
+    /// and blocks the bitmap gets wrong.
+    #[must_use]
+    pub fn check(&self) -> Vec<String> {
+        let version = self.version();
+        let mut problems = Vec::new();
+        if version == Version::V2 {
+            problems.extend(self.check_superblock());
+            if !problems.is_empty() {
+                return problems;
+            }
+        }
+        for (index, raw) in raw_entries(&self.image).enumerate() {
+            if raw[0] > 1 {
+                problems.push(format!("entry {index}: invalid flag {:#04x}", raw[0]));
+            }
+        }
+
+        let entries = self.entries();
+        let reserved = self.reserved_blocks();
+        let blocks = self.blocks();
+        let mut owner: Vec<Option<&Entry>> = vec![None; blocks];
+        for entry in &entries {
+            let name = &entry.name;
+            let index = entry.index;
+            let raw = raw_entries(&self.image)
+                .nth(index)
+                .map(|raw| raw_name(version, raw))
+                .unwrap_or_default();
+            let len = raw.iter().position(|&c| c == 0).unwrap_or(raw.len());
+            if len == 0 || raw[..len].iter().any(|c| !c.is_ascii_graphic()) {
+                problems.push(format!("entry {index}: invalid name {name:?}"));
+            }
+            if raw[len..].iter().any(|&c| c != 0) {
+                problems.push(format!("entry {index}: {name}: name is not zero padded"));
+            }
+            if let Some(first) = entries
+                .iter()
+                .find(|other| other.name == *name && other.index < index)
+            {
+                problems.push(format!(
+                    "entry {index}: {name}: same name as entry {}",
+                    first.index
+                ));
+            }
+
+            let range = entry.block_range();
+            if entry.blocks == 0 {
+                problems.push(format!("entry {index}: {name}: has no blocks"));
+            }
+            if version == Version::V2 && entry.size as usize > range.len() * BLOCK_SIZE {
+                problems.push(format!(
+                    "entry {index}: {name}: {} bytes do not fit in {} blocks",
+                    entry.size, entry.blocks
+                ));
+            }
+            if range.start < reserved {
+                problems.push(format!(
+                    "entry {index}: {name}: starts in the file system metadata"
+                ));
+            }
+            if range.end > blocks {
+                problems.push(format!(
+                    "entry {index}: {name}: blocks {}..{} run past the end of the disk",
+                    range.start, range.end
+                ));
+            }
+            let blocks = owner
+                .iter_mut()
+                .enumerate()
+                .take(range.end.min(blocks))
+                .skip(range.start.max(reserved));
+            for (block, owner) in blocks {
+                if let Some(other) = owner {
+                    problems.push(format!(
+                        "entry {index}: {name}: block {block} is also used by {}",
+                        other.name
+                    ));
+                    break;
+                }
+                *owner = Some(entry);
+            }
+        }
+
+        if version == Version::V2 {
+            for (block, owner) in owner.iter().enumerate().skip(reserved) {
+                match (owner, self.is_used(block)) {
+                    (Some(entry), false) => problems.push(format!(
+                        "block {block}: used by {} but free in the bitmap",
+                        entry.name
+                    )),
+                    (None, true) => {
+                        problems.push(format!("block {block}: used in the bitmap but by no file"));
+                    }
+                    _ => {}
+                }
+            }
+            if let Some(block) = (0..reserved).find(|&block| !self.is_used(block)) {
+                problems.push(format!("block {block}: metadata is free in the bitmap"));
+            }
+        }
+        problems
+    }
+
+    fn check_superblock(&self) -> Vec<String> {
+        let image = &self.image;
+        let mut problems = Vec::new();
+        if image[v2::SB_VERSION] != v2::VERSION {
+            problems.push(format!(
+                "superblock: unknown version {}",
+                image[v2::SB_VERSION]
+            ));
+        }
+        let blocks = usize::from(read_u16(image, v2::SB_BLOCKS));
+        let on_image = self.blocks_on_image().min(v2::MAX_BLOCKS);
+        if blocks != on_image {
+            problems.push(format!(
+                "superblock: {blocks} blocks, the disk has {on_image}"
+            ));
+        }
+        let bitmap = usize::from(read_u16(image, v2::SB_BITMAP));
+        let dir = usize::from(read_u16(image, v2::SB_DIR));
+        let dir_blocks = usize::from(read_u16(image, v2::SB_DIR_BLOCKS));
+        let bitmap_end = bitmap + bitmap_blocks(blocks);
+        if bitmap == 0 || bitmap_end > on_image {
+            problems.push(format!("superblock: bitmap at invalid block {bitmap}"));
+        }
+        if dir == 0
+            || dir_blocks == 0
+            || dir + dir_blocks > on_image
+            || (dir < bitmap_end && bitmap < dir + dir_blocks)
+        {
+            problems.push(format!(
+                "superblock: directory at invalid blocks {dir}..{}",
+                dir + dir_blocks
+            ));
+        }
+        problems
+    }
+
+    /// Blocks before the first one files may use.
+    fn reserved_blocks(&self) -> usize {
+        match self.version() {
+            Version::V1 => 1,
+            Version::V2 => {
+                let image = &self.image;
+                let bitmap = usize::from(read_u16(image, v2::SB_BITMAP))
+                    + bitmap_blocks(usize::from(read_u16(image, v2::SB_BLOCKS)));
+                let dir = usize::from(read_u16(image, v2::SB_DIR))
+                    + usize::from(read_u16(image, v2::SB_DIR_BLOCKS));
+                bitmap.max(dir)
+            }
+        }
+    }
+
+    /// Whether each block is taken: from the bitmap in version 2, from the
+    /// directory in version 1.
+    fn used_blocks(&self) -> Vec<bool> {
+        match self.version() {
+            Version::V1 => {
+                let blocks = self.blocks();
+                let mut used = vec![false; blocks];
+                if let Some(directory) = used.first_mut() {
+                    *directory = true;
+                }
+                for entry in self.entries() {
+                    let range = entry.block_range();
+                    used[range.start.min(blocks)..range.end.min(blocks)].fill(true);
+                }
+                used
+            }
+            Version::V2 => (0..self.blocks())
+                .map(|block| self.is_used(block))
+                .collect(),
+        }
+    }
+
+    /// First block of a run of `blocks` free blocks.
+    fn free_run(&self, blocks: usize) -> Option<usize> {
+        let used = self.used_blocks();
+        (1..=used.len().saturating_sub(blocks))
+            .find(|&start| !used[start..start + blocks].contains(&true))
+    }
+
+    fn bitmap_bit(&self, block: usize) -> (usize, u8) {
+        let bitmap = usize::from(read_u16(&self.image, v2::SB_BITMAP));
+        (bitmap * BLOCK_SIZE + block / 8, 0x80 >> (block % 8))
+    }
+
+    fn is_used(&self, block: usize) -> bool {
+        let (byte, bit) = self.bitmap_bit(block);
+        self.image.get(byte).is_some_and(|&value| value & bit != 0)
+    }
+
+    fn set_used(&mut self, block: usize, used: bool) {
+        let (byte, bit) = self.bitmap_bit(block);
+        if let Some(value) = self.image.get_mut(byte) {
+            if used {
+                *value |= bit;
+            } else {
+                *value &= !bit;
+            }
+        }
+    }
+
+    fn raw_entry_mut(&mut self, index: usize) -> &mut [u8] {
+        let (offset, size) = match self.version() {
+            Version::V1 => (0, v1::DIR_ENTRY_SIZE),
+            Version::V2 => (
+                usize::from(read_u16(&self.image, v2::SB_DIR)) * BLOCK_SIZE,
+                v2::DIR_ENTRY_SIZE,
+            ),
+        };
+        &mut self.image[offset + index * size..offset + (index + 1) * size]
+    }
+}
@@ -0,0 +1,275 @@
+//! High-level emulation (HLE) of the kernel syscalls.
+//!
+//! With [`VirtualMachine::hle`] set, a call to the syscall entry is served in Rust
+//! against the devices on the bus instead of running the ROM kernel, so a user
+//! program can be loaded with [`VirtualMachine::load_program`] and run directly.
+//! The syscalls behave as documented in `docs/syscalls.md`.
+
+use mb8_isa::{
+    exec::{ExecHeader, EXEC_HEADER_SIZE, EXEC_MAGIC, EXEC_VERSION},
+    registers::Register,
+};
+
+use crate::{
+    dev::{
+        gpu::registers::GPU_MODE_TTY, rtc::registers::CTRL_LATCH, uart::registers::STATUS_RX_READY,
+    },
+    fs::{self, Entry, Version, BLOCK_SIZE},
+    vm::VirtualMachine,
+};
+
+/// Address user programs `CALL` to enter the kernel.
+pub const SYSCALL_ENTRY: u16 = 0xE500;
+
+pub mod syscalls {
+    pub const SYS_GPU_MODE: u8 = 0x01;
+    pub const SYS_WRITE: u8 = 0x02;
+    pub const SYS_WRITELN: u8 = 0x03;
+    pub const SYS_WAIT_FOR_KEY: u8 = 0x04;
+    pub const SYS_READ_KEY: u8 = 0x05;
+    pub const SYS_DISK_SET_BLOCK: u8 = 0x06;
+    pub const SYS_DISK_READ_BLOCK: u8 = 0x07;
+    pub const SYS_DISK_WRITE_BLOCK: u8 = 0x08;
+    pub const SYS_FS_LIST: u8 = 0x09;
+    pub const SYS_FS_FIND: u8 = 0x0A;
+    pub const SYS_FS_READ: u8 = 0x0B;
+    pub const SYS_FS_WRITE: u8 = 0x0C;
+    pub const SYS_FS_DELETE: u8 = 0x0D;
+    pub const SYS_EXEC: u8 = 0x0E;
+    pub const SYS_EXIT: u8 = 0x0F;
+    pub const SYS_RAND: u8 = 0x10;
+    pub const SYS_TIME: u8 = 0x11;
+}
+
+use syscalls::{
+    SYS_DISK_READ_BLOCK, SYS_DISK_SET_BLOCK, SYS_DISK_WRITE_BLOCK, SYS_EXEC, SYS_EXIT, SYS_FS_FIND,
+    SYS_FS_LIST, SYS_FS_READ, SYS_GPU_MODE, SYS_RAND, SYS_READ_KEY, SYS_TIME, SYS_WAIT_FOR_KEY,
+    SYS_WRITE, SYS_WRITELN,
+};
+
+const GPU_MODE: u16 = 0xF000;
+const TTY_DATA: u16 = 0xF001;
+const KEYBOARD_STATUS: u16 = 0xF101;
+const KEYBOARD_DATA: u16 = 0xF102;
+const DISK_BLOCK: u16 = 0xF200;
+const DISK_BLOCK_HIGH: u16 = 0xF310;
+const DISK_CMD: u16 = 0xF201;
+const DISK_STATUS: u16 = 0xF312;
+const RAND_DATA: u16 = 0xF400;
+const UART_STATUS: u16 = 0xF700;
+const UART_DATA: u16 = 0xF701;
+const RTC_CTRL: u16 = 0xF800;
+
+const MAX_STRING: usize = 255;
+
+/// Cycles of one pass of the kernel's `sys_wait_for_key` loop.
+const WAIT_FOR_KEY_CYCLES: u64 = 18;
+
+/// State of the syscall emulation.
+#[derive(Debug, Default)]
+pub struct Hle {
+    /// Every character written with `SYS_WRITE` and `SYS_WRITELN`.
+    pub output: Vec<u8>,
+    /// Set when the program ended with `SYS_EXIT`.
+    pub exited: bool,
+}
+
+impl Hle {
+    /// Terminal output as text.
+    #[must_use]
+    pub fn output(&self) -> String {
+        String::from_utf8_lossy(&self.output).into_owned()
+    }
+}
+
+impl VirtualMachine {
+    /// Put the GPU in TTY mode, as the kernel does at boot.
+    pub(crate) fn hle_boot(&mut self) {
+        self.devices.write(GPU_MODE, GPU_MODE_TTY);
+    }
+
+    /// Serve the syscall selected by `R0` and return to the caller.
+    pub(crate) fn hle_syscall(&mut self) {
+        let r1 = self.registers.read(Register::R1);
+        let r2 = self.registers.read(Register::R2);
+        match self.registers.read(Register::R0) {
+            SYS_GPU_MODE => self.devices.write(GPU_MODE, r1),
+            SYS_WRITE => self.hle_write(&[r1]),
+            SYS_WRITELN => {
+                let text = self.read_string(u16::from_be_bytes([r1, r2]));
+                self.hle_write(&text);
+            }
+            // Stay on the syscall entry until a key or serial byte arrives, spending
+            // the time the kernel's polling loop would.
+            SYS_WAIT_FOR_KEY
+                if self.devices.read(KEYBOARD_STATUS) == 0
+                    && self.devices.read(UART_STATUS) & STATUS_RX_READY == 0 =>
+            {
+                self.cycles += WAIT_FOR_KEY_CYCLES;
+                self.devices.tick(WAIT_FOR_KEY_CYCLES);
+                return;
+            }
+            SYS_READ_KEY => {
+                let key = if self.devices.read(KEYBOARD_STATUS) == 0 {
+                    self.devices.read(UART_DATA)
+                } else {
+                    self.devices.read(KEYBOARD_DATA)
+                };
+                self.registers.write(Register::R0, key);
+            }
+            SYS_DISK_SET_BLOCK => {
+                self.devices.write(DISK_BLOCK, r1);
+                self.devices.write(DISK_BLOCK_HIGH, 0);
+            }
+            id @ (SYS_DISK_READ_BLOCK | SYS_DISK_WRITE_BLOCK) => {
+                let command = if id == SYS_DISK_READ_BLOCK {
+                    0x01
+                } else {
+                    0x02
+                };
+                self.devices.write(DISK_CMD, command);
+                // Syscalls return once the disk is done, whatever its latency.
+                self.devices.disk().finish();
+                let status = self.devices.read(DISK_STATUS);
+                self.registers.write(Register::R0, status);
+            }
+            SYS_FS_LIST => {
+                let block = self.directory_block(self.registers.read(Register::R3));
+                self.write_memory(u16::from_be_bytes([r1, r2]), &block);
+            }
+            SYS_FS_FIND => match self.find_file(u16::from_be_bytes([r1, r2])) {
+                // `R1` and `R2` hold a byte each, so a file starting past block
+                // 0xFF or longer than 0xFF blocks is out of reach.
+                Some(entry) => match (u8::try_from(entry.start), u8::try_from(entry.blocks)) {
+                    (Ok(start), Ok(blocks)) => {
+                        self.registers.write(Register::R0, 0);
+                        self.registers.write(Register::R1, start);
+                        self.registers.write(Register::R2, blocks);
+                        if fs::version(self.devices.disk().dump()) == Version::V2 {
+                            // At most 0xFF blocks, so the size fits in 16 bits.
+                            let size = (entry.size as usize).min(usize::from(blocks) * BLOCK_SIZE);
+                            let [hi, lo] = (size as u16).to_be_bytes();
+                            self.registers.write(Register::R3, hi);
+                            self.registers.write(Register::R4, lo);
+                        }
+                    }
+                    _ => self.registers.write(Register::R0, 2),
+                },
+                None => self.registers.write(Register::R0, 1),
+            },
+            SYS_FS_READ => match self.read_file(u16::from_be_bytes([r1, r2])) {
+                Some(data) => {
+                    let r3 = self.registers.read(Register::R3);
+                    let r4 = self.registers.read(Register::R4);
+                    self.write_memory(u16::from_be_bytes([r3, r4]), &data);
+                    self.registers.write(Register::R0, 0);
+                }
+                None => self.registers.write(Register::R0, 1),
+            },
+            SYS_EXEC => {
+                match self.hle_exec(u16::from_be_bytes([r1, r2])) {
+                    // The program replaces the caller: drop the return address.
+                    Ok(entry) => {
+                        self.drop_return_address();
+                        self.program_counter = entry;
+                        return;
+                    }
+                    Err(status) => self.registers.write(Register::R0, status),
+                }
+            }
+            SYS_EXIT => {
+                if let Some(hle) = &mut self.hle {
+                    hle.exited = true;
+                }
+                self.halted = true;
+                self.exit_status = Some(0);
+                return;
+            }
+            SYS_RAND => {
+                let value = self.devices.read(RAND_DATA);
+                self.registers.write(Register::R0, value);
+            }
+            SYS_TIME => {
+                self.devices.write(RTC_CTRL, CTRL_LATCH);
+                let time: Vec<u8> = (1..=7).map(|i| self.devices.read(RTC_CTRL + i)).collect();
+                self.write_memory(u16::from_be_bytes([r1, r2]), &time);
+            }
+            // A key is waiting, or SYS_FS_WRITE, SYS_FS_DELETE and unknown calls that do
+            // nothing, as in the kernel.
+            _ => {}
+        }
+        self.ret();
+    }
+
+    fn hle_write(&mut self, text: &[u8]) {
+        for &byte in text {
+            self.devices.write(TTY_DATA, byte);
+            self.devices.write(UART_DATA, byte);
+        }
+        if let Some(hle) = &mut self.hle {
+            hle.output.extend_from_slice(text);
+        }
+    }
+
+    /// Load the executable `name` from disk, returning its entry point or the
+    /// `SYS_EXEC` error status.
+    fn hle_exec(&mut self, name: u16) -> Result<u16, u8> {
+        let data = self.read_file(name).ok_or(1)?;
+        if !data.starts_with(EXEC_MAGIC) || data.get(4) != Some(&EXEC_VERSION) {
+            return Err(2);
+        }
+        let header = ExecHeader::parse(&data).map_err(|_| 3)?;
+        let code = data
+            .get(EXEC_HEADER_SIZE..EXEC_HEADER_SIZE + usize::from(header.code_size))
+            .ok_or(2)?;
+        self.load_image(&header, code);
+        Ok(header.entry)
+    }
+
+    /// Directory entry of the file named by the string at `name`.
+    fn find_file(&mut self, name: u16) -> Option<Entry> {
+        let name = self.read_string(name);
+        fs::find(self.devices.disk().dump(), &name)
+    }
+
+    /// Contents of the file named by the string at `name`, see [`fs::read`].
+    fn read_file(&mut self, name: u16) -> Option<Vec<u8>> {
+        let name = self.read_string(name);
+        fs::read(self.devices.disk().dump(), &name)
+    }
+
+    /// Block `index` of the directory, or zeros past its end. Version 1 disks
+    /// have one directory block, which is listed whatever `index` is.
+    fn directory_block(&mut self, index: u8) -> Vec<u8> {
+        let image = self.devices.disk().dump();
+        let index = match fs::version(image) {
+            Version::V1 => 0,
+            Version::V2 => usize::from(index),
+        };
+        fs::directory_block(image, index).map_or_else(|| vec![0; BLOCK_SIZE], <[u8]>::to_vec)
+    }
+
+    /// Zero-terminated string at `addr`, at most 255 characters long.
+    fn read_string(&mut self, mut addr: u16) -> Vec<u8> {
+        let mut text = Vec::new();
+        while text.len() < MAX_STRING {
+            let byte = self.devices.read(addr);
+            if byte == 0 {
+                break;
+            }
+            text.push(byte);
+            addr = addr.wrapping_add(1);
+        }
+        text
+    }
+
+    fn drop_return_address(&mut self) {
+        let stack_pointer = u16::from_be_bytes([
+            self.registers.read(Register::SPH),
+            self.registers.read(Register::SPL),
+        ]);
+        let [sp_hi, sp_lo] = stack_pointer.saturating_add(2).min(0xBFFF).to_be_bytes();
+        self.registers.write(Register::SPH, sp_hi);
+        self.registers.write(Register::SPL, sp_lo);
+    }
+}
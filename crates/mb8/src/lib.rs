@@ -1,4 +1,7 @@
 pub mod dev;
+pub mod fs;
+pub mod hle;
 pub mod ops;
+pub mod protection;
 pub mod registers;
 pub mod vm;
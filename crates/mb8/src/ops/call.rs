@@ -16,7 +16,7 @@ impl VirtualMachine {
         let addr = u16::from_be_bytes([hi, lo]);
 
         for byte in program_counter.to_le_bytes() {
-            self.devices.write(stack_pointer, byte);
+            self.store(stack_pointer, byte);
             stack_pointer -= 1;
 
             if stack_pointer as usize <= STACK_BOTTOM {
@@ -29,6 +29,7 @@ impl VirtualMachine {
         self.registers.write(Register::SPH, sp_hi);
         self.registers.write(Register::SPL, sp_lo);
         self.program_counter = addr;
+        self.enter_supervisor(addr);
     }
 }
 
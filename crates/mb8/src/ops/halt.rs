@@ -1,8 +1,11 @@
+use mb8_isa::registers::Register;
+
 use crate::vm::VirtualMachine;
 
 impl VirtualMachine {
     pub fn halt(&mut self) {
         self.halted = true;
+        self.exit_status = Some(self.registers.read(Register::R0));
     }
 }
 
@@ -20,4 +23,14 @@ mod tests {
         vm.execute(&Opcode::Halt);
         assert!(vm.halted);
     }
+
+    #[test]
+    fn records_exit_status() {
+        // R0 is the exit status of the machine
+        let mut vm = VirtualMachine::default();
+        assert_eq!(vm.exit_status, None);
+        vm.registers.write(Register::R0, 0x03);
+        vm.execute(&Opcode::Halt);
+        assert_eq!(vm.exit_status, Some(0x03));
+    }
 }
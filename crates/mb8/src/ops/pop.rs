@@ -14,7 +14,7 @@ impl VirtualMachine {
             return;
         }
         let [sp_hi, sp_lo] = stack_pointer.to_be_bytes();
-        let value = self.devices.read(stack_pointer);
+        let value = self.load(stack_pointer);
         self.registers.write(Register::SPH, sp_hi);
         self.registers.write(Register::SPL, sp_lo);
         self.registers.write(dst, value);
@@ -10,7 +10,7 @@ impl VirtualMachine {
         ]);
         let value = self.registers.read(src);
 
-        self.devices.write(stack_pointer, value);
+        self.store(stack_pointer, value);
 
         stack_pointer -= 1;
 
@@ -13,9 +13,9 @@ impl VirtualMachine {
             return;
         }
         stack_pointer += 1;
-        let hi = self.devices.read(stack_pointer);
+        let hi = self.load(stack_pointer);
         stack_pointer += 1;
-        let lo = self.devices.read(stack_pointer);
+        let lo = self.load(stack_pointer);
         let [sp_hi, sp_lo] = stack_pointer.to_be_bytes();
         self.registers.write(Register::SPH, sp_hi);
         self.registers.write(Register::SPL, sp_lo);
@@ -8,7 +8,7 @@ impl VirtualMachine {
         let addr_lo = self.registers.read(lo);
         let addr = u16::from_be_bytes([addr_hi, addr_lo]);
         let value = self.registers.read(src);
-        self.devices.write(addr, value);
+        self.store(addr, value);
     }
 }
 
@@ -0,0 +1,143 @@
+//! Supervisor/user privilege levels and memory protection.
+//!
+//! The CPU starts in supervisor mode. It drops to user mode when the kernel hands
+//! control to code outside the kernel ROM (returning from a syscall or starting a
+//! program with `SYS_EXEC`), and a `CALL` to the syscall entry switches back.
+//!
+//! With [`Protection::enabled`], user code that writes to ROM or a protected RAM
+//! range, touches MMIO, or executes kernel ROM other than the syscall entry raises
+//! a [`Fault`]: the access is dropped and the CPU enters supervisor mode at
+//! [`FAULT_VECTOR`], where the kernel ends the program.
+
+use std::ops::RangeInclusive;
+
+use crate::{hle::SYSCALL_ENTRY, vm::VirtualMachine};
+
+/// Kernel address the CPU jumps to when user code faults.
+pub const FAULT_VECTOR: u16 = 0xE080;
+
+const KERNEL_ROM: RangeInclusive<u16> = 0xE000..=0xEFFF;
+const MMIO: RangeInclusive<u16> = 0xF000..=0xFFFF;
+/// RAM owned by the kernel: its variables and the header of the running program.
+const KERNEL_RAM: RangeInclusive<u16> = 0x0000..=0x0FFF;
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Privilege {
+    #[default]
+    Supervisor,
+    User,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Execute,
+}
+
+/// A denied access by user code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Fault {
+    /// Address of the faulting instruction.
+    pub pc: u16,
+    pub addr: u16,
+    pub access: Access,
+}
+
+/// Memory protection settings and the last fault.
+#[derive(Debug)]
+pub struct Protection {
+    /// Check user accesses. Off by default, as user programs such as `hello` and
+    /// `pong` drive MMIO directly.
+    pub enabled: bool,
+    /// RAM ranges user code may not write.
+    pub ranges: Vec<RangeInclusive<u16>>,
+    /// The last fault raised.
+    pub fault: Option<Fault>,
+    pending: Option<(u16, Access)>,
+}
+
+impl Default for Protection {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            ranges: vec![KERNEL_RAM],
+            fault: None,
+            pending: None,
+        }
+    }
+}
+
+impl Protection {
+    /// Whether user code may perform `access` at `addr`.
+    #[must_use]
+    pub fn allows(&self, addr: u16, access: Access) -> bool {
+        if MMIO.contains(&addr) {
+            return false;
+        }
+        match access {
+            Access::Read => true,
+            Access::Write => {
+                !KERNEL_ROM.contains(&addr) && !self.ranges.iter().any(|r| r.contains(&addr))
+            }
+            Access::Execute => !KERNEL_ROM.contains(&addr) || addr == SYSCALL_ENTRY,
+        }
+    }
+}
+
+impl VirtualMachine {
+    /// Check an access by the current instruction, raising a fault if it is denied.
+    pub(crate) fn check_access(&mut self, addr: u16, access: Access) -> bool {
+        if self.privilege == Privilege::Supervisor
+            || !self.protection.enabled
+            || self.protection.allows(addr, access)
+        {
+            return true;
+        }
+        // Only the first fault of an instruction is reported.
+        self.protection.pending.get_or_insert((addr, access));
+        false
+    }
+
+    /// Read memory on behalf of the running program. Denied reads return 0.
+    pub(crate) fn load(&mut self, addr: u16) -> u8 {
+        if self.check_access(addr, Access::Read) {
+            self.devices.read(addr)
+        } else {
+            0
+        }
+    }
+
+    /// Write memory on behalf of the running program. Denied writes are dropped.
+    pub(crate) fn store(&mut self, addr: u16, value: u8) {
+        if self.check_access(addr, Access::Write) {
+            self.devices.write(addr, value);
+        }
+    }
+
+    /// Enter the kernel through the syscall entry.
+    pub(crate) fn enter_supervisor(&mut self, target: u16) {
+        if target == SYSCALL_ENTRY {
+            self.privilege = Privilege::Supervisor;
+        }
+    }
+
+    /// Deliver a fault raised by the instruction at `pc`, or drop to user mode once
+    /// the kernel has passed control outside its ROM.
+    pub(crate) fn update_privilege(&mut self, pc: u16) {
+        if let Some((addr, access)) = self.protection.pending.take() {
+            self.protection.fault = Some(Fault { pc, addr, access });
+            self.privilege = Privilege::Supervisor;
+            if self.hle.is_some() {
+                // There is no kernel to clean up after the program.
+                self.halted = true;
+            } else {
+                self.program_counter = FAULT_VECTOR;
+            }
+        } else if self.privilege == Privilege::Supervisor
+            && !KERNEL_ROM.contains(&self.program_counter)
+        {
+            self.privilege = Privilege::User;
+        }
+    }
+}
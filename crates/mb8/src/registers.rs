@@ -1,6 +1,6 @@
 use std::fmt::Display;
 
-use mb8_isa::{registers::Register, REGISTERS_COUNT};
+use mb8_isa::{encode::encode_register, registers::Register, REGISTERS_COUNT};
 
 /// API for accessing and manipulating the registers.
 #[derive(Debug)]
@@ -21,48 +21,13 @@ impl Default for Registers {
 impl Registers {
     /// Write a value to a register.
     pub fn write(&mut self, register: impl Into<Register>, value: u8) {
-        let register = register.into();
-        match register {
-            Register::R0 | Register::A => self.registers[0x0] = value,
-            Register::R1 => self.registers[0x1] = value,
-            Register::R2 => self.registers[0x2] = value,
-            Register::R3 => self.registers[0x3] = value,
-            Register::R4 => self.registers[0x4] = value,
-            Register::R5 => self.registers[0x5] = value,
-            Register::R6 => self.registers[0x6] = value,
-            Register::R7 => self.registers[0x7] = value,
-            Register::R8 => self.registers[0x8] = value,
-            Register::R9 | Register::IH => self.registers[0x9] = value,
-            Register::R10 | Register::IL => self.registers[0xA] = value,
-            Register::R11 | Register::FPH => self.registers[0xB] = value,
-            Register::R12 | Register::FPL => self.registers[0xC] = value,
-            Register::R13 | Register::SPH => self.registers[0xD] = value,
-            Register::R14 | Register::SPL => self.registers[0xE] = value,
-            Register::R15 | Register::F => self.registers[0xF] = value,
-        }
+        self.registers[encode_register(register.into()) as usize] = value;
     }
 
     /// Read a value from a register.
     #[must_use]
     pub fn read(&self, register: Register) -> u8 {
-        match register {
-            Register::R0 | Register::A => self.registers[0x0],
-            Register::R1 => self.registers[0x1],
-            Register::R2 => self.registers[0x2],
-            Register::R3 => self.registers[0x3],
-            Register::R4 => self.registers[0x4],
-            Register::R5 => self.registers[0x5],
-            Register::R6 => self.registers[0x6],
-            Register::R7 => self.registers[0x7],
-            Register::R8 => self.registers[0x8],
-            Register::R9 | Register::IH => self.registers[0x9],
-            Register::R10 | Register::IL => self.registers[0xA],
-            Register::R11 | Register::FPH => self.registers[0xB],
-            Register::R12 | Register::FPL => self.registers[0xC],
-            Register::R13 | Register::SPH => self.registers[0xD],
-            Register::R14 | Register::SPL => self.registers[0xE],
-            Register::R15 | Register::F => self.registers[0xF],
-        }
+        self.registers[encode_register(register) as usize]
     }
 }
 
@@ -1,6 +1,21 @@
-use mb8_isa::{decode::decode, opcodes::Opcode};
+use mb8_isa::{
+    decode::decode,
+    exec::{is_executable, ExecHeader, Executable, USER_BASE},
+    opcodes::Opcode,
+    table::spec,
+    RAM_SIZE, REGISTERS_COUNT, ROM_SIZE,
+};
 
-use crate::{dev::bus::Bus, registers::Registers};
+use crate::{
+    dev::bus::Bus,
+    hle::{Hle, SYSCALL_ENTRY},
+    protection::{Access, Privilege, Protection},
+    registers::Registers,
+};
+
+/// Bytes of a machine state before the bank state: the registers, the program
+/// counter, the cycle count, the privilege, halted and exit status, RAM and ROM.
+const STATE_HEADER: usize = REGISTERS_COUNT + 2 + 8 + 4 + RAM_SIZE + ROM_SIZE;
 
 /// MB8 Virtual Machine
 #[derive(Debug)]
@@ -8,7 +23,18 @@ pub struct VirtualMachine {
     pub devices: Bus,
     pub registers: Registers,
     pub halted: bool,
+    /// `R0` when a `HALT` instruction stopped the machine, or the status written to
+    /// the debug port's `EXIT`. `None` while running or if it stopped on an error.
+    pub exit_status: Option<u8>,
     pub program_counter: u16,
+    /// CPU cycles spent so far.
+    pub cycles: u64,
+    /// Serve syscalls in Rust instead of the ROM kernel when set.
+    pub hle: Option<Hle>,
+    pub privilege: Privilege,
+    pub protection: Protection,
+    /// Print every executed instruction and the registers to stdout.
+    pub trace: bool,
 }
 
 impl Default for VirtualMachine {
@@ -17,7 +43,13 @@ impl Default for VirtualMachine {
             devices: Bus::default(),
             registers: Registers::default(),
             halted: false,
+            exit_status: None,
             program_counter: 0xE000,
+            cycles: 0,
+            hle: None,
+            privilege: Privilege::default(),
+            protection: Protection::default(),
+            trace: false,
         }
     }
 }
@@ -56,6 +88,20 @@ impl VirtualMachine {
 
     pub fn step(&mut self) {
         let pc = self.program_counter;
+        if pc == SYSCALL_ENTRY && self.hle.is_some() {
+            self.hle_syscall();
+        } else if self.check_access(pc, Access::Execute) {
+            self.fetch_and_execute(pc);
+        }
+        self.update_privilege(pc);
+
+        if let Some(status) = self.devices.debug_port().take_exit() {
+            self.halted = true;
+            self.exit_status = Some(status);
+        }
+    }
+
+    fn fetch_and_execute(&mut self, pc: u16) {
         self.program_counter = pc.saturating_add(2);
 
         let hi = self.devices.read(pc);
@@ -66,12 +112,17 @@ impl VirtualMachine {
             return;
         };
 
-        println!("{pc:X}:\t({binary_instruction:?})");
-        println!("{opcode:?}");
-        println!("{}", self.registers);
-        // println!("=");
+        if self.trace {
+            println!("{pc:X}:\t({binary_instruction:?})");
+            println!("{opcode:?}");
+            println!("{}", self.registers);
+        }
 
         self.execute(&opcode);
+
+        let cycles = u64::from(spec(&opcode).cycles);
+        self.cycles += cycles;
+        self.devices.tick(cycles);
     }
 
     /// Execute a program.
@@ -86,6 +137,92 @@ impl VirtualMachine {
             self.devices.write((0xE000 + i) as u16, byte);
         }
     }
+
+    /// Load a user program and point the program counter at its entry. Executables
+    /// are placed as their header says, raw images are copied to `USER_BASE`.
+    /// In HLE mode the devices are also set up as the kernel would leave them.
+    ///
+    /// # Errors
+    /// Returns an error if `program` is an invalid executable.
+    pub fn load_program(&mut self, program: &[u8]) -> Result<(), String> {
+        if self.hle.is_some() {
+            self.hle_boot();
+        }
+        if is_executable(program) {
+            let exec = Executable::from_bytes(program)?;
+            self.load_image(&exec.header, &exec.code);
+            self.program_counter = exec.header.entry;
+        } else {
+            self.write_memory(USER_BASE, program);
+            self.program_counter = USER_BASE;
+        }
+        Ok(())
+    }
+
+    /// Copy `code` to the load address of `header` and zero the BSS after it.
+    pub(crate) fn load_image(&mut self, header: &ExecHeader, code: &[u8]) {
+        self.write_memory(header.load, code);
+        let bss = header.load.wrapping_add(header.code_size);
+        self.write_memory(bss, &vec![0; usize::from(header.bss_size)]);
+    }
+
+    pub(crate) fn write_memory(&mut self, addr: u16, data: &[u8]) {
+        for (i, &byte) in data.iter().enumerate() {
+            self.devices.write(addr.wrapping_add(i as u16), byte);
+        }
+    }
+
+    /// Serialize the CPU, RAM, ROM and the bank controller, see
+    /// [`Banks::save_state`](crate::dev::bank::Banks::save_state). The other
+    /// devices, the disk included, are not part of the state.
+    #[must_use]
+    pub fn save_state(&mut self) -> Vec<u8> {
+        let mut state = self.registers.registers.to_vec();
+        state.extend(self.program_counter.to_be_bytes());
+        state.extend(self.cycles.to_be_bytes());
+        state.push(u8::from(self.privilege == Privilege::User));
+        state.push(u8::from(self.halted));
+        state.push(u8::from(self.exit_status.is_some()));
+        state.push(self.exit_status.unwrap_or(0));
+        state.extend((0..RAM_SIZE).map(|addr| self.devices.read(addr as u16)));
+        state.extend((0xE000..0xE000 + ROM_SIZE).map(|addr| self.devices.read(addr as u16)));
+        state.extend(self.devices.banks().save_state());
+        state
+    }
+
+    /// Restore a state written by [`VirtualMachine::save_state`].
+    ///
+    /// # Errors
+    /// Returns an error if `state` is malformed or its bank state does not fit the
+    /// bank controller. The machine is left unchanged then.
+    pub fn load_state(&mut self, state: &[u8]) -> Result<(), String> {
+        if state.len() < STATE_HEADER {
+            return Err("machine state is truncated".to_string());
+        }
+        let (header, banks) = state.split_at(STATE_HEADER);
+        self.devices.banks().load_state(banks)?;
+
+        let (registers, rest) = header.split_at(REGISTERS_COUNT);
+        let (pc, rest) = rest.split_at(2);
+        let (cycles, rest) = rest.split_at(8);
+        let (flags, memory) = rest.split_at(4);
+        let (ram, rom) = memory.split_at(RAM_SIZE);
+        self.registers.registers.copy_from_slice(registers);
+        self.program_counter = u16::from_be_bytes([pc[0], pc[1]]);
+        let mut cycle_bytes = [0; 8];
+        cycle_bytes.copy_from_slice(cycles);
+        self.cycles = u64::from_be_bytes(cycle_bytes);
+        self.privilege = if flags[0] == 0 {
+            Privilege::Supervisor
+        } else {
+            Privilege::User
+        };
+        self.halted = flags[1] != 0;
+        self.exit_status = (flags[2] != 0).then_some(flags[3]);
+        self.write_memory(0x0000, ram);
+        self.write_memory(0xE000, rom);
+        Ok(())
+    }
 }
 
 #[cfg(test)]
@@ -0,0 +1,107 @@
+//! Runs every `kernel/tests/*.bin` ROM and reports what it told the debug port.
+//!
+//! A test passes if it reports no failed assertion and exits with status 0,
+//! through the port's `EXIT` or `HALT` with `R0` = 0. A test that never touches
+//! the port fails, as it cannot have checked anything.
+
+use std::{
+    panic::{self, AssertUnwindSafe},
+    path::{Path, PathBuf},
+    process::ExitCode,
+};
+
+use mb8::vm::VirtualMachine;
+
+/// Cycles a test may take.
+const MAX_CYCLES: u64 = 10_000_000;
+
+enum Outcome {
+    Passed(usize),
+    Failed(String),
+}
+
+fn run(rom: &[u8]) -> (Outcome, String) {
+    let mut vm = VirtualMachine::default();
+    vm.load_rom(rom);
+    let ran = panic::catch_unwind(AssertUnwindSafe(|| {
+        while !vm.halted && vm.cycles < MAX_CYCLES {
+            vm.step();
+        }
+    }));
+
+    let port = vm.devices.debug_port();
+    let log = String::from_utf8_lossy(port.log()).into_owned();
+    let assertions = port.assertions().to_vec();
+    let failed: Vec<String> = assertions
+        .iter()
+        .filter(|assertion| !assertion.passed)
+        .map(|assertion| assertion.id.to_string())
+        .collect();
+
+    let outcome = if ran.is_err() {
+        Outcome::Failed("the VM panicked".to_string())
+    } else if !vm.halted {
+        Outcome::Failed(format!("still running after {MAX_CYCLES} cycles"))
+    } else if !port.used() {
+        Outcome::Failed("never reported through the debug port".to_string())
+    } else if !failed.is_empty() {
+        Outcome::Failed(format!("assertions {} failed", failed.join(", ")))
+    } else if vm.exit_status != Some(0) {
+        let status = vm
+            .exit_status
+            .map_or("none".to_string(), |status| status.to_string());
+        Outcome::Failed(format!("exit status {status}"))
+    } else {
+        Outcome::Passed(assertions.len())
+    };
+    (outcome, log)
+}
+
+fn main() -> ExitCode {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../kernel/tests");
+    let mut roms: Vec<PathBuf> = std::fs::read_dir(&dir)
+        .map(|entries| entries.filter_map(Result::ok).map(|e| e.path()).collect())
+        .unwrap_or_default();
+    roms.retain(|path| path.extension().is_some_and(|ext| ext == "bin"));
+    roms.sort();
+
+    // Quiet the default hook; panics are reported as failures.
+    panic::set_hook(Box::new(|_| {}));
+
+    println!("\nrunning {} kernel tests", roms.len());
+    let (mut passed, mut failed) = (0, 0);
+    for path in &roms {
+        let name = path.file_stem().unwrap_or_default().to_string_lossy();
+        let Ok(rom) = std::fs::read(path) else {
+            println!("test {name} ... FAILED: cannot read {}", path.display());
+            failed += 1;
+            continue;
+        };
+        let (outcome, log) = run(&rom);
+        match outcome {
+            Outcome::Passed(assertions) => {
+                println!("test {name} ... ok ({assertions} assertions)");
+                passed += 1;
+            }
+            Outcome::Failed(reason) => {
+                println!("test {name} ... FAILED: {reason}");
+                failed += 1;
+            }
+        }
+        if !log.is_empty() {
+            println!("    log: {log:?}");
+        }
+    }
+
+    let result = if failed == 0 { "ok" } else { "FAILED" };
+    println!("\nkernel test result: {result}. {passed} passed; {failed} failed\n");
+    if roms.is_empty() {
+        println!("no kernel tests found, run `make tests`");
+        return ExitCode::FAILURE;
+    }
+    if failed == 0 {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
@@ -0,0 +1,103 @@
+use mb8::vm::VirtualMachine;
+use mb8_isa::registers::Register;
+
+const BANK: u16 = 0xF500;
+const RAM_BANKS: u16 = 0xF501;
+const CART_PAGES: u16 = 0xF502;
+
+#[test]
+fn test_ram_banks_switch_window() {
+    let mut vm = VirtualMachine::default();
+    assert_eq!(vm.devices.read(RAM_BANKS), 8);
+    assert_eq!(vm.devices.read(CART_PAGES), 0);
+
+    for bank in 0..8 {
+        vm.devices.write(BANK, bank);
+        vm.devices.write(0xC000, bank + 1);
+        vm.devices.write(0xDFFF, bank + 0x10);
+    }
+    for bank in 0..8 {
+        vm.devices.write(BANK, bank);
+        assert_eq!(vm.devices.read(BANK), bank);
+        assert_eq!(vm.devices.read(0xC000), bank + 1);
+        assert_eq!(vm.devices.read(0xDFFF), bank + 0x10);
+    }
+
+    // Unmapped banks read as 0xFF and ignore writes.
+    vm.devices.write(BANK, 8);
+    vm.devices.write(0xC000, 0x42);
+    assert_eq!(vm.devices.read(0xC000), 0xFF);
+}
+
+#[test]
+fn test_cartridge_pages_are_read_only() {
+    let mut vm = VirtualMachine::default();
+    let mut image = vec![0xAA; 0x2000];
+    image.extend([0xBB; 0x10]);
+    assert_eq!(vm.devices.banks().load_cartridge(&image), Ok(()));
+    assert_eq!(vm.devices.read(CART_PAGES), 2);
+
+    vm.devices.write(BANK, 0x81);
+    assert_eq!(vm.devices.read(0xC000), 0xBB);
+    assert_eq!(vm.devices.read(0xC010), 0xFF, "padding");
+    vm.devices.write(0xC000, 0x00);
+    assert_eq!(vm.devices.read(0xC000), 0xBB);
+
+    vm.devices.write(BANK, 0x80);
+    assert_eq!(vm.devices.read(0xDFFF), 0xAA);
+    vm.devices.write(BANK, 0x82);
+    assert_eq!(vm.devices.read(0xC000), 0xFF);
+
+    assert!(vm.devices.banks().load_cartridge(&[]).is_err());
+    assert!(vm
+        .devices
+        .banks()
+        .load_cartridge(&vec![0; 0x2000 * 128 + 1])
+        .is_err());
+}
+
+#[test]
+fn test_bank_state_roundtrip() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.write(BANK, 3);
+    vm.devices.write(0xC123, 0x55);
+    let state = vm.devices.banks().save_state();
+
+    let mut restored = VirtualMachine::default();
+    assert_eq!(restored.devices.banks().load_state(&state), Ok(()));
+    assert_eq!(restored.devices.read(BANK), 3);
+    assert_eq!(restored.devices.read(0xC123), 0x55);
+
+    assert!(restored.devices.banks().load_state(&state[..10]).is_err());
+}
+
+#[test]
+fn test_machine_state_roundtrip() {
+    let mut vm = VirtualMachine::default();
+    vm.registers.write(Register::R3, 0x33);
+    vm.program_counter = 0x1234;
+    vm.cycles = 0x0102_0304_0506;
+    vm.devices.write(0x0042, 0x42);
+    vm.devices.write(0xBFFF, 0x99);
+    vm.load_rom(&[0x01, 0x02]);
+    vm.devices.write(BANK, 5);
+    vm.devices.write(0xD000, 0x77);
+    let state = vm.save_state();
+
+    let mut restored = VirtualMachine::default();
+    assert_eq!(restored.load_state(&state), Ok(()));
+    assert_eq!(restored.registers.read(Register::R3), 0x33);
+    assert_eq!(restored.program_counter, 0x1234);
+    assert_eq!(restored.cycles, 0x0102_0304_0506);
+    assert_eq!(restored.devices.read(0x0042), 0x42);
+    assert_eq!(restored.devices.read(0xBFFF), 0x99);
+    assert_eq!(restored.devices.read(0xE001), 0x02);
+    assert_eq!(restored.devices.read(BANK), 5);
+    assert_eq!(restored.devices.read(0xD000), 0x77);
+    assert_eq!(restored.save_state(), state);
+
+    let mut untouched = VirtualMachine::default();
+    assert!(untouched.load_state(&state[..100]).is_err());
+    assert!(untouched.load_state(&state[..state.len() - 1]).is_err());
+    assert_eq!(untouched.program_counter, 0xE000);
+}
@@ -0,0 +1,72 @@
+use mb8::{dev::debug::Assertion, vm::VirtualMachine};
+use mb8_isa::{encode::encode, opcodes::Opcode, registers::Register};
+
+#[test]
+fn test_debug_port_registers() {
+    let mut vm = VirtualMachine::default();
+    assert!(!vm.devices.debug_port().used());
+
+    vm.devices.write(0xFA00, b'x');
+    vm.devices.write(0xFA01, 0x0F);
+    vm.devices.write(0xFA02, 7);
+    vm.devices.write(0xFA03, 8);
+    assert_eq!(vm.devices.read(0xFA00), 0);
+
+    let port = vm.devices.debug_port();
+    assert!(port.used());
+    assert_eq!(port.log(), b"x0F");
+    assert_eq!(
+        port.assertions(),
+        [
+            Assertion {
+                id: 7,
+                passed: true
+            },
+            Assertion {
+                id: 8,
+                passed: false
+            }
+        ]
+    );
+    assert_eq!(port.take_exit(), None);
+}
+
+#[test]
+fn test_debug_port_exit() {
+    let ldi = |dst, value| Opcode::Ldi { dst, value };
+    let program: Vec<u8> = [
+        ldi(Register::R0, 0x05),
+        ldi(Register::R6, 0xFA),
+        ldi(Register::R7, 0x04),
+        Opcode::St {
+            src: Register::R0,
+            hi: Register::R6,
+            lo: Register::R7,
+        },
+        ldi(Register::R0, 0x00),
+        Opcode::Halt,
+    ]
+    .iter()
+    .flat_map(|op| encode(op).to_be_bytes())
+    .collect();
+    let mut vm = VirtualMachine::default();
+    vm.load_rom(&program);
+    vm.run();
+
+    assert_eq!(vm.exit_status, Some(0x05));
+    assert_eq!(vm.program_counter, 0xE008, "stopped right after the write");
+}
+
+#[test]
+fn test_debug_port_macros() {
+    let bin = include_bytes!("../../../kernel/tests/test_debug_port.bin");
+    let mut vm = VirtualMachine::default();
+    vm.load_rom(bin);
+    vm.run();
+
+    assert_eq!(vm.exit_status, Some(0));
+    let port = vm.devices.debug_port();
+    assert_eq!(port.log(), b"ok 2A");
+    assert!(port.assertions().iter().all(|assertion| assertion.passed));
+    assert_eq!(port.assertions().len(), 2);
+}
@@ -0,0 +1,142 @@
+use mb8::dev::{
+    disk::{
+        registers::{
+            DISK_BLOCK, DISK_BLOCK_HIGH, DISK_BUFFER_START, DISK_CMD, DISK_CMD_FLUSH,
+            DISK_CMD_READ, DISK_CMD_WRITE, DISK_DRIVE, DISK_STATUS, DISK_STATUS_BAD_BLOCK,
+            DISK_STATUS_BUSY, DISK_STATUS_INVALID_COMMAND, DISK_STATUS_OK,
+            DISK_STATUS_OUT_OF_RANGE, DISK_STATUS_WRITE_PROTECTED,
+        },
+        Disk, Latency,
+    },
+    Device,
+};
+
+fn write_block(disk: &mut Disk, block: u8, value: u8) {
+    disk.write(DISK_BLOCK, block);
+    disk.write(DISK_BUFFER_START, value);
+    disk.write(DISK_CMD, DISK_CMD_WRITE);
+}
+
+#[test]
+fn test_disk_write_marks_dirty() {
+    let mut disk = Disk::default();
+    assert!(!disk.drive(0).is_dirty());
+    write_block(&mut disk, 3, 0xAB);
+    assert!(disk.drive(0).is_dirty());
+    assert_eq!(disk.dump()[3 * 256], 0xAB);
+
+    disk.drive(0).mark_clean();
+    assert!(!disk.drive(0).is_dirty());
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert!(!disk.drive(0).is_dirty());
+}
+
+#[test]
+fn test_disk_write_protected() {
+    let mut disk = Disk::default();
+    disk.drive(0).write_protected = true;
+    write_block(&mut disk, 1, 0xAB);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_WRITE_PROTECTED);
+    assert!(!disk.drive(0).is_dirty());
+    assert_eq!(disk.dump()[256], 0);
+}
+
+#[test]
+fn test_disk_flush_request() {
+    let mut disk = Disk::default();
+    assert!(!disk.take_flush());
+    disk.write(DISK_CMD, DISK_CMD_FLUSH);
+    assert!(disk.take_flush());
+    assert!(!disk.take_flush());
+}
+
+#[test]
+fn test_disk_high_blocks_and_drives() {
+    let mut disk = Disk::default();
+    disk.drive(2).set(vec![0; 1024 * 256]);
+    disk.write(DISK_DRIVE, 2);
+    disk.write(DISK_BLOCK_HIGH, 0x03);
+    write_block(&mut disk, 0xE7, 0xCD);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OK);
+    assert_eq!(disk.read(DISK_BLOCK_HIGH), 0x03);
+    assert_eq!(disk.drive(2).dump()[999 * 256], 0xCD);
+    assert!(disk.drive(2).is_dirty());
+    assert!(!disk.drive(0).is_dirty());
+
+    disk.write(DISK_BUFFER_START, 0);
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert_eq!(disk.read(DISK_BUFFER_START), 0xCD);
+}
+
+#[test]
+fn test_disk_out_of_range() {
+    let mut disk = Disk::default();
+    disk.write(DISK_BUFFER_START, 0x42);
+    disk.write(DISK_BLOCK_HIGH, 0x01);
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OUT_OF_RANGE);
+    assert_eq!(disk.read(DISK_BUFFER_START), 0x42);
+    disk.write(DISK_CMD, DISK_CMD_WRITE);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OUT_OF_RANGE);
+    assert!(!disk.drive(0).is_dirty());
+
+    // Drive 1 is empty and drive 9 does not exist.
+    disk.write(DISK_BLOCK_HIGH, 0);
+    for drive in [1, 9] {
+        disk.write(DISK_DRIVE, drive);
+        disk.write(DISK_CMD, DISK_CMD_READ);
+        assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OUT_OF_RANGE);
+    }
+    disk.write(DISK_DRIVE, 0);
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OK);
+}
+
+#[test]
+fn test_disk_invalid_command_and_bad_blocks() {
+    let mut disk = Disk::default();
+    disk.write(DISK_CMD, 0x7F);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_INVALID_COMMAND);
+
+    disk.drive(0).bad_blocks.insert(5);
+    write_block(&mut disk, 5, 0xAB);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_BAD_BLOCK);
+    assert_eq!(disk.dump()[5 * 256], 0);
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_BAD_BLOCK);
+
+    write_block(&mut disk, 6, 0xAB);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OK);
+}
+
+#[test]
+fn test_disk_latency() {
+    let mut disk = Disk::default();
+    disk.latency = Latency {
+        seek: 100,
+        transfer: 10,
+    };
+    write_block(&mut disk, 2, 0xAB);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_BUSY);
+    // Registers and buffer are locked until the command completes.
+    disk.write(DISK_BLOCK, 3);
+    disk.write(DISK_BUFFER_START, 0xCD);
+    disk.tick(109);
+    assert!(disk.busy());
+    assert_eq!(disk.dump()[2 * 256], 0);
+    disk.tick(1);
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OK);
+    assert_eq!(disk.read(DISK_BLOCK), 2);
+    assert_eq!(disk.dump()[2 * 256], 0xAB);
+
+    // Same block again: no seek.
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    disk.tick(10);
+    assert!(!disk.busy());
+
+    disk.write(DISK_BLOCK, 7);
+    disk.write(DISK_CMD, DISK_CMD_READ);
+    assert!(disk.busy());
+    disk.finish();
+    assert_eq!(disk.read(DISK_STATUS), DISK_STATUS_OK);
+}
@@ -1,4 +1,11 @@
-use mb8::vm::VirtualMachine;
+use mb8::{
+    dev::disk::{
+        registers::{DISK_STATUS_BAD_BLOCK, DISK_STATUS_OK},
+        Latency,
+    },
+    vm::VirtualMachine,
+};
+use mb8_isa::registers::Register;
 
 #[test]
 fn test_sys_disk_set_block() {
@@ -12,13 +19,13 @@ fn test_sys_disk_set_block() {
 #[test]
 fn test_sys_disk_read_block() {
     let bin = include_bytes!("../../../kernel/tests/test_sys_disk_read_block.bin");
-    let mut img = vec![0; 65536].into_boxed_slice();
+    let mut img = vec![0; 65536];
     for i in 0..256 {
         img[i + 256] = i as u8;
     }
 
     let mut vm = VirtualMachine::default();
-    vm.devices.disk().set(img.try_into().unwrap());
+    vm.devices.disk().set(img);
     vm.load_rom(bin);
     vm.run();
 
@@ -38,3 +45,29 @@ fn test_sys_disk_write_block() {
     assert_eq!(vm.devices.disk().dump()[256], 228);
     assert_eq!(vm.devices.disk().dump()[257], 0);
 }
+
+#[test]
+fn test_sys_disk_read_block_waits_and_reports_errors() {
+    let bin = include_bytes!("../../../kernel/tests/test_sys_disk_read_block.bin");
+    let mut img = vec![0; 65536];
+    img[256] = 0x42;
+
+    let mut vm = VirtualMachine::default();
+    vm.devices.disk().set(img.clone());
+    vm.devices.disk().latency = Latency {
+        seek: 500,
+        transfer: 50,
+    };
+    vm.load_rom(bin);
+    vm.run();
+    assert_eq!(vm.registers.read(Register::R0), DISK_STATUS_OK);
+    assert_eq!(vm.devices.read(0xF202), 0x42);
+
+    let mut vm = VirtualMachine::default();
+    vm.devices.disk().set(img);
+    vm.devices.disk().drive(0).bad_blocks.insert(1);
+    vm.load_rom(bin);
+    vm.run();
+    assert_eq!(vm.registers.read(Register::R0), DISK_STATUS_BAD_BLOCK);
+    assert_eq!(vm.devices.read(0xF202), 0);
+}
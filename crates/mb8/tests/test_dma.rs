@@ -0,0 +1,82 @@
+use mb8::{
+    dev::{dma::registers::STATUS_DONE, gpu::registers::GPU_MODE_TTY},
+    vm::VirtualMachine,
+};
+
+const DMA: u16 = 0xF600;
+const STATUS: u16 = DMA + 7;
+
+/// Program a transfer of `len` bytes from `src` to `dst` and start it with `ctrl`.
+fn start(vm: &mut VirtualMachine, src: u16, dst: u16, len: u16, ctrl: u8) {
+    let registers = [src.to_be_bytes(), dst.to_be_bytes(), len.to_be_bytes()];
+    for (i, value) in registers.iter().flatten().enumerate() {
+        vm.devices.write(DMA + i as u16, *value);
+    }
+    vm.devices.write(DMA + 6, ctrl);
+}
+
+#[test]
+fn test_dma_takes_one_cycle_per_byte() {
+    let mut vm = VirtualMachine::default();
+    for i in 0..0x40 {
+        vm.devices.write(0x2000 + i, i as u8 + 1);
+    }
+    start(&mut vm, 0x2000, 0x3000, 0x40, 0x01);
+    assert_eq!(vm.devices.read(STATUS), 0x01);
+
+    // RAM is zeroed: the CPU runs one-cycle NOPs at 0x1000.
+    vm.program_counter = 0x1000;
+    for _ in 0..0x10 {
+        vm.step();
+    }
+    assert_eq!(vm.cycles, 0x10);
+    assert_eq!(vm.devices.read(0x300F), 0x10);
+    assert_eq!(vm.devices.read(0x3010), 0x00);
+    // The registers count along with the transfer.
+    assert_eq!(vm.devices.read(DMA + 1), 0x10);
+    assert_eq!(vm.devices.read(DMA + 5), 0x30);
+
+    for _ in 0..0x30 {
+        vm.step();
+    }
+    assert_eq!(vm.devices.read(STATUS), STATUS_DONE);
+    for i in 0..0x40 {
+        assert_eq!(vm.devices.read(0x3000 + i), i as u8 + 1);
+    }
+}
+
+#[test]
+fn test_dma_copies_disk_buffer() {
+    let mut vm = VirtualMachine::default();
+    let mut img = vec![0; 65536];
+    for (i, byte) in img[0x0300..0x0400].iter_mut().enumerate() {
+        *byte = i as u8;
+    }
+    vm.devices.disk().set(img);
+    vm.devices.write(0xF200, 3);
+    vm.devices.write(0xF201, 1);
+
+    start(&mut vm, 0xF202, 0x2000, 0x100, 0x01);
+    vm.devices.tick(0x100);
+
+    assert_eq!(vm.devices.read(STATUS), STATUS_DONE);
+    for i in 0..0x100 {
+        assert_eq!(vm.devices.read(0x2000 + i), i as u8);
+    }
+}
+
+#[test]
+fn test_dma_writes_to_a_fixed_port() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.write(0xF000, GPU_MODE_TTY);
+    for (i, byte) in b"DMA".iter().enumerate() {
+        vm.devices.write(0x2000 + i as u16, *byte);
+    }
+    start(&mut vm, 0x2000, 0xF001, 3, 0x05);
+    vm.devices.tick(10);
+
+    assert!(vm.devices.gpu().tty_buffer().starts_with(b"DMA"));
+    // An empty transfer completes at once.
+    start(&mut vm, 0x2000, 0x2000, 0, 0x01);
+    assert_eq!(vm.devices.read(STATUS), STATUS_DONE);
+}
@@ -0,0 +1,150 @@
+use mb8::vm::VirtualMachine;
+use mb8_isa::{
+    exec::{ExecHeader, Executable},
+    registers::Register,
+};
+
+const BIN: &[u8] = include_bytes!("../../../kernel/tests/test_sys_exec.bin");
+
+/// Run `test_sys_exec.bin` with `file` stored on disk as `prog`.
+fn run_exec(name: &[u8], file: &[u8]) -> VirtualMachine {
+    run_exec_with_bad_blocks(name, file, &[])
+}
+
+fn run_exec_with_bad_blocks(name: &[u8], file: &[u8], bad_blocks: &[usize]) -> VirtualMachine {
+    let mut img = vec![0; 65536];
+    img[0] = 1; // status
+    img[1] = 1; // start block
+    img[2] = (file.len() / 256 + 1) as u8; // size
+    img[3..3 + name.len()].copy_from_slice(name);
+    img[256..256 + file.len()].copy_from_slice(file);
+
+    let mut vm = VirtualMachine::default();
+    vm.devices.disk().set(img);
+    vm.devices.disk().drive(0).bad_blocks.extend(bad_blocks);
+    for addr in 0x2000..0x2200 {
+        vm.devices.write(addr, 0xFF);
+    }
+    vm.load_rom(BIN);
+    vm.run();
+    vm
+}
+
+fn header(code_size: u16, bss_size: u16) -> ExecHeader {
+    ExecHeader {
+        load: 0x2000,
+        entry: 0x2000 + code_size - 4,
+        code_size,
+        bss_size,
+        stack_size: 0x100,
+    }
+}
+
+#[test]
+fn test_sys_exec() {
+    // Spans two disk blocks; the entry point is the last instruction pair.
+    let mut code = vec![0; 300];
+    code[..2].copy_from_slice(&[0xAA, 0x55]);
+    code[296..].copy_from_slice(&[0x21, 0x42, 0x01, 0x00]); // LDI R1 0x42; HALT
+    let program = Executable {
+        header: header(300, 0x20),
+        code: code.clone(),
+    };
+
+    let mut vm = run_exec(b"prog\0", &program.to_bytes());
+
+    assert_eq!(vm.registers.read(Register::R1), 0x42);
+    assert_eq!(vm.program_counter, 0x2000 + 300);
+    for (i, byte) in code.iter().enumerate() {
+        assert_eq!(vm.devices.read(0x2000 + i as u16), *byte, "code {i}");
+    }
+    for addr in 0x2000 + 300..0x2000 + 300 + 0x20 {
+        assert_eq!(vm.devices.read(addr), 0, "bss {addr:04X}");
+    }
+    assert_eq!(vm.devices.read(0x2000 + 300 + 0x20), 0xFF);
+    // The syscall return address was dropped.
+    assert_eq!(vm.registers.read(Register::SPH), 0xBF);
+    assert_eq!(vm.registers.read(Register::SPL), 0xFF);
+}
+
+#[test]
+fn test_sys_exec_not_found() {
+    let program = Executable {
+        header: header(4, 0),
+        code: vec![0x21, 0x42, 0x01, 0x00],
+    };
+    let vm = run_exec(b"other\0", &program.to_bytes());
+
+    assert_eq!(vm.registers.read(Register::R0), 1);
+    assert!(vm.program_counter >= 0xE000);
+}
+
+#[test]
+fn test_sys_exec_rejects_raw_image() {
+    let mut vm = run_exec(b"prog\0", &[0x21, 0x42, 0x01, 0x00]);
+
+    assert_eq!(vm.registers.read(Register::R0), 2);
+    assert!(vm.program_counter >= 0xE000);
+    assert_eq!(vm.devices.read(0x2000), 0xFF);
+}
+
+#[test]
+fn test_sys_exec_rejects_oversized_program() {
+    for header in [
+        header(4, 0xA000),
+        ExecHeader {
+            load: 0x0800,
+            entry: 0x0800,
+            ..header(4, 0)
+        },
+    ] {
+        let mut file = header.to_bytes().to_vec();
+        file.extend([0x21, 0x42, 0x01, 0x00]);
+        let mut vm = run_exec(b"prog\0", &file);
+
+        assert_eq!(vm.registers.read(Register::R0), 3, "{header:?}");
+        assert!(vm.program_counter >= 0xE000);
+        assert_eq!(vm.devices.read(0x2000), 0xFF);
+    }
+}
+
+#[test]
+fn test_sys_exec_rejects_code_past_end_of_file() {
+    // The file takes one block, which holds the header and 240 bytes of code.
+    for code_size in [241, 300] {
+        let mut file = header(code_size, 0).to_bytes().to_vec();
+        file.extend([0x21, 0x42, 0x01, 0x00]);
+        let mut vm = run_exec(b"prog\0", &file);
+
+        assert_eq!(vm.registers.read(Register::R0), 2, "{code_size}");
+        assert!(vm.program_counter >= 0xE000);
+        assert_eq!(vm.devices.read(0x2000), 0xFF);
+    }
+
+    let mut code = vec![0; 240];
+    code[236..].copy_from_slice(&[0x21, 0x42, 0x01, 0x00]); // LDI R1 0x42; HALT
+    let program = Executable {
+        header: header(240, 0),
+        code,
+    };
+    let vm = run_exec(b"prog\0", &program.to_bytes());
+    assert_eq!(vm.registers.read(Register::R1), 0x42);
+}
+
+#[test]
+fn test_sys_exec_disk_error() {
+    let program = Executable {
+        header: header(300, 0),
+        code: vec![0x21; 300],
+    };
+    let file = program.to_bytes();
+
+    let mut vm = run_exec_with_bad_blocks(b"prog\0", &file, &[1]);
+    assert_eq!(vm.registers.read(Register::R0), 4);
+    assert!(vm.program_counter >= 0xE000);
+    assert_eq!(vm.devices.read(0x2000), 0xFF);
+
+    let vm = run_exec_with_bad_blocks(b"prog\0", &file, &[2]);
+    assert_eq!(vm.registers.read(Register::R0), 4);
+    assert!(vm.program_counter >= 0xE000);
+}
@@ -5,11 +5,11 @@ use mb8_isa::registers::Register;
 fn test_sys_fs_list() {
     let bin = include_bytes!("../../../kernel/tests/test_sys_fs_list.bin");
     let mut vm = VirtualMachine::default();
-    let mut img = vec![0; 65536].into_boxed_slice();
+    let mut img = vec![0; 65536];
     for i in 0..256 {
         img[i] = i as u8;
     }
-    vm.devices.disk().set(img.try_into().unwrap());
+    vm.devices.disk().set(img);
     vm.load_rom(bin);
     vm.run();
 
@@ -21,14 +21,14 @@ fn test_sys_fs_list() {
 #[test]
 fn test_sys_fs_find() {
     let bin = include_bytes!("../../../kernel/tests/test_sys_fs_find.bin");
-    let mut img = vec![0; 65536].into_boxed_slice();
+    let mut img = vec![0; 65536];
     img[0] = 1; // status
     img[1] = 2; // start block
     img[2] = 1; // size
     img[3..8].copy_from_slice(b"file\0");
 
     let mut vm = VirtualMachine::default();
-    vm.devices.disk().set(img.try_into().unwrap());
+    vm.devices.disk().set(img);
     vm.load_rom(bin);
     vm.run();
 
@@ -40,14 +40,14 @@ fn test_sys_fs_find() {
 #[test]
 fn test_sys_fs_find_not_exist() {
     let bin = include_bytes!("../../../kernel/tests/test_sys_fs_find.bin");
-    let mut img = vec![0; 65536].into_boxed_slice();
+    let mut img = vec![0; 65536];
     img[0] = 1; // status
     img[1] = 2; // start block
     img[2] = 1; // size
     img[3..8].copy_from_slice(b"ffff\0");
 
     let mut vm = VirtualMachine::default();
-    vm.devices.disk().set(img.try_into().unwrap());
+    vm.devices.disk().set(img);
     vm.load_rom(bin);
     vm.run();
 
@@ -57,7 +57,7 @@ fn test_sys_fs_find_not_exist() {
 #[test]
 fn test_sys_fs_read() {
     let bin = include_bytes!("../../../kernel/tests/test_sys_fs_read.bin");
-    let mut img = vec![0; 65536].into_boxed_slice();
+    let mut img = vec![0; 65536];
     img[0] = 1; // status
     img[1] = 2; // start block
     img[2] = 2; // size
@@ -65,7 +65,7 @@ fn test_sys_fs_read() {
     img[256 * 2..256 * 4].copy_from_slice(&[1; 256 * 2]);
 
     let mut vm = VirtualMachine::default();
-    vm.devices.disk().set(img.try_into().unwrap());
+    vm.devices.disk().set(img);
     vm.load_rom(bin);
     vm.run();
 
@@ -0,0 +1,176 @@
+use mb8::{
+    fs::{FileSystem, Version, BLOCK_SIZE},
+    hle::{
+        syscalls::{SYS_DISK_SET_BLOCK, SYS_FS_FIND},
+        Hle, SYSCALL_ENTRY,
+    },
+    vm::VirtualMachine,
+};
+use mb8_isa::{encode::encode, exec::USER_BASE, opcodes::Opcode, registers::Register};
+
+const SH: &[u8] = include_bytes!("../../../user/sh.bin");
+const LS: &[u8] = include_bytes!("../../../user/ls.bin");
+const HELP: &[u8] = include_bytes!("../../../user/help.bin");
+const HELLO: &[u8] = include_bytes!("../../../user/hello.bin");
+
+/// A disk image holding `files` in the layout written by `makefs`.
+fn disk(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut img = vec![0; 65536];
+    let mut block = 1;
+    for (i, (name, data)) in files.iter().enumerate() {
+        let size = data.len() / 256 + 1;
+        img[i * 16] = 1;
+        img[i * 16 + 1] = block as u8;
+        img[i * 16 + 2] = size as u8;
+        img[i * 16 + 3..i * 16 + 3 + name.len()].copy_from_slice(name.as_bytes());
+        img[block * 256..block * 256 + data.len()].copy_from_slice(data);
+        block += size;
+    }
+    img
+}
+
+/// Load `program` into a VM without a kernel and run it for at most `steps` instructions.
+fn run(program: &[u8], files: &[(&str, &[u8])], keys: &[u8], steps: usize) -> VirtualMachine {
+    run_disk(program, disk(files), keys, steps)
+}
+
+/// Like [`run`], with the disk `image`.
+fn run_disk(program: &[u8], image: Vec<u8>, keys: &[u8], steps: usize) -> VirtualMachine {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.devices.disk().set(image);
+    for key in keys {
+        vm.devices.keyboard().key_pressed(*key);
+    }
+    vm.load_program(program).unwrap();
+    for _ in 0..steps {
+        if vm.halted {
+            break;
+        }
+        vm.step();
+    }
+    vm
+}
+
+fn output(vm: &VirtualMachine) -> String {
+    vm.hle.as_ref().unwrap().output()
+}
+
+fn screen(vm: &mut VirtualMachine) -> String {
+    String::from_utf8_lossy(vm.devices.gpu().tty_buffer()).replace('\0', " ")
+}
+
+#[test]
+fn test_hle_ls() {
+    let vm = run(LS, &[("sh", SH), ("hello", HELLO)], b"", 100_000);
+
+    assert!(vm.halted);
+    assert!(vm.hle.as_ref().unwrap().exited);
+    assert_eq!(output(&vm), "sh\nhello\n");
+}
+
+#[test]
+fn test_hle_help() {
+    let mut vm = run(HELP, &[], b"", 100_000);
+
+    assert!(vm.hle.as_ref().unwrap().exited);
+    assert!(output(&vm).contains("Commands:"));
+    assert!(screen(&mut vm).starts_with("MB8 - 8bit fantasy computer"));
+}
+
+#[test]
+fn test_hle_shell_exec() {
+    let mut vm = run(
+        SH,
+        &[("hello", HELLO), ("ls", LS)],
+        b"nope\nhello\n",
+        1_000_000,
+    );
+
+    assert!(output(&vm).starts_with(">nope\nNot found\n>hello\n"));
+    // `hello` writes to the TTY register directly.
+    assert!(screen(&mut vm).contains("Hello"));
+}
+
+#[test]
+fn test_hle_exec_rejects_invalid_files() {
+    let raw: &[u8] = &[0x21, 0x42, 0x01, 0x00];
+    let vm = run(SH, &[("raw", raw)], b"raw\n", 1_000_000);
+
+    assert!(output(&vm).starts_with(">raw\nNot found\n>"));
+}
+
+#[test]
+fn test_hle_exec_from_v2_disk() {
+    let mut fs = FileSystem::format(Version::V2);
+    fs.add("a_much_longer_name", LS).unwrap();
+    fs.add("hello", HELLO).unwrap();
+    let mut vm = run_disk(SH, fs.into_image(), b"hello\n", 1_000_000);
+
+    assert!(screen(&mut vm).contains("Hello"));
+}
+
+/// A raw program calling syscall `id` with `r1` and `r2`, then halting. It
+/// takes 14 bytes.
+fn syscall(id: u8, r1: u8, r2: u8) -> Vec<u8> {
+    let [entry_hi, entry_lo] = SYSCALL_ENTRY.to_be_bytes();
+    let ldi = |dst, value| Opcode::Ldi { dst, value };
+    let program = [
+        ldi(Register::R0, id),
+        ldi(Register::R1, r1),
+        ldi(Register::R2, r2),
+        ldi(Register::R6, entry_hi),
+        ldi(Register::R7, entry_lo),
+        Opcode::Call {
+            hi: Register::R6,
+            lo: Register::R7,
+        },
+        Opcode::Halt,
+    ];
+    program
+        .iter()
+        .flat_map(|op| encode(op).to_be_bytes())
+        .collect()
+}
+
+/// A raw program calling `SYS_FS_FIND` on `name`, then halting.
+fn find(name: &str) -> Vec<u8> {
+    let [name_hi, name_lo] = (USER_BASE + 14).to_be_bytes();
+    let mut image = syscall(SYS_FS_FIND, name_hi, name_lo);
+    image.extend_from_slice(name.as_bytes());
+    image.push(0);
+    image
+}
+
+#[test]
+fn test_hle_fs_find_out_of_reach() {
+    let mut fs = FileSystem::with_blocks(Version::V2, 0x400).unwrap();
+    fs.add("filler", &vec![1; 0xFF * BLOCK_SIZE]).unwrap();
+    fs.add("far", b"far away").unwrap();
+    fs.add("huge", &vec![2; 0x100 * BLOCK_SIZE]).unwrap();
+    let image = fs.into_image();
+
+    let vm = run_disk(&find("filler"), image.clone(), b"", 100);
+    assert_eq!(vm.registers.read(Register::R0), 0);
+    assert_eq!(vm.registers.read(Register::R2), 0xFF);
+    assert_eq!(vm.registers.read(Register::R3), 0xFF);
+    assert_eq!(vm.registers.read(Register::R4), 0x00);
+
+    for name in ["far", "huge"] {
+        let vm = run_disk(&find(name), image.clone(), b"", 100);
+        assert_eq!(vm.registers.read(Register::R0), 2, "{name}");
+    }
+}
+
+#[test]
+fn test_hle_disk_set_block_clears_high_byte() {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.devices.write(0xF310, 0x01);
+    vm.load_program(&syscall(SYS_DISK_SET_BLOCK, 0x02, 0))
+        .unwrap();
+    vm.run();
+
+    assert_eq!(vm.devices.read(0xF200), 0x02);
+    assert_eq!(vm.devices.read(0xF310), 0x00);
+}
@@ -0,0 +1,182 @@
+use std::fs;
+
+use mb8::dev::{
+    hostfs::{
+        registers::{
+            BUFFER_START, CMD, CMD_CLOSE, CMD_LIST, CMD_OPEN_READ, CMD_OPEN_WRITE, CMD_READ,
+            CMD_WRITE, HANDLE, INDEX, LEN, SIZE, STATUS, STATUS_BAD_HANDLE, STATUS_DISABLED,
+            STATUS_INVALID_COMMAND, STATUS_INVALID_NAME, STATUS_NOT_FOUND, STATUS_NO_HANDLES,
+            STATUS_OK,
+        },
+        HostFs, MAX_FILES,
+    },
+    Device,
+};
+use tempfile::tempdir;
+
+fn command(hostfs: &mut HostFs, cmd: u8) -> u8 {
+    hostfs.write(CMD, cmd);
+    hostfs.read(STATUS)
+}
+
+fn set_buffer(hostfs: &mut HostFs, data: &[u8]) {
+    for (i, &byte) in data.iter().enumerate() {
+        hostfs.write(BUFFER_START + i as u16, byte);
+    }
+}
+
+fn buffer(hostfs: &mut HostFs, len: u8) -> Vec<u8> {
+    (0..u16::from(len))
+        .map(|i| hostfs.read(BUFFER_START + i))
+        .collect()
+}
+
+fn size(hostfs: &mut HostFs) -> u32 {
+    u32::from_be_bytes([0, 1, 2, 3].map(|i| hostfs.read(SIZE + i)))
+}
+
+fn open(hostfs: &mut HostFs, name: &str, cmd: u8) -> u8 {
+    set_buffer(hostfs, name.as_bytes());
+    hostfs.write(BUFFER_START + name.len() as u16, 0);
+    command(hostfs, cmd)
+}
+
+#[test]
+fn test_hostfs_disabled() {
+    let mut hostfs = HostFs::default();
+    assert_eq!(open(&mut hostfs, "file", CMD_OPEN_READ), STATUS_DISABLED);
+    assert_eq!(command(&mut hostfs, CMD_LIST), STATUS_DISABLED);
+    assert_eq!(command(&mut hostfs, 0x42), STATUS_INVALID_COMMAND);
+}
+
+#[test]
+fn test_hostfs_write_read_and_list() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("b.txt"), vec![7; 200]).unwrap();
+    fs::create_dir(dir.path().join("sub")).unwrap();
+    let mut hostfs = HostFs::default();
+    hostfs.set_root(dir.path()).unwrap();
+
+    assert_eq!(open(&mut hostfs, "a.txt", CMD_OPEN_WRITE), STATUS_OK);
+    let handle = hostfs.read(HANDLE);
+    set_buffer(&mut hostfs, b"hello");
+    hostfs.write(LEN, 5);
+    assert_eq!(command(&mut hostfs, CMD_WRITE), STATUS_OK);
+    assert_eq!(size(&mut hostfs), 5);
+    assert_eq!(command(&mut hostfs, CMD_READ), STATUS_BAD_HANDLE);
+    assert_eq!(command(&mut hostfs, CMD_CLOSE), STATUS_OK);
+    assert_eq!(command(&mut hostfs, CMD_CLOSE), STATUS_BAD_HANDLE);
+    assert_eq!(fs::read(dir.path().join("a.txt")).unwrap(), b"hello");
+
+    assert_eq!(open(&mut hostfs, "b.txt", CMD_OPEN_READ), STATUS_OK);
+    assert_eq!(hostfs.read(HANDLE), handle);
+    assert_eq!(size(&mut hostfs), 200);
+    let mut data = Vec::new();
+    loop {
+        hostfs.write(LEN, 255);
+        assert_eq!(command(&mut hostfs, CMD_READ), STATUS_OK);
+        let len = hostfs.read(LEN);
+        if len == 0 {
+            break;
+        }
+        data.extend(buffer(&mut hostfs, len));
+    }
+    assert_eq!(data, vec![7; 200]);
+    assert_eq!(command(&mut hostfs, CMD_WRITE), STATUS_BAD_HANDLE);
+
+    let mut names = Vec::new();
+    for index in 0.. {
+        hostfs.write(INDEX, index);
+        match command(&mut hostfs, CMD_LIST) {
+            STATUS_OK => {}
+            status => {
+                assert_eq!(status, STATUS_NOT_FOUND);
+                break;
+            }
+        }
+        let len = hostfs.read(LEN);
+        names.push((
+            String::from_utf8(buffer(&mut hostfs, len)).unwrap(),
+            size(&mut hostfs),
+        ));
+        assert_eq!(hostfs.read(BUFFER_START + u16::from(len)), 0);
+    }
+    assert_eq!(
+        names,
+        [("a.txt".to_string(), 5), ("b.txt".to_string(), 200)]
+    );
+}
+
+#[test]
+fn test_hostfs_stays_in_directory() {
+    let outer = tempdir().unwrap();
+    fs::write(outer.path().join("secret"), b"secret").unwrap();
+    let dir = outer.path().join("shared");
+    fs::create_dir_all(dir.join("sub")).unwrap();
+    fs::write(dir.join("sub/file"), b"nested").unwrap();
+    let mut hostfs = HostFs::default();
+    hostfs.set_root(&dir).unwrap();
+
+    for name in [
+        "",
+        "../secret",
+        "sub/file",
+        ".",
+        "..",
+        ".hidden",
+        "a\\b",
+        "C:secret",
+        "C:",
+        "tab\t",
+    ] {
+        assert_eq!(
+            open(&mut hostfs, name, CMD_OPEN_READ),
+            STATUS_INVALID_NAME,
+            "{name:?}"
+        );
+    }
+    set_buffer(&mut hostfs, &[b'x'; 128]);
+    assert_eq!(command(&mut hostfs, CMD_OPEN_READ), STATUS_INVALID_NAME);
+    assert_eq!(open(&mut hostfs, "sub", CMD_OPEN_READ), STATUS_NOT_FOUND);
+    assert_eq!(open(&mut hostfs, "sub", CMD_OPEN_WRITE), STATUS_NOT_FOUND);
+    assert_eq!(
+        open(&mut hostfs, "missing", CMD_OPEN_READ),
+        STATUS_NOT_FOUND
+    );
+
+    #[cfg(unix)]
+    {
+        std::os::unix::fs::symlink(outer.path().join("secret"), dir.join("link")).unwrap();
+        assert_eq!(open(&mut hostfs, "link", CMD_OPEN_READ), STATUS_NOT_FOUND);
+        assert_eq!(open(&mut hostfs, "link", CMD_OPEN_WRITE), STATUS_NOT_FOUND);
+        assert_eq!(fs::read(outer.path().join("secret")).unwrap(), b"secret");
+    }
+
+    hostfs.write(INDEX, 0);
+    assert_eq!(command(&mut hostfs, CMD_LIST), STATUS_NOT_FOUND);
+}
+
+#[test]
+fn test_hostfs_handles() {
+    let dir = tempdir().unwrap();
+    fs::write(dir.path().join("file"), b"data").unwrap();
+    let mut hostfs = HostFs::default();
+    hostfs.set_root(dir.path()).unwrap();
+
+    let mut handles = Vec::new();
+    for _ in 0..MAX_FILES {
+        assert_eq!(open(&mut hostfs, "file", CMD_OPEN_READ), STATUS_OK);
+        handles.push(hostfs.read(HANDLE));
+    }
+    assert_eq!(handles, [0, 1, 2, 3]);
+    assert_eq!(open(&mut hostfs, "file", CMD_OPEN_READ), STATUS_NO_HANDLES);
+
+    hostfs.write(HANDLE, 2);
+    assert_eq!(command(&mut hostfs, CMD_CLOSE), STATUS_OK);
+    assert_eq!(open(&mut hostfs, "file", CMD_OPEN_READ), STATUS_OK);
+    assert_eq!(hostfs.read(HANDLE), 2);
+
+    hostfs.write(HANDLE, MAX_FILES as u8);
+    assert_eq!(command(&mut hostfs, CMD_READ), STATUS_BAD_HANDLE);
+    assert!(hostfs.set_root(&dir.path().join("file")).is_err());
+}
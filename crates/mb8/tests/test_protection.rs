@@ -0,0 +1,162 @@
+use mb8::{
+    dev::gpu::registers::TTY_COLS,
+    hle::Hle,
+    protection::{Access, Fault, Privilege},
+    vm::VirtualMachine,
+};
+use mb8_isa::{encode::encode, exec::Executable, opcodes::Opcode, registers::Register};
+
+const KERNEL: &[u8] = include_bytes!("../../../kernel/main.bin");
+const SH: &[u8] = include_bytes!("../../../user/sh.bin");
+const LS: &[u8] = include_bytes!("../../../user/ls.bin");
+
+/// An executable storing 0x42 at `addr`, then halting.
+fn poke(addr: u16) -> Vec<u8> {
+    let [hi, lo] = addr.to_be_bytes();
+    let program = [
+        Opcode::Ldi {
+            dst: Register::R0,
+            value: hi,
+        },
+        Opcode::Ldi {
+            dst: Register::R1,
+            value: lo,
+        },
+        Opcode::Ldi {
+            dst: Register::R2,
+            value: 0x42,
+        },
+        Opcode::St {
+            src: Register::R2,
+            hi: Register::R0,
+            lo: Register::R1,
+        },
+        Opcode::Halt,
+    ];
+    let image: Vec<u8> = program
+        .iter()
+        .flat_map(|op| encode(op).to_be_bytes())
+        .collect();
+    Executable::from_image(&image, 0x1000, 0x1000)
+        .unwrap()
+        .to_bytes()
+}
+
+/// A disk image holding `files` in the layout written by `makefs`.
+fn disk(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut img = vec![0; 65536];
+    let mut block = 1;
+    for (i, (name, data)) in files.iter().enumerate() {
+        let size = data.len() / 256 + 1;
+        img[i * 16] = 1;
+        img[i * 16 + 1] = block as u8;
+        img[i * 16 + 2] = size as u8;
+        img[i * 16 + 3..i * 16 + 3 + name.len()].copy_from_slice(name.as_bytes());
+        img[block * 256..block * 256 + data.len()].copy_from_slice(data);
+        block += size;
+    }
+    img
+}
+
+/// Boot the kernel with `files` on disk and type `keys` into the shell.
+fn boot(files: &[(&str, &[u8])], keys: &[u8], protect: bool) -> VirtualMachine {
+    let mut vm = VirtualMachine::default();
+    vm.protection.enabled = protect;
+    vm.devices.disk().set(disk(files));
+    for key in keys {
+        vm.devices.keyboard().key_pressed(*key);
+    }
+    vm.load_rom(KERNEL);
+    for _ in 0..1_000_000 {
+        if vm.halted {
+            break;
+        }
+        vm.step();
+    }
+    vm
+}
+
+/// Non-empty lines on the TTY screen.
+fn screen(vm: &mut VirtualMachine) -> Vec<String> {
+    vm.devices
+        .gpu()
+        .tty_buffer()
+        .chunks(usize::from(TTY_COLS))
+        .map(|line| {
+            String::from_utf8_lossy(line)
+                .replace('\0', " ")
+                .trim_end()
+                .to_string()
+        })
+        .filter(|line| !line.is_empty())
+        .collect()
+}
+
+#[test]
+fn test_kernel_writes_fault() {
+    let program = poke(0x0F00);
+    let mut vm = boot(&[("sh", SH), ("poke", &program)], b"poke\n", true);
+
+    assert!(!vm.halted);
+    assert_eq!(
+        vm.protection.fault,
+        Some(Fault {
+            pc: 0x1006,
+            addr: 0x0F00,
+            access: Access::Write,
+        })
+    );
+    // The header the kernel stored is intact and the shell is back.
+    assert_eq!(vm.devices.read(0x0F00), b'M');
+    assert!(screen(&mut vm).ends_with(&[">poke", "Protection fault", ">"].map(String::from)));
+}
+
+#[test]
+fn test_unprotected_writes_succeed() {
+    let program = poke(0x0F00);
+    let mut vm = boot(&[("sh", SH), ("poke", &program)], b"poke\n", false);
+
+    assert!(vm.halted);
+    assert_eq!(vm.protection.fault, None);
+    assert_eq!(vm.devices.read(0x0F00), 0x42);
+}
+
+#[test]
+fn test_syscalls_work_under_protection() {
+    let mut vm = boot(&[("sh", SH), ("ls", LS)], b"ls\n", true);
+
+    assert_eq!(vm.protection.fault, None);
+    assert!(screen(&mut vm).ends_with(&[">ls", "sh", "ls", ">"].map(String::from)));
+}
+
+#[test]
+fn test_hle_mmio_fault_halts() {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.protection.enabled = true;
+    vm.load_program(&poke(0xF001)).unwrap();
+    vm.run();
+
+    assert_eq!(
+        vm.protection.fault,
+        Some(Fault {
+            pc: 0x1006,
+            addr: 0xF001,
+            access: Access::Write,
+        })
+    );
+    assert_eq!(vm.privilege, Privilege::Supervisor);
+}
+
+#[test]
+fn test_protected_ranges_are_configurable() {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.protection.enabled = true;
+    vm.protection.ranges.push(0x8000..=0x80FF);
+    vm.load_program(&poke(0x8010)).unwrap();
+    vm.run();
+
+    assert_eq!(vm.devices.read(0x8010), 0);
+    assert!(vm.protection.fault.is_some());
+}
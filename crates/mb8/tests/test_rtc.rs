@@ -0,0 +1,90 @@
+use mb8::{
+    dev::rtc::{DateTime, TimeSource},
+    hle::Hle,
+    vm::VirtualMachine,
+};
+use mb8_isa::{encode::encode, opcodes::Opcode, registers::Register};
+
+/// 2024-02-29 13:45:07 UTC.
+const LEAP_DAY: u64 = 1_709_214_307;
+
+#[test]
+fn test_datetime_from_unix() {
+    assert_eq!(
+        DateTime::from_unix(0),
+        DateTime {
+            year: 1970,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        }
+    );
+    assert_eq!(
+        DateTime::from_unix(LEAP_DAY),
+        DateTime {
+            year: 2024,
+            month: 2,
+            day: 29,
+            hour: 13,
+            minute: 45,
+            second: 7,
+        }
+    );
+    assert_eq!(DateTime::from_unix(951_868_799).day, 29, "2000-02-29");
+    assert_eq!(DateTime::from_unix(4_102_444_800).year, 2100);
+}
+
+#[test]
+fn test_rtc_registers_are_latched() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.rtc().source = TimeSource::Fixed(LEAP_DAY);
+    assert_eq!(vm.devices.read(0xF807), 0, "nothing latched yet");
+
+    vm.devices.write(0xF800, 0x01);
+    vm.devices.rtc().source = TimeSource::Fixed(0);
+    let registers: Vec<u8> = (0xF801..=0xF807).map(|a| vm.devices.read(a)).collect();
+    assert_eq!(registers, [0x07, 0xE8, 2, 29, 13, 45, 7]);
+}
+
+#[test]
+fn test_sys_time() {
+    let bin = include_bytes!("../../../kernel/tests/test_sys_time.bin");
+
+    let mut vm = VirtualMachine::default();
+    vm.devices.rtc().source = TimeSource::Fixed(LEAP_DAY);
+    vm.load_rom(bin);
+    vm.run();
+
+    let buffer: Vec<u8> = (0x0200..0x0208).map(|a| vm.devices.read(a)).collect();
+    assert_eq!(buffer, [0x07, 0xE8, 2, 29, 13, 45, 7, 0]);
+}
+
+#[test]
+fn test_hle_sys_time() {
+    let ldi = |dst, value| Opcode::Ldi { dst, value };
+    let program: Vec<u8> = [
+        ldi(Register::R0, 0x11),
+        ldi(Register::R1, 0x20),
+        ldi(Register::R2, 0x00),
+        ldi(Register::R6, 0xE5),
+        ldi(Register::R7, 0x00),
+        Opcode::Call {
+            hi: Register::R6,
+            lo: Register::R7,
+        },
+        Opcode::Halt,
+    ]
+    .iter()
+    .flat_map(|op| encode(op).to_be_bytes())
+    .collect();
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.devices.rtc().source = TimeSource::Fixed(LEAP_DAY);
+    vm.load_program(&program).unwrap();
+    vm.run();
+
+    let buffer: Vec<u8> = (0x2000..0x2007).map(|a| vm.devices.read(a)).collect();
+    assert_eq!(buffer, [0x07, 0xE8, 2, 29, 13, 45, 7]);
+}
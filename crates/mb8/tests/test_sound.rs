@@ -0,0 +1,90 @@
+use mb8::{dev::sound::registers::SAMPLE_RATE, vm::VirtualMachine};
+use mb8_isa::CPU_FREQUENCY;
+
+/// Start a note on `channel` at `frequency` Hz.
+fn play(vm: &mut VirtualMachine, channel: u16, frequency: u16, volume: u8, envelope: u8) {
+    let base = 0xF900 + channel * 4;
+    let [hi, lo] = frequency.to_be_bytes();
+    vm.devices.write(base, hi);
+    vm.devices.write(base + 1, lo);
+    vm.devices.write(base + 3, envelope);
+    vm.devices.write(base + 2, volume);
+}
+
+#[test]
+fn test_sound_registers() {
+    let mut vm = VirtualMachine::default();
+    play(&mut vm, 2, 0x1234, 99, 7);
+    let registers: Vec<u8> = (0xF908..=0xF90B).map(|a| vm.devices.read(a)).collect();
+    assert_eq!(registers, [0x12, 0x34, 15, 7], "volume is clamped to 15");
+    assert_eq!(vm.devices.read(0xF900), 0);
+}
+
+#[test]
+fn test_sound_follows_cycles() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.tick(CPU_FREQUENCY);
+    assert!(
+        vm.devices.sound().take_samples().is_empty(),
+        "not recording"
+    );
+
+    vm.devices.sound().recording = true;
+    for _ in 0..CPU_FREQUENCY / 4 {
+        vm.devices.tick(4);
+    }
+    let samples = vm.devices.sound().take_samples();
+    assert_eq!(samples.len(), SAMPLE_RATE as usize);
+    assert!(samples.iter().all(|&s| s == 0), "all channels are silent");
+}
+
+#[test]
+fn test_square_wave() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.sound().recording = true;
+    play(&mut vm, 0, 441, 15, 0);
+    vm.devices.tick(CPU_FREQUENCY);
+    let samples = vm.devices.sound().take_samples();
+
+    // 441 Hz at 22050 Hz is a period of 50 samples: 25 high, 25 low.
+    let high = samples[0];
+    assert!(high > 0);
+    assert_eq!(samples[..25], [high; 25]);
+    assert_eq!(samples[25..50], [-high; 25]);
+    let edges = samples.windows(2).filter(|w| w[0] != w[1]).count();
+    assert_eq!(edges, 2 * 441 - 1);
+}
+
+#[test]
+fn test_envelope_decays() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.sound().recording = true;
+    play(&mut vm, 1, 1000, 8, 2);
+    assert_eq!(vm.devices.read(0xF906), 8);
+
+    // One step every 2/64 s, so 8 steps take a quarter of a second.
+    vm.devices.tick(CPU_FREQUENCY / 8);
+    assert_eq!(vm.devices.read(0xF906), 4);
+    vm.devices.tick(CPU_FREQUENCY / 8);
+    assert_eq!(vm.devices.read(0xF906), 0);
+    vm.devices.sound().take_samples();
+    vm.devices.tick(CPU_FREQUENCY / 100);
+    assert!(vm.devices.sound().take_samples().iter().all(|&s| s == 0));
+}
+
+#[test]
+fn test_noise() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.sound().recording = true;
+    play(&mut vm, 3, 8000, 15, 0);
+    vm.devices.tick(CPU_FREQUENCY / 10);
+    let samples = vm.devices.sound().take_samples();
+
+    let high = samples.iter().filter(|&&s| s > 0).count();
+    let low = samples.iter().filter(|&&s| s < 0).count();
+    assert_eq!(high + low, samples.len());
+    assert!(
+        high > samples.len() / 4 && low > samples.len() / 4,
+        "{high} / {low}"
+    );
+}
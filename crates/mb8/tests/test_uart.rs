@@ -0,0 +1,83 @@
+use mb8::{dev::uart::registers::FIFO_SIZE, hle::Hle, vm::VirtualMachine};
+
+const KERNEL: &[u8] = include_bytes!("../../../kernel/main.bin");
+const SH: &[u8] = include_bytes!("../../../user/sh.bin");
+const LS: &[u8] = include_bytes!("../../../user/ls.bin");
+
+/// A disk image holding `files` in the layout written by `makefs`.
+fn disk(files: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut img = vec![0; 65536];
+    let mut block = 1;
+    for (i, (name, data)) in files.iter().enumerate() {
+        let size = data.len() / 256 + 1;
+        img[i * 16] = 1;
+        img[i * 16 + 1] = block as u8;
+        img[i * 16 + 2] = size as u8;
+        img[i * 16 + 3..i * 16 + 3 + name.len()].copy_from_slice(name.as_bytes());
+        img[block * 256..block * 256 + data.len()].copy_from_slice(data);
+        block += size;
+    }
+    img
+}
+
+/// Step `vm`, collecting what it transmits on the UART.
+fn serial_output(vm: &mut VirtualMachine, steps: usize) -> String {
+    let mut output = Vec::new();
+    for _ in 0..steps {
+        if vm.halted {
+            break;
+        }
+        vm.step();
+        output.extend(vm.devices.uart().transmit());
+    }
+    String::from_utf8_lossy(&output).into_owned()
+}
+
+#[test]
+fn test_uart_fifos() {
+    let mut vm = VirtualMachine::default();
+    assert_eq!(vm.devices.read(0xF700), 0x00);
+
+    for byte in 0..FIFO_SIZE as u8 + 1 {
+        vm.devices.write(0xF701, byte);
+        assert_eq!(
+            vm.devices.uart().receive(byte),
+            usize::from(byte) < FIFO_SIZE
+        );
+    }
+    assert_eq!(vm.devices.read(0xF700), 0x03);
+    assert_eq!(vm.devices.read(0xF701), 0);
+    assert_eq!(
+        vm.devices.uart().transmit(),
+        (0..FIFO_SIZE as u8).collect::<Vec<_>>()
+    );
+    assert_eq!(vm.devices.read(0xF700), 0x01);
+}
+
+#[test]
+fn test_kernel_serial_console() {
+    let mut vm = VirtualMachine::default();
+    vm.devices.disk().set(disk(&[("sh", SH), ("ls", LS)]));
+    for byte in b"ls\n" {
+        vm.devices.uart().receive(*byte);
+    }
+    vm.load_rom(KERNEL);
+
+    let output = serial_output(&mut vm, 1_000_000);
+    assert!(output.starts_with("MB8 kernel is starting...\n"));
+    assert!(output.ends_with(">ls\nsh\nls\n>"), "{output:?}");
+}
+
+#[test]
+fn test_hle_serial_console() {
+    let mut vm = VirtualMachine::default();
+    vm.hle = Some(Hle::default());
+    vm.devices.disk().set(disk(&[("sh", SH), ("ls", LS)]));
+    for byte in b"ls\n" {
+        vm.devices.uart().receive(*byte);
+    }
+    vm.load_program(SH).unwrap();
+
+    // `ls` exits, which ends the run in HLE mode.
+    assert_eq!(serial_output(&mut vm, 1_000_000), ">ls\nsh\nls\n");
+}
@@ -19,6 +19,18 @@ K_KERNEL_INIT:
 
     JMP [0xE100]
 
+; Entered by the CPU when a user program faults: drop its stack and restart the shell.
+#addr 0xE080
+K_FAULT:
+    LDI SPH 0xBF
+    LDI SPL 0xFF
+
+    LDI R0 SYS_WRITELN
+    LDI R1 R2 PROTECTION_FAULT
+    CALL [K_SYSCALL_ENTRY]
+
+    JMP [0xE100]
+
 #addr 0xE100
 START_SHELL:
     LDI R0 SYS_EXEC
@@ -44,5 +56,8 @@ LOADING:
 SHELL_BIN:
     #d "sh\0"
 
+PROTECTION_FAULT:
+    #d "Protection fault\n\0"
+
 SHELL_NOT_FOUND:
     #d "KERNEL PANIC: shell executable not found\n\0"
@@ -18,6 +18,32 @@ SYS_FS_DELETE = 0x0D
 SYS_EXEC = 0x0E
 SYS_EXIT = 0x0F
 SYS_RAND = 0x10
+SYS_TIME = 0x11
+
+DISK_BUFFER = 0xF202
+DISK_BLOCK_HIGH = 0xF310
+DISK_STATUS = 0xF312
+DISK_STATUS_BUSY = 0x80
+UART_STATUS = 0xF700
+UART_DATA = 0xF701
+UART_RX_READY = 0x01
+RTC_CTRL = 0xF800
+RTC_LATCH = 0x01
+
+; Executable header (see docs/syscalls.md), copied here by SYS_EXEC
+K_EXEC_HEADER = 0x0F00
+EXEC_HEADER_SIZE = 16
+EXEC_LOAD = K_EXEC_HEADER + 6
+EXEC_ENTRY = K_EXEC_HEADER + 8
+EXEC_CODE_SIZE = K_EXEC_HEADER + 10
+EXEC_BSS_SIZE = K_EXEC_HEADER + 12
+USER_BASE = 0x1000
+RAM_END = 0xC000
+
+EXEC_NOT_FOUND = 0x01
+EXEC_INVALID = 0x02
+EXEC_TOO_LARGE = 0x03
+EXEC_DISK_ERROR = 0x04
 
 #addr 0xE500
 K_SYSCALL_ENTRY:
@@ -84,8 +110,12 @@ syscall_table:
     JMP [sys_exit]
 .sys_rand:
     CMPI R0 SYS_RAND
-    JNZR [.not_found]
+    JNZR [.sys_time]
     JMP [sys_rand]
+.sys_time:
+    CMPI R0 SYS_TIME
+    JNZR [.not_found]
+    JMP [sys_time]
 .not_found:
     RET
 
@@ -105,7 +135,7 @@ sys_gpu_mode:
     ST [R6:R7] R1
     RET
 
-; Writes a character to the terminal
+; Writes a character to the terminal and the serial port
 ;
 ; Input
 ; R1: The character to write
@@ -119,9 +149,10 @@ sys_tty_write:
     LDI R6 0xF0
     LDI R7 0x01
     ST [R6:R7] R1
+    ST [UART_DATA] R1
     RET
 
-; Writes a in-memory string to the terminal
+; Writes a in-memory string to the terminal and the serial port
 ;
 ; Input
 ; R1: High address of the string to write
@@ -141,12 +172,13 @@ sys_tty_writeln:
     CMPI R5 0x00
     JZR [.end_loop]
     ST [R6:R7] R5
+    ST [UART_DATA] R5
     INC R2
     JR [.loop]
 .end_loop:
     RET
 
-; Waits for a key press
+; Waits for a key press or a byte on the serial port
 ;
 ; Input
 ; None
@@ -162,10 +194,15 @@ sys_wait_for_key:
 .loop:
     LD R5 [R6:R7]
     CMPI R5 0x00
+    JNZR [.end_loop]
+    LD R5 [UART_STATUS]
+    LDI R4 UART_RX_READY
+    AND R5 R4
     JZR [.loop]
+.end_loop:
     RET
 
-; Reads a key press
+; Reads a key press, or a byte from the serial port when no key is queued
 ;
 ; Input
 ; None
@@ -175,11 +212,18 @@ sys_wait_for_key:
 sys_read_key:
     ; Locals
     ; R0 - return value
-    ; R6:R7 = 0xF102
+    ; R6:R7 = 0xF101
     LDI R6 0xF1
+    LDI R7 0x01
+    LD R0 [R6:R7]
+    CMPI R0 0x00
+    JZR [.serial]
     LDI R7 0x02
     LD R0 [R6:R7]
     RET
+.serial:
+    LD R0 [UART_DATA]
+    RET
 
 ; Sets a disk block
 ;
@@ -191,10 +235,16 @@ sys_read_key:
 sys_disk_set_block:
     ; Locals
     ; R1 - args
-    ; R6:R7 = 0xF200
+    ; R6:R7 = 0xF200, then DISK_BLOCK_HIGH
     LDI R6 0xF2
     LDI R7 0x00
     ST [R6:R7] R1
+    ; Blocks past 0xFF are out of reach: clear the high byte
+    LDI R6 R7 DISK_BLOCK_HIGH
+    PUSH R1
+    LDI R1 0x00
+    ST [R6:R7] R1
+    POP R1
     RET
 
 ; Reads a disk block into the disk buffer
@@ -203,7 +253,7 @@ sys_disk_set_block:
 ; None
 ;
 ; Output
-; None
+; R0 - disk status (0 = success, otherwise the error code)
 sys_disk_read_block:
     ; Locals
     ; R5 - DISK_CMD_READ
@@ -212,7 +262,7 @@ sys_disk_read_block:
     LDI R7 0x01
     LDI R5 0x01
     ST [R6:R7] R5
-    RET
+    JMP [disk_wait]
 
 ; Writes a disk buffer into the disk
 ;
@@ -220,7 +270,7 @@ sys_disk_read_block:
 ; None
 ;
 ; Output
-; None
+; R0 - disk status (0 = success, otherwise the error code)
 sys_disk_write_block:
     ; Locals
     ; R5 - DISK_CMD_WRITE
@@ -229,6 +279,23 @@ sys_disk_write_block:
     LDI R7 0x01
     LDI R5 0x02
     ST [R6:R7] R5
+    JMP [disk_wait]
+
+; Waits until the disk command completes
+;
+; Input
+; None
+;
+; Output
+; R0 - disk status (0 = success, otherwise the error code)
+disk_wait:
+    ; Locals
+    ; R5 - busy bit
+.loop:
+    LD R0 [DISK_STATUS]
+    LDI R5 DISK_STATUS_BUSY
+    AND R5 R0
+    JNZR [.loop]
     RET
 
 ; Writes a directory block into the memory
@@ -354,7 +421,7 @@ sys_fs_find:
 ; R4: Low address of the buffer to write to
 ;
 ; Output
-; R0 - status (0 = success, 1 = not found)
+; R0 - status (0 = success, 1 = not found, 2 = disk error)
 sys_fs_read:
     PUSH R3
     PUSH R4
@@ -363,11 +430,16 @@ sys_fs_read:
     POP R3
 
     CMPI R0 0x00
-    JNZR [.not_found]
+    JZR [.copy_block]
+    JMP [.not_found]
 .copy_block:
     CALL [sys_disk_set_block]
     CALL [sys_disk_read_block]
-
+    CMPI R0 0x00
+    JZR [.copy]
+    LDI R0 0x02
+    RET
+.copy:
     LDI R6 0xF2
     LDI R5 0x02
 
@@ -395,7 +467,7 @@ sys_fs_read:
     JZR [.eof]
 
     INC R1
-    JR  [.copy_block]
+    JMP [.copy_block]
 .eof:
     LDI R0 0x00
     RET
@@ -411,28 +483,216 @@ sys_fs_delete:
 
 ; Executes a file in the FS
 ;
+; The file must start with an executable header. Its code is copied to the
+; load address, the BSS after it is zeroed and execution continues at the
+; entry point. The syscall only returns on failure.
+;
 ; Input
 ; R1: High address of the filename to find
 ; R2: Low address of the filename to find
 ;
 ; Output
-; R0 - status (0 = success, 1 = not found)
+; R0 - status (1 = not found, 2 = not an executable or shorter than its code,
+;      3 = does not fit in memory,
+;      4 = disk error, the load area may be partly overwritten)
 sys_exec:
-    LDI R0 SYS_FS_READ
-    LDI R3 0x10
-    LDI R4 0x00
-    CALL [K_SYSCALL_ENTRY]
+    CALL [sys_fs_find]
     CMPI R0 0x00
-    JNZR [.error]
-
+    JZR [.found]
+    RET
+.found:
+    ; Copy the header out of the first block
+    PUSH R1
+    PUSH R2
+    CALL [sys_disk_set_block]
+    CALL [sys_disk_read_block]
+    CMPI R0 0x00
+    JZR [.header]
+    POP R2
+    POP R1
+    LDI R0 EXEC_DISK_ERROR
+    RET
+.header:
+    LDI R3 R4 K_EXEC_HEADER
+    LDI R5 R6 DISK_BUFFER
+    LDI R7 EXEC_HEADER_SIZE - 1
+    MEMCPY [R3:R4] [R5:R6] R7
+    POP R8
+    CALL [exec_check_header]
+    POP R1
+    CMPI R0 0x00
+    JZR [.load]
+    RET
+.load:
+    ; Locals
+    ; R0 - byte
+    ; R1 - current block
+    ; R2:R3 - destination
+    ; R4:R5 - bytes left
+    ; R8 - offset in the current block
+    ; R11:R12 - disk buffer pointer
+    LD R2 [EXEC_LOAD]
+    LD R3 [EXEC_LOAD + 1]
+    LD R4 [EXEC_CODE_SIZE]
+    LD R5 [EXEC_CODE_SIZE + 1]
+    LDI R8 EXEC_HEADER_SIZE
+    LDI R11 R12 DISK_BUFFER + EXEC_HEADER_SIZE
+.copy:
+    CMPI R4 0x00
+    JNZR [.copy_byte]
+    CMPI R5 0x00
+    JNZR [.copy_byte]
+    JMP [.bss]
+.copy_byte:
+    LD R0 [R11:R12]
+    ST [R2:R3] R0
+    INC16 R2 R3
+    INC16 R11 R12
+    CMPI R5 0x00
+    JNZR [.copy_dec]
+    DEC R4
+.copy_dec:
+    DEC R5
+    INC R8
+    CMPI R8 0x00
+    JZR [.next_block]
+    JMP [.copy]
+.next_block:
+    INC R1
+    PUSH R5
+    CALL [sys_disk_set_block]
+    CALL [sys_disk_read_block]
+    POP R5
+    CMPI R0 0x00
+    JZR [.next_copy]
+    LDI R0 EXEC_DISK_ERROR
+    RET
+.next_copy:
+    LDI R11 R12 DISK_BUFFER
+    JMP [.copy]
+.bss:
+    ; R2:R3 points right after the code
+    LD R4 [EXEC_BSS_SIZE]
+    LD R5 [EXEC_BSS_SIZE + 1]
+    LDI R0 0x00
+.zero:
+    CMPI R4 0x00
+    JNZR [.zero_byte]
+    CMPI R5 0x00
+    JZR [.start]
+.zero_byte:
+    ST [R2:R3] R0
+    INC16 R2 R3
+    CMPI R5 0x00
+    JNZR [.zero_dec]
+    DEC R4
+.zero_dec:
+    DEC R5
+    JR [.zero]
+.start:
+    ; Drop the syscall return address and enter the program
     POP R0
     POP R0
+    LD R1 [EXEC_ENTRY]
+    LD R2 [EXEC_ENTRY + 1]
+    JMP [R1:R2]
 
-    JMP [0x1000]
+; Validates the executable header at K_EXEC_HEADER
+;
+; Input
+; R8 - file size in blocks
+;
+; Output
+; R0 - status (0 = valid, 2 = not an executable or shorter than its code,
+;      3 = does not fit in memory)
+exec_check_header:
+    ; Locals
+    ; R1:R2 - header pointer, then end of the program
+    ; R3:R4 - expected magic pointer, then size field pointer
+    ; R5 - counter
+    ; R6, R7 - bytes
+    LDI R1 R2 K_EXEC_HEADER
+    LDI R3 R4 EXEC_MAGIC
+    LDI R5 EXEC_MAGIC_SIZE
+.magic:
+    LD R6 [R1:R2]
+    LD R7 [R3:R4]
+    CMP R6 R7
+    JZR [.magic_next]
+    LDI R0 EXEC_INVALID
+    RET
+.magic_next:
+    INC16 R1 R2
+    INC16 R3 R4
+    DEC R5
+    CMPI R5 0x00
+    JNZR [.magic]
+
+    ; The code must be loaded into user memory
+    LD R1 [EXEC_LOAD]
+    LD R2 [EXEC_LOAD + 1]
+    LDI R7 USER_BASE >> 8
+    CMP R1 R7
+    JNCR [.sizes]
+    LDI R0 EXEC_TOO_LARGE
+    RET
+.sizes:
+    ; load + code + bss + stack must not pass the end of RAM
+    LDI R3 R4 EXEC_CODE_SIZE
+    LDI R5 0x03
+.add:
+    LD R6 [R3:R4]
+    INC16 R3 R4
+    LD R7 [R3:R4]
+    INC16 R3 R4
+    ADD R2 R7
+    JNCR [.add_hi]
+    INC R1
+    JNZR [.add_hi]
+    LDI R0 EXEC_TOO_LARGE
+    RET
+.add_hi:
+    ADD R1 R6
+    JNCR [.add_next]
+    LDI R0 EXEC_TOO_LARGE
+    RET
+.add_next:
+    DEC R5
+    CMPI R5 0x00
+    JNZR [.add]
 
-.error:
+    LDI R7 RAM_END >> 8
+    CMP R1 R7
+    JCR [.blocks]
+    JNZR [.too_large]
+    CMPI R2 0x00
+    JZR [.blocks]
+.too_large:
+    LDI R0 EXEC_TOO_LARGE
+    RET
+.blocks:
+    ; The code must be in the file: its last byte, at header + code_size - 1,
+    ; must be in one of its R8 blocks
+    LD R1 [EXEC_CODE_SIZE]
+    LD R2 [EXEC_CODE_SIZE + 1]
+    LDI R7 EXEC_HEADER_SIZE - 1
+    ADD R2 R7
+    JNCR [.last_block]
+    INC R1
+    JZR [.invalid]
+.last_block:
+    CMP R1 R8
+    JCR [.valid]
+.invalid:
+    LDI R0 EXEC_INVALID
+    RET
+.valid:
+    LDI R0 0x00
     RET
 
+EXEC_MAGIC:
+    #d "MB8X", 0x01`8
+EXEC_MAGIC_SIZE = $ - EXEC_MAGIC
 sys_exit:
     POP R0
     POP R0
@@ -451,3 +711,24 @@ sys_rand:
     LDI R7 0x00
     LD R0 [R6:R7]
     RET
+
+; Reads the real-time clock
+;
+; Input:
+; R1: High address of a 7-byte buffer
+; R2: Low address of a 7-byte buffer
+;
+; Output:
+; Buffer: year (high, low), month, day, hour, minute, second
+sys_time:
+    ; Locals
+    ; R1, R2 - args
+    ; R5:R6 = 0xF801 (latched time)
+    ; R7 - bytes to copy - 1
+    LDI R7 RTC_LATCH
+    ST [RTC_CTRL] R7
+    LDI R5 0xF8
+    LDI R6 0x01
+    LDI R7 0x06
+    MEMCPY [R1:R2] [R5:R6] R7
+    RET
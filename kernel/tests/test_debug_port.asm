@@ -0,0 +1,27 @@
+#include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+start:
+    LDI R0 "o"
+    DBG_LOG R0
+    LDI R0 "k"
+    DBG_LOG R0
+    LDI R0 " "
+    DBG_LOG R0
+    LDI R0 0x2A
+    DBG_HEX R0
+
+    ASSERT_EQ R0 0x2A 1
+    LDI R1 0x2B
+    ASSERT_EQ R1 0x2B 2
+
+    DBG_EXIT 0
+    HALT
@@ -0,0 +1,41 @@
+#include "../../asm/isa.asm"
+#include "../../asm/std.asm"
+#include "../../asm/debug.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; MEMCPY and STRCMP on strings from ROM, checked with the debug port.
+start:
+    ; Copy "mb8\0" to 0x0200 and compare it with the original
+    ; MEMCPY uses A (R0) itself
+    LDI R1 R2 0x0200
+    LDI R3 R4 STRING
+    LDI R5 0x03
+    MEMCPY [R1:R2] [R3:R4] R5
+
+    LDI R0 R1 0x0200
+    LD R5 [R0:R1]
+    ASSERT_EQ R5 "m" 1
+
+    LDI R2 R3 0x0200
+    LDI R4 R5 STRING
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 0 2
+
+    LDI R2 R3 0x0200
+    LDI R4 R5 OTHER
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 1 3
+
+    DBG_EXIT 0
+
+STRING:
+    #d "mb8\0"
+OTHER:
+    #d "mb9\0"
@@ -1,5 +1,6 @@
 #include "../../asm/isa.asm"
 #include "../../asm/std.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -9,14 +10,33 @@
     #fill
 }
 
+; Copies 0x0000..=0x00FF to 0x0150 and checks the copy.
 start:
+    ; Fill the source with its offsets
+    LDI R2 0x00
+    LDI R3 0x00
+.fill:
+    ST [R2:R3] R3
+    INC R3
+    CMPI R3 0x00
+    JNZR [.fill]
+
     LDI R0 0
     LDI R1 255
     LDI R2 0x00
     LDI R3 0x00
     LDI R4 0x01
     LDI R5 0x50
-
     MEMCPY [R4:R5] [R2:R3] R1
 
-    HALT
+    LD R1 [0x0150]
+    ASSERT_EQ R1 0x00 1
+    LD R1 [0x01D0]
+    ASSERT_EQ R1 0x80 2
+    LD R1 [0x024F]
+    ASSERT_EQ R1 0xFF 3
+    ; Nothing past the end
+    LD R1 [0x0250]
+    ASSERT_EQ R1 0x00 4
+
+    DBG_EXIT 0
@@ -1,5 +1,6 @@
 #include "../../asm/isa.asm"
 #include "../../asm/std.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -9,14 +10,48 @@
     #fill
 }
 
+; STRCMP on strings from ROM, then on the strings at 0x0000 and 0x0014 the
+; Rust tests put there, whose result is left in R0.
 start:
+    LDI R2 R3 MB8
+    LDI R4 R5 MB8
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 0 1
+
+    LDI R2 R3 MB8
+    LDI R4 R5 MB9
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 1 2
+
+    LDI R2 R3 MB8
+    LDI R4 R5 MB
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 1 3
+
+    LDI R2 R3 MB
+    LDI R4 R5 MB8
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 1 4
+
+    LDI R2 R3 EMPTY
+    LDI R4 R5 EMPTY
+    STRCMP R0 R1 R2 R3 R4 R5
+    ASSERT_EQ R0 0 5
+
     LDI R0 0
     LDI R1 0
     LDI R2 0x00
     LDI R3 0x00
     LDI R4 0x00
     LDI R5 0x14
-
     STRCMP R0 R1 R2 R3 R4 R5
+    DBG_EXIT 0
 
-    HALT
+MB8:
+    #d "mb8\0"
+MB9:
+    #d "mb9\0"
+MB:
+    #d "mb\0"
+EMPTY:
+    #d "\0"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,14 +9,32 @@
     #fill
 }
 
+; Writes a marker to block 2 and reads it back, then reads block 1, which the
+; Rust tests prepare, leaving the status in R0.
 start:
     LDI R0 SYS_DISK_SET_BLOCK
-    LDI R1 0x01
+    LDI R1 0x02
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 0x5A
+    ST [DISK_BUFFER] R1
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 0x00
+    ST [DISK_BUFFER] R1
+    LDI R0 SYS_DISK_READ_BLOCK
     CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 0x00 1
+    LD R1 [DISK_BUFFER]
+    ASSERT_EQ R1 0x5A 2
+    LDI R1 0x00
+    ST [DISK_BUFFER] R1
 
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x01
+    CALL [K_SYSCALL_ENTRY]
     LDI R0 SYS_DISK_READ_BLOCK
     CALL [K_SYSCALL_ENTRY]
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
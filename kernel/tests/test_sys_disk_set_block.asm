@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,10 +9,20 @@
     #fill
 }
 
+; Selects block 1 and checks the controller's block registers.
 start:
+    ; A high byte left over from a 16-bit block number
+    LDI R1 0x01
+    ST [DISK_BLOCK_HIGH] R1
+
     LDI R0 SYS_DISK_SET_BLOCK
     LDI R1 0x01
     CALL [K_SYSCALL_ENTRY]
-    HALT
+    LD R2 [0xF200]
+    ASSERT_EQ R2 0x01 1
+    LD R2 [DISK_BLOCK_HIGH]
+    ASSERT_EQ R2 0x00 2
+
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
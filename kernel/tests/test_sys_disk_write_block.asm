@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,19 +9,27 @@
     #fill
 }
 
+; Writes 228 to the first byte of block 1 and reads it back.
 start:
     LDI R0 SYS_DISK_SET_BLOCK
     LDI R1 0x01
     CALL [K_SYSCALL_ENTRY]
-
     LDI R1 228
     LDI R2 0xF2
     LDI R3 0x02
     ST [R2:R3] R1
-
     LDI R0 SYS_DISK_WRITE_BLOCK
     CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 0x00 1
+
+    LDI R1 0x00
+    ST [DISK_BUFFER] R1
+    LDI R0 SYS_DISK_READ_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 0x00 2
+    LD R1 [DISK_BUFFER]
+    ASSERT_EQ R1 228 3
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
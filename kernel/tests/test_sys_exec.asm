@@ -0,0 +1,69 @@
+#include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; Checks that SYS_EXEC rejects a missing file and an executable shorter than its
+; code size, then runs `prog`, which the Rust tests put on the disk.
+start:
+    LDI R0 SYS_EXEC
+    LDI R1 R2 MISSING
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 EXEC_NOT_FOUND 1
+
+    ; The header of `short`, in block 0x30
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x30
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 R2 DISK_BUFFER
+    LDI R3 R4 SHORT_HEADER
+    LDI R5 EXEC_HEADER_SIZE - 1
+    MEMCPY [R1:R2] [R3:R4] R5
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    ; `short` takes directory entry 15
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x00
+    CALL [K_SYSCALL_ENTRY]
+    LDI R0 SYS_DISK_READ_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 R2 DISK_BUFFER + 0xF0
+    LDI R3 R4 SHORT_ENTRY
+    LDI R5 SHORT_ENTRY_SIZE - 1
+    MEMCPY [R1:R2] [R3:R4] R5
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    LDI R0 SYS_EXEC
+    LDI R1 R2 SHORT
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 EXEC_INVALID 2
+
+    LDI R0 SYS_EXEC
+    LDI R1 R2 FILENAME
+    CALL [K_SYSCALL_ENTRY]
+
+    DBG_EXIT 0
+
+    #include "../syscalls.asm"
+
+; One block holds 240 bytes of code after the header, not 241
+SHORT_HEADER:
+    #d "MB8X", 0x01`8, 0x00`8
+    #d 0x2000`16, 0x2000`16, 0x00F1`16, 0x0000`16, 0x0100`16
+; Status, start block, size in blocks and name
+SHORT_ENTRY:
+    #d 0x01`8, 0x30`8, 0x01`8, "short\0"
+SHORT_ENTRY_SIZE = $ - SHORT_ENTRY
+SHORT = SHORT_ENTRY + 3
+MISSING:
+    #d "missing\0"
+FILENAME:
+    #d "prog\0"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,15 +9,48 @@
     #fill
 }
 
+; Adds `self` to the directory and finds it, then finds `file`, which the Rust
+; tests put on the disk, leaving the result in R0 to R2.
 start:
+    ; `self` takes directory entry 15
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x00
+    CALL [K_SYSCALL_ENTRY]
+    LDI R0 SYS_DISK_READ_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 R2 DISK_BUFFER + 0xF0
+    LDI R3 R4 SELF_ENTRY
+    LDI R5 SELF_ENTRY_SIZE - 1
+    MEMCPY [R1:R2] [R3:R4] R5
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    LDI R0 SYS_FS_FIND
+    LDI R1 R2 SELF
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 0 1
+    ASSERT_EQ R1 0x30 2
+    ASSERT_EQ R2 2 3
+
+    LDI R0 SYS_FS_FIND
+    LDI R1 R2 MISSING
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 1 4
+
     LDI R0 SYS_FS_FIND
     LDI R1 R2 FILENAME
     CALL [K_SYSCALL_ENTRY]
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
 
-
+; Status, start block, size in blocks and name
+SELF_ENTRY:
+    #d 0x01`8, 0x30`8, 0x02`8, "self\0"
+SELF_ENTRY_SIZE = $ - SELF_ENTRY
+SELF = SELF_ENTRY + 3
+MISSING:
+    #d "missing\0"
 FILENAME:
     #d "file\0"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,12 +9,53 @@
     #fill
 }
 
+; Lists the directory to 0x0150 and checks it against directory block 0.
 start:
+    ; Fill the destination, so the listing has to overwrite all of it
+    LDI R2 R3 0x0150
+    LDI R1 0xFF
+    LDI R4 0x00
+.fill:
+    ST [R2:R3] R1
+    INC16 R2 R3
+    INC R4
+    CMPI R4 0x00
+    JNZR [.fill]
+
     LDI R0 SYS_FS_LIST
     LDI R1 0x01
     LDI R2 0x50
     CALL [K_SYSCALL_ENTRY]
 
-    HALT
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x00
+    CALL [K_SYSCALL_ENTRY]
+    LDI R0 SYS_DISK_READ_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    ; Locals
+    ; R2:R3 - listing pointer
+    ; R4:R5 - disk buffer pointer
+    ; R8 - counter
+    ; R11 - set if a byte differs
+    LDI R2 R3 0x0150
+    LDI R4 R5 DISK_BUFFER
+    LDI R8 0x00
+    LDI R11 0x00
+.compare:
+    LD R0 [R2:R3]
+    LD R1 [R4:R5]
+    CMP R0 R1
+    JZR [.next]
+    LDI R11 0x01
+.next:
+    INC16 R2 R3
+    INC16 R4 R5
+    INC R8
+    CMPI R8 0x00
+    JNZR [.compare]
+    ASSERT_EQ R11 0 1
+
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,17 +9,67 @@
     #fill
 }
 
+; Adds `self` to the disk and reads it to 0x0400, then reads `file`, which the
+; Rust tests put on the disk, to 0x0000, leaving the status in R0.
 start:
+    ; The contents of `self`, in block 0x30
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x30
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 0x5A
+    ST [DISK_BUFFER] R1
+    LDI R1 0xA5
+    ST [DISK_BUFFER + 0xFF] R1
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    ; `self` takes directory entry 15
+    LDI R0 SYS_DISK_SET_BLOCK
+    LDI R1 0x00
+    CALL [K_SYSCALL_ENTRY]
+    LDI R0 SYS_DISK_READ_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+    LDI R1 R2 DISK_BUFFER + 0xF0
+    LDI R3 R4 SELF_ENTRY
+    LDI R5 SELF_ENTRY_SIZE - 1
+    MEMCPY [R1:R2] [R3:R4] R5
+    LDI R0 SYS_DISK_WRITE_BLOCK
+    CALL [K_SYSCALL_ENTRY]
+
+    LDI R0 SYS_FS_READ
+    LDI R1 R2 SELF
+    LDI R3 0x04
+    LDI R4 0x00
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 0 1
+    LD R1 [0x0400]
+    ASSERT_EQ R1 0x5A 2
+    LD R1 [0x04FF]
+    ASSERT_EQ R1 0xA5 3
+
+    LDI R0 SYS_FS_READ
+    LDI R1 R2 MISSING
+    LDI R3 0x04
+    LDI R4 0x00
+    CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R0 1 4
+
     LDI R0 SYS_FS_READ
     LDI R1 R2 FILENAME
     LDI R3 0x00
     LDI R4 0x00
     CALL [K_SYSCALL_ENTRY]
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
 
-
+; Status, start block, size in blocks and name
+SELF_ENTRY:
+    #d 0x01`8, 0x30`8, 0x01`8, "self\0"
+SELF_ENTRY_SIZE = $ - SELF_ENTRY
+SELF = SELF_ENTRY + 3
+MISSING:
+    #d "missing\0"
 FILENAME:
     #d "file\0"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,15 +9,17 @@
     #fill
 }
 
+; SYS_FS_WRITE is a placeholder: it returns without touching its arguments.
 start:
     LDI R0 SYS_FS_WRITE
     LDI R1 R2 FILENAME
     CALL [K_SYSCALL_ENTRY]
+    ASSERT_EQ R1 FILENAME >> 8 1
+    ASSERT_EQ R2 FILENAME & 0xFF 2
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
 
-
 FILENAME:
     #d "file\0"
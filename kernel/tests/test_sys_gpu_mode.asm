@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,10 +9,14 @@
     #fill
 }
 
+; Switches the GPU to TTY mode and reads the mode back.
 start:
     LDI R0 SYS_GPU_MODE
     LDI R1 0x01
     CALL [K_SYSCALL_ENTRY]
-    HALT
+    LD R2 [0xF000]
+    ASSERT_EQ R2 0x01 1
+
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,6 +9,7 @@
     #fill
 }
 
+; Stores 16 random numbers at 0x0200.
 start:
     LDI R3 0x02
     LDI R4 0x00
@@ -42,6 +44,17 @@ rand_loop:
     CMP R5 R6
     JNZR [rand_loop]
 
-    HALT
+    ; Consecutive numbers differ
+    LD R1 [0x0200]
+    LD R2 [0x0201]
+    CMP R1 R2
+    JZR [.same]
+    DBG_PASS 1
+    JR [.end]
+.same:
+    DBG_FAIL 1
+.end:
+
+    DBG_EXIT 0
 
 #include "../syscalls.asm"
@@ -0,0 +1,43 @@
+#include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
+
+#bankdef rom
+{
+    #addr 0xE000
+    #size 0x1000
+    #outp 0
+    #fill
+}
+
+; Reads the clock into 0x0200 and checks the values are plausible, as the
+; harness runs on host time.
+start:
+    LDI R0 SYS_TIME
+    LDI R1 0x02
+    LDI R2 0x00
+    CALL [K_SYSCALL_ENTRY]
+
+    ; Years 1792 to 2047
+    LD R1 [0x0200]
+    ASSERT_EQ R1 0x07 1
+
+    ; Month 1 to 12
+    LD R1 [0x0202]
+    CMPI R1 0x00
+    JZR [.bad_month]
+    LDI R2 13
+    CMP R1 R2
+    JNCR [.bad_month]
+    DBG_PASS 2
+    JR [.end_month]
+.bad_month:
+    DBG_FAIL 2
+.end_month:
+
+    ; Seven bytes, no more
+    LD R1 [0x0207]
+    ASSERT_EQ R1 0x00 3
+
+    DBG_EXIT 0
+
+    #include "../syscalls.asm"
@@ -1,4 +1,5 @@
 #include "../../asm/isa.asm"
+#include "../../asm/debug.asm"
 
 #bankdef rom
 {
@@ -8,16 +9,19 @@
     #fill
 }
 
+; Writes a line to the TTY. The TTY cannot be read back, so the Rust tests check
+; the screen.
 start:
     LDI R0 SYS_GPU_MODE
     LDI R1 0x01
     CALL [K_SYSCALL_ENTRY]
-
     LDI R0 SYS_WRITELN
     LDI R1 R2 HELLO_WORLD
     CALL [K_SYSCALL_ENTRY]
+    LD R2 [0xF000]
+    ASSERT_EQ R2 0x01 1
 
-    HALT
+    DBG_EXIT 0
 
     #include "../syscalls.asm"
 
@@ -1,4 +1,5 @@
 #include "../asm/cpu.asm"
 
 start:
+    LDI R0 0x00
     HALT
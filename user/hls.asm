@@ -0,0 +1,29 @@
+#include "../asm/cpu.asm"
+#include "../asm/ext.asm"
+#include "../asm/hostfs.asm"
+
+; List the files of the host directory shared with `run --hostfs`.
+start:
+    LDI R5 0x00     ; index
+.file:
+    ST [HOSTFS_INDEX] R5
+    LDI R0 HOSTFS_CMD_LIST
+    ST [HOSTFS_CMD] R0
+    LD R0 [HOSTFS_STATUS]
+    CMPI R0 HOSTFS_OK
+    JNZR [.end]
+
+    PUSH R5
+    LDI R1 R2 HOSTFS_BUFFER
+    LDI R0 0x03     ; SYS_WRITELN
+    CALL [0xE500]
+    LDI R0 0x02     ; SYS_WRITEL
+    LDI R1 "\n"
+    CALL [0xE500]
+    POP R5
+
+    INC R5
+    JMP [.file]
+.end:
+    LDI R0 0x0F     ; SYS_EXIT
+    CALL [0xE500]
@@ -10,6 +10,12 @@
 PADDLE_H = 0x04
 PADDLE_MAX_Y = 0x1C
 
+; Sound channel 0
+SND_FREQ_HI = 0xF900
+SND_FREQ_LO = 0xF901
+SND_VOLUME = 0xF902
+SND_ENVELOPE = 0xF903
+
 start:
     LDI R1 0x02
     ST [0xF000] R1
@@ -161,6 +167,7 @@ update_ball:
 .bounce_right:
     LDI R2 0x01
     ST [DX] R2
+    CALL [beep]
 
 .check_right_paddle:
     LD R1 [BALL_X]
@@ -189,6 +196,7 @@ update_ball:
 .bounce_left:
     LDI R2 0xFF
     ST [DX] R2
+    CALL [beep]
 
 .apply_x:
     LD R1 [BALL_X]
@@ -219,6 +227,18 @@ update_ball:
 .end:
     RET
 
+; Short 880 Hz blip that fades out
+beep:
+    LDI R1 0x03
+    ST [SND_FREQ_HI] R1
+    LDI R1 0x70
+    ST [SND_FREQ_LO] R1
+    LDI R1 0x01
+    ST [SND_ENVELOPE] R1
+    LDI R1 0x0F
+    ST [SND_VOLUME] R1
+    RET
+
 render:
     LD R1 [PREV_P1_Y]
     ZERO R2
@@ -1,11 +1,12 @@
 #include "../asm/cpu.asm"
 #include "../asm/ext.asm"
 
-; Minimal shell: read a line, exec filename at 0x0200 when Enter is pressed.
+; Minimal shell: read a line, exec filename at BUF when Enter is pressed.
 
 start:
-    LDI R1 0x01
-    ST [0xF000] R1
+    LDI R0 0x01       ; SYS_GPU_MODE
+    LDI R1 0x01       ; TTY
+    CALL [0xE500]
 prompt:
     PUSH R2
     LDI R0 0x02       ; SYS_WRITE
@@ -34,10 +35,9 @@ read_key:
     JMP [read_key]
 
 .print_char:
-    ; store char at BUF + idx (page 0x02)
-    LDI R4 0x02       ; hi
-    ZERO R5
-    MOV R5 R2         ; lo = idx (base lo = 0)
+    ; store char at BUF + idx (BUF is page aligned)
+    LDI R4 BUF >> 8   ; hi
+    MOV R5 R2         ; lo = idx
     ST [R4:R5] R0
     INC R2
 
@@ -50,7 +50,7 @@ read_key:
     JMP [read_key]
 exec_line:
     ; null-terminate at BUF+idx
-    LDI R4 0x02
+    LDI R4 BUF >> 8
     MOV R5 R2
     LDI R1 0x00
     ST [R4:R5] R1
@@ -60,10 +60,9 @@ exec_line:
     LDI R1 "\n"
     CALL [0xE500]
 
-    ; SYS_EXEC filename at BUF (0x0200)
+    ; SYS_EXEC filename at BUF
     LDI R0 0x0E
-    LDI R1 0x02       ; hi
-    LDI R2 0x00       ; lo
+    LDI R1 R2 BUF
     CALL [0xE500]
 
     LDI R0 0x03
@@ -72,9 +71,9 @@ exec_line:
 
     JMP [prompt]
 
+NOT_FOUND:
+    #d "Not found\n\0"
+
 #addr 0x1200
 BUF:
     #d8 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00
-
-NOT_FOUND:
-    #d "Not found\n\0"